@@ -240,3 +240,8 @@ pub mod msgpack {
     pub const TESTNET_LOCAL_STATE_DELTA_TX: &str =
         include_str!("../msgpack/testnet_local_state_delta_tx.json");
 }
+
+pub mod go_algorand {
+    /// The protocol version declarations of go-algorand's `protocol/consensus.go`.
+    pub const PROTOCOL_CONSENSUS: &str = include_str!("../go_algorand/consensus.go");
+}
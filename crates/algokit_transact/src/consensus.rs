@@ -0,0 +1,454 @@
+//! Consensus parameters for the Algorand protocol.
+//!
+//! This module provides the [`ConsensusParams`] type, which holds the protocol limits that
+//! constrain transactions (app argument and reference limits, note size, group size, program
+//! page size, minimum balances, etc.), and the [`ConsensusTable`] type, which maps protocol
+//! version strings, as reported by algod's `GetStatus.last_version` and the `current-protocol`
+//! of block headers, to their parameters.
+//!
+//! Field names follow the go-algorand consensus parameter names, so the JSON produced by
+//! `algod -G` or a custom `consensus.json` file can be loaded directly to support protocol
+//! versions that are newer than the built-in table.
+
+use crate::constants::{
+    MAX_ACCOUNT_REFERENCES, MAX_APP_ARGS, MAX_APP_REFERENCES, MAX_ARGS_SIZE, MAX_ASSET_REFERENCES,
+    MAX_BOX_REFERENCES, MAX_EXTRA_PROGRAM_PAGES, MAX_GLOBAL_STATE_KEYS, MAX_LOCAL_STATE_KEYS,
+    MAX_OVERALL_REFERENCES, MAX_TX_GROUP_SIZE, MIN_TXN_FEE, PROGRAM_PAGE_SIZE,
+};
+use crate::error::AlgoKitTransactError;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+
+/// Protocol version string for the consensus upgrade that introduced applications (AVM 2).
+pub const CONSENSUS_V24: &str =
+    "https://github.com/algorandfoundation/specs/tree/3a83c4c743f8b17adfd73944b4319c25722a6782";
+/// Protocol version string for the consensus upgrade that added asset close amounts.
+pub const CONSENSUS_V25: &str =
+    "https://github.com/algorandfoundation/specs/tree/bea19289bf41217d2c0af30522fa222ef1366466";
+/// Protocol version string for the consensus upgrade that introduced AVM 3.
+pub const CONSENSUS_V26: &str =
+    "https://github.com/algorandfoundation/specs/tree/ac2255d586c4474d4ebcf3809acccb59b7ef34ff";
+/// Protocol version string for the consensus upgrade that fixed the application close-out rules.
+pub const CONSENSUS_V27: &str =
+    "https://github.com/algorandfoundation/specs/tree/d050b3cade6d5c664df8bd729bf219f179812595";
+/// Protocol version string for the consensus upgrade that introduced AVM 4, extra program pages
+/// and fee pooling.
+pub const CONSENSUS_V28: &str =
+    "https://github.com/algorandfoundation/specs/tree/65b4ab3266c52c56a0fa7d591754887d68faad0a";
+/// Protocol version string for the consensus upgrade that fixed the app budget accounting.
+pub const CONSENSUS_V29: &str =
+    "https://github.com/algorandfoundation/specs/tree/abc54f79f9ad679d2d22f0fb9909fb005c16f8a1";
+/// Protocol version string for the consensus upgrade that introduced inner transactions (AVM 5).
+pub const CONSENSUS_V30: &str =
+    "https://github.com/algorandfoundation/specs/tree/bc36005dbd776e6d1eaf0c560619bb183215645c";
+/// Protocol version string for the consensus upgrade that introduced AVM 6.
+pub const CONSENSUS_V31: &str =
+    "https://github.com/algorandfoundation/specs/tree/85e6db1fdbdef00aa232c75199e10dc5fe9498f6";
+/// Protocol version string for the consensus upgrade that removed the asset and app holding limits.
+pub const CONSENSUS_V32: &str =
+    "https://github.com/algorandfoundation/specs/tree/d5ac876d7ede07367dbaa26e149aa42589aac1f7";
+/// Protocol version string for the consensus upgrade that raised the block size.
+pub const CONSENSUS_V33: &str =
+    "https://github.com/algorandfoundation/specs/tree/830a4e673148498cc7230a0d1ba1ed0a5471acc6";
+/// Protocol version string for the consensus upgrade that introduced state proofs (AVM 7).
+pub const CONSENSUS_V34: &str =
+    "https://github.com/algorandfoundation/specs/tree/2dd5435993f6f6d65691140f592ebca5ef19ffbd";
+/// Protocol version string for the consensus upgrade that fixed the state proof weights.
+pub const CONSENSUS_V35: &str =
+    "https://github.com/algorandfoundation/specs/tree/433d8e9a7274b6fca703d91213e05c7e6a589e69";
+/// Protocol version string for the consensus upgrade that introduced boxes (AVM 8).
+pub const CONSENSUS_V36: &str =
+    "https://github.com/algorandfoundation/specs/tree/44fa607d6051730f5264526bf3c108d51f0eadb6";
+/// Protocol version string for the consensus upgrade that shortened the catchpoint interval.
+pub const CONSENSUS_V37: &str =
+    "https://github.com/algorandfoundation/specs/tree/1ac4dd1f85470e1fb36c8a65520e1313d7dab9d5";
+/// Protocol version string for the consensus upgrade that introduced resource sharing (AVM 9).
+pub const CONSENSUS_V38: &str =
+    "https://github.com/algorandfoundation/specs/tree/abd3d4823c6f77349fc04c3af7b1e99fe4df699f";
+/// Protocol version string for the consensus upgrade that introduced dynamic round times (AVM 10).
+pub const CONSENSUS_V39: &str =
+    "https://github.com/algorandfoundation/specs/tree/925a46433742afb0b51bb939354bd907fa88bf95";
+/// Protocol version string for the consensus upgrade that introduced heartbeats (AVM 11).
+pub const CONSENSUS_V40: &str =
+    "https://github.com/algorandfoundation/specs/tree/236dcc18c9c507d794813ab768e467ea42d1b4d9";
+/// Protocol version string for the consensus upgrade that introduced access lists (AVM 12).
+pub const CONSENSUS_V41: &str =
+    "https://github.com/algorandfoundation/specs/tree/953304de35264fc3ef91bcd05c123242015eeaed";
+/// Protocol version string used by development networks running unreleased consensus rules.
+pub const CONSENSUS_FUTURE: &str = "future";
+
+/// Protocol parameters that constrain transactions and account balances.
+///
+/// The [`Default`] value holds the parameters of the latest built-in protocol version.
+/// Fields that are missing when deserializing from JSON take their default value, so a
+/// partial go-algorand consensus parameter object can be loaded.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq)]
+#[serde(rename_all = "PascalCase", default)]
+pub struct ConsensusParams {
+    /// Maximum number of rounds a transaction can be valid for.
+    pub max_txn_life: u64,
+
+    /// Maximum size of a transaction note in bytes.
+    pub max_txn_note_bytes: usize,
+
+    /// Maximum number of transactions in an atomic group.
+    pub max_tx_group_size: usize,
+
+    /// Minimum fee per transaction in microALGO.
+    pub min_txn_fee: u64,
+
+    /// Minimum balance of any account in microALGO.
+    ///
+    /// This is also the minimum balance increase for each asset held by an account.
+    pub min_balance: u64,
+
+    /// Maximum number of app call arguments.
+    pub max_app_args: usize,
+
+    /// Maximum combined size of all app call arguments in bytes.
+    pub max_app_total_arg_len: usize,
+
+    /// Maximum number of account references in an app call.
+    pub max_app_txn_accounts: usize,
+
+    /// Maximum number of app references in an app call.
+    pub max_app_txn_foreign_apps: usize,
+
+    /// Maximum number of asset references in an app call.
+    pub max_app_txn_foreign_assets: usize,
+
+    /// Maximum number of box references in an app call.
+    pub max_app_box_references: usize,
+
+    /// Maximum number of account, app, asset and box references combined in an app call.
+    pub max_app_total_txn_references: usize,
+
+    /// Size of a single program page in bytes.
+    pub max_app_program_len: usize,
+
+    /// Maximum number of extra program pages an app can request.
+    pub max_extra_app_program_pages: u32,
+
+    /// Maximum number of global state keys an app can request.
+    pub max_global_schema_entries: u32,
+
+    /// Maximum number of local state keys an app can request.
+    pub max_local_schema_entries: u32,
+
+    /// Minimum balance increase in microALGO for each app created by an account.
+    pub app_flat_params_min_balance: u64,
+
+    /// Minimum balance increase in microALGO for each app an account is opted into.
+    pub app_flat_opt_in_min_balance: u64,
+
+    /// Minimum balance increase in microALGO for each state schema entry.
+    pub schema_min_balance_per_entry: u64,
+
+    /// Additional minimum balance increase in microALGO for each uint schema entry.
+    pub schema_uint_min_balance: u64,
+
+    /// Additional minimum balance increase in microALGO for each byte slice schema entry.
+    pub schema_bytes_min_balance: u64,
+
+    /// Minimum balance increase in microALGO for each box created by an app.
+    pub box_flat_min_balance: u64,
+
+    /// Minimum balance increase in microALGO for each byte of box name and value.
+    pub box_byte_min_balance: u64,
+
+    /// Maximum size of a box value in bytes.
+    pub max_box_size: u64,
+
+    /// Maximum length of an asset name in bytes.
+    pub max_asset_name_bytes: usize,
+
+    /// Maximum length of an asset unit name in bytes.
+    pub max_asset_unit_name_bytes: usize,
+
+    /// Maximum length of an asset URL in bytes.
+    #[serde(rename = "MaxAssetURLBytes")]
+    pub max_asset_url_bytes: usize,
+
+    /// Maximum number of decimal places an asset can have.
+    pub max_asset_decimals: u32,
+}
+
+impl Default for ConsensusParams {
+    fn default() -> Self {
+        Self {
+            max_txn_life: 1000,
+            max_txn_note_bytes: 1024,
+            max_tx_group_size: MAX_TX_GROUP_SIZE,
+            min_txn_fee: MIN_TXN_FEE,
+            min_balance: 100_000,
+            max_app_args: MAX_APP_ARGS,
+            max_app_total_arg_len: MAX_ARGS_SIZE,
+            max_app_txn_accounts: MAX_ACCOUNT_REFERENCES,
+            max_app_txn_foreign_apps: MAX_APP_REFERENCES,
+            max_app_txn_foreign_assets: MAX_ASSET_REFERENCES,
+            max_app_box_references: MAX_BOX_REFERENCES,
+            max_app_total_txn_references: MAX_OVERALL_REFERENCES,
+            max_app_program_len: PROGRAM_PAGE_SIZE,
+            max_extra_app_program_pages: MAX_EXTRA_PROGRAM_PAGES,
+            max_global_schema_entries: MAX_GLOBAL_STATE_KEYS,
+            max_local_schema_entries: MAX_LOCAL_STATE_KEYS,
+            app_flat_params_min_balance: 100_000,
+            app_flat_opt_in_min_balance: 100_000,
+            schema_min_balance_per_entry: 25_000,
+            schema_uint_min_balance: 3_500,
+            schema_bytes_min_balance: 25_000,
+            box_flat_min_balance: 2_500,
+            box_byte_min_balance: 400,
+            max_box_size: 32_768,
+            max_asset_name_bytes: 32,
+            max_asset_unit_name_bytes: 8,
+            max_asset_url_bytes: 96,
+            max_asset_decimals: 19,
+        }
+    }
+}
+
+impl ConsensusParams {
+    /// Returns the built-in parameters for a protocol version, if the version is known.
+    pub fn for_version(version: &str) -> Option<Self> {
+        ConsensusTable::builtin().get(version).cloned()
+    }
+
+    /// Parses a single set of consensus parameters from JSON.
+    ///
+    /// Missing fields take the values of the latest built-in protocol version.
+    pub fn from_json(json: &str) -> Result<Self, AlgoKitTransactError> {
+        serde_json::from_str(json).map_err(|e| AlgoKitTransactError::InputError {
+            err_msg: format!("Failed to parse consensus parameters: {}", e),
+        })
+    }
+
+    /// Returns the maximum combined size of the approval and clear state programs for the
+    /// given number of extra program pages.
+    pub fn max_program_size(&self, extra_program_pages: u32) -> usize {
+        self.max_app_program_len * (1 + extra_program_pages as usize)
+    }
+}
+
+/// A table of consensus parameters keyed by protocol version string.
+#[derive(Debug, Clone, PartialEq, Eq, Default)]
+pub struct ConsensusTable {
+    versions: HashMap<String, ConsensusParams>,
+}
+
+impl ConsensusTable {
+    /// Returns the table of protocol versions known to this library.
+    ///
+    /// The table covers every version since applications were introduced, with the values
+    /// from go-algorand's `config/consensus.go`. Each version starts from the previous one and
+    /// applies the limits that changed in that upgrade.
+    pub fn builtin() -> Self {
+        let v24 = ConsensusParams {
+            max_txn_life: 1000,
+            max_txn_note_bytes: 1024,
+            max_tx_group_size: 16,
+            min_txn_fee: 1000,
+            min_balance: 100_000,
+            max_app_args: 16,
+            max_app_total_arg_len: 2048,
+            max_app_txn_accounts: 4,
+            max_app_txn_foreign_apps: 2,
+            max_app_txn_foreign_assets: 2,
+            max_app_box_references: 0,
+            max_app_total_txn_references: 8,
+            max_app_program_len: 1024,
+            max_extra_app_program_pages: 0,
+            max_global_schema_entries: 64,
+            max_local_schema_entries: 16,
+            app_flat_params_min_balance: 100_000,
+            app_flat_opt_in_min_balance: 100_000,
+            schema_min_balance_per_entry: 25_000,
+            schema_uint_min_balance: 3_500,
+            schema_bytes_min_balance: 25_000,
+            box_flat_min_balance: 0,
+            box_byte_min_balance: 0,
+            max_box_size: 0,
+            max_asset_name_bytes: 32,
+            max_asset_unit_name_bytes: 8,
+            max_asset_url_bytes: 32,
+            max_asset_decimals: 19,
+        };
+
+        // Larger programs and asset URLs, and more foreign references per app call.
+        let v28 = ConsensusParams {
+            max_app_program_len: 2048,
+            max_extra_app_program_pages: 3,
+            max_asset_url_bytes: 96,
+            max_app_txn_foreign_apps: 8,
+            max_app_txn_foreign_assets: 8,
+            ..v24.clone()
+        };
+
+        // Box storage.
+        let v36 = ConsensusParams {
+            max_app_box_references: 8,
+            box_flat_min_balance: 2_500,
+            box_byte_min_balance: 400,
+            max_box_size: 32_768,
+            ..v28.clone()
+        };
+
+        let versions = [
+            (CONSENSUS_V24, &v24),
+            (CONSENSUS_V25, &v24),
+            (CONSENSUS_V26, &v24),
+            (CONSENSUS_V27, &v24),
+            (CONSENSUS_V28, &v28),
+            (CONSENSUS_V29, &v28),
+            (CONSENSUS_V30, &v28),
+            (CONSENSUS_V31, &v28),
+            (CONSENSUS_V32, &v28),
+            (CONSENSUS_V33, &v28),
+            (CONSENSUS_V34, &v28),
+            (CONSENSUS_V35, &v28),
+            (CONSENSUS_V36, &v36),
+            (CONSENSUS_V37, &v36),
+            (CONSENSUS_V38, &v36),
+            (CONSENSUS_V39, &v36),
+            (CONSENSUS_V40, &v36),
+            (CONSENSUS_V41, &v36),
+            (CONSENSUS_FUTURE, &v36),
+        ]
+        .into_iter()
+        .map(|(version, params)| (version.to_string(), params.clone()))
+        .collect();
+
+        Self { versions }
+    }
+
+    /// Returns the parameters for a protocol version, if the version is in the table.
+    pub fn get(&self, version: &str) -> Option<&ConsensusParams> {
+        self.versions.get(version)
+    }
+
+    /// Adds or replaces the parameters for a protocol version.
+    pub fn insert(&mut self, version: impl Into<String>, params: ConsensusParams) {
+        self.versions.insert(version.into(), params);
+    }
+
+    /// Loads protocol versions from a JSON object mapping version strings to parameters,
+    /// in the format of a go-algorand `consensus.json` file.
+    ///
+    /// Versions already in the table are replaced.
+    pub fn load_json(&mut self, json: &str) -> Result<(), AlgoKitTransactError> {
+        let versions: HashMap<String, ConsensusParams> =
+            serde_json::from_str(json).map_err(|e| AlgoKitTransactError::InputError {
+                err_msg: format!("Failed to parse consensus parameters: {}", e),
+            })?;
+        self.versions.extend(versions);
+        Ok(())
+    }
+
+    /// Returns an iterator over the protocol version strings in the table.
+    pub fn versions(&self) -> impl Iterator<Item = &str> {
+        self.versions.keys().map(String::as_str)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_builtin_versions() {
+        let table = ConsensusTable::builtin();
+        assert_eq!(table.versions().count(), 19);
+
+        let v41 = table.get(CONSENSUS_V41).unwrap();
+        assert_eq!(v41, &ConsensusParams::default());
+        assert_eq!(v41.max_app_total_txn_references, 8);
+        assert_eq!(v41.max_program_size(3), 8192);
+
+        let v36 = table.get(CONSENSUS_V36).unwrap();
+        assert_eq!(v36.max_app_box_references, 8);
+        assert_eq!(v36.box_byte_min_balance, 400);
+
+        let v35 = table.get(CONSENSUS_V35).unwrap();
+        assert_eq!(v35.max_app_box_references, 0);
+        assert_eq!(v35.max_box_size, 0);
+        assert_eq!(v35.max_app_txn_foreign_apps, 8);
+
+        let v27 = table.get(CONSENSUS_V27).unwrap();
+        assert_eq!(v27.max_app_program_len, 1024);
+        assert_eq!(v27.max_extra_app_program_pages, 0);
+        assert_eq!(v27.max_asset_url_bytes, 32);
+        assert_eq!(v27.max_app_txn_foreign_assets, 2);
+
+        assert!(table.get("v7").is_none());
+        assert_eq!(
+            ConsensusParams::for_version(CONSENSUS_FUTURE),
+            Some(ConsensusParams::default())
+        );
+    }
+
+    #[test]
+    fn test_versions_match_go_algorand() {
+        // go-algorand declares each version as `const ConsensusVN = ConsensusVersion(` with the
+        // version string on the following line.
+        let source = algokit_test_artifacts::go_algorand::PROTOCOL_CONSENSUS;
+        let lines = source.lines().collect::<Vec<_>>();
+        let go_versions = lines
+            .windows(2)
+            .filter_map(|pair| {
+                let name = pair[0]
+                    .strip_prefix("const ")?
+                    .strip_suffix(" = ConsensusVersion(")?;
+                let version = pair[1].trim().trim_end_matches(',').trim_matches('"');
+                Some((name, version))
+            })
+            .collect::<HashMap<_, _>>();
+
+        assert_eq!(go_versions["ConsensusV33"], CONSENSUS_V33);
+        assert_eq!(go_versions["ConsensusFuture"], CONSENSUS_FUTURE);
+        let mut builtin = ConsensusTable::builtin()
+            .versions()
+            .map(str::to_string)
+            .collect::<Vec<_>>();
+        let mut expected = go_versions
+            .values()
+            .map(|version| version.to_string())
+            .collect::<Vec<_>>();
+        builtin.sort();
+        expected.sort();
+        assert_eq!(builtin, expected);
+    }
+
+    #[test]
+    fn test_from_json_fills_missing_fields() {
+        let params =
+            ConsensusParams::from_json(r#"{"MaxAppArgs": 32, "MaxAssetURLBytes": 128}"#).unwrap();
+
+        assert_eq!(params.max_app_args, 32);
+        assert_eq!(params.max_asset_url_bytes, 128);
+        assert_eq!(params.min_txn_fee, 1000);
+    }
+
+    #[test]
+    fn test_load_json_adds_versions() {
+        let mut table = ConsensusTable::builtin();
+        table
+            .load_json(r#"{"v99": {"MaxTxGroupSize": 32}, "future": {"MaxTxnNoteBytes": 2048}}"#)
+            .unwrap();
+
+        assert_eq!(table.get("v99").unwrap().max_tx_group_size, 32);
+        assert_eq!(
+            table.get(CONSENSUS_FUTURE).unwrap().max_txn_note_bytes,
+            2048
+        );
+        assert!(table.load_json("[]").is_err());
+    }
+
+    #[test]
+    fn test_json_round_trip() {
+        let params = ConsensusParams::default();
+        let json = serde_json::to_string(&params).unwrap();
+
+        assert!(json.contains("\"MaxAppTotalTxnReferences\":8"));
+        assert_eq!(ConsensusParams::from_json(&json).unwrap(), params);
+    }
+}
@@ -7,8 +7,10 @@ pub const ALGORAND_SIGNATURE_BYTE_LENGTH: usize = 64;
 pub const ALGORAND_SIGNATURE_ENCODING_INCR: usize = 75;
 pub type Byte32 = [u8; 32];
 pub const MAX_TX_GROUP_SIZE: usize = 16;
+pub const MIN_TXN_FEE: u64 = 1000; // In microALGO
 
 pub const MULTISIG_DOMAIN_SEPARATOR: &str = "MultisigAddr";
+pub const PROGRAM_DOMAIN_SEPARATOR: &str = "Program";
 pub const EMPTY_SIGNATURE: [u8; ALGORAND_SIGNATURE_BYTE_LENGTH] =
     [0; ALGORAND_SIGNATURE_BYTE_LENGTH];
 
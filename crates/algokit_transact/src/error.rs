@@ -35,6 +35,15 @@ pub enum AlgoKitTransactError {
 
     #[snafu(display("Invalid multisig signature: {err_msg}"))]
     InvalidMultisigSignature { err_msg: String },
+
+    #[snafu(display("Transaction file error at byte offset {offset}: {err_msg}"))]
+    TransactionFileError { offset: u64, err_msg: String },
+
+    #[snafu(display("Transaction file write error at byte offset {offset}: {err_msg}"))]
+    TransactionFileWriteError { offset: u64, err_msg: String },
+
+    #[snafu(display("Invalid wallet transaction: {err_msg}"))]
+    InvalidWalletTransaction { err_msg: String },
 }
 
 impl From<rmp_serde::encode::Error> for AlgoKitTransactError {
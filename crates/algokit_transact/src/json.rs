@@ -0,0 +1,379 @@
+//! Canonical algod-style JSON encoding for transactions.
+//!
+//! algod's JSON endpoints and `goal clerk inspect` render transactions using the same short
+//! field names as the msgpack encoding, with byte fields as base64 strings and addresses as
+//! base32 strings. This differs from the serde JSON shape of [`Transaction`], so this module
+//! converts between the canonical msgpack form and that JSON representation.
+
+use crate::address::Address;
+use crate::constants::ALGORAND_PUBLIC_KEY_BYTE_LENGTH;
+use crate::error::AlgoKitTransactError;
+use crate::traits::AlgorandMsgpack;
+use crate::transactions::{SignedTransaction, Transaction};
+use base64::{Engine, prelude::BASE64_STANDARD};
+use rmpv::Value;
+use serde_json::{Map, Number, Value as JsonValue};
+use std::str::FromStr;
+
+/// The object a msgpack map represents, which determines how its fields are rendered.
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Context {
+    SignedTransaction,
+    Transaction,
+    AssetParams,
+    Heartbeat,
+    StateProof,
+    Other,
+}
+
+/// How a single field is rendered in JSON.
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Field {
+    Address,
+    AddressList,
+    Text,
+    Object(Context),
+    IntegerKeyedObject,
+    Bytes,
+}
+
+fn field(context: Context, key: &str) -> Field {
+    match (context, key) {
+        (Context::SignedTransaction, "txn") => Field::Object(Context::Transaction),
+        (Context::SignedTransaction, "sgnr") => Field::Address,
+        (
+            Context::Transaction,
+            "snd" | "rcv" | "close" | "asnd" | "arcv" | "aclose" | "fadd" | "rekey",
+        ) => Field::Address,
+        (Context::Transaction, "type" | "gen") => Field::Text,
+        (Context::Transaction, "apat") => Field::AddressList,
+        (Context::Transaction, "apar") => Field::Object(Context::AssetParams),
+        (Context::Transaction, "hb") => Field::Object(Context::Heartbeat),
+        (Context::Transaction, "sp") => Field::Object(Context::StateProof),
+        (Context::AssetParams, "m" | "r" | "f" | "c") => Field::Address,
+        (Context::AssetParams, "an" | "un" | "au") => Field::Text,
+        (Context::Heartbeat, "a") => Field::Address,
+        (Context::StateProof, "r") => Field::IntegerKeyedObject,
+        _ => Field::Bytes,
+    }
+}
+
+fn json_error(err_msg: String) -> AlgoKitTransactError {
+    AlgoKitTransactError::InputError {
+        err_msg: format!("Invalid algod JSON: {}", err_msg),
+    }
+}
+
+fn to_json(value: &Value, field: Field) -> Result<JsonValue, AlgoKitTransactError> {
+    Ok(match value {
+        Value::Nil => JsonValue::Null,
+        Value::Boolean(b) => JsonValue::Bool(*b),
+        Value::Integer(i) => match i.as_u64() {
+            Some(u) => JsonValue::from(u),
+            None => JsonValue::from(i.as_i64().unwrap_or_default()),
+        },
+        Value::F32(f) => Number::from_f64(*f as f64).map_or(JsonValue::Null, JsonValue::Number),
+        Value::F64(f) => Number::from_f64(*f).map_or(JsonValue::Null, JsonValue::Number),
+        Value::String(s) => JsonValue::String(
+            s.as_str()
+                .ok_or_else(|| json_error("string field is not valid UTF-8".to_string()))?
+                .to_string(),
+        ),
+        Value::Binary(bytes) => match field {
+            Field::Address if bytes.len() == ALGORAND_PUBLIC_KEY_BYTE_LENGTH => {
+                let mut pub_key = [0u8; ALGORAND_PUBLIC_KEY_BYTE_LENGTH];
+                pub_key.copy_from_slice(bytes);
+                JsonValue::String(Address(pub_key).to_string())
+            }
+            _ => JsonValue::String(BASE64_STANDARD.encode(bytes)),
+        },
+        Value::Array(items) => {
+            let item_field = match field {
+                Field::AddressList => Field::Address,
+                other => other,
+            };
+            JsonValue::Array(
+                items
+                    .iter()
+                    .map(|item| to_json(item, item_field))
+                    .collect::<Result<_, _>>()?,
+            )
+        }
+        Value::Map(entries) => {
+            let context = match field {
+                Field::Object(context) => context,
+                _ => Context::Other,
+            };
+            let mut map = Map::new();
+            for (key, value) in entries {
+                let key = match key {
+                    Value::String(s) => s
+                        .as_str()
+                        .ok_or_else(|| json_error("map key is not valid UTF-8".to_string()))?
+                        .to_string(),
+                    Value::Integer(i) => i.to_string(),
+                    other => return Err(json_error(format!("unsupported map key {}", other))),
+                };
+                let value = to_json(value, self::field(context, &key))?;
+                map.insert(key, value);
+            }
+            JsonValue::Object(map)
+        }
+        Value::Ext(_, _) => return Err(json_error("msgpack extensions are not supported".into())),
+    })
+}
+
+fn from_json(value: &JsonValue, field: Field) -> Result<Value, AlgoKitTransactError> {
+    Ok(match value {
+        JsonValue::Null => Value::Nil,
+        JsonValue::Bool(b) => Value::Boolean(*b),
+        JsonValue::Number(n) => match (n.as_u64(), n.as_i64(), n.as_f64()) {
+            (Some(u), _, _) => Value::from(u),
+            (None, Some(i), _) => Value::from(i),
+            (None, None, Some(f)) => Value::F64(f),
+            _ => return Err(json_error(format!("unsupported number {}", n))),
+        },
+        JsonValue::String(s) => match field {
+            Field::Text => Value::from(s.as_str()),
+            Field::Address | Field::AddressList => Value::Binary(
+                Address::from_str(s)
+                    .map_err(|e| json_error(format!("invalid address {}: {}", s, e)))?
+                    .0
+                    .to_vec(),
+            ),
+            _ => Value::Binary(
+                BASE64_STANDARD
+                    .decode(s)
+                    .map_err(|e| json_error(format!("invalid base64 value {}: {}", s, e)))?,
+            ),
+        },
+        JsonValue::Array(items) => {
+            let item_field = match field {
+                Field::AddressList => Field::Address,
+                other => other,
+            };
+            Value::Array(
+                items
+                    .iter()
+                    .map(|item| from_json(item, item_field))
+                    .collect::<Result<_, _>>()?,
+            )
+        }
+        JsonValue::Object(map) => {
+            let context = match field {
+                Field::Object(context) => context,
+                _ => Context::Other,
+            };
+            let mut entries = Vec::with_capacity(map.len());
+            for (key, value) in map {
+                let msgpack_key =
+                    if field == Field::IntegerKeyedObject {
+                        Value::from(key.parse::<u64>().map_err(|_| {
+                            json_error(format!("expected an integer key, got {}", key))
+                        })?)
+                    } else {
+                        Value::from(key.as_str())
+                    };
+                entries.push((msgpack_key, from_json(value, self::field(context, key))?));
+            }
+            Value::Map(entries)
+        }
+    })
+}
+
+fn encode_json<T: AlgorandMsgpack>(
+    item: &T,
+    context: Context,
+) -> Result<JsonValue, AlgoKitTransactError> {
+    let bytes = item.encode_raw()?;
+    let value = rmpv::decode::read_value(&mut bytes.as_slice())?;
+    to_json(&value, Field::Object(context))
+}
+
+fn decode_json(json: &JsonValue, context: Context) -> Result<Vec<u8>, AlgoKitTransactError> {
+    if !json.is_object() {
+        return Err(json_error("expected a JSON object".to_string()));
+    }
+    let value = from_json(json, Field::Object(context))?;
+    let mut bytes = Vec::new();
+    rmpv::encode::write_value(&mut bytes, &value)?;
+    Ok(bytes)
+}
+
+fn parse_json(json: &str) -> Result<JsonValue, AlgoKitTransactError> {
+    serde_json::from_str(json).map_err(|e| json_error(e.to_string()))
+}
+
+impl Transaction {
+    /// Encodes the transaction as canonical algod-style JSON.
+    pub fn to_algod_json(&self) -> Result<JsonValue, AlgoKitTransactError> {
+        encode_json(self, Context::Transaction)
+    }
+
+    /// Encodes the transaction as a canonical algod-style JSON string.
+    pub fn to_algod_json_string(&self) -> Result<String, AlgoKitTransactError> {
+        Ok(self.to_algod_json()?.to_string())
+    }
+
+    /// Decodes a transaction from canonical algod-style JSON.
+    pub fn from_algod_json(json: &JsonValue) -> Result<Self, AlgoKitTransactError> {
+        Transaction::decode(&decode_json(json, Context::Transaction)?)
+    }
+
+    /// Decodes a transaction from a canonical algod-style JSON string.
+    pub fn from_algod_json_str(json: &str) -> Result<Self, AlgoKitTransactError> {
+        Self::from_algod_json(&parse_json(json)?)
+    }
+}
+
+impl SignedTransaction {
+    /// Encodes the signed transaction as canonical algod-style JSON.
+    pub fn to_algod_json(&self) -> Result<JsonValue, AlgoKitTransactError> {
+        encode_json(self, Context::SignedTransaction)
+    }
+
+    /// Encodes the signed transaction as a canonical algod-style JSON string.
+    pub fn to_algod_json_string(&self) -> Result<String, AlgoKitTransactError> {
+        Ok(self.to_algod_json()?.to_string())
+    }
+
+    /// Decodes a signed transaction from canonical algod-style JSON.
+    pub fn from_algod_json(json: &JsonValue) -> Result<Self, AlgoKitTransactError> {
+        let json_txn = json
+            .get("txn")
+            .ok_or_else(|| json_error("signed transaction is missing the txn field".into()))?;
+        if !json_txn.is_object() {
+            return Err(json_error("expected txn to be a JSON object".to_string()));
+        }
+        SignedTransaction::decode(&decode_json(json, Context::SignedTransaction)?)
+    }
+
+    /// Decodes a signed transaction from a canonical algod-style JSON string.
+    pub fn from_algod_json_str(json: &str) -> Result<Self, AlgoKitTransactError> {
+        Self::from_algod_json(&parse_json(json)?)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_utils::{
+        AccountMother, AppCallTransactionMother, AssetConfigTransactionMother,
+        HeartbeatTransactionMother, TestDataMother, TransactionMother,
+    };
+    use serde_json::json;
+
+    fn assert_round_trip(tx: Transaction) {
+        let from_msgpack = Transaction::decode(&tx.encode().unwrap()).unwrap();
+
+        let json = tx.to_algod_json_string().unwrap();
+        let from_json = Transaction::from_algod_json_str(&json).unwrap();
+
+        assert_eq!(from_json, from_msgpack);
+        assert_eq!(from_json.encode().unwrap(), tx.encode().unwrap());
+    }
+
+    #[test]
+    fn test_payment_json() {
+        let tx = TransactionMother::simple_payment().build().unwrap();
+        let header = tx.header();
+
+        let json = tx.to_algod_json().unwrap();
+
+        assert_eq!(json["type"], json!("pay"));
+        assert_eq!(json["snd"], json!(header.sender.to_string()));
+        assert_eq!(json["fv"], json!(header.first_valid));
+        assert_eq!(
+            json["gh"],
+            json!(BASE64_STANDARD.encode(header.genesis_hash.unwrap()))
+        );
+        assert_eq!(json["gen"], json!(header.genesis_id.clone().unwrap()));
+    }
+
+    #[test]
+    fn test_round_trips_all_transaction_types() {
+        assert_round_trip(TransactionMother::simple_payment().build().unwrap());
+        assert_round_trip(TransactionMother::payment_with_note().build().unwrap());
+        assert_round_trip(TransactionMother::simple_asset_transfer().build().unwrap());
+        assert_round_trip(
+            AssetConfigTransactionMother::asset_create()
+                .build()
+                .unwrap(),
+        );
+        assert_round_trip(AppCallTransactionMother::app_call().build().unwrap());
+        assert_round_trip(AppCallTransactionMother::app_create().build().unwrap());
+        assert_round_trip(HeartbeatTransactionMother::heartbeat().build().unwrap());
+        assert_round_trip(TestDataMother::state_proof().transaction);
+    }
+
+    #[test]
+    fn test_asset_params_use_addresses_and_text() {
+        let tx = AssetConfigTransactionMother::asset_create()
+            .build()
+            .unwrap();
+        let Transaction::AssetConfig(fields) = &tx else {
+            unreachable!()
+        };
+
+        let json = tx.to_algod_json().unwrap();
+
+        if let Some(manager) = &fields.manager {
+            assert_eq!(json["apar"]["m"], json!(manager.to_string()));
+        }
+        if let Some(name) = &fields.asset_name {
+            assert_eq!(json["apar"]["an"], json!(name));
+        }
+    }
+
+    #[test]
+    fn test_signed_transaction_round_trip() {
+        let stx = SignedTransaction {
+            transaction: TransactionMother::simple_payment().build().unwrap(),
+            signature: Some([7; 64]),
+            auth_address: Some(AccountMother::neil().address()),
+            multisignature: None,
+        };
+
+        let json = stx.to_algod_json().unwrap();
+        assert_eq!(json["sig"], json!(BASE64_STANDARD.encode([7; 64])));
+        assert_eq!(
+            json["sgnr"],
+            json!(AccountMother::neil().address().to_string())
+        );
+
+        let decoded = SignedTransaction::from_algod_json(&json).unwrap();
+        assert_eq!(decoded, stx);
+        assert_eq!(decoded.encode().unwrap(), stx.encode().unwrap());
+    }
+
+    #[test]
+    fn test_multisig_round_trip() {
+        let stx = SignedTransaction {
+            transaction: TransactionMother::simple_payment().build().unwrap(),
+            signature: None,
+            auth_address: None,
+            multisignature: Some(AccountMother::msig()),
+        };
+
+        let decoded =
+            SignedTransaction::from_algod_json_str(&stx.to_algod_json_string().unwrap()).unwrap();
+
+        assert_eq!(decoded, stx);
+    }
+
+    #[test]
+    fn test_invalid_json() {
+        let error = Transaction::from_algod_json(&json!({"type": "pay", "snd": "nope"}))
+            .unwrap_err()
+            .to_string();
+        assert!(error.starts_with("Invalid algod JSON: invalid address nope"));
+
+        let error = SignedTransaction::from_algod_json(&json!({"sig": "AA=="}))
+            .unwrap_err()
+            .to_string();
+        assert_eq!(
+            error,
+            "Invalid algod JSON: signed transaction is missing the txn field"
+        );
+    }
+}
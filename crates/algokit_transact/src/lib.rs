@@ -1,31 +1,44 @@
 mod address;
+pub mod consensus;
 pub mod constants;
 mod error;
+mod json;
 mod keypair_account;
 pub mod multisig;
+mod multisig_account;
 mod traits;
+pub mod transaction_file;
 mod transactions;
 mod utils;
+pub mod wallet_transaction;
 
 // Re-export all the public items
 pub use address::Address;
+pub use consensus::{ConsensusParams, ConsensusTable};
 pub use constants::*;
 pub use error::AlgoKitTransactError;
 pub use keypair_account::KeyPairAccount;
 pub use multisig::*;
+pub use multisig_account::{MultisigAccount, MultisigProgress};
 pub use traits::{AlgorandMsgpack, EstimateTransactionSize, TransactionId, Transactions, Validate};
+pub use transaction_file::{
+    LogicSigTransaction, TransactionFileEntry, TransactionFileReader, TransactionFileWriter,
+};
 pub use transactions::{
     AppCallTransactionBuilder, AppCallTransactionFields, AssetConfigTransactionBuilder,
     AssetConfigTransactionFields, AssetFreezeTransactionBuilder, AssetFreezeTransactionFields,
     AssetTransferTransactionBuilder, AssetTransferTransactionFields, BoxReference,
-    FalconSignatureStruct, FalconVerifier, FeeParams, HashFactory, HeartbeatProof,
-    HeartbeatProofBuilder, HeartbeatTransactionBuilder, HeartbeatTransactionFields,
+    FalconSignatureStruct, FalconVerifier, FeeParams, GroupValidationError, HashFactory,
+    HeartbeatProof, HeartbeatProofBuilder, HeartbeatTransactionBuilder, HeartbeatTransactionFields,
     KeyRegistrationTransactionBuilder, KeyRegistrationTransactionFields, MerkleArrayProof,
     MerkleSignatureVerifier, OnApplicationComplete, Participant, PaymentTransactionBuilder,
     PaymentTransactionFields, Reveal, SignedTransaction, SigslotCommit, StateProof,
     StateProofMessage, StateProofTransactionBuilder, StateProofTransactionFields, StateSchema,
     Transaction, TransactionHeader, TransactionHeaderBuilder,
 };
+pub use wallet_transaction::{
+    MultisigMetadata, WalletTransaction, merge_wallet_signatures, validate_wallet_transactions,
+};
 
 #[cfg(feature = "test_utils")]
 pub mod test_utils;
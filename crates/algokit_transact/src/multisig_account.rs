@@ -0,0 +1,390 @@
+//! Algorand multisignature account representation and signing workflow.
+//!
+//! This module provides the [`MultisigAccount`] type, which pairs the parameters of a multisignature
+//! account with the steps of signing for it: creating an unsigned [`SignedTransaction`] shell,
+//! applying the subsignatures of participants as they arrive (possibly across many offline sessions),
+//! merging partially signed copies, and finalizing once the threshold is met.
+//!
+//! Subsignatures are applied as given; it is up to each participant to sign the bytes returned by
+//! [`MultisigAccount::bytes_to_sign`] or [`MultisigAccount::program_bytes_to_sign`].
+
+use crate::address::Address;
+use crate::constants::{ALGORAND_SIGNATURE_BYTE_LENGTH, PROGRAM_DOMAIN_SEPARATOR};
+use crate::error::AlgoKitTransactError;
+use crate::multisig::{MultisigSignature, MultisigSubsignature};
+use crate::traits::AlgorandMsgpack;
+use crate::transactions::{SignedTransaction, Transaction};
+use std::fmt::{Display, Formatter, Result as FmtResult};
+
+/// Represents an Algorand multisignature account.
+///
+/// A multisignature account is defined by a version, a threshold, and an ordered list of participant
+/// addresses. Its [`Address`] is derived from all three, so the same participants in a different
+/// order form a different account.
+#[derive(Debug, PartialEq, Clone)]
+pub struct MultisigAccount {
+    /// The account's multisignature without any subsignatures, from which its address is derived.
+    unsigned: MultisigSignature,
+}
+
+/// How far a multisignature is from meeting its threshold.
+#[derive(Debug, PartialEq, Clone)]
+pub struct MultisigProgress {
+    /// Minimum number of subsignatures required.
+    pub threshold: u8,
+    /// Participants that have signed, once for each time they appear.
+    pub signed: Vec<Address>,
+    /// Participants that have not signed, once for each time they appear.
+    pub pending: Vec<Address>,
+}
+
+impl MultisigProgress {
+    /// Reports the progress of a multisignature.
+    pub fn from_signature(multisig: &MultisigSignature) -> Self {
+        let (signed, pending): (Vec<_>, Vec<_>) = multisig
+            .subsignatures
+            .iter()
+            .partition(|subsig| subsig.signature.is_some());
+        Self {
+            threshold: multisig.threshold,
+            signed: signed
+                .into_iter()
+                .map(|subsig| subsig.address.clone())
+                .collect(),
+            pending: pending
+                .into_iter()
+                .map(|subsig| subsig.address.clone())
+                .collect(),
+        }
+    }
+
+    /// Returns the number of additional subsignatures needed to meet the threshold.
+    pub fn remaining(&self) -> usize {
+        (self.threshold as usize).saturating_sub(self.signed.len())
+    }
+
+    /// Returns whether the threshold has been met.
+    pub fn is_complete(&self) -> bool {
+        self.remaining() == 0
+    }
+}
+
+impl MultisigAccount {
+    /// Creates a new multisignature account.
+    ///
+    /// # Errors
+    ///
+    /// Returns [`AlgoKitTransactError::InvalidMultisigSignature`] if:
+    /// - `version` is zero,
+    /// - `participants` is empty,
+    /// - `threshold` is zero or greater than the number of participants.
+    pub fn new(
+        version: u8,
+        threshold: u8,
+        participants: Vec<Address>,
+    ) -> Result<Self, AlgoKitTransactError> {
+        Ok(Self {
+            unsigned: MultisigSignature::from_participants(version, threshold, participants)?,
+        })
+    }
+
+    /// Returns the multisig version.
+    pub fn version(&self) -> u8 {
+        self.unsigned.version
+    }
+
+    /// Returns the minimum number of subsignatures required.
+    pub fn threshold(&self) -> u8 {
+        self.unsigned.threshold
+    }
+
+    /// Returns the participant addresses, in signing order.
+    pub fn participants(&self) -> Vec<Address> {
+        self.unsigned.participants()
+    }
+
+    /// Returns the [`Address`] derived from the account's version, threshold, and participants.
+    pub fn address(&self) -> Address {
+        Address::from(self.unsigned_signature())
+    }
+
+    /// Returns a multisignature for this account without any subsignatures.
+    ///
+    /// The multisignature of a delegated logic signature is collected from this as well: each
+    /// participant signs [`program_bytes_to_sign`](Self::program_bytes_to_sign) and the results
+    /// are added with [`MultisigSignature::apply_subsignature`]. Attaching the multisignature to
+    /// the logic signature is left to the caller.
+    pub fn unsigned_signature(&self) -> MultisigSignature {
+        self.unsigned.clone()
+    }
+
+    /// Returns the bytes each participant signs to authorize a transaction.
+    pub fn bytes_to_sign(
+        &self,
+        transaction: &Transaction,
+    ) -> Result<Vec<u8>, AlgoKitTransactError> {
+        transaction.encode()
+    }
+
+    /// Returns the bytes each participant signs to delegate this account's authority to a logic
+    /// signature program.
+    pub fn program_bytes_to_sign(program: &[u8]) -> Vec<u8> {
+        [PROGRAM_DOMAIN_SEPARATOR.as_bytes(), program].concat()
+    }
+
+    /// Creates a signed transaction shell with an empty multisignature for this account.
+    ///
+    /// The auth address is set when the transaction sender is not this account, as is the case for
+    /// an account that has been rekeyed to it.
+    pub fn create_unsigned(&self, transaction: Transaction) -> SignedTransaction {
+        let address = self.address();
+        let auth_address = (*transaction.sender() != address).then_some(address);
+        SignedTransaction {
+            transaction,
+            signature: None,
+            auth_address,
+            multisignature: Some(self.unsigned_signature()),
+        }
+    }
+
+    /// Applies a participant's subsignature to a signed transaction.
+    ///
+    /// # Errors
+    ///
+    /// Returns [`AlgoKitTransactError::InvalidMultisigSignature`] if the transaction's multisignature
+    /// does not belong to this account or the address is not a participant.
+    pub fn apply_subsignature(
+        &self,
+        signed_transaction: &SignedTransaction,
+        participant: Address,
+        subsignature: [u8; ALGORAND_SIGNATURE_BYTE_LENGTH],
+    ) -> Result<SignedTransaction, AlgoKitTransactError> {
+        let multisig = self
+            .multisignature(signed_transaction)?
+            .apply_subsignature(participant, subsignature)?;
+        Ok(SignedTransaction {
+            multisignature: Some(multisig),
+            ..signed_transaction.clone()
+        })
+    }
+
+    /// Merges copies of the same transaction signed by different participants.
+    ///
+    /// # Errors
+    ///
+    /// Returns [`AlgoKitTransactError::InvalidMultisigSignature`] if the copies are of different
+    /// transactions or their multisignatures do not belong to this account.
+    pub fn merge(
+        &self,
+        signed_transaction: &SignedTransaction,
+        other: &SignedTransaction,
+    ) -> Result<SignedTransaction, AlgoKitTransactError> {
+        if signed_transaction.transaction != other.transaction {
+            return Err(AlgoKitTransactError::InvalidMultisigSignature {
+                err_msg: "Cannot merge signatures of different transactions".to_string(),
+            });
+        }
+        let multisig = self
+            .multisignature(signed_transaction)?
+            .merge(self.multisignature(other)?)?;
+        Ok(SignedTransaction {
+            multisignature: Some(multisig),
+            ..signed_transaction.clone()
+        })
+    }
+
+    /// Reports which participants have signed a transaction and how many subsignatures remain.
+    ///
+    /// # Errors
+    ///
+    /// Returns [`AlgoKitTransactError::InvalidMultisigSignature`] if the transaction's multisignature
+    /// does not belong to this account.
+    pub fn progress(
+        &self,
+        signed_transaction: &SignedTransaction,
+    ) -> Result<MultisigProgress, AlgoKitTransactError> {
+        Ok(MultisigProgress::from_signature(
+            self.multisignature(signed_transaction)?,
+        ))
+    }
+
+    /// Returns the signed transaction once enough participants have signed it to be submitted.
+    ///
+    /// # Errors
+    ///
+    /// Returns [`AlgoKitTransactError::InvalidMultisigSignature`] if the transaction's multisignature
+    /// does not belong to this account or is below the threshold.
+    pub fn finalize(
+        &self,
+        signed_transaction: &SignedTransaction,
+    ) -> Result<SignedTransaction, AlgoKitTransactError> {
+        let progress = self.progress(signed_transaction)?;
+        if !progress.is_complete() {
+            return Err(AlgoKitTransactError::InvalidMultisigSignature {
+                err_msg: format!(
+                    "Multisig requires {} subsignatures but has {}",
+                    progress.threshold,
+                    progress.signed.len()
+                ),
+            });
+        }
+        Ok(signed_transaction.clone())
+    }
+
+    fn multisignature<'a>(
+        &self,
+        signed_transaction: &'a SignedTransaction,
+    ) -> Result<&'a MultisigSignature, AlgoKitTransactError> {
+        let multisig = signed_transaction.multisignature.as_ref().ok_or_else(|| {
+            AlgoKitTransactError::InvalidMultisigSignature {
+                err_msg: "Transaction has no multisig signature".to_string(),
+            }
+        })?;
+        if multisig.version != self.unsigned.version
+            || multisig.threshold != self.unsigned.threshold
+            || multisig.participants() != self.participants()
+        {
+            return Err(AlgoKitTransactError::InvalidMultisigSignature {
+                err_msg: "Multisig signature does not belong to this account".to_string(),
+            });
+        }
+        Ok(multisig)
+    }
+}
+
+impl From<MultisigSignature> for MultisigAccount {
+    /// Converts a [`MultisigSignature`] into the [`MultisigAccount`] it signs for.
+    fn from(multisig: MultisigSignature) -> Self {
+        Self {
+            unsigned: MultisigSignature {
+                subsignatures: multisig
+                    .subsignatures
+                    .into_iter()
+                    .map(|subsig| MultisigSubsignature {
+                        signature: None,
+                        ..subsig
+                    })
+                    .collect(),
+                ..multisig
+            },
+        }
+    }
+}
+
+impl From<MultisigAccount> for Address {
+    /// Converts a [`MultisigAccount`] into its derived [`Address`].
+    fn from(account: MultisigAccount) -> Address {
+        account.address()
+    }
+}
+
+impl Display for MultisigAccount {
+    /// Formats the [`MultisigAccount`] as a base32-encoded Algorand address string.
+    fn fmt(&self, f: &mut Formatter<'_>) -> FmtResult {
+        write!(f, "{}", self.address().as_str())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_utils::{AccountMother, TransactionMother};
+    use ed25519_dalek::{Signer, SigningKey};
+
+    fn signer(seed: u8) -> (Address, SigningKey) {
+        let key = SigningKey::from_bytes(&[seed; 32]);
+        (Address(key.verifying_key().to_bytes()), key)
+    }
+
+    #[test]
+    fn test_derives_same_address_as_multisig_signature() {
+        let msig = AccountMother::msig();
+        let account = MultisigAccount::from(msig.clone());
+        assert_eq!(account.address(), Address::from(msig.clone()));
+        assert_eq!(account.to_string(), msig.to_string());
+        assert_eq!(account.unsigned_signature(), msig);
+        assert_eq!(account.version(), msig.version);
+        assert_eq!(account.threshold(), msig.threshold);
+
+        let (participant, key) = signer(1);
+        let signed = MultisigSignature::from_participants(1, 1, vec![participant.clone()])
+            .unwrap()
+            .apply_subsignature(participant, key.sign(b"data").to_bytes())
+            .unwrap();
+        let from_signed = MultisigAccount::from(signed.clone());
+        assert_eq!(from_signed.address(), Address::from(signed));
+        assert!(
+            from_signed
+                .unsigned_signature()
+                .subsignatures
+                .iter()
+                .all(|subsig| subsig.signature.is_none())
+        );
+
+        assert!(MultisigAccount::new(1, 3, account.participants()).is_err());
+        assert!(MultisigAccount::new(0, 1, account.participants()).is_err());
+    }
+
+    #[test]
+    fn test_collects_subsignatures_across_sessions() {
+        let signers = [signer(1), signer(2), signer(3)];
+        let account =
+            MultisigAccount::new(1, 2, signers.iter().map(|(a, _)| a.clone()).collect()).unwrap();
+        let transaction = TransactionMother::simple_payment()
+            .header(
+                crate::test_utils::TransactionHeaderMother::simple_testnet()
+                    .sender(account.address())
+                    .build()
+                    .unwrap(),
+            )
+            .build()
+            .unwrap();
+
+        let unsigned = account.create_unsigned(transaction.clone());
+        assert_eq!(unsigned.auth_address, None);
+        assert!(account.finalize(&unsigned).is_err());
+
+        let bytes = account.bytes_to_sign(&transaction).unwrap();
+        let sign = |index: usize| signers[index].1.sign(&bytes).to_bytes();
+        let first = account
+            .apply_subsignature(&unsigned, signers[0].0.clone(), sign(0))
+            .unwrap();
+        let third = account
+            .apply_subsignature(&unsigned, signers[2].0.clone(), sign(2))
+            .unwrap();
+        let progress = account.progress(&first).unwrap();
+        assert_eq!(progress.signed, vec![signers[0].0.clone()]);
+        assert_eq!(progress.remaining(), 1);
+
+        let merged = account.merge(&first, &third).unwrap();
+        let progress = account.progress(&merged).unwrap();
+        assert!(progress.is_complete());
+        assert_eq!(progress.pending, vec![signers[1].0.clone()]);
+        let finalized = account.finalize(&merged).unwrap();
+        let decoded = SignedTransaction::decode(&finalized.encode().unwrap()).unwrap();
+        assert_eq!(decoded, finalized);
+
+        let stranger = signer(4);
+        assert!(
+            account
+                .apply_subsignature(&unsigned, stranger.0, sign(0))
+                .is_err()
+        );
+    }
+
+    #[test]
+    fn test_signs_for_rekeyed_accounts_and_logic_sigs() {
+        let account = MultisigAccount::from(AccountMother::msig());
+        let unsigned =
+            account.create_unsigned(TransactionMother::simple_payment().build().unwrap());
+        assert_eq!(unsigned.auth_address, Some(account.address()));
+
+        let other = MultisigAccount::new(1, 1, account.participants()).unwrap();
+        assert!(other.progress(&unsigned).is_err());
+
+        assert_eq!(
+            MultisigAccount::program_bytes_to_sign(&[1, 32, 1, 1, 34]),
+            b"Program\x01\x20\x01\x01\x22".to_vec()
+        );
+    }
+}
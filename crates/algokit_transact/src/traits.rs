@@ -4,7 +4,9 @@
 //! Algorand data structures and for calculating transaction identifiers.
 
 use crate::Transaction;
+use crate::consensus::ConsensusParams;
 use crate::error::AlgoKitTransactError;
+use crate::transactions::GroupValidationError;
 use crate::utils::sort_msgpack_value;
 use crate::{constants::HASH_BYTES_LENGTH, utils::hash};
 use serde::{Deserialize, Serialize};
@@ -147,10 +149,34 @@ pub trait Transactions: Sized {
     /// # Returns
     /// A result containing the transactions with group assign or an error if grouping fails.
     fn assign_group(self) -> Result<Vec<Transaction>, AlgoKitTransactError>;
+
+    /// Validates the supplied transactions as a single atomic group.
+    ///
+    /// # Returns
+    /// `Ok(())` if the group is valid, otherwise every violation found along with the
+    /// indices of the offending transactions.
+    fn validate_group(self) -> Result<(), Vec<GroupValidationError>>;
+
+    /// Validates the supplied transactions as a single atomic group against the limits of
+    /// the supplied consensus parameters.
+    ///
+    /// # Returns
+    /// `Ok(())` if the group is valid, otherwise every violation found along with the
+    /// indices of the offending transactions.
+    fn validate_group_with_params(
+        self,
+        params: &ConsensusParams,
+    ) -> Result<(), Vec<GroupValidationError>>;
 }
 
 pub trait Validate {
+    /// Validates against the limits of the latest known consensus parameters.
     fn validate(&self) -> Result<(), Vec<String>> {
+        self.validate_with_params(&ConsensusParams::default())
+    }
+
+    /// Validates against the limits of the supplied consensus parameters.
+    fn validate_with_params(&self, _params: &ConsensusParams) -> Result<(), Vec<String>> {
         Ok(())
     }
 }
@@ -0,0 +1,593 @@
+//! Reading and writing of `goal` compatible transaction files.
+//!
+//! `goal clerk` stores transactions in `.txn`, `.stxn` and `.tx` files, which are simply
+//! concatenated MessagePack objects without any framing. Unsigned transactions are written as
+//! signed transaction objects that only contain the `txn` field, so a single file can mix
+//! signed and unsigned content.
+//!
+//! Logic signatures are not modelled by this crate, so transactions authorized by one are
+//! kept as their original encoding and written back byte for byte.
+//!
+//! The [`TransactionFileReader`] decodes one object at a time from any [`Read`] source, so
+//! arbitrarily large files can be processed without loading them into memory, and the
+//! [`TransactionFileWriter`] appends objects in the same format.
+
+use crate::address::Address;
+use crate::error::AlgoKitTransactError;
+use crate::traits::AlgorandMsgpack;
+use crate::transactions::{SignedTransaction, Transaction};
+use std::fs::File;
+use std::io::{BufRead, BufReader, BufWriter, Read, Write};
+use std::path::Path;
+
+/// A single object read from or written to a transaction file.
+#[derive(Debug, PartialEq, Clone)]
+pub enum TransactionFileEntry {
+    /// A transaction without any authorization.
+    Transaction(Transaction),
+    /// A transaction with a signature, multisignature or auth address.
+    SignedTransaction(SignedTransaction),
+    /// A transaction authorized by a logic signature.
+    LogicSigTransaction(LogicSigTransaction),
+}
+
+/// A signed transaction authorized by a logic signature, kept in its original encoding.
+#[derive(Debug, PartialEq, Clone)]
+pub struct LogicSigTransaction {
+    signed_transaction: SignedTransaction,
+    encoded: Vec<u8>,
+}
+
+impl LogicSigTransaction {
+    /// Decodes a msgpack encoded signed transaction that contains an `lsig` field.
+    ///
+    /// # Errors
+    ///
+    /// Returns [`AlgoKitTransactError::InputError`] if the object has no `lsig` field, or a
+    /// decoding error if it is not a valid signed transaction.
+    pub fn decode(bytes: &[u8]) -> Result<Self, AlgoKitTransactError> {
+        let value = rmpv::decode::read_value(&mut &bytes[..])?;
+        if !map_keys(&value).is_some_and(|keys| keys.contains(&"lsig")) {
+            return Err(AlgoKitTransactError::InputError {
+                err_msg: "Signed transaction has no logic signature".to_string(),
+            });
+        }
+        Self::from_encoded(bytes.to_vec())
+    }
+
+    fn from_encoded(encoded: Vec<u8>) -> Result<Self, AlgoKitTransactError> {
+        Ok(Self {
+            signed_transaction: SignedTransaction::decode(&encoded)?,
+            encoded,
+        })
+    }
+
+    /// Returns the transaction authorized by the logic signature.
+    pub fn transaction(&self) -> &Transaction {
+        &self.signed_transaction.transaction
+    }
+
+    /// Returns the auth address, set when a delegated logic signature signs for a rekeyed account.
+    pub fn auth_address(&self) -> Option<&Address> {
+        self.signed_transaction.auth_address.as_ref()
+    }
+
+    /// Returns the signed transaction exactly as it was encoded, including the logic signature.
+    pub fn encoded(&self) -> &[u8] {
+        &self.encoded
+    }
+}
+
+impl TransactionFileEntry {
+    /// Returns the transaction contained in the entry.
+    pub fn transaction(&self) -> &Transaction {
+        match self {
+            TransactionFileEntry::Transaction(tx) => tx,
+            TransactionFileEntry::SignedTransaction(stx) => &stx.transaction,
+            TransactionFileEntry::LogicSigTransaction(lstx) => lstx.transaction(),
+        }
+    }
+
+    /// Returns whether the entry carries any authorization.
+    pub fn is_signed(&self) -> bool {
+        !matches!(self, TransactionFileEntry::Transaction(_))
+    }
+
+    /// Converts the entry into a signed transaction, leaving the authorization empty for
+    /// unsigned entries.
+    ///
+    /// [`SignedTransaction`] cannot hold a logic signature, so for logic signature entries only
+    /// the transaction and auth address are kept; use [`LogicSigTransaction::encoded`] to keep
+    /// the full object.
+    pub fn into_signed_transaction(self) -> SignedTransaction {
+        match self {
+            TransactionFileEntry::Transaction(transaction) => SignedTransaction {
+                transaction,
+                signature: None,
+                auth_address: None,
+                multisignature: None,
+            },
+            TransactionFileEntry::SignedTransaction(stx) => stx,
+            TransactionFileEntry::LogicSigTransaction(lstx) => lstx.signed_transaction,
+        }
+    }
+}
+
+impl From<Transaction> for TransactionFileEntry {
+    fn from(tx: Transaction) -> Self {
+        TransactionFileEntry::Transaction(tx)
+    }
+}
+
+impl From<SignedTransaction> for TransactionFileEntry {
+    fn from(stx: SignedTransaction) -> Self {
+        TransactionFileEntry::SignedTransaction(stx)
+    }
+}
+
+impl From<LogicSigTransaction> for TransactionFileEntry {
+    fn from(lstx: LogicSigTransaction) -> Self {
+        TransactionFileEntry::LogicSigTransaction(lstx)
+    }
+}
+
+/// A streaming reader over the concatenated MessagePack objects of a transaction file.
+///
+/// The reader is an [`Iterator`] that yields one entry per object. Both `goal` style signed
+/// transaction objects and bare transaction objects are accepted. Once an error is returned,
+/// for example because the file ends with trailing garbage or a truncated object, the
+/// iterator is exhausted.
+pub struct TransactionFileReader<R: Read> {
+    reader: BufReader<R>,
+    offset: u64,
+    failed: bool,
+}
+
+impl TransactionFileReader<File> {
+    /// Opens the transaction file at `path` for reading.
+    pub fn open(path: impl AsRef<Path>) -> Result<Self, AlgoKitTransactError> {
+        let file = File::open(path).map_err(|e| io_error(0, e))?;
+        Ok(Self::new(file))
+    }
+}
+
+impl<R: Read> TransactionFileReader<R> {
+    /// Creates a reader over the supplied source.
+    pub fn new(reader: R) -> Self {
+        Self {
+            reader: BufReader::new(reader),
+            offset: 0,
+            failed: false,
+        }
+    }
+
+    /// Returns the byte offset of the next object to be read.
+    pub fn offset(&self) -> u64 {
+        self.offset
+    }
+
+    /// Reads the next entry, returning `None` at the end of the file.
+    pub fn read_entry(&mut self) -> Option<Result<TransactionFileEntry, AlgoKitTransactError>> {
+        if self.failed {
+            return None;
+        }
+
+        let result = self.read_next().transpose();
+        if matches!(result, Some(Err(_))) {
+            self.failed = true;
+        }
+        result
+    }
+
+    fn read_next(&mut self) -> Result<Option<TransactionFileEntry>, AlgoKitTransactError> {
+        let start = self.offset;
+        let at_end = self
+            .reader
+            .fill_buf()
+            .map_err(|e| io_error(start, e))?
+            .is_empty();
+        if at_end {
+            return Ok(None);
+        }
+
+        let mut recorder = RecordingReader {
+            inner: &mut self.reader,
+            bytes: Vec::new(),
+        };
+        let value = rmpv::decode::read_value(&mut recorder).map_err(|e| {
+            file_error(
+                start,
+                format!("expected a msgpack encoded transaction: {}", e),
+            )
+        })?;
+        let bytes = recorder.bytes;
+        self.offset += bytes.len() as u64;
+
+        let Some(keys) = map_keys(&value) else {
+            return Err(file_error(
+                start,
+                "expected a msgpack map but found another value".to_string(),
+            ));
+        };
+
+        let entry = if keys.contains(&"txn") {
+            if keys.contains(&"lsig") {
+                return Ok(Some(TransactionFileEntry::LogicSigTransaction(
+                    LogicSigTransaction::from_encoded(bytes).map_err(|e| decode_error(start, e))?,
+                )));
+            }
+            let stx = SignedTransaction::decode(&bytes).map_err(|e| decode_error(start, e))?;
+            if keys.iter().all(|k| *k == "txn") {
+                TransactionFileEntry::Transaction(stx.transaction)
+            } else {
+                TransactionFileEntry::SignedTransaction(stx)
+            }
+        } else if keys.contains(&"type") {
+            TransactionFileEntry::Transaction(
+                Transaction::decode(&bytes).map_err(|e| decode_error(start, e))?,
+            )
+        } else {
+            return Err(file_error(
+                start,
+                "object is neither a transaction nor a signed transaction".to_string(),
+            ));
+        };
+
+        Ok(Some(entry))
+    }
+}
+
+impl<R: Read> Iterator for TransactionFileReader<R> {
+    type Item = Result<TransactionFileEntry, AlgoKitTransactError>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        self.read_entry()
+    }
+}
+
+/// A streaming writer that appends entries to a transaction file.
+///
+/// Unsigned transactions are written as signed transaction objects that only contain the
+/// `txn` field, which is the format `goal clerk` expects.
+pub struct TransactionFileWriter<W: Write> {
+    writer: W,
+    offset: u64,
+}
+
+impl TransactionFileWriter<BufWriter<File>> {
+    /// Creates (or truncates) the transaction file at `path` for writing.
+    pub fn create(path: impl AsRef<Path>) -> Result<Self, AlgoKitTransactError> {
+        let file = File::create(path).map_err(|e| write_error(0, e))?;
+        Ok(Self::new(BufWriter::new(file)))
+    }
+}
+
+impl<W: Write> TransactionFileWriter<W> {
+    /// Creates a writer over the supplied destination.
+    pub fn new(writer: W) -> Self {
+        Self { writer, offset: 0 }
+    }
+
+    /// Returns the number of bytes written so far.
+    pub fn offset(&self) -> u64 {
+        self.offset
+    }
+
+    /// Writes an unsigned transaction.
+    pub fn write_transaction(&mut self, tx: &Transaction) -> Result<(), AlgoKitTransactError> {
+        self.write_entry(&TransactionFileEntry::Transaction(tx.clone()))
+    }
+
+    /// Writes a signed transaction.
+    pub fn write_signed_transaction(
+        &mut self,
+        stx: &SignedTransaction,
+    ) -> Result<(), AlgoKitTransactError> {
+        self.write_bytes(&stx.encode_raw()?)
+    }
+
+    /// Writes a single entry.
+    pub fn write_entry(
+        &mut self,
+        entry: &TransactionFileEntry,
+    ) -> Result<(), AlgoKitTransactError> {
+        match entry {
+            TransactionFileEntry::Transaction(_) => {
+                self.write_bytes(&entry.clone().into_signed_transaction().encode_raw()?)
+            }
+            TransactionFileEntry::SignedTransaction(stx) => self.write_signed_transaction(stx),
+            TransactionFileEntry::LogicSigTransaction(lstx) => self.write_bytes(lstx.encoded()),
+        }
+    }
+
+    /// Flushes any buffered bytes to the destination.
+    pub fn flush(&mut self) -> Result<(), AlgoKitTransactError> {
+        self.writer.flush().map_err(|e| write_error(self.offset, e))
+    }
+
+    /// Flushes and returns the underlying destination.
+    pub fn into_inner(mut self) -> Result<W, AlgoKitTransactError> {
+        self.flush()?;
+        Ok(self.writer)
+    }
+
+    fn write_bytes(&mut self, bytes: &[u8]) -> Result<(), AlgoKitTransactError> {
+        self.writer
+            .write_all(bytes)
+            .map_err(|e| write_error(self.offset, e))?;
+        self.offset += bytes.len() as u64;
+        Ok(())
+    }
+}
+
+/// Records every byte read from the inner reader so that the exact encoding of an object can
+/// be decoded once its boundaries are known.
+struct RecordingReader<'a, R: Read> {
+    inner: &'a mut R,
+    bytes: Vec<u8>,
+}
+
+impl<R: Read> Read for RecordingReader<'_, R> {
+    fn read(&mut self, buf: &mut [u8]) -> std::io::Result<usize> {
+        let read = self.inner.read(buf)?;
+        self.bytes.extend_from_slice(&buf[..read]);
+        Ok(read)
+    }
+}
+
+fn file_error(offset: u64, err_msg: String) -> AlgoKitTransactError {
+    AlgoKitTransactError::TransactionFileError { offset, err_msg }
+}
+
+fn io_error(offset: u64, e: std::io::Error) -> AlgoKitTransactError {
+    file_error(offset, e.to_string())
+}
+
+fn write_error(offset: u64, e: std::io::Error) -> AlgoKitTransactError {
+    AlgoKitTransactError::TransactionFileWriteError {
+        offset,
+        err_msg: e.to_string(),
+    }
+}
+
+/// Returns the string keys of a msgpack map, or `None` if the value is not a map.
+fn map_keys(value: &rmpv::Value) -> Option<Vec<&str>> {
+    match value {
+        rmpv::Value::Map(map) => Some(map.iter().filter_map(|(k, _)| k.as_str()).collect()),
+        _ => None,
+    }
+}
+
+fn decode_error(offset: u64, e: AlgoKitTransactError) -> AlgoKitTransactError {
+    file_error(offset, e.to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_utils::{AccountMother, TransactionMother};
+    use std::io::Cursor;
+
+    fn signed(tx: Transaction) -> SignedTransaction {
+        SignedTransaction {
+            transaction: tx,
+            signature: Some([1; 64]),
+            auth_address: None,
+            multisignature: None,
+        }
+    }
+
+    fn write(entries: &[TransactionFileEntry]) -> Vec<u8> {
+        let mut writer = TransactionFileWriter::new(Vec::new());
+        for entry in entries {
+            writer.write_entry(entry).unwrap();
+        }
+        writer.into_inner().unwrap()
+    }
+
+    #[test]
+    fn test_round_trip_mixed_entries() {
+        let payment = TransactionMother::simple_payment().build().unwrap();
+        let transfer = TransactionMother::simple_asset_transfer().build().unwrap();
+        let rekeyed = SignedTransaction {
+            auth_address: Some(AccountMother::neil().address()),
+            ..signed(transfer.clone())
+        };
+        let entries = vec![
+            TransactionFileEntry::from(payment.clone()),
+            TransactionFileEntry::from(signed(transfer.clone())),
+            TransactionFileEntry::from(rekeyed),
+        ];
+
+        let bytes = write(&entries);
+        let read: Vec<_> = TransactionFileReader::new(Cursor::new(bytes))
+            .collect::<Result<_, _>>()
+            .unwrap();
+
+        assert_eq!(read, entries);
+        assert!(!read[0].is_signed());
+        assert!(read[1].is_signed());
+    }
+
+    #[test]
+    fn test_unsigned_entries_use_goal_format() {
+        let payment = TransactionMother::simple_payment().build().unwrap();
+
+        let bytes = write(&[payment.clone().into()]);
+
+        assert_eq!(
+            SignedTransaction::decode(&bytes).unwrap(),
+            TransactionFileEntry::from(payment).into_signed_transaction()
+        );
+    }
+
+    #[test]
+    fn test_reads_bare_transactions() {
+        let payment = TransactionMother::simple_payment().build().unwrap();
+        let mut bytes = payment.encode_raw().unwrap();
+        bytes.extend(payment.encode_raw().unwrap());
+
+        let read: Vec<_> = TransactionFileReader::new(bytes.as_slice())
+            .collect::<Result<_, _>>()
+            .unwrap();
+
+        assert_eq!(read, vec![payment.clone().into(), payment.into()]);
+    }
+
+    #[test]
+    fn test_trailing_garbage_reports_offset() {
+        let payment = TransactionMother::simple_payment().build().unwrap();
+        let mut bytes = write(&[payment.clone().into(), payment.into()]);
+        let garbage_offset = bytes.len() as u64;
+        bytes.push(0xc1);
+
+        let mut reader = TransactionFileReader::new(bytes.as_slice());
+        assert!(reader.next().unwrap().is_ok());
+        assert!(reader.next().unwrap().is_ok());
+        assert_eq!(reader.offset(), garbage_offset);
+
+        let error = reader.next().unwrap().unwrap_err();
+        assert!(matches!(
+            error,
+            AlgoKitTransactError::TransactionFileError { offset, .. } if offset == garbage_offset
+        ));
+        assert!(reader.next().is_none());
+    }
+
+    #[test]
+    fn test_truncated_object_reports_offset() {
+        let payment = TransactionMother::simple_payment().build().unwrap();
+        let bytes = write(&[payment.clone().into(), payment.into()]);
+        let second_offset = (bytes.len() / 2) as u64;
+
+        let results: Vec<_> = TransactionFileReader::new(&bytes[..bytes.len() - 3]).collect();
+
+        assert_eq!(results.len(), 2);
+        assert!(results[0].is_ok());
+        assert!(matches!(
+            results[1],
+            Err(AlgoKitTransactError::TransactionFileError { offset, .. }) if offset == second_offset
+        ));
+    }
+
+    #[test]
+    fn test_rejects_non_transaction_objects() {
+        let mut bytes = Vec::new();
+        rmpv::encode::write_value(&mut bytes, &rmpv::Value::from(42)).unwrap();
+
+        let error = TransactionFileReader::new(bytes.as_slice())
+            .next()
+            .unwrap()
+            .unwrap_err();
+
+        assert_eq!(
+            error.to_string(),
+            "Transaction file error at byte offset 0: expected a msgpack map but found another value"
+        );
+    }
+
+    fn logic_sig_transaction(tx: &Transaction) -> LogicSigTransaction {
+        let txn = rmpv::decode::read_value(&mut tx.encode_raw().unwrap().as_slice()).unwrap();
+        let lsig = rmpv::Value::Map(vec![(
+            rmpv::Value::from("l"),
+            rmpv::Value::Binary(vec![0x0a, 0x81, 0x01]),
+        )]);
+        let mut bytes = Vec::new();
+        rmpv::encode::write_value(
+            &mut bytes,
+            &rmpv::Value::Map(vec![
+                (rmpv::Value::from("lsig"), lsig),
+                (rmpv::Value::from("txn"), txn),
+            ]),
+        )
+        .unwrap();
+        LogicSigTransaction::decode(&bytes).unwrap()
+    }
+
+    #[test]
+    fn test_round_trip_logic_sig_entries() {
+        let payment = TransactionMother::simple_payment().build().unwrap();
+        let lstx = logic_sig_transaction(&payment);
+        let entries = vec![
+            TransactionFileEntry::from(lstx.clone()),
+            TransactionFileEntry::from(payment.clone()),
+        ];
+
+        let bytes = write(&entries);
+        assert!(bytes.starts_with(lstx.encoded()));
+        let read: Vec<_> = TransactionFileReader::new(bytes.as_slice())
+            .collect::<Result<_, _>>()
+            .unwrap();
+
+        assert_eq!(read, entries);
+        assert!(read[0].is_signed());
+        assert_eq!(read[0].transaction(), &payment);
+        assert!(LogicSigTransaction::decode(&write(&[payment.into()])).is_err());
+    }
+
+    struct FailingWriter;
+
+    impl Write for FailingWriter {
+        fn write(&mut self, _buf: &[u8]) -> std::io::Result<usize> {
+            Err(std::io::Error::other("disk full"))
+        }
+
+        fn flush(&mut self) -> std::io::Result<()> {
+            Ok(())
+        }
+    }
+
+    #[test]
+    fn test_write_failures_are_write_errors() {
+        let payment = TransactionMother::simple_payment().build().unwrap();
+
+        let error = TransactionFileWriter::new(FailingWriter)
+            .write_transaction(&payment)
+            .unwrap_err();
+
+        assert_eq!(
+            error.to_string(),
+            "Transaction file write error at byte offset 0: disk full"
+        );
+    }
+
+    /// Produces `remaining` copies of an object without holding them all in memory.
+    struct RepeatedObjects {
+        object: Vec<u8>,
+        position: usize,
+        remaining: usize,
+    }
+
+    impl Read for RepeatedObjects {
+        fn read(&mut self, buf: &mut [u8]) -> std::io::Result<usize> {
+            if self.remaining == 0 {
+                return Ok(0);
+            }
+            let read = (&self.object[self.position..]).read(buf)?;
+            self.position += read;
+            if self.position == self.object.len() {
+                self.position = 0;
+                self.remaining -= 1;
+            }
+            Ok(read)
+        }
+    }
+
+    #[test]
+    fn test_streams_many_entries() {
+        let payment = TransactionMother::simple_payment().build().unwrap();
+        let object = write(&[payment.into()]);
+        let count = 10_000;
+        let object_len = object.len();
+
+        let mut reader = TransactionFileReader::new(RepeatedObjects {
+            object,
+            position: 0,
+            remaining: count,
+        });
+        let read = reader.by_ref().filter(|entry| entry.is_ok()).count();
+
+        assert_eq!(read, count);
+        assert_eq!(reader.offset(), (object_len * count) as u64);
+    }
+}
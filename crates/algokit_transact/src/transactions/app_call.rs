@@ -3,14 +3,11 @@
 //! This module provides functionality for creating and managing app transactions,
 //! which are used to create, update, delete and call Algorand Smart Contracts (Applications).
 
+use crate::consensus::ConsensusParams;
 use crate::traits::{MsgPackEmpty, Validate};
 use crate::transactions::common::{TransactionHeader, TransactionValidationError};
 use crate::utils::{is_empty_struct_opt, is_empty_vec_opt, is_zero, is_zero_opt};
-use crate::{
-    Address, MAX_ACCOUNT_REFERENCES, MAX_APP_ARGS, MAX_APP_REFERENCES, MAX_ARGS_SIZE,
-    MAX_ASSET_REFERENCES, MAX_BOX_REFERENCES, MAX_EXTRA_PROGRAM_PAGES, MAX_GLOBAL_STATE_KEYS,
-    MAX_LOCAL_STATE_KEYS, MAX_OVERALL_REFERENCES, PROGRAM_PAGE_SIZE, Transaction,
-};
+use crate::{Address, Transaction};
 use derive_builder::Builder;
 use serde::{Deserialize, Serialize};
 use serde_repr::{Deserialize_repr, Serialize_repr};
@@ -400,23 +397,31 @@ impl AppCallTransactionFields {
 
     /// Validates fields specific to app creation.
     pub fn validate_for_create(&self) -> Result<(), Vec<TransactionValidationError>> {
+        self.validate_for_create_with_params(&ConsensusParams::default())
+    }
+
+    /// Validates fields specific to app creation, against the limits of the given consensus parameters.
+    pub fn validate_for_create_with_params(
+        &self,
+        params: &ConsensusParams,
+    ) -> Result<(), Vec<TransactionValidationError>> {
         let mut errors = Vec::new();
 
         self.validate_programs_required(&mut errors);
 
         // Validate extra program pages
         if let Some(extra_pages) = self.extra_program_pages {
-            if extra_pages > MAX_EXTRA_PROGRAM_PAGES {
+            if extra_pages > params.max_extra_app_program_pages {
                 errors.push(TransactionValidationError::FieldTooLong {
                     field: FIELD_EXTRA_PROGRAM_PAGES.to_string(),
                     actual: extra_pages as usize,
-                    max: MAX_EXTRA_PROGRAM_PAGES as usize,
+                    max: params.max_extra_app_program_pages as usize,
                     unit: "pages".to_string(),
                 });
             }
         }
 
-        let max_program_size = self.calculate_max_program_size();
+        let max_program_size = params.max_program_size(self.extra_program_pages.unwrap_or(0));
 
         // Validate approval program size
         if let Some(ref approval_program) = self.approval_program {
@@ -459,28 +464,32 @@ impl AppCallTransactionFields {
 
         // Validate state schemas
         if let Some(ref global_schema) = self.global_state_schema {
-            if global_schema.num_uints + global_schema.num_byte_slices > MAX_GLOBAL_STATE_KEYS {
+            if global_schema.num_uints + global_schema.num_byte_slices
+                > params.max_global_schema_entries
+            {
                 errors.push(TransactionValidationError::FieldTooLong {
                     field: FIELD_GLOBAL_STATE_SCHEMA.to_string(),
                     actual: (global_schema.num_uints + global_schema.num_byte_slices) as usize,
-                    max: MAX_GLOBAL_STATE_KEYS as usize,
+                    max: params.max_global_schema_entries as usize,
                     unit: "keys".to_string(),
                 });
             }
         }
 
         if let Some(ref local_schema) = self.local_state_schema {
-            if local_schema.num_uints + local_schema.num_byte_slices > MAX_LOCAL_STATE_KEYS {
+            if local_schema.num_uints + local_schema.num_byte_slices
+                > params.max_local_schema_entries
+            {
                 errors.push(TransactionValidationError::FieldTooLong {
                     field: FIELD_LOCAL_STATE_SCHEMA.to_string(),
                     actual: (local_schema.num_uints + local_schema.num_byte_slices) as usize,
-                    max: MAX_LOCAL_STATE_KEYS as usize,
+                    max: params.max_local_schema_entries as usize,
                     unit: "keys".to_string(),
                 });
             }
         }
 
-        self.validate_common_fields(&mut errors);
+        self.validate_common_fields(params, &mut errors);
 
         match errors.is_empty() {
             true => Ok(()),
@@ -490,13 +499,21 @@ impl AppCallTransactionFields {
 
     /// Validates fields specific to app update.
     pub fn validate_for_update(&self) -> Result<(), Vec<TransactionValidationError>> {
+        self.validate_for_update_with_params(&ConsensusParams::default())
+    }
+
+    /// Validates fields specific to app update, against the limits of the given consensus parameters.
+    pub fn validate_for_update_with_params(
+        &self,
+        params: &ConsensusParams,
+    ) -> Result<(), Vec<TransactionValidationError>> {
         let mut errors = Vec::new();
 
         self.validate_app_id_not_zero(&mut errors);
         self.validate_programs_required(&mut errors);
         // We can't validate the extra program pages on update, as we don't know what the initial create value was.
         self.validate_immutable_fields_not_set(&mut errors);
-        self.validate_common_fields(&mut errors);
+        self.validate_common_fields(params, &mut errors);
 
         match errors.is_empty() {
             true => Ok(()),
@@ -506,12 +523,20 @@ impl AppCallTransactionFields {
 
     /// Validates fields for app call (no-op), opt-in, close-out, clear-state operations.
     pub fn validate_for_call(&self) -> Result<(), Vec<TransactionValidationError>> {
+        self.validate_for_call_with_params(&ConsensusParams::default())
+    }
+
+    /// Validates fields for app call (no-op), opt-in, close-out, clear-state operations, against the limits of the given consensus parameters.
+    pub fn validate_for_call_with_params(
+        &self,
+        params: &ConsensusParams,
+    ) -> Result<(), Vec<TransactionValidationError>> {
         let mut errors = Vec::new();
 
         self.validate_app_id_not_zero(&mut errors);
         self.validate_immutable_fields_not_set(&mut errors);
 
-        self.validate_common_fields(&mut errors);
+        self.validate_common_fields(params, &mut errors);
 
         match errors.is_empty() {
             true => Ok(()),
@@ -521,12 +546,20 @@ impl AppCallTransactionFields {
 
     /// Validates fields for app deletion.
     pub fn validate_for_delete(&self) -> Result<(), Vec<TransactionValidationError>> {
+        self.validate_for_delete_with_params(&ConsensusParams::default())
+    }
+
+    /// Validates fields for app deletion, against the limits of the given consensus parameters.
+    pub fn validate_for_delete_with_params(
+        &self,
+        params: &ConsensusParams,
+    ) -> Result<(), Vec<TransactionValidationError>> {
         let mut errors = Vec::new();
 
         self.validate_app_id_not_zero(&mut errors);
         self.validate_immutable_fields_not_set(&mut errors);
 
-        self.validate_common_fields(&mut errors);
+        self.validate_common_fields(params, &mut errors);
 
         match errors.is_empty() {
             true => Ok(()),
@@ -535,25 +568,29 @@ impl AppCallTransactionFields {
     }
 
     /// Validates common fields that apply to all app call types.
-    fn validate_common_fields(&self, errors: &mut Vec<TransactionValidationError>) {
+    fn validate_common_fields(
+        &self,
+        params: &ConsensusParams,
+        errors: &mut Vec<TransactionValidationError>,
+    ) {
         if let Some(ref args) = self.args {
             // Validate number of args
-            if args.len() > MAX_APP_ARGS {
+            if args.len() > params.max_app_args {
                 errors.push(TransactionValidationError::FieldTooLong {
                     field: FIELD_ARGS.to_string(),
                     actual: args.len(),
-                    max: MAX_APP_ARGS,
+                    max: params.max_app_args,
                     unit: "arguments".to_string(),
                 });
             }
 
             // Validate total size of args
             let total_args_size: usize = args.iter().map(|arg| arg.len()).sum();
-            if total_args_size > MAX_ARGS_SIZE {
+            if total_args_size > params.max_app_total_arg_len {
                 errors.push(TransactionValidationError::FieldTooLong {
                     field: "Args total size".to_string(),
                     actual: total_args_size,
-                    max: MAX_ARGS_SIZE,
+                    max: params.max_app_total_arg_len,
                     unit: "bytes".to_string(),
                 });
             }
@@ -561,11 +598,11 @@ impl AppCallTransactionFields {
 
         // Validate account references
         if let Some(ref account_refs) = self.account_references {
-            if account_refs.len() > MAX_ACCOUNT_REFERENCES {
+            if account_refs.len() > params.max_app_txn_accounts {
                 errors.push(TransactionValidationError::FieldTooLong {
                     field: "Account references".to_string(),
                     actual: account_refs.len(),
-                    max: MAX_ACCOUNT_REFERENCES,
+                    max: params.max_app_txn_accounts,
                     unit: "refs".to_string(),
                 });
             }
@@ -573,11 +610,11 @@ impl AppCallTransactionFields {
 
         // Validate app references
         if let Some(ref app_refs) = self.app_references {
-            if app_refs.len() > MAX_APP_REFERENCES {
+            if app_refs.len() > params.max_app_txn_foreign_apps {
                 errors.push(TransactionValidationError::FieldTooLong {
                     field: "App references".to_string(),
                     actual: app_refs.len(),
-                    max: MAX_APP_REFERENCES,
+                    max: params.max_app_txn_foreign_apps,
                     unit: "refs".to_string(),
                 });
             }
@@ -585,11 +622,11 @@ impl AppCallTransactionFields {
 
         // Validate asset references
         if let Some(ref asset_refs) = self.asset_references {
-            if asset_refs.len() > MAX_ASSET_REFERENCES {
+            if asset_refs.len() > params.max_app_txn_foreign_assets {
                 errors.push(TransactionValidationError::FieldTooLong {
                     field: "Asset references".to_string(),
                     actual: asset_refs.len(),
-                    max: MAX_ASSET_REFERENCES,
+                    max: params.max_app_txn_foreign_assets,
                     unit: "refs".to_string(),
                 });
             }
@@ -597,11 +634,11 @@ impl AppCallTransactionFields {
 
         // Validate box references
         if let Some(ref box_refs) = self.box_references {
-            if box_refs.len() > MAX_BOX_REFERENCES {
+            if box_refs.len() > params.max_app_box_references {
                 errors.push(TransactionValidationError::FieldTooLong {
                     field: "Box references".to_string(),
                     actual: box_refs.len(),
-                    max: MAX_BOX_REFERENCES,
+                    max: params.max_app_box_references,
                     unit: "refs".to_string(),
                 });
             }
@@ -627,21 +664,15 @@ impl AppCallTransactionFields {
             + self.asset_references.as_ref().map_or(0, |v| v.len())
             + self.box_references.as_ref().map_or(0, |v| v.len());
 
-        if total_references > MAX_OVERALL_REFERENCES {
+        if total_references > params.max_app_total_txn_references {
             errors.push(TransactionValidationError::FieldTooLong {
                 field: "Total references".to_string(),
                 actual: total_references,
-                max: MAX_OVERALL_REFERENCES,
+                max: params.max_app_total_txn_references,
                 unit: "refs".to_string(),
             });
         }
     }
-
-    /// Calculates the maximum allowed program size based on extra program pages.
-    fn calculate_max_program_size(&self) -> usize {
-        let extra_pages = self.extra_program_pages.unwrap_or(0) as usize;
-        PROGRAM_PAGE_SIZE + (extra_pages * PROGRAM_PAGE_SIZE)
-    }
 }
 
 impl AppCallTransactionBuilder {
@@ -658,19 +689,23 @@ impl AppCallTransactionBuilder {
 }
 
 impl Validate for AppCallTransactionFields {
-    fn validate(&self) -> Result<(), Vec<String>> {
+    fn validate_with_params(&self, params: &ConsensusParams) -> Result<(), Vec<String>> {
         let result = match (self.app_id, &self.on_complete) {
             // Application creation (app_id = 0)
-            (0, _) => self.validate_for_create(),
+            (0, _) => self.validate_for_create_with_params(params),
 
             // Application update
-            (_, OnApplicationComplete::UpdateApplication) => self.validate_for_update(),
+            (_, OnApplicationComplete::UpdateApplication) => {
+                self.validate_for_update_with_params(params)
+            }
 
             // Application deletion
-            (_, OnApplicationComplete::DeleteApplication) => self.validate_for_delete(),
+            (_, OnApplicationComplete::DeleteApplication) => {
+                self.validate_for_delete_with_params(params)
+            }
 
             // Regular app calls (NoOp, OptIn, CloseOut, ClearState)
-            (_, _) => self.validate_for_call(),
+            (_, _) => self.validate_for_call_with_params(params),
         };
 
         result.map_err(|errors| errors.iter().map(|e| e.to_string()).collect())
@@ -680,11 +715,15 @@ impl Validate for AppCallTransactionFields {
 #[cfg(test)]
 mod tests {
     use super::*;
-    use crate::AlgorandMsgpack;
     use crate::test_utils::{
         AccountMother, AppCallTransactionMother, TestDataMother, TransactionHeaderMother,
     };
     use crate::test_utils::{check_transaction_encoding, check_transaction_id};
+    use crate::{
+        AlgorandMsgpack, MAX_ACCOUNT_REFERENCES, MAX_APP_ARGS, MAX_APP_REFERENCES,
+        MAX_ASSET_REFERENCES, MAX_BOX_REFERENCES, MAX_EXTRA_PROGRAM_PAGES, MAX_GLOBAL_STATE_KEYS,
+        MAX_LOCAL_STATE_KEYS, PROGRAM_PAGE_SIZE,
+    };
 
     #[test]
     fn test_app_create_transaction_encoding() {
@@ -3,6 +3,7 @@
 //! This module provides functionality for creating and managing asset configuration transactions,
 //! which are used to create, reconfige, or destroy Algorand Standard Assets (ASAs).
 
+use crate::consensus::ConsensusParams;
 use crate::traits::Validate;
 use crate::transactions::common::{TransactionHeader, TransactionValidationError};
 use crate::utils::{is_false_opt, is_zero, is_zero_addr_opt, is_zero_opt};
@@ -320,6 +321,14 @@ where
 
 impl AssetConfigTransactionFields {
     pub fn validate_for_creation(&self) -> Result<(), Vec<TransactionValidationError>> {
+        self.validate_for_creation_with_params(&ConsensusParams::default())
+    }
+
+    /// Validates fields for asset creation against the limits of the given consensus parameters.
+    pub fn validate_for_creation_with_params(
+        &self,
+        params: &ConsensusParams,
+    ) -> Result<(), Vec<TransactionValidationError>> {
         let mut errors = Vec::new();
 
         if self.total.is_none() {
@@ -329,44 +338,44 @@ impl AssetConfigTransactionFields {
         }
 
         if let Some(decimals) = self.decimals {
-            if decimals > 19 {
+            if decimals > params.max_asset_decimals {
                 errors.push(TransactionValidationError::FieldTooLong {
                     field: "Decimals".to_string(),
                     actual: decimals as usize,
-                    max: 19,
+                    max: params.max_asset_decimals as usize,
                     unit: "decimal places".to_string(),
                 });
             }
         }
 
         if let Some(ref unit_name) = self.unit_name {
-            if unit_name.len() > 8 {
+            if unit_name.len() > params.max_asset_unit_name_bytes {
                 errors.push(TransactionValidationError::FieldTooLong {
                     field: "Unit name".to_string(),
                     actual: unit_name.len(),
-                    max: 8,
+                    max: params.max_asset_unit_name_bytes,
                     unit: "bytes".to_string(),
                 });
             }
         }
 
         if let Some(ref asset_name) = self.asset_name {
-            if asset_name.len() > 32 {
+            if asset_name.len() > params.max_asset_name_bytes {
                 errors.push(TransactionValidationError::FieldTooLong {
                     field: "Asset name".to_string(),
                     actual: asset_name.len(),
-                    max: 32,
+                    max: params.max_asset_name_bytes,
                     unit: "bytes".to_string(),
                 });
             }
         }
 
         if let Some(ref url) = self.url {
-            if url.len() > 96 {
+            if url.len() > params.max_asset_url_bytes {
                 errors.push(TransactionValidationError::FieldTooLong {
                     field: "Url".to_string(),
                     actual: url.len(),
-                    max: 96,
+                    max: params.max_asset_url_bytes,
                     unit: "bytes".to_string(),
                 });
             }
@@ -444,11 +453,11 @@ impl AssetConfigTransactionBuilder {
 }
 
 impl Validate for AssetConfigTransactionFields {
-    fn validate(&self) -> Result<(), Vec<String>> {
+    fn validate_with_params(&self, params: &ConsensusParams) -> Result<(), Vec<String>> {
         match self.asset_id {
             0 => {
                 // Asset creation
-                self.validate_for_creation()
+                self.validate_for_creation_with_params(params)
                     .map_err(|errors| errors.iter().map(|e| e.to_string()).collect())
             }
             _ => {
@@ -5,6 +5,7 @@
 
 use crate::Transaction;
 use crate::address::Address;
+use crate::consensus::ConsensusParams;
 use crate::traits::Validate;
 use crate::transactions::common::{TransactionHeader, TransactionValidationError};
 use crate::utils::{is_zero, is_zero_addr};
@@ -66,7 +67,7 @@ impl AssetFreezeTransactionBuilder {
 }
 
 impl Validate for AssetFreezeTransactionFields {
-    fn validate(&self) -> Result<(), Vec<String>> {
+    fn validate_with_params(&self, _params: &ConsensusParams) -> Result<(), Vec<String>> {
         let mut errors = Vec::new();
 
         if self.asset_id == 0 {
@@ -2,6 +2,7 @@
 //!
 //! This module provides functionality for creating and managing asset transfer transactions.
 
+use crate::consensus::ConsensusParams;
 use crate::traits::Validate;
 use crate::transactions::common::{TransactionHeader, TransactionValidationError};
 use crate::utils::{is_zero, is_zero_addr, is_zero_addr_opt};
@@ -90,7 +91,7 @@ impl AssetTransferTransactionBuilder {
 }
 
 impl Validate for AssetTransferTransactionFields {
-    fn validate(&self) -> Result<(), Vec<String>> {
+    fn validate_with_params(&self, _params: &ConsensusParams) -> Result<(), Vec<String>> {
         let mut errors = Vec::new();
 
         if self.asset_id == 0 {
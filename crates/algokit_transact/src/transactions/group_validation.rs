@@ -0,0 +1,464 @@
+//! Group-level transaction validation for AlgoKit Core.
+//!
+//! The per-type [`Validate`] implementations only check a single transaction in isolation.
+//! This module checks the rules that apply to a transaction group as a whole, such as the
+//! group ID, shared validity window, network consistency and fee pooling, and reports every
+//! violation together with the indices of the transactions involved.
+
+use crate::consensus::ConsensusParams;
+use crate::constants::Byte32;
+use crate::traits::Validate;
+use crate::utils::compute_group;
+use crate::{Address, Transaction};
+use std::cmp::Reverse;
+use std::collections::HashMap;
+use std::fmt::{Display, Formatter, Result as FmtResult};
+
+/// A single rule violation found while validating a transaction group.
+#[derive(Debug, Clone, PartialEq)]
+pub struct GroupValidationError {
+    /// Indices of the transactions the violation relates to.
+    ///
+    /// Empty when the violation applies to the group as a whole.
+    pub indices: Vec<usize>,
+    /// Human-readable description of the violation.
+    pub message: String,
+}
+
+impl GroupValidationError {
+    fn group(message: String) -> Self {
+        Self {
+            indices: Vec::new(),
+            message,
+        }
+    }
+
+    fn at(indices: Vec<usize>, message: String) -> Self {
+        Self { indices, message }
+    }
+}
+
+impl Display for GroupValidationError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> FmtResult {
+        match self.indices.as_slice() {
+            [] => write!(f, "{}", self.message),
+            [index] => write!(f, "Transaction {}: {}", index, self.message),
+            indices => {
+                let indices: Vec<String> = indices.iter().map(|i| i.to_string()).collect();
+                write!(f, "Transactions {}: {}", indices.join(", "), self.message)
+            }
+        }
+    }
+}
+
+impl std::error::Error for GroupValidationError {}
+
+/// Validates a transaction group, returning every violation found.
+///
+/// The following rules are checked:
+/// - the group is not empty and does not exceed the maximum group size,
+/// - every transaction passes its own per-type validation (which covers reference limits and
+///   app program and extra page size limits),
+/// - group IDs are either absent from a single transaction or present on all transactions and
+///   equal to the ID computed from the group,
+/// - all validity windows overlap,
+/// - no sender uses the same lease twice,
+/// - all transactions share the same genesis hash and genesis ID,
+/// - the pooled fee covers the minimum fee of every transaction in the group.
+pub(crate) fn validate_group(
+    txs: &[Transaction],
+    params: &ConsensusParams,
+) -> Result<(), Vec<GroupValidationError>> {
+    let mut errors = Vec::new();
+
+    if txs.is_empty() {
+        errors.push(GroupValidationError::group(
+            "Transaction group size cannot be 0".to_string(),
+        ));
+        return Err(errors);
+    }
+
+    if txs.len() > params.max_tx_group_size {
+        errors.push(GroupValidationError::group(format!(
+            "Transaction group size {} exceeds the max limit of {}",
+            txs.len(),
+            params.max_tx_group_size
+        )));
+    }
+
+    for (index, tx) in txs.iter().enumerate() {
+        if let Err(tx_errors) = tx.validate_with_params(params) {
+            errors.extend(
+                tx_errors
+                    .into_iter()
+                    .map(|message| GroupValidationError::at(vec![index], message)),
+            );
+        }
+    }
+
+    validate_group_id(txs, &mut errors);
+    validate_validity_windows(txs, &mut errors);
+    validate_leases(txs, &mut errors);
+    validate_genesis(txs, &mut errors);
+    validate_fee_pooling(txs, params, &mut errors);
+
+    match errors.is_empty() {
+        true => Ok(()),
+        false => Err(errors),
+    }
+}
+
+fn validate_group_id(txs: &[Transaction], errors: &mut Vec<GroupValidationError>) {
+    let missing: Vec<usize> = txs
+        .iter()
+        .enumerate()
+        .filter(|(_, tx)| tx.header().group.is_none())
+        .map(|(i, _)| i)
+        .collect();
+
+    if missing.len() == txs.len() {
+        if txs.len() > 1 {
+            errors.push(GroupValidationError::group(
+                "Transactions in a group of more than one must have a group ID".to_string(),
+            ));
+        }
+        return;
+    }
+
+    if !missing.is_empty() {
+        errors.push(GroupValidationError::at(
+            missing,
+            "Group ID is missing".to_string(),
+        ));
+        return;
+    }
+
+    // Computing the group only fails for groups over the max size, which is reported separately.
+    let ungrouped: Vec<Transaction> = txs
+        .iter()
+        .map(|tx| {
+            let mut tx = tx.clone();
+            tx.header_mut().group = None;
+            tx
+        })
+        .collect();
+    let Ok(expected_group) = compute_group(&ungrouped) else {
+        return;
+    };
+
+    let mismatched: Vec<usize> = txs
+        .iter()
+        .enumerate()
+        .filter(|(_, tx)| tx.header().group != Some(expected_group))
+        .map(|(i, _)| i)
+        .collect();
+    if !mismatched.is_empty() {
+        errors.push(GroupValidationError::at(
+            mismatched,
+            "Group ID does not match the group computed from the transactions".to_string(),
+        ));
+    }
+}
+
+fn validate_validity_windows(txs: &[Transaction], errors: &mut Vec<GroupValidationError>) {
+    for (index, tx) in txs.iter().enumerate() {
+        if tx.first_valid_round() > tx.last_valid_round() {
+            errors.push(GroupValidationError::at(
+                vec![index],
+                format!(
+                    "First valid round {} is after last valid round {}",
+                    tx.first_valid_round(),
+                    tx.last_valid_round()
+                ),
+            ));
+        }
+    }
+
+    let (latest_first_index, latest_first) = txs
+        .iter()
+        .enumerate()
+        .map(|(i, tx)| (i, tx.first_valid_round()))
+        .min_by_key(|(_, round)| Reverse(*round))
+        .unwrap_or_default();
+    let (earliest_last_index, earliest_last) = txs
+        .iter()
+        .enumerate()
+        .map(|(i, tx)| (i, tx.last_valid_round()))
+        .min_by_key(|(_, round)| *round)
+        .unwrap_or_default();
+
+    if latest_first > earliest_last && latest_first_index != earliest_last_index {
+        errors.push(GroupValidationError::at(
+            vec![latest_first_index, earliest_last_index],
+            format!(
+                "Validity windows do not overlap; first valid round {} is after last valid round {}",
+                latest_first, earliest_last
+            ),
+        ));
+    }
+}
+
+fn validate_leases(txs: &[Transaction], errors: &mut Vec<GroupValidationError>) {
+    let mut leases: HashMap<(&Address, &Byte32), Vec<usize>> = HashMap::new();
+    for (index, tx) in txs.iter().enumerate() {
+        if let Some(lease) = tx.header().lease.as_ref().filter(|l| **l != [0u8; 32]) {
+            leases.entry((tx.sender(), lease)).or_default().push(index);
+        }
+    }
+
+    let mut duplicates: Vec<Vec<usize>> = leases
+        .into_values()
+        .filter(|indices| indices.len() > 1)
+        .collect();
+    duplicates.sort();
+
+    for indices in duplicates {
+        errors.push(GroupValidationError::at(
+            indices,
+            "Duplicate lease for the same sender".to_string(),
+        ));
+    }
+}
+
+fn validate_genesis(txs: &[Transaction], errors: &mut Vec<GroupValidationError>) {
+    let first = txs[0].header();
+
+    let mismatched_hash: Vec<usize> = txs
+        .iter()
+        .enumerate()
+        .filter(|(_, tx)| tx.header().genesis_hash != first.genesis_hash)
+        .map(|(i, _)| i)
+        .collect();
+    if !mismatched_hash.is_empty() {
+        errors.push(GroupValidationError::at(
+            mismatched_hash,
+            "Genesis hash differs from the first transaction in the group".to_string(),
+        ));
+    }
+
+    let mismatched_id: Vec<usize> = txs
+        .iter()
+        .enumerate()
+        .filter(|(_, tx)| tx.header().genesis_id != first.genesis_id)
+        .map(|(i, _)| i)
+        .collect();
+    if !mismatched_id.is_empty() {
+        errors.push(GroupValidationError::at(
+            mismatched_id,
+            "Genesis ID differs from the first transaction in the group".to_string(),
+        ));
+    }
+}
+
+fn validate_fee_pooling(
+    txs: &[Transaction],
+    params: &ConsensusParams,
+    errors: &mut Vec<GroupValidationError>,
+) {
+    // State proof transactions are free and do not contribute to the required fee.
+    let fee_paying = txs
+        .iter()
+        .filter(|tx| !matches!(tx, Transaction::StateProof(_)))
+        .count() as u64;
+    let required = fee_paying.saturating_mul(params.min_txn_fee);
+    let total = txs
+        .iter()
+        .fold(0u64, |acc, tx| acc.saturating_add(tx.fee().unwrap_or(0)));
+
+    if total < required {
+        errors.push(GroupValidationError::group(format!(
+            "Total group fee {} µALGO is less than the required {} µALGO",
+            total, required
+        )));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_utils::{AppCallTransactionMother, TransactionGroupMother};
+    use crate::{MAX_TX_GROUP_SIZE, Transactions};
+
+    fn grouped(txs: Vec<Transaction>) -> Vec<Transaction> {
+        txs.assign_group().unwrap()
+    }
+
+    fn messages(errors: &[GroupValidationError]) -> Vec<String> {
+        errors.iter().map(|e| e.to_string()).collect()
+    }
+
+    #[test]
+    fn test_validate_valid_group() {
+        let txs = grouped(TransactionGroupMother::testnet_payment_group());
+
+        assert!(txs.validate_group().is_ok());
+    }
+
+    #[test]
+    fn test_validate_single_transaction_without_group() {
+        let txs = TransactionGroupMother::group_of(1);
+
+        assert!(txs.validate_group().is_ok());
+    }
+
+    #[test]
+    fn test_validate_empty_group() {
+        let txs: Vec<Transaction> = vec![];
+
+        let errors = txs.validate_group().unwrap_err();
+        assert_eq!(
+            messages(&errors),
+            vec!["Transaction group size cannot be 0"]
+        );
+    }
+
+    #[test]
+    fn test_validate_group_too_big() {
+        let txs = TransactionGroupMother::group_of(MAX_TX_GROUP_SIZE + 1);
+
+        let errors = txs.validate_group().unwrap_err();
+        assert!(
+            messages(&errors)
+                .contains(&"Transaction group size 17 exceeds the max limit of 16".to_string())
+        );
+    }
+
+    #[test]
+    fn test_validate_group_id_missing_and_mismatched() {
+        let txs = TransactionGroupMother::group_of(2);
+        let errors = txs.validate_group().unwrap_err();
+        assert_eq!(
+            messages(&errors),
+            vec!["Transactions in a group of more than one must have a group ID"]
+        );
+
+        let mut txs = grouped(TransactionGroupMother::group_of(3));
+        txs[1].header_mut().group = None;
+        txs[2].header_mut().note = Some(b"tampered".to_vec());
+        let errors = txs.validate_group().unwrap_err();
+        assert_eq!(
+            messages(&errors),
+            vec!["Transaction 1: Group ID is missing"]
+        );
+
+        txs[1].header_mut().group = txs[0].header().group;
+        let errors = txs.validate_group().unwrap_err();
+        assert_eq!(
+            messages(&errors),
+            vec![
+                "Transactions 0, 1, 2: Group ID does not match the group computed from the transactions"
+            ]
+        );
+    }
+
+    #[test]
+    fn test_validate_non_overlapping_validity_windows() {
+        let mut txs = TransactionGroupMother::group_of(3);
+        txs[1].header_mut().first_valid = 100;
+        txs[1].header_mut().last_valid = 200;
+        let txs = grouped(txs);
+
+        let errors = txs.validate_group().unwrap_err();
+        assert_eq!(
+            messages(&errors),
+            vec![
+                "Transactions 0, 1: Validity windows do not overlap; first valid round 51532821 is after last valid round 200"
+            ]
+        );
+    }
+
+    #[test]
+    fn test_validate_duplicate_leases() {
+        let mut txs = TransactionGroupMother::group_of(3);
+        txs[0].header_mut().lease = Some([1u8; 32]);
+        txs[2].header_mut().lease = Some([1u8; 32]);
+        txs[1].header_mut().lease = Some([2u8; 32]);
+        let txs = grouped(txs);
+
+        let errors = txs.validate_group().unwrap_err();
+        assert_eq!(
+            messages(&errors),
+            vec!["Transactions 0, 2: Duplicate lease for the same sender"]
+        );
+    }
+
+    #[test]
+    fn test_validate_mismatched_genesis() {
+        let mut txs = TransactionGroupMother::group_of(3);
+        txs[1].header_mut().genesis_hash = Some([9u8; 32]);
+        txs[2].header_mut().genesis_id = Some("mainnet-v1.0".to_string());
+        let txs = grouped(txs);
+
+        let errors = txs.validate_group().unwrap_err();
+        assert_eq!(
+            messages(&errors),
+            vec![
+                "Transaction 1: Genesis hash differs from the first transaction in the group",
+                "Transaction 2: Genesis ID differs from the first transaction in the group",
+            ]
+        );
+    }
+
+    #[test]
+    fn test_validate_fee_pooling() {
+        let mut txs = TransactionGroupMother::group_of(3);
+        txs[0].header_mut().fee = Some(3000);
+        txs[1].header_mut().fee = None;
+        txs[2].header_mut().fee = Some(0);
+        assert!(grouped(txs.clone()).validate_group().is_ok());
+
+        txs[0].header_mut().fee = Some(2999);
+        let errors = grouped(txs).validate_group().unwrap_err();
+        assert_eq!(
+            messages(&errors),
+            vec!["Total group fee 2999 µALGO is less than the required 3000 µALGO"]
+        );
+    }
+
+    #[test]
+    fn test_validate_reports_per_transaction_errors_with_index() {
+        let mut txs = TransactionGroupMother::group_of(2);
+        let mut app_create = AppCallTransactionMother::app_create().build().unwrap();
+        if let Transaction::AppCall(ref mut fields) = app_create {
+            fields.header = txs[0].header().clone();
+            fields.extra_program_pages = Some(4);
+            fields.account_references = Some(vec![Address::default(); 9]);
+        }
+        txs.push(app_create);
+        let txs = grouped(txs);
+
+        let errors = txs.validate_group().unwrap_err();
+        assert_eq!(
+            messages(&errors),
+            vec![
+                "Transaction 2: Extra program pages cannot exceed 3 pages, got 4",
+                "Transaction 2: Account references cannot exceed 4 refs, got 9",
+                "Transaction 2: Total references cannot exceed 8 refs, got 9",
+            ]
+        );
+    }
+
+    #[test]
+    fn test_validate_with_consensus_params() {
+        let mut txs = TransactionGroupMother::group_of(3);
+        txs[1].header_mut().note = Some(vec![0u8; 65]);
+        txs[2].header_mut().last_valid = txs[2].header().first_valid + 201;
+        let txs = grouped(txs);
+        let params = ConsensusParams {
+            max_tx_group_size: 2,
+            max_txn_note_bytes: 64,
+            max_txn_life: 200,
+            ..Default::default()
+        };
+
+        let errors = txs.validate_group_with_params(&params).unwrap_err();
+        assert_eq!(
+            messages(&errors),
+            vec![
+                "Transaction group size 3 exceeds the max limit of 2",
+                "Transaction 1: Note cannot exceed 64 bytes, got 65",
+                "Transaction 2: Validity window cannot exceed 200 rounds, got 201",
+            ]
+        );
+    }
+}
@@ -4,6 +4,7 @@
 //! which are used to register accounts online or offline for participation in Algorand consensus.
 
 use crate::Transaction;
+use crate::consensus::ConsensusParams;
 use crate::traits::Validate;
 use crate::transactions::common::{TransactionHeader, TransactionValidationError};
 use crate::utils::{is_false_opt, is_zero_opt};
@@ -148,7 +149,7 @@ impl KeyRegistrationTransactionBuilder {
 }
 
 impl Validate for KeyRegistrationTransactionFields {
-    fn validate(&self) -> Result<(), Vec<String>> {
+    fn validate_with_params(&self, _params: &ConsensusParams) -> Result<(), Vec<String>> {
         let has_any_participation_fields = self.vote_key.is_some()
             || self.selection_key.is_some()
             || self.state_proof_key.is_some()
@@ -9,6 +9,7 @@ mod asset_config;
 mod asset_freeze;
 mod asset_transfer;
 mod common;
+mod group_validation;
 mod heartbeat;
 mod key_registration;
 mod payment;
@@ -26,6 +27,7 @@ pub use asset_config::{
 pub use asset_freeze::{AssetFreezeTransactionBuilder, AssetFreezeTransactionFields};
 pub use asset_transfer::{AssetTransferTransactionBuilder, AssetTransferTransactionFields};
 pub use common::{TransactionHeader, TransactionHeaderBuilder};
+pub use group_validation::GroupValidationError;
 pub use heartbeat::{
     HeartbeatProof, HeartbeatProofBuilder, HeartbeatTransactionBuilder, HeartbeatTransactionFields,
     heartbeat_deserializer, heartbeat_serializer,
@@ -38,13 +40,17 @@ pub use state_proof::{
     StateProofTransactionBuilder, StateProofTransactionFields,
 };
 
+use crate::consensus::ConsensusParams;
 use crate::constants::{
     ALGORAND_SIGNATURE_BYTE_LENGTH, ALGORAND_SIGNATURE_ENCODING_INCR, HASH_BYTES_LENGTH,
 };
 use crate::error::AlgoKitTransactError;
-use crate::traits::{AlgorandMsgpack, EstimateTransactionSize, TransactionId, Transactions};
+use crate::traits::{
+    AlgorandMsgpack, EstimateTransactionSize, TransactionId, Transactions, Validate,
+};
 use crate::utils::{compute_group, is_zero_addr_opt};
 use crate::{Address, MultisigSignature};
+use common::TransactionValidationError;
 use serde::{Deserialize, Serialize};
 use serde_with::{Bytes, serde_as};
 use std::any::Any;
@@ -270,6 +276,83 @@ impl Transactions for &[Transaction] {
             })
             .collect())
     }
+
+    /// Validates the supplied transactions as a single atomic group.
+    ///
+    /// # Returns
+    /// `Ok(())` if the group is valid, otherwise every violation found along with the
+    /// indices of the offending transactions.
+    fn validate_group(self) -> Result<(), Vec<GroupValidationError>> {
+        group_validation::validate_group(self, &ConsensusParams::default())
+    }
+
+    /// Validates the supplied transactions as a single atomic group against the limits of
+    /// the supplied consensus parameters.
+    ///
+    /// # Returns
+    /// `Ok(())` if the group is valid, otherwise every violation found along with the
+    /// indices of the offending transactions.
+    fn validate_group_with_params(
+        self,
+        params: &ConsensusParams,
+    ) -> Result<(), Vec<GroupValidationError>> {
+        group_validation::validate_group(self, params)
+    }
+}
+
+impl Validate for Transaction {
+    /// Validates the common header fields and the fields specific to the transaction type.
+    fn validate_with_params(&self, params: &ConsensusParams) -> Result<(), Vec<String>> {
+        let mut errors = Vec::new();
+
+        if let Some(note) = self.note() {
+            if note.len() > params.max_txn_note_bytes {
+                errors.push(
+                    TransactionValidationError::FieldTooLong {
+                        field: "Note".to_string(),
+                        actual: note.len(),
+                        max: params.max_txn_note_bytes,
+                        unit: "bytes".to_string(),
+                    }
+                    .to_string(),
+                );
+            }
+        }
+
+        let validity_window = self
+            .last_valid_round()
+            .saturating_sub(self.first_valid_round());
+        if validity_window > params.max_txn_life {
+            errors.push(
+                TransactionValidationError::FieldTooLong {
+                    field: "Validity window".to_string(),
+                    actual: validity_window as usize,
+                    max: params.max_txn_life as usize,
+                    unit: "rounds".to_string(),
+                }
+                .to_string(),
+            );
+        }
+
+        let type_result = match self {
+            Transaction::AssetTransfer(fields) => fields.validate_with_params(params),
+            Transaction::AssetConfig(fields) => fields.validate_with_params(params),
+            Transaction::AppCall(fields) => fields.validate_with_params(params),
+            Transaction::AssetFreeze(fields) => fields.validate_with_params(params),
+            Transaction::KeyRegistration(fields) => fields.validate_with_params(params),
+            Transaction::Payment(_) | Transaction::Heartbeat(_) | Transaction::StateProof(_) => {
+                Ok(())
+            }
+        };
+        if let Err(type_errors) = type_result {
+            errors.extend(type_errors);
+        }
+
+        match errors.is_empty() {
+            true => Ok(()),
+            false => Err(errors),
+        }
+    }
 }
 
 impl Transaction {
@@ -0,0 +1,568 @@
+//! ARC-1 wallet transaction exchange format.
+//!
+//! [ARC-1](https://arc.algorand.foundation/ARCs/arc-0001) defines the JSON objects a dapp hands
+//! to a wallet when requesting signatures. Each [`WalletTransaction`] carries a base64 msgpack
+//! encoded transaction along with optional hints about who should sign it: an auth address for
+//! rekeyed accounts, multisig metadata, an explicit list of signers, or a signed transaction for
+//! entries the wallet must not sign.
+//!
+//! The wallet answers with one base64 signed transaction per entry, or `null` for entries it did
+//! not sign. [`merge_wallet_signatures`] combines the answers of one or more wallets with the
+//! request.
+
+use crate::address::Address;
+use crate::error::AlgoKitTransactError;
+use crate::multisig::MultisigSignature;
+use crate::traits::AlgorandMsgpack;
+use crate::transactions::{SignedTransaction, Transaction};
+use crate::utils::compute_group;
+use base64::{Engine, prelude::BASE64_STANDARD};
+use serde::{Deserialize, Serialize};
+use std::str::FromStr;
+
+/// The multisig account a wallet transaction should be signed for.
+#[derive(Debug, PartialEq, Clone, Serialize, Deserialize)]
+pub struct MultisigMetadata {
+    /// Multisig version.
+    pub version: u8,
+    /// Minimum number of subsignatures required.
+    pub threshold: u8,
+    /// Base32 encoded participant addresses, in signing order.
+    pub addrs: Vec<String>,
+}
+
+impl MultisigMetadata {
+    /// Converts the metadata into an unsigned [`MultisigSignature`].
+    pub fn to_multisig_signature(&self) -> Result<MultisigSignature, AlgoKitTransactError> {
+        let participants = self
+            .addrs
+            .iter()
+            .map(|addr| Address::from_str(addr))
+            .collect::<Result<Vec<_>, _>>()?;
+        MultisigSignature::from_participants(self.version, self.threshold, participants)
+    }
+}
+
+impl From<&MultisigSignature> for MultisigMetadata {
+    fn from(multisig: &MultisigSignature) -> Self {
+        Self {
+            version: multisig.version,
+            threshold: multisig.threshold,
+            addrs: multisig
+                .participants()
+                .iter()
+                .map(Address::as_str)
+                .collect(),
+        }
+    }
+}
+
+/// A transaction as exchanged with a wallet under ARC-1.
+///
+/// The `signers` field decides whether the wallet signs the transaction:
+/// - when absent, the wallet signs with the auth address, or the sender if there is none,
+/// - when empty, the wallet must not sign, and `stxn` may carry the already signed transaction,
+/// - otherwise, the wallet signs with the listed addresses only.
+#[derive(Debug, PartialEq, Clone, Default, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct WalletTransaction {
+    /// Base64 msgpack encoding of the unsigned transaction.
+    pub txn: String,
+    /// Base32 address of the account authorized to sign for the sender.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub auth_addr: Option<String>,
+    /// The multisig account the transaction should be signed for.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub msig: Option<MultisigMetadata>,
+    /// Base32 addresses the wallet should sign with.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub signers: Option<Vec<String>>,
+    /// Base64 msgpack encoding of the signed transaction, for entries the wallet must not sign.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub stxn: Option<String>,
+    /// A message to display to the user.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub message: Option<String>,
+}
+
+impl WalletTransaction {
+    /// Creates a wallet transaction the wallet signs with the sender.
+    pub fn new(transaction: &Transaction) -> Result<Self, AlgoKitTransactError> {
+        Ok(Self {
+            txn: BASE64_STANDARD.encode(transaction.encode_raw()?),
+            ..Default::default()
+        })
+    }
+
+    /// Sets the account authorized to sign for a rekeyed sender.
+    pub fn with_auth_address(mut self, auth_address: &Address) -> Self {
+        self.auth_addr = Some(auth_address.as_str());
+        self
+    }
+
+    /// Sets the multisig account the transaction should be signed for.
+    pub fn with_multisig(mut self, multisig: &MultisigSignature) -> Self {
+        self.msig = Some(multisig.into());
+        self
+    }
+
+    /// Restricts the wallet to signing with the given addresses.
+    pub fn with_signers(mut self, signers: &[Address]) -> Self {
+        self.signers = Some(signers.iter().map(Address::as_str).collect());
+        self
+    }
+
+    /// Marks the transaction as one the wallet must not sign, attaching its signed form.
+    pub fn with_signed_transaction(
+        mut self,
+        signed_transaction: &SignedTransaction,
+    ) -> Result<Self, AlgoKitTransactError> {
+        self.signers = Some(Vec::new());
+        self.stxn = Some(BASE64_STANDARD.encode(signed_transaction.encode()?));
+        Ok(self)
+    }
+
+    /// Sets the message to display to the user.
+    pub fn with_message(mut self, message: impl Into<String>) -> Self {
+        self.message = Some(message.into());
+        self
+    }
+
+    /// Decodes the unsigned transaction.
+    pub fn transaction(&self) -> Result<Transaction, AlgoKitTransactError> {
+        Transaction::decode(&decode_base64("txn", &self.txn)?)
+    }
+
+    /// Decodes the attached signed transaction, if any.
+    pub fn signed_transaction(&self) -> Result<Option<SignedTransaction>, AlgoKitTransactError> {
+        self.stxn
+            .as_deref()
+            .map(|stxn| SignedTransaction::decode(&decode_base64("stxn", stxn)?))
+            .transpose()
+    }
+
+    /// Decodes the auth address, if any.
+    pub fn auth_address(&self) -> Result<Option<Address>, AlgoKitTransactError> {
+        self.auth_addr.as_deref().map(Address::from_str).transpose()
+    }
+
+    /// Decodes the signers, if any.
+    pub fn signers(&self) -> Result<Option<Vec<Address>>, AlgoKitTransactError> {
+        self.signers
+            .as_ref()
+            .map(|signers| {
+                signers
+                    .iter()
+                    .map(|signer| Address::from_str(signer))
+                    .collect()
+            })
+            .transpose()
+    }
+
+    /// Returns whether the wallet is asked to sign the transaction.
+    pub fn must_sign(&self) -> bool {
+        self.signers
+            .as_ref()
+            .is_none_or(|signers| !signers.is_empty())
+    }
+
+    /// Validates the wallet transaction on its own.
+    ///
+    /// # Errors
+    ///
+    /// Returns [`AlgoKitTransactError::InvalidWalletTransaction`] if:
+    /// - `stxn` is set for a transaction the wallet is asked to sign, or is for another transaction,
+    /// - the multisig account is neither the auth address nor, without one, the sender,
+    /// - a signer is not a multisig participant,
+    /// - without multisig, the signers are anything but the auth address or, without one, the
+    ///   sender.
+    pub fn validate(&self) -> Result<(), AlgoKitTransactError> {
+        let transaction = self.transaction()?;
+        let signers = self.signers()?.unwrap_or_default();
+        let authorizer = self
+            .auth_address()?
+            .unwrap_or_else(|| transaction.sender().clone());
+
+        if let Some(signed_transaction) = self.signed_transaction()? {
+            if self.must_sign() {
+                return Err(invalid(
+                    "stxn may only be set when signers is empty".to_string(),
+                ));
+            }
+            if signed_transaction.transaction != transaction {
+                return Err(invalid(
+                    "stxn does not match the unsigned transaction".to_string(),
+                ));
+            }
+        }
+
+        match &self.msig {
+            Some(metadata) => {
+                let multisig = metadata.to_multisig_signature()?;
+                if Address::from(multisig.clone()) != authorizer {
+                    return Err(invalid(format!(
+                        "Multisig address {} does not match the authorizing address {}",
+                        multisig, authorizer
+                    )));
+                }
+                let participants = multisig.participants();
+                if let Some(signer) = signers.iter().find(|s| !participants.contains(s)) {
+                    return Err(invalid(format!(
+                        "Signer {} is not a multisig participant",
+                        signer
+                    )));
+                }
+            }
+            None => {
+                if !signers.is_empty() && signers != [authorizer.clone()] {
+                    return Err(invalid(format!(
+                        "Signers must be exactly the authorizing address {}",
+                        authorizer
+                    )));
+                }
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Validates wallet transactions that are sent to a wallet together.
+///
+/// Besides validating every entry, this requires that the wallet is asked to sign at least one
+/// transaction and that the wallet is given every transaction of a group: when there are
+/// several transactions, or a single one with a group ID, they must form a single atomic group
+/// with the correct group ID.
+pub fn validate_wallet_transactions(
+    wallet_transactions: &[WalletTransaction],
+) -> Result<(), AlgoKitTransactError> {
+    if wallet_transactions.is_empty() {
+        return Err(invalid("No transactions were provided".to_string()));
+    }
+
+    let mut transactions = Vec::with_capacity(wallet_transactions.len());
+    for (index, wallet_transaction) in wallet_transactions.iter().enumerate() {
+        wallet_transaction
+            .validate()
+            .map_err(|e| invalid(format!("transaction {}: {}", index, e)))?;
+        transactions.push(wallet_transaction.transaction()?);
+    }
+
+    if !wallet_transactions.iter().any(WalletTransaction::must_sign) {
+        return Err(invalid(
+            "At least one transaction must be signed by the wallet".to_string(),
+        ));
+    }
+
+    if transactions.len() > 1 || transactions[0].header().group.is_some() {
+        let groups: Vec<_> = transactions.iter().map(|tx| tx.header().group).collect();
+        let ungrouped: Vec<Transaction> = transactions
+            .into_iter()
+            .map(|mut tx| {
+                tx.header_mut().group = None;
+                tx
+            })
+            .collect();
+        let expected = compute_group(&ungrouped)?;
+        if let Some(index) = groups.iter().position(|group| *group != Some(expected)) {
+            return Err(invalid(format!(
+                "transaction {}: group ID does not match the group of the provided transactions",
+                index
+            )));
+        }
+    }
+    Ok(())
+}
+
+/// Combines the ARC-1 responses of one or more wallets with the transactions they were asked to
+/// sign.
+///
+/// Each response holds one base64 signed transaction per wallet transaction, or `None` where
+/// that wallet did not sign. Entries no wallet signed fall back to their attached `stxn`. When
+/// several wallets sign the same multisig transaction, as happens when its participants use
+/// different wallets, their subsignatures are merged.
+///
+/// # Returns
+///
+/// One signed transaction per wallet transaction, or `None` where neither the wallets nor the
+/// request supplied one.
+///
+/// # Errors
+///
+/// Returns [`AlgoKitTransactError::InvalidWalletTransaction`] if:
+/// - a response does not hold one entry per wallet transaction,
+/// - no wallet signed a transaction the wallets were asked to sign,
+/// - a wallet signed a transaction it was not asked to sign, or returned another transaction,
+/// - several wallets signed a transaction without multisig.
+pub fn merge_wallet_signatures(
+    wallet_transactions: &[WalletTransaction],
+    responses: &[Vec<Option<String>>],
+) -> Result<Vec<Option<SignedTransaction>>, AlgoKitTransactError> {
+    if let Some(response) = responses
+        .iter()
+        .find(|response| response.len() != wallet_transactions.len())
+    {
+        return Err(invalid(format!(
+            "Expected {} signed transactions but a wallet returned {}",
+            wallet_transactions.len(),
+            response.len()
+        )));
+    }
+
+    wallet_transactions
+        .iter()
+        .enumerate()
+        .map(|(index, wallet_transaction)| {
+            let mut returned = responses
+                .iter()
+                .filter_map(|response| response[index].as_deref())
+                .map(|signed| {
+                    SignedTransaction::decode(&decode_base64("signed transaction", signed)?)
+                })
+                .collect::<Result<Vec<_>, _>>()?
+                .into_iter();
+            let Some(mut merged) = returned.next() else {
+                if wallet_transaction.must_sign() {
+                    return Err(invalid(format!(
+                        "transaction {}: no wallet returned a signature",
+                        index
+                    )));
+                }
+                return wallet_transaction.signed_transaction();
+            };
+            if !wallet_transaction.must_sign() {
+                return Err(invalid(format!(
+                    "transaction {}: a wallet signed a transaction it was not asked to sign",
+                    index
+                )));
+            }
+
+            let transaction = wallet_transaction.transaction()?;
+            if merged.transaction != transaction {
+                return Err(different_transaction(index));
+            }
+            for signed_transaction in returned {
+                if signed_transaction.transaction != transaction {
+                    return Err(different_transaction(index));
+                }
+                let (Some(existing), Some(other)) =
+                    (&merged.multisignature, &signed_transaction.multisignature)
+                else {
+                    return Err(invalid(format!(
+                        "transaction {}: several wallets signed a transaction without multisig",
+                        index
+                    )));
+                };
+                merged.multisignature = Some(existing.merge(other)?);
+            }
+            Ok(Some(merged))
+        })
+        .collect()
+}
+
+fn different_transaction(index: usize) -> AlgoKitTransactError {
+    invalid(format!(
+        "transaction {}: a wallet returned a different transaction",
+        index
+    ))
+}
+
+fn decode_base64(field: &str, value: &str) -> Result<Vec<u8>, AlgoKitTransactError> {
+    BASE64_STANDARD
+        .decode(value)
+        .map_err(|e| invalid(format!("{} is not valid base64: {}", field, e)))
+}
+
+fn invalid(err_msg: String) -> AlgoKitTransactError {
+    AlgoKitTransactError::InvalidWalletTransaction { err_msg }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_utils::{AccountMother, TransactionGroupMother, TransactionMother};
+    use crate::traits::Transactions;
+
+    fn signed(transaction: &Transaction, signature: [u8; 64]) -> String {
+        let stxn = SignedTransaction {
+            transaction: transaction.clone(),
+            signature: Some(signature),
+            auth_address: None,
+            multisignature: None,
+        };
+        BASE64_STANDARD.encode(stxn.encode().unwrap())
+    }
+
+    #[test]
+    fn test_json_round_trip() {
+        let transaction = TransactionMother::simple_payment().build().unwrap();
+        let wallet_transaction = WalletTransaction::new(&transaction)
+            .unwrap()
+            .with_auth_address(&AccountMother::neil().address())
+            .with_message("Pay the invoice");
+
+        let json = serde_json::to_value(&wallet_transaction).unwrap();
+        assert_eq!(json["authAddr"], AccountMother::neil().address().as_str());
+        assert!(json.get("signers").is_none());
+
+        let decoded: WalletTransaction = serde_json::from_value(json).unwrap();
+        assert_eq!(decoded, wallet_transaction);
+        assert_eq!(decoded.transaction().unwrap(), transaction);
+        assert!(decoded.must_sign());
+        assert!(decoded.validate().is_ok());
+    }
+
+    #[test]
+    fn test_validates_signers_and_multisig() {
+        let msig = AccountMother::msig();
+        let transaction = TransactionMother::simple_payment().build().unwrap();
+        let wallet_transaction = WalletTransaction::new(&transaction).unwrap();
+
+        assert!(
+            wallet_transaction
+                .clone()
+                .with_signers(&[AccountMother::neil().address()])
+                .validate()
+                .is_err()
+        );
+
+        let multisig = wallet_transaction
+            .clone()
+            .with_auth_address(&Address::from(msig.clone()))
+            .with_multisig(&msig);
+        assert!(
+            multisig
+                .clone()
+                .with_signers(&[AccountMother::account().address()])
+                .validate()
+                .is_ok()
+        );
+        assert!(
+            multisig
+                .with_signers(&[AccountMother::neil().address()])
+                .validate()
+                .is_err()
+        );
+        assert!(wallet_transaction.with_multisig(&msig).validate().is_err());
+    }
+
+    #[test]
+    fn test_validates_groups() {
+        let group = TransactionGroupMother::testnet_payment_group()
+            .assign_group()
+            .unwrap();
+        let signed_first = SignedTransaction {
+            transaction: group[0].clone(),
+            signature: Some([1; 64]),
+            auth_address: None,
+            multisignature: None,
+        };
+        let wallet_transactions = vec![
+            WalletTransaction::new(&group[0])
+                .unwrap()
+                .with_signed_transaction(&signed_first)
+                .unwrap(),
+            WalletTransaction::new(&group[1]).unwrap(),
+        ];
+        assert!(validate_wallet_transactions(&wallet_transactions).is_ok());
+        assert!(validate_wallet_transactions(&wallet_transactions[..1]).is_err());
+        assert_eq!(
+            validate_wallet_transactions(&wallet_transactions[1..])
+                .unwrap_err()
+                .to_string(),
+            "Invalid wallet transaction: transaction 0: group ID does not match the group of the provided transactions"
+        );
+
+        let ungrouped = TransactionGroupMother::testnet_payment_group();
+        let mismatched = vec![
+            wallet_transactions[0].clone(),
+            WalletTransaction::new(&ungrouped[1]).unwrap(),
+        ];
+        assert!(validate_wallet_transactions(&mismatched).is_err());
+    }
+
+    #[test]
+    fn test_merges_wallet_signatures() {
+        let group = TransactionGroupMother::testnet_payment_group()
+            .assign_group()
+            .unwrap();
+        let signed_first = SignedTransaction {
+            transaction: group[0].clone(),
+            signature: Some([1; 64]),
+            auth_address: None,
+            multisignature: None,
+        };
+        let wallet_transactions = vec![
+            WalletTransaction::new(&group[0])
+                .unwrap()
+                .with_signed_transaction(&signed_first)
+                .unwrap(),
+            WalletTransaction::new(&group[1]).unwrap(),
+        ];
+
+        let merged = merge_wallet_signatures(
+            &wallet_transactions,
+            &[vec![None, Some(signed(&group[1], [2; 64]))]],
+        )
+        .unwrap();
+        assert_eq!(merged[0], Some(signed_first));
+        assert_eq!(merged[1].as_ref().unwrap().signature, Some([2; 64]));
+
+        assert!(merge_wallet_signatures(&wallet_transactions, &[vec![None, None]]).is_err());
+        assert!(
+            merge_wallet_signatures(
+                &wallet_transactions,
+                &[vec![None, Some(signed(&group[0], [2; 64]))]]
+            )
+            .is_err()
+        );
+        assert!(
+            merge_wallet_signatures(
+                &wallet_transactions,
+                &[
+                    vec![None, Some(signed(&group[1], [2; 64]))],
+                    vec![None, Some(signed(&group[1], [3; 64]))],
+                ]
+            )
+            .is_err()
+        );
+    }
+
+    #[test]
+    fn test_merges_multisig_subsignatures_from_several_wallets() {
+        let msig = AccountMother::msig();
+        let mut transaction = TransactionMother::simple_payment().build().unwrap();
+        transaction.header_mut().sender = Address::from(msig.clone());
+        let wallet_transactions = vec![
+            WalletTransaction::new(&transaction)
+                .unwrap()
+                .with_multisig(&msig),
+        ];
+        let response = |participant: Address, signature: [u8; 64]| {
+            let stxn = SignedTransaction {
+                transaction: transaction.clone(),
+                signature: None,
+                auth_address: None,
+                multisignature: Some(msig.apply_subsignature(participant, signature).unwrap()),
+            };
+            vec![Some(BASE64_STANDARD.encode(stxn.encode().unwrap()))]
+        };
+
+        let merged = merge_wallet_signatures(
+            &wallet_transactions,
+            &[
+                response(AccountMother::account().address(), [1; 64]),
+                vec![None],
+                response(AccountMother::example().address(), [2; 64]),
+            ],
+        )
+        .unwrap();
+
+        let multisignature = merged[0].as_ref().unwrap().multisignature.clone().unwrap();
+        let signatures: Vec<_> = multisignature
+            .subsignatures
+            .iter()
+            .map(|subsignature| subsignature.signature)
+            .collect();
+        assert_eq!(signatures, vec![Some([1; 64]), Some([2; 64])]);
+    }
+}
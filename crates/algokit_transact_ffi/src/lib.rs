@@ -77,6 +77,21 @@ impl From<algokit_transact::AlgoKitTransactError> for AlgoKitTransactError {
                     error_msg: e.to_string(),
                 }
             }
+            algokit_transact::AlgoKitTransactError::TransactionFileError { .. } => {
+                AlgoKitTransactError::DecodingError {
+                    error_msg: e.to_string(),
+                }
+            }
+            algokit_transact::AlgoKitTransactError::TransactionFileWriteError { .. } => {
+                AlgoKitTransactError::EncodingError {
+                    error_msg: e.to_string(),
+                }
+            }
+            algokit_transact::AlgoKitTransactError::InvalidWalletTransaction { .. } => {
+                AlgoKitTransactError::InputError {
+                    error_msg: e.to_string(),
+                }
+            }
         }
     }
 }
@@ -0,0 +1,444 @@
+//! Reconstruction of signed transactions from algod blocks.
+//!
+//! Transactions are stored in a block as [`SignedTxnInBlock`] entries, where the genesis ID and
+//! genesis hash are stripped from each transaction. The genesis ID is flagged by `hgi`, while the
+//! genesis hash is always that of the block, and the effects of the transaction are recorded
+//! alongside it as apply data. This module restores the complete [`SignedTransaction`] from the
+//! block header, so that its ID matches the on-chain transaction ID, and exposes the apply data as
+//! typed values.
+
+use crate::error::AlgoKitUtilsError;
+use algod_client::models::{Block, BlockAppEvalDelta, BlockStateDelta, SignedTxnInBlock};
+use algokit_transact::{Address, Byte32, SignedTransaction, TransactionId};
+use std::collections::HashMap;
+
+const SET_BYTES_ACTION: u32 = 1;
+const SET_UINT_ACTION: u32 = 2;
+const DELETE_ACTION: u32 = 3;
+
+/// A change to a single key of app global or local state.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ValueDelta {
+    /// The key was set to a byte value.
+    Bytes(String),
+    /// The key was set to an integer value.
+    Uint(u64),
+    /// The key was deleted.
+    Delete,
+}
+
+/// The state changes made by an app call.
+#[derive(Debug, Clone, PartialEq, Default)]
+pub struct EvalDelta {
+    /// Changes to the app's global state, by key.
+    pub global_delta: HashMap<String, ValueDelta>,
+    /// Changes to local state, by the index of the account in the transaction's accounts
+    /// (0 being the sender) and then by key.
+    pub local_deltas: HashMap<u64, HashMap<String, ValueDelta>>,
+    /// Inner transactions issued by the app call.
+    pub inner_transactions: Vec<BlockTransaction>,
+    /// Accounts referenced by local deltas that are not in the transaction's accounts.
+    pub shared_accounts: Vec<Address>,
+    /// Logs emitted by the app call.
+    pub logs: Vec<String>,
+}
+
+/// The effects of a transaction recorded in the block alongside it.
+#[derive(Debug, Clone, PartialEq, Default)]
+pub struct ApplyData {
+    /// Amount sent to the close-remainder-to account of a payment.
+    pub closing_amount: Option<u64>,
+    /// Amount of the asset sent to the close-remainder-to account of an asset transfer.
+    pub asset_closing_amount: Option<u64>,
+    /// Rewards applied to the sender.
+    pub sender_rewards: Option<u64>,
+    /// Rewards applied to the receiver.
+    pub receiver_rewards: Option<u64>,
+    /// Rewards applied to the close-remainder-to account.
+    pub close_rewards: Option<u64>,
+    /// State changes made by an app call.
+    pub eval_delta: Option<EvalDelta>,
+    /// ID of the asset created by the transaction.
+    pub created_asset_id: Option<u64>,
+    /// ID of the app created by the transaction.
+    pub created_app_id: Option<u64>,
+}
+
+/// A transaction from a block, restored to the form it was signed in.
+#[derive(Debug, Clone, PartialEq)]
+pub struct BlockTransaction {
+    /// The complete signed transaction, including its genesis ID and hash.
+    pub signed_transaction: SignedTransaction,
+    /// The msgpack encoded logic signature, if the transaction was signed by one.
+    pub logic_signature: Option<Vec<u8>>,
+    /// The effects of the transaction.
+    pub apply_data: ApplyData,
+}
+
+impl BlockTransaction {
+    /// Restores a transaction from a block entry using the genesis ID and hash of the block.
+    pub fn from_signed_txn_in_block(
+        stib: &SignedTxnInBlock,
+        genesis_id: Option<&str>,
+        genesis_hash: Option<&[u8]>,
+    ) -> Result<Self, AlgoKitUtilsError> {
+        let mut signed_transaction = stib.signed_transaction.clone();
+        let header = signed_transaction.transaction.header_mut();
+
+        if stib.has_genesis_id.unwrap_or(false) {
+            let genesis_id = genesis_id.ok_or_else(|| AlgoKitUtilsError::InputError {
+                err_msg: "Block transaction has a genesis ID but the block does not".to_string(),
+            })?;
+            header.genesis_id = Some(genesis_id.to_string());
+        }
+
+        // Protocols since genesis hashes became required never set the `hgh` flag, so like
+        // go-algorand the block's genesis hash is restored whenever the entry has none.
+        if header.genesis_hash.is_none() {
+            let genesis_hash = genesis_hash.ok_or_else(|| AlgoKitUtilsError::InputError {
+                err_msg: "Block transaction has no genesis hash and neither does the block"
+                    .to_string(),
+            })?;
+            let genesis_hash: Byte32 =
+                genesis_hash
+                    .try_into()
+                    .map_err(|_| AlgoKitUtilsError::InputError {
+                        err_msg: format!(
+                            "Block genesis hash must be 32 bytes, got {}",
+                            genesis_hash.len()
+                        ),
+                    })?;
+            header.genesis_hash = Some(genesis_hash);
+        }
+
+        Self::with_apply_data(signed_transaction, stib)
+    }
+
+    /// Restores an inner transaction, which is recorded as it was issued by the app and so has
+    /// no genesis fields to restore.
+    fn from_inner_txn(stib: &SignedTxnInBlock) -> Result<Self, AlgoKitUtilsError> {
+        Self::with_apply_data(stib.signed_transaction.clone(), stib)
+    }
+
+    fn with_apply_data(
+        signed_transaction: SignedTransaction,
+        stib: &SignedTxnInBlock,
+    ) -> Result<Self, AlgoKitUtilsError> {
+        let eval_delta = stib
+            .eval_delta
+            .as_ref()
+            .map(EvalDelta::from_block_delta)
+            .transpose()?;
+
+        Ok(Self {
+            signed_transaction,
+            logic_signature: stib.logic_signature.clone(),
+            apply_data: ApplyData {
+                closing_amount: stib.closing_amount,
+                asset_closing_amount: stib.asset_closing_amount,
+                sender_rewards: stib.sender_rewards,
+                receiver_rewards: stib.receiver_rewards,
+                close_rewards: stib.close_rewards,
+                eval_delta,
+                created_asset_id: stib.config_asset,
+                created_app_id: stib.application_id,
+            },
+        })
+    }
+
+    /// Returns the on-chain ID of the transaction.
+    pub fn id(&self) -> Result<String, AlgoKitUtilsError> {
+        Ok(self.signed_transaction.id()?)
+    }
+}
+
+impl EvalDelta {
+    fn from_block_delta(delta: &BlockAppEvalDelta) -> Result<Self, AlgoKitUtilsError> {
+        let global_delta = delta
+            .global_delta
+            .as_ref()
+            .map(state_delta)
+            .transpose()?
+            .unwrap_or_default();
+
+        let local_deltas = delta
+            .local_deltas
+            .iter()
+            .flatten()
+            .map(|(index, delta)| Ok((*index, state_delta(delta)?)))
+            .collect::<Result<_, AlgoKitUtilsError>>()?;
+
+        let inner_transactions = delta
+            .inner_txns
+            .iter()
+            .flatten()
+            .map(BlockTransaction::from_inner_txn)
+            .collect::<Result<_, _>>()?;
+
+        let shared_accounts = delta
+            .shared_accounts
+            .iter()
+            .flatten()
+            .map(|bytes| {
+                let pub_key: Byte32 =
+                    bytes
+                        .as_slice()
+                        .try_into()
+                        .map_err(|_| AlgoKitUtilsError::InputError {
+                            err_msg: format!(
+                                "Shared account must be 32 bytes, got {}",
+                                bytes.len()
+                            ),
+                        })?;
+                Ok(Address(pub_key))
+            })
+            .collect::<Result<_, AlgoKitUtilsError>>()?;
+
+        Ok(Self {
+            global_delta,
+            local_deltas,
+            inner_transactions,
+            shared_accounts,
+            logs: delta.logs.clone().unwrap_or_default(),
+        })
+    }
+}
+
+fn state_delta(delta: &BlockStateDelta) -> Result<HashMap<String, ValueDelta>, AlgoKitUtilsError> {
+    delta
+        .iter()
+        .map(|(key, value)| {
+            let value = match value.action {
+                SET_BYTES_ACTION => ValueDelta::Bytes(value.bytes.clone().unwrap_or_default()),
+                SET_UINT_ACTION => ValueDelta::Uint(value.uint.unwrap_or_default()),
+                DELETE_ACTION => ValueDelta::Delete,
+                action => {
+                    return Err(AlgoKitUtilsError::InputError {
+                        err_msg: format!("Unknown state delta action {} for key {}", action, key),
+                    });
+                }
+            };
+            Ok((key.clone(), value))
+        })
+        .collect()
+}
+
+/// Restores every transaction in a block using the block's genesis ID and hash.
+pub fn block_transactions(block: &Block) -> Result<Vec<BlockTransaction>, AlgoKitUtilsError> {
+    block
+        .transactions
+        .iter()
+        .flatten()
+        .map(|stib| {
+            BlockTransaction::from_signed_txn_in_block(
+                stib,
+                block.genesis_id.as_deref(),
+                block.genesis_hash.as_deref(),
+            )
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use algod_client::models::BlockEvalDelta;
+    use algokit_transact::AlgorandMsgpack;
+    use algokit_transact::test_utils::{TestDataMother, TransactionMother};
+
+    fn stripped(stx: &SignedTransaction) -> SignedTxnInBlock {
+        let mut stripped = stx.clone();
+        let header = stripped.transaction.header_mut();
+        header.genesis_id = None;
+        header.genesis_hash = None;
+
+        SignedTxnInBlock {
+            signed_transaction: stripped,
+            has_genesis_id: Some(true),
+            ..Default::default()
+        }
+    }
+
+    fn signed_payment() -> SignedTransaction {
+        SignedTransaction {
+            transaction: TransactionMother::simple_payment().build().unwrap(),
+            signature: Some([1; 64]),
+            auth_address: None,
+            multisignature: None,
+        }
+    }
+
+    fn block_for(stx: &SignedTransaction, entries: Vec<SignedTxnInBlock>) -> Block {
+        let header = stx.transaction.header();
+        Block {
+            genesis_id: header.genesis_id.clone(),
+            genesis_hash: header.genesis_hash.map(|hash| hash.to_vec()),
+            transactions: Some(entries),
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn test_restores_genesis_and_id() {
+        let stx = signed_payment();
+        let stib = stripped(&stx);
+        assert_ne!(stib.signed_transaction.id().unwrap(), stx.id().unwrap());
+
+        let txns = block_transactions(&block_for(&stx, vec![stib])).unwrap();
+
+        assert_eq!(txns.len(), 1);
+        assert_eq!(txns[0].signed_transaction, stx);
+        assert_eq!(txns[0].id().unwrap(), stx.id().unwrap());
+    }
+
+    #[test]
+    fn test_respects_genesis_id_flag() {
+        let stx = signed_payment();
+        let stib = SignedTxnInBlock {
+            has_genesis_id: None,
+            ..stripped(&stx)
+        };
+
+        let txns = block_transactions(&block_for(&stx, vec![stib])).unwrap();
+        let header = txns[0].signed_transaction.transaction.header();
+
+        assert_eq!(header.genesis_id, None);
+        assert_eq!(header.genesis_hash, stx.transaction.header().genesis_hash);
+    }
+
+    #[test]
+    fn test_missing_block_genesis_hash() {
+        let stx = signed_payment();
+        let block = Block {
+            genesis_hash: None,
+            ..block_for(&stx, vec![stripped(&stx)])
+        };
+
+        let error = block_transactions(&block).unwrap_err();
+
+        assert_eq!(
+            error.to_string(),
+            "Block transaction has no genesis hash and neither does the block"
+        );
+    }
+
+    #[test]
+    fn test_apply_data() {
+        let stx = signed_payment();
+        let inner = SignedTxnInBlock {
+            signed_transaction: stx.clone(),
+            ..Default::default()
+        };
+        let stib = SignedTxnInBlock {
+            closing_amount: Some(5),
+            sender_rewards: Some(2),
+            application_id: Some(1234),
+            eval_delta: Some(BlockAppEvalDelta {
+                global_delta: Some(HashMap::from([
+                    (
+                        "count".to_string(),
+                        BlockEvalDelta {
+                            action: SET_UINT_ACTION,
+                            uint: Some(3),
+                            bytes: None,
+                        },
+                    ),
+                    ("old".to_string(), BlockEvalDelta::new(DELETE_ACTION)),
+                ])),
+                local_deltas: Some(HashMap::from([(
+                    0,
+                    HashMap::from([(
+                        "name".to_string(),
+                        BlockEvalDelta {
+                            action: SET_BYTES_ACTION,
+                            uint: None,
+                            bytes: Some("algo".to_string()),
+                        },
+                    )]),
+                )])),
+                inner_txns: Some(vec![inner]),
+                shared_accounts: Some(vec![stx.transaction.sender().0.to_vec()]),
+                logs: Some(vec!["log".to_string()]),
+            }),
+            ..stripped(&stx)
+        };
+
+        let txn = BlockTransaction::from_signed_txn_in_block(
+            &stib,
+            stx.transaction.header().genesis_id.as_deref(),
+            stx.transaction
+                .header()
+                .genesis_hash
+                .as_ref()
+                .map(|hash| hash.as_slice()),
+        )
+        .unwrap();
+        let apply_data = &txn.apply_data;
+        let eval_delta = apply_data.eval_delta.as_ref().unwrap();
+
+        assert_eq!(apply_data.closing_amount, Some(5));
+        assert_eq!(apply_data.sender_rewards, Some(2));
+        assert_eq!(apply_data.created_app_id, Some(1234));
+        assert_eq!(apply_data.created_asset_id, None);
+        assert_eq!(eval_delta.global_delta["count"], ValueDelta::Uint(3));
+        assert_eq!(eval_delta.global_delta["old"], ValueDelta::Delete);
+        assert_eq!(
+            eval_delta.local_deltas[&0]["name"],
+            ValueDelta::Bytes("algo".to_string())
+        );
+        assert_eq!(eval_delta.inner_transactions[0].signed_transaction, stx);
+        assert_eq!(
+            eval_delta.shared_accounts,
+            vec![stx.transaction.sender().clone()]
+        );
+        assert_eq!(eval_delta.logs, vec!["log".to_string()]);
+    }
+
+    #[test]
+    fn test_decoded_block() {
+        let stx = signed_payment();
+        let block = block_for(&stx, vec![stripped(&stx)]);
+        let decoded = Block::decode(&block.encode().unwrap()).unwrap();
+
+        let txns = block_transactions(&decoded).unwrap();
+
+        assert_eq!(txns[0].id().unwrap(), stx.id().unwrap());
+    }
+
+    #[test]
+    fn test_restores_testnet_transaction_id() {
+        // A testnet state proof transaction, stored the way algod stores it under protocols that
+        // require a genesis hash: stripped of the hash and without the `hgh` flag.
+        let data = TestDataMother::state_proof();
+        let genesis_hash = data.transaction.header().genesis_hash.unwrap();
+        let mut transaction = data.transaction;
+        transaction.header_mut().genesis_hash = None;
+        let block = Block {
+            round: Some(24098947),
+            genesis_id: Some("testnet-v1.0".to_string()),
+            genesis_hash: Some(genesis_hash.to_vec()),
+            transactions: Some(vec![SignedTxnInBlock {
+                signed_transaction: SignedTransaction {
+                    transaction,
+                    signature: None,
+                    auth_address: None,
+                    multisignature: None,
+                },
+                ..Default::default()
+            }]),
+            ..Default::default()
+        };
+        let decoded = Block::decode(&block.encode().unwrap()).unwrap();
+        assert_eq!(
+            decoded.transactions.as_ref().unwrap()[0].has_genesis_hash,
+            None
+        );
+
+        let txns = block_transactions(&decoded).unwrap();
+
+        assert_eq!(
+            txns[0].id().unwrap(),
+            "6D3MLKOASKUXHFTTWYUG563UBKZ5RW3FFKN6ZUUWBCY47RZT3HIA"
+        );
+    }
+}
@@ -0,0 +1,277 @@
+//! Block header hashing and chain-link verification.
+//!
+//! A block hash is the SHA-512/256 digest of the canonical msgpack encoding of the block header,
+//! prefixed with the "BH" domain separator. The header is the block without its transactions,
+//! and every header records the hash of the previous block in `prev`, so a sequence of headers
+//! can be checked for tampering or reordering without trusting the node that served them.
+//!
+//! Headers are only read from the msgpack bytes algod serves. A decoded
+//! [`Block`](algod_client::models::Block) drops the
+//! fields its model does not know about, which would change the hash and make an honest chain
+//! look tampered with.
+
+use crate::error::AlgoKitUtilsError;
+use algokit_transact::{AlgorandMsgpack, Byte32};
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha512_256};
+
+const TRANSACTIONS_KEY: &str = "txns";
+
+/// The header of a block, kept as its msgpack representation so that it can be re-encoded
+/// exactly as algod encoded it.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(transparent)]
+pub struct BlockHeader(rmpv::Value);
+
+impl AlgorandMsgpack for BlockHeader {
+    const PREFIX: &'static [u8] = b"BH";
+}
+
+impl BlockHeader {
+    /// Creates a header from msgpack encoded block bytes.
+    ///
+    /// Both a bare block and a `GetBlock` response, where the block is nested under `block`,
+    /// are accepted.
+    pub fn from_msgpack(bytes: &[u8]) -> Result<Self, AlgoKitUtilsError> {
+        let value = rmpv::decode::read_value(&mut &bytes[..]).map_err(|e| {
+            AlgoKitUtilsError::InputError {
+                err_msg: format!("Failed to decode block: {}", e),
+            }
+        })?;
+
+        let rmpv::Value::Map(mut entries) = value else {
+            return Err(AlgoKitUtilsError::InputError {
+                err_msg: "Expected block to be a msgpack map".to_string(),
+            });
+        };
+
+        if let Some(index) = entries
+            .iter()
+            .position(|(k, _)| k.as_str() == Some("block"))
+        {
+            let (_, block) = entries.swap_remove(index);
+            let mut bytes = Vec::new();
+            rmpv::encode::write_value(&mut bytes, &block).map_err(|e| {
+                AlgoKitUtilsError::InputError {
+                    err_msg: format!("Failed to encode block: {}", e),
+                }
+            })?;
+            return Self::from_msgpack(&bytes);
+        }
+
+        entries.retain(|(k, _)| k.as_str() != Some(TRANSACTIONS_KEY));
+        Ok(Self(rmpv::Value::Map(entries)))
+    }
+
+    /// Returns the round of the block, which is omitted from the encoding of round 0.
+    pub fn round(&self) -> u64 {
+        self.field("rnd").and_then(|v| v.as_u64()).unwrap_or(0)
+    }
+
+    /// Returns the hash of the previous block, or zeros for the genesis block.
+    pub fn previous_block_hash(&self) -> Byte32 {
+        self.bytes32("prev").unwrap_or_default()
+    }
+
+    /// Returns the genesis hash of the chain the block belongs to.
+    pub fn genesis_hash(&self) -> Option<Byte32> {
+        self.bytes32("gh")
+    }
+
+    /// Returns the genesis ID of the chain the block belongs to.
+    pub fn genesis_id(&self) -> Option<&str> {
+        self.field("gen").and_then(|v| v.as_str())
+    }
+
+    /// Computes the hash of the block.
+    pub fn hash(&self) -> Result<Byte32, AlgoKitUtilsError> {
+        let mut hasher = Sha512_256::new();
+        hasher.update(self.encode()?);
+        Ok(hasher.finalize().into())
+    }
+
+    /// Computes the hash of the block as the base32 string returned by algod.
+    pub fn hash_base32(&self) -> Result<String, AlgoKitUtilsError> {
+        Ok(base32::encode(
+            base32::Alphabet::Rfc4648 { padding: false },
+            &self.hash()?,
+        ))
+    }
+
+    pub(crate) fn field(&self, key: &str) -> Option<&rmpv::Value> {
+        self.0
+            .as_map()?
+            .iter()
+            .find(|(k, _)| k.as_str() == Some(key))
+            .map(|(_, v)| v)
+    }
+
+    pub(crate) fn bytes32(&self, key: &str) -> Option<Byte32> {
+        self.field(key)?.as_slice()?.try_into().ok()
+    }
+}
+
+/// Verifies that a sequence of headers forms a contiguous, correctly linked chain.
+///
+/// Each header must be for the round after the previous header, belong to the same genesis
+/// and record the hash of the previous header as its `previous_block_hash`.
+pub fn verify_chain(headers: &[BlockHeader]) -> Result<(), AlgoKitUtilsError> {
+    for pair in headers.windows(2) {
+        let (previous, current) = (&pair[0], &pair[1]);
+        let round = current.round();
+        let broken = |err_msg: String| AlgoKitUtilsError::BrokenChainLink { round, err_msg };
+
+        let Some(expected) = previous.round().checked_add(1) else {
+            return Err(broken(format!(
+                "no round can follow round {}",
+                previous.round()
+            )));
+        };
+        if round != expected {
+            return Err(broken(format!(
+                "expected round {} to follow round {}",
+                expected,
+                previous.round()
+            )));
+        }
+
+        if current.genesis_hash() != previous.genesis_hash()
+            || current.genesis_id() != previous.genesis_id()
+        {
+            return Err(broken(
+                "genesis differs from the previous block".to_string(),
+            ));
+        }
+
+        let previous_hash = previous.hash()?;
+        if current.previous_block_hash() != previous_hash {
+            return Err(broken(format!(
+                "previous block hash {} does not match the hash of block {} ({})",
+                base32::encode(
+                    base32::Alphabet::Rfc4648 { padding: false },
+                    &current.previous_block_hash()
+                ),
+                previous.round(),
+                previous.hash_base32()?
+            )));
+        }
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use algod_client::models::Block;
+
+    fn header(block: &Block) -> BlockHeader {
+        BlockHeader::from_msgpack(&block.encode_raw().unwrap()).unwrap()
+    }
+
+    fn chain(length: u64) -> Vec<Block> {
+        let mut blocks: Vec<Block> = Vec::new();
+        for round in 0..length {
+            let prev = blocks
+                .last()
+                .map(|previous| header(previous).hash().unwrap().to_vec());
+            blocks.push(Block {
+                round: (round > 0).then_some(round),
+                previous_block_hash: prev,
+                genesis_id: Some("testnet-v1.0".to_string()),
+                genesis_hash: Some(vec![7; 32]),
+                timestamp: Some(1_700_000_000 + round),
+                seed: Some(vec![round as u8; 32]),
+                ..Default::default()
+            });
+        }
+        blocks
+    }
+
+    fn headers(blocks: &[Block]) -> Vec<BlockHeader> {
+        blocks.iter().map(header).collect()
+    }
+
+    #[test]
+    fn test_hash_uses_bh_prefix_and_ignores_transactions() {
+        let block = chain(1).remove(0);
+        let block_header = header(&block);
+
+        let encoded = block_header.encode().unwrap();
+        assert_eq!(&encoded[..2], b"BH");
+        assert_eq!(
+            block_header.hash().unwrap(),
+            <[u8; 32]>::from(Sha512_256::digest(&encoded))
+        );
+
+        let with_txns = Block {
+            transactions: Some(vec![Default::default()]),
+            ..block
+        };
+        assert_eq!(
+            header(&with_txns).hash().unwrap(),
+            block_header.hash().unwrap()
+        );
+    }
+
+    #[test]
+    fn test_from_get_block_response() {
+        let block = chain(2).remove(1);
+        let response = algod_client::models::GetBlock::new(block.clone());
+
+        let from_response = BlockHeader::from_msgpack(&response.encode().unwrap()).unwrap();
+
+        assert_eq!(from_response, header(&block));
+        assert_eq!(from_response.round(), 1);
+        assert_eq!(from_response.genesis_id(), Some("testnet-v1.0"));
+    }
+
+    #[test]
+    fn test_verify_chain() {
+        let blocks = chain(5);
+
+        assert!(verify_chain(&headers(&blocks)).is_ok());
+    }
+
+    #[test]
+    fn test_detects_tampered_block() {
+        let mut blocks = chain(4);
+        blocks[1].timestamp = Some(1);
+
+        let error = verify_chain(&headers(&blocks)).unwrap_err();
+
+        assert!(matches!(
+            error,
+            AlgoKitUtilsError::BrokenChainLink { round: 2, .. }
+        ));
+    }
+
+    #[test]
+    fn test_detects_out_of_order_blocks() {
+        let mut blocks = chain(4);
+        blocks.swap(1, 2);
+
+        let error = verify_chain(&headers(&blocks)).unwrap_err();
+
+        assert_eq!(
+            error.to_string(),
+            "Block 2 does not link to the previous block: expected round 1 to follow round 0"
+        );
+    }
+
+    #[test]
+    fn test_rejects_blocks_after_the_last_round() {
+        let mut blocks = chain(2);
+        blocks[0].round = Some(u64::MAX);
+
+        let error = verify_chain(&headers(&blocks)).unwrap_err();
+
+        assert_eq!(
+            error.to_string(),
+            format!(
+                "Block 1 does not link to the previous block: no round can follow round {}",
+                u64::MAX
+            )
+        );
+    }
+}
@@ -0,0 +1,400 @@
+//! Following the chain as a stream of blocks.
+//!
+//! [`ChainFollower`] yields every block from a starting round onwards, in order. While it is
+//! behind the node it fetches blocks in concurrent batches, and once caught up it waits for each
+//! new round with `wait_for_block`. Blocks are only fetched when the consumer polls the stream, so
+//! at most one batch is buffered at a time. Transient HTTP failures are retried with exponential
+//! backoff, while client errors end the stream straight away. An optional watermark callback
+//! reports the last round the consumer has finished with so that it can be persisted and used as
+//! the starting round after a restart.
+
+use crate::error::AlgoKitUtilsError;
+use algod_client::AlgodClient;
+use algod_client::apis::Error as AlgodError;
+use algod_client::models::GetBlock;
+use algokit_http_client::HttpError;
+use futures_util::Stream;
+use futures_util::future::try_join_all;
+use std::collections::VecDeque;
+use std::future::Future;
+use std::time::Duration;
+
+const DEFAULT_BATCH_SIZE: u64 = 10;
+const DEFAULT_RETRY_DELAY: Duration = Duration::from_millis(500);
+const DEFAULT_MAX_RETRY_DELAY: Duration = Duration::from_secs(30);
+
+type WatermarkCallback = Box<dyn FnMut(u64) + Send + Sync>;
+
+/// Follows the chain from a starting round.
+pub struct ChainFollower {
+    client: AlgodClient,
+    next_round: u64,
+    batch_size: u64,
+    max_retries: Option<u32>,
+    retry_delay: Duration,
+    max_retry_delay: Duration,
+    watermark: Option<WatermarkCallback>,
+}
+
+impl ChainFollower {
+    /// Creates a follower that starts with the block of `start_round`.
+    pub fn new(client: AlgodClient, start_round: u64) -> Self {
+        Self {
+            client,
+            next_round: start_round,
+            batch_size: DEFAULT_BATCH_SIZE,
+            max_retries: None,
+            retry_delay: DEFAULT_RETRY_DELAY,
+            max_retry_delay: DEFAULT_MAX_RETRY_DELAY,
+            watermark: None,
+        }
+    }
+
+    /// Sets the maximum number of blocks fetched concurrently while catching up.
+    pub fn with_batch_size(mut self, batch_size: u64) -> Self {
+        self.batch_size = batch_size.max(1);
+        self
+    }
+
+    /// Sets how many times a request that failed with a transient HTTP error is retried before
+    /// the error is returned. Requests are retried indefinitely by default.
+    pub fn with_max_retries(mut self, max_retries: u32) -> Self {
+        self.max_retries = Some(max_retries);
+        self
+    }
+
+    /// Sets the delay before the first retry and the maximum delay it doubles up to.
+    pub fn with_retry_delay(mut self, retry_delay: Duration, max_retry_delay: Duration) -> Self {
+        self.retry_delay = retry_delay;
+        self.max_retry_delay = max_retry_delay.max(retry_delay);
+        self
+    }
+
+    /// Sets a callback that is called with the round of each block once the consumer has
+    /// finished with it, which is when the next block is requested from the stream.
+    pub fn with_watermark<F>(mut self, watermark: F) -> Self
+    where
+        F: FnMut(u64) + Send + Sync + 'static,
+    {
+        self.watermark = Some(Box::new(watermark));
+        self
+    }
+
+    /// Turns the follower into a stream of blocks.
+    ///
+    /// The stream ends after yielding an error that could not be retried.
+    pub fn into_stream(self) -> impl Stream<Item = Result<GetBlock, AlgoKitUtilsError>> + Send {
+        let state = FollowerState {
+            follower: self,
+            last_round: None,
+            buffer: VecDeque::new(),
+            consumed_round: None,
+            done: false,
+        };
+
+        futures_util::stream::unfold(state, |mut state| async move {
+            if state.done {
+                return None;
+            }
+            if let (Some(round), Some(watermark)) = (
+                state.consumed_round.take(),
+                state.follower.watermark.as_mut(),
+            ) {
+                watermark(round);
+            }
+
+            match state.next_block().await {
+                Ok((round, block)) => {
+                    state.consumed_round = Some(round);
+                    Some((Ok(block), state))
+                }
+                Err(e) => {
+                    state.done = true;
+                    Some((Err(e), state))
+                }
+            }
+        })
+    }
+
+    async fn with_retries<T, F, Fut>(&self, mut request: F) -> Result<T, AlgoKitUtilsError>
+    where
+        F: FnMut() -> Fut,
+        Fut: Future<Output = Result<T, AlgodError>>,
+    {
+        let mut attempt = 0;
+        let mut delay = self.retry_delay;
+        loop {
+            match request().await {
+                Ok(value) => return Ok(value),
+                Err(e) if is_transient(&e) && self.max_retries.is_none_or(|max| attempt < max) => {
+                    attempt += 1;
+                    tokio::time::sleep(delay).await;
+                    delay = (delay * 2).min(self.max_retry_delay);
+                }
+                Err(e) => return Err(e.into()),
+            }
+        }
+    }
+}
+
+/// Whether a failed request may succeed if tried again: connection failures, server errors and
+/// rate limiting. Other status errors, such as a pruned round or a rejected token, are permanent.
+fn is_transient(error: &AlgodError) -> bool {
+    match error {
+        AlgodError::Http {
+            source: HttpError::RequestError { .. },
+        } => true,
+        AlgodError::Http {
+            source: HttpError::StatusError { status, .. },
+        } => *status == 429 || (500..600).contains(status),
+        _ => false,
+    }
+}
+
+struct FollowerState {
+    follower: ChainFollower,
+    /// The latest round known to be available on the node.
+    last_round: Option<u64>,
+    buffer: VecDeque<(u64, GetBlock)>,
+    /// The round of the block most recently handed to the consumer.
+    consumed_round: Option<u64>,
+    done: bool,
+}
+
+impl FollowerState {
+    async fn next_block(&mut self) -> Result<(u64, GetBlock), AlgoKitUtilsError> {
+        loop {
+            if let Some(block) = self.buffer.pop_front() {
+                return Ok(block);
+            }
+
+            let follower = &self.follower;
+            let next_round = follower.next_round;
+            match self.last_round {
+                Some(last_round) if next_round <= last_round => {
+                    let count = (last_round - next_round + 1).min(follower.batch_size);
+                    let blocks = try_join_all((next_round..next_round + count).map(|round| {
+                        follower.with_retries(move || follower.client.get_block(round, None))
+                    }))
+                    .await?;
+
+                    self.buffer
+                        .extend((next_round..next_round + count).zip(blocks));
+                    self.follower.next_round += count;
+                }
+                _ => {
+                    let status = follower
+                        .with_retries(|| {
+                            follower.client.wait_for_block(next_round.saturating_sub(1))
+                        })
+                        .await?;
+                    self.last_round = Some(status.last_round);
+                }
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use algod_client::models::Block;
+    use algokit_http_client::{HttpClient, HttpMethod, HttpResponse};
+    use algokit_transact::AlgorandMsgpack;
+    use async_trait::async_trait;
+    use futures_util::StreamExt;
+    use std::collections::HashMap;
+    use std::sync::{Arc, Mutex};
+
+    #[derive(Default)]
+    struct MockState {
+        last_round: u64,
+        failures: u32,
+        /// A status every request fails with, if set.
+        status: Option<u16>,
+        block_requests: Vec<u64>,
+        waits: Vec<u64>,
+    }
+
+    /// A node that produces a new round each time it is waited on while caught up.
+    #[derive(Default)]
+    struct MockAlgod {
+        state: Mutex<MockState>,
+    }
+
+    #[async_trait]
+    impl HttpClient for MockAlgod {
+        async fn request(
+            &self,
+            _method: HttpMethod,
+            path: String,
+            _query: Option<HashMap<String, String>>,
+            _body: Option<Vec<u8>>,
+            _headers: Option<HashMap<String, String>>,
+        ) -> Result<HttpResponse, HttpError> {
+            let mut state = self.state.lock().unwrap();
+            if let Some(status) = state.status {
+                return Err(HttpError::StatusError {
+                    status,
+                    message: "not found".to_string(),
+                });
+            }
+            if state.failures > 0 {
+                state.failures -= 1;
+                return Err(HttpError::RequestError {
+                    message: "connection reset".to_string(),
+                });
+            }
+
+            if let Some(round) = path.strip_prefix("/v2/status/wait-for-block-after/") {
+                let round: u64 = round.parse().unwrap();
+                state.waits.push(round);
+                state.last_round = state.last_round.max(round + 1);
+                let body = format!(
+                    r#"{{"catchup-time":0,"last-round":{},"last-version":"v1","next-version":"v1","next-version-round":0,"next-version-supported":true,"stopped-at-unsupported-round":false,"time-since-last-round":0}}"#,
+                    state.last_round
+                );
+                return Ok(response(body.into_bytes(), "application/json"));
+            }
+
+            let round: u64 = path.strip_prefix("/v2/blocks/").unwrap().parse().unwrap();
+            state.block_requests.push(round);
+            let block = GetBlock::new(Block {
+                round: Some(round),
+                ..Default::default()
+            });
+            Ok(response(block.encode().unwrap(), "application/msgpack"))
+        }
+    }
+
+    fn response(body: Vec<u8>, content_type: &str) -> HttpResponse {
+        HttpResponse {
+            body,
+            headers: HashMap::from([("content-type".to_string(), content_type.to_string())]),
+        }
+    }
+
+    fn node(last_round: u64, failures: u32) -> (Arc<MockAlgod>, AlgodClient) {
+        let mock = Arc::new(MockAlgod {
+            state: Mutex::new(MockState {
+                last_round,
+                failures,
+                ..Default::default()
+            }),
+        });
+        (mock.clone(), AlgodClient::new(mock))
+    }
+
+    fn rounds(blocks: Vec<Result<GetBlock, AlgoKitUtilsError>>) -> Vec<u64> {
+        blocks
+            .into_iter()
+            .map(|block| block.unwrap().block.round.unwrap())
+            .collect()
+    }
+
+    #[tokio::test]
+    async fn test_catches_up_in_batches_then_follows() {
+        let (mock, client) = node(10, 0);
+
+        let blocks = ChainFollower::new(client, 3)
+            .with_batch_size(4)
+            .into_stream()
+            .take(10)
+            .collect::<Vec<_>>()
+            .await;
+
+        assert_eq!(rounds(blocks), (3..13).collect::<Vec<_>>());
+        let state = mock.state.lock().unwrap();
+        assert_eq!(state.block_requests, (3..13).collect::<Vec<_>>());
+        // One wait to learn the last round, then one per new round after catching up.
+        assert_eq!(state.waits, vec![2, 10, 11]);
+    }
+
+    #[tokio::test]
+    async fn test_only_fetches_when_polled() {
+        let (mock, client) = node(100, 0);
+        let mut stream = Box::pin(
+            ChainFollower::new(client, 1)
+                .with_batch_size(5)
+                .into_stream(),
+        );
+
+        stream.next().await.unwrap().unwrap();
+
+        assert_eq!(mock.state.lock().unwrap().block_requests.len(), 5);
+    }
+
+    #[tokio::test]
+    async fn test_retries_http_errors() {
+        let (_, client) = node(5, 3);
+
+        let blocks = ChainFollower::new(client, 1)
+            .with_retry_delay(Duration::from_millis(1), Duration::from_millis(2))
+            .into_stream()
+            .take(3)
+            .collect::<Vec<_>>()
+            .await;
+
+        assert_eq!(rounds(blocks), vec![1, 2, 3]);
+    }
+
+    #[tokio::test]
+    async fn test_ends_after_retries_are_exhausted() {
+        let (_, client) = node(5, 3);
+
+        let blocks = ChainFollower::new(client, 1)
+            .with_max_retries(2)
+            .with_retry_delay(Duration::from_millis(1), Duration::from_millis(2))
+            .into_stream()
+            .collect::<Vec<_>>()
+            .await;
+
+        assert_eq!(blocks.len(), 1);
+        assert!(matches!(
+            blocks[0],
+            Err(AlgoKitUtilsError::AlgodError {
+                source: AlgodError::Http { .. }
+            })
+        ));
+    }
+
+    #[tokio::test]
+    async fn test_ends_on_client_errors_without_retrying() {
+        let (mock, client) = node(5, 0);
+        mock.state.lock().unwrap().status = Some(404);
+
+        let blocks = ChainFollower::new(client, 1)
+            .with_retry_delay(Duration::from_millis(1), Duration::from_millis(2))
+            .into_stream()
+            .collect::<Vec<_>>()
+            .await;
+
+        assert_eq!(blocks.len(), 1);
+        assert!(matches!(
+            blocks[0],
+            Err(AlgoKitUtilsError::AlgodError {
+                source: AlgodError::Http {
+                    source: HttpError::StatusError { status: 404, .. }
+                }
+            })
+        ));
+    }
+
+    #[tokio::test]
+    async fn test_reports_watermark_after_consumption() {
+        let (_, client) = node(10, 0);
+        let watermarks = Arc::new(Mutex::new(Vec::new()));
+        let recorded = watermarks.clone();
+
+        let mut stream = Box::pin(
+            ChainFollower::new(client, 4)
+                .with_watermark(move |round| recorded.lock().unwrap().push(round))
+                .into_stream(),
+        );
+        for _ in 0..3 {
+            stream.next().await.unwrap().unwrap();
+        }
+
+        assert_eq!(*watermarks.lock().unwrap(), vec![4, 5]);
+    }
+}
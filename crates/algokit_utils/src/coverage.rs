@@ -0,0 +1,430 @@
+//! Line coverage of TEAL programs from execution traces.
+//!
+//! A [`CoverageCollector`] is given the programs to measure, each with a [`ProgramSourceMap`]
+//! and the hash simulate identifies it by, and then fed the execution traces of a test run,
+//! whether returned by simulate with `exec_trace_config` enabled or recorded by the offline
+//! [`Emulator`](crate::teal::Emulator). Every TEAL line that an instruction maps to is counted as
+//! executable, and the resulting [`CoverageReport`] can be written in the lcov and Cobertura
+//! formats that coverage tooling and CI gates already understand.
+
+use crate::error::AlgoKitUtilsError;
+use crate::simulate_trace::{ProgramSourceMap, TraceProgram, program_hash};
+use algod_client::models::{SimulateTransactionGroupResult, SimulationTransactionExecTrace};
+use algokit_abi::Arc56Contract;
+use std::collections::{BTreeMap, HashMap};
+use std::fmt::Write;
+
+/// A program whose execution is being measured.
+#[derive(Debug, Clone)]
+struct TrackedProgram {
+    name: String,
+    source_map: ProgramSourceMap,
+    /// The number of times each program counter was executed.
+    hits: HashMap<u64, u64>,
+}
+
+/// Accumulates the program counters executed by traced programs.
+#[derive(Debug, Clone, Default)]
+pub struct CoverageCollector {
+    programs: Vec<TrackedProgram>,
+    hashes: HashMap<Vec<u8>, usize>,
+}
+
+impl CoverageCollector {
+    /// Creates a collector that measures no programs.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Measures the program with the given hash, as computed by [`program_hash`], reporting it
+    /// under `name`. Traces of programs that were not added are ignored.
+    pub fn with_program(mut self, name: &str, hash: &[u8], source_map: ProgramSourceMap) -> Self {
+        let index = *self.hashes.entry(hash.to_vec()).or_insert_with(|| {
+            self.programs.push(TrackedProgram {
+                name: String::new(),
+                source_map: ProgramSourceMap::default(),
+                hits: HashMap::new(),
+            });
+            self.programs.len() - 1
+        });
+        let program = &mut self.programs[index];
+        program.name = name.to_string();
+        program.source_map = source_map;
+        self
+    }
+
+    /// Measures the approval and clear state programs of an ARC-56 contract, mapped through its
+    /// `sourceInfo` and reported as `<name>.approval.teal` and `<name>.clear.teal`.
+    pub fn with_arc56(
+        self,
+        contract: &Arc56Contract,
+        approval: &[u8],
+        clear: &[u8],
+    ) -> Result<Self, AlgoKitUtilsError> {
+        let approval_map =
+            ProgramSourceMap::from_arc56(contract, TraceProgram::Approval, Some(approval))?;
+        let clear_map =
+            ProgramSourceMap::from_arc56(contract, TraceProgram::ClearState, Some(clear))?;
+        Ok(self
+            .with_program(
+                &format!("{}.approval.teal", contract.name),
+                &program_hash(approval),
+                approval_map,
+            )
+            .with_program(
+                &format!("{}.clear.teal", contract.name),
+                &program_hash(clear),
+                clear_map,
+            ))
+    }
+
+    /// Counts the opcodes executed in a transaction's trace, including those of its inner
+    /// transactions.
+    pub fn record(&mut self, trace: &SimulationTransactionExecTrace) {
+        let programs = [
+            (&trace.logic_sig_trace, &trace.logic_sig_hash),
+            (&trace.approval_program_trace, &trace.approval_program_hash),
+            (
+                &trace.clear_state_program_trace,
+                &trace.clear_state_program_hash,
+            ),
+        ];
+        for (units, hash) in programs {
+            let Some(index) = hash.as_ref().and_then(|hash| self.hashes.get(hash)) else {
+                continue;
+            };
+            let hits = &mut self.programs[*index].hits;
+            for unit in units.iter().flatten() {
+                *hits.entry(unit.pc).or_default() += 1;
+            }
+        }
+        for inner in trace.inner_trace.iter().flatten() {
+            self.record(inner);
+        }
+    }
+
+    /// Counts the opcodes executed by every transaction of a simulated group.
+    pub fn record_simulation(&mut self, group: &SimulateTransactionGroupResult) {
+        for result in &group.txn_results {
+            if let Some(trace) = &result.exec_trace {
+                self.record(trace);
+            }
+        }
+    }
+
+    /// The line coverage of each program measured, in the order they were added.
+    ///
+    /// A line with several instructions counts as executed as many times as its most executed
+    /// instruction.
+    pub fn report(&self) -> CoverageReport {
+        let files = self
+            .programs
+            .iter()
+            .map(|program| {
+                let mut lines = BTreeMap::new();
+                for (pc, source) in program.source_map.lines() {
+                    if let Some(line) = source.teal_line {
+                        let hits = program.hits.get(&pc).copied().unwrap_or_default();
+                        let entry = lines.entry(line).or_insert(0);
+                        *entry = hits.max(*entry);
+                    }
+                }
+                FileCoverage {
+                    name: program.name.clone(),
+                    lines,
+                }
+            })
+            .collect();
+        CoverageReport { files }
+    }
+}
+
+/// The line coverage of one program.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct FileCoverage {
+    pub name: String,
+    /// The number of times each executable 1-based TEAL line was executed.
+    pub lines: BTreeMap<u64, u64>,
+}
+
+impl FileCoverage {
+    /// The number of executable lines.
+    pub fn lines_found(&self) -> u64 {
+        self.lines.len() as u64
+    }
+
+    /// The number of executable lines executed at least once.
+    pub fn lines_hit(&self) -> u64 {
+        self.lines.values().filter(|hits| **hits > 0).count() as u64
+    }
+
+    /// The fraction of executable lines executed, which is 1 for a program without any.
+    pub fn line_rate(&self) -> f64 {
+        rate(self.lines_hit(), self.lines_found())
+    }
+}
+
+/// The line coverage of the programs measured by a [`CoverageCollector`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct CoverageReport {
+    pub files: Vec<FileCoverage>,
+}
+
+impl CoverageReport {
+    /// The number of executable lines across every program.
+    pub fn lines_found(&self) -> u64 {
+        self.files.iter().map(FileCoverage::lines_found).sum()
+    }
+
+    /// The number of executable lines executed at least once across every program.
+    pub fn lines_hit(&self) -> u64 {
+        self.files.iter().map(FileCoverage::lines_hit).sum()
+    }
+
+    /// The fraction of executable lines executed across every program.
+    pub fn line_rate(&self) -> f64 {
+        rate(self.lines_hit(), self.lines_found())
+    }
+
+    /// Writes the report as an lcov tracefile, with one record per program.
+    pub fn to_lcov(&self) -> String {
+        let mut lcov = String::new();
+        for file in &self.files {
+            let _ = writeln!(lcov, "TN:");
+            let _ = writeln!(lcov, "SF:{}", file.name);
+            for (line, hits) in &file.lines {
+                let _ = writeln!(lcov, "DA:{},{}", line, hits);
+            }
+            let _ = writeln!(lcov, "LF:{}", file.lines_found());
+            let _ = writeln!(lcov, "LH:{}", file.lines_hit());
+            let _ = writeln!(lcov, "end_of_record");
+        }
+        lcov
+    }
+
+    /// Writes the report as a Cobertura XML document, with one class per program in a single
+    /// `teal` package. Branch coverage is not measured and is reported as zero.
+    pub fn to_cobertura(&self) -> String {
+        let mut xml = String::new();
+        let _ = writeln!(xml, r#"<?xml version="1.0" ?>"#);
+        let _ = writeln!(
+            xml,
+            r#"<coverage line-rate="{:.4}" branch-rate="0" lines-covered="{}" lines-valid="{}" branches-covered="0" branches-valid="0" complexity="0" version="1" timestamp="0">"#,
+            self.line_rate(),
+            self.lines_hit(),
+            self.lines_found()
+        );
+        let _ = writeln!(xml, "  <packages>");
+        let _ = writeln!(
+            xml,
+            r#"    <package name="teal" line-rate="{:.4}" branch-rate="0" complexity="0">"#,
+            self.line_rate()
+        );
+        let _ = writeln!(xml, "      <classes>");
+        for file in &self.files {
+            let name = escape_xml(&file.name);
+            let _ = writeln!(
+                xml,
+                r#"        <class name="{}" filename="{}" line-rate="{:.4}" branch-rate="0" complexity="0">"#,
+                name,
+                name,
+                file.line_rate()
+            );
+            let _ = writeln!(xml, "          <methods/>");
+            let _ = writeln!(xml, "          <lines>");
+            for (line, hits) in &file.lines {
+                let _ = writeln!(
+                    xml,
+                    r#"            <line number="{}" hits="{}"/>"#,
+                    line, hits
+                );
+            }
+            let _ = writeln!(xml, "          </lines>");
+            let _ = writeln!(xml, "        </class>");
+        }
+        let _ = writeln!(xml, "      </classes>");
+        let _ = writeln!(xml, "    </package>");
+        let _ = writeln!(xml, "  </packages>");
+        let _ = writeln!(xml, "</coverage>");
+        xml
+    }
+}
+
+fn rate(hit: u64, found: u64) -> f64 {
+    if found == 0 {
+        1.0
+    } else {
+        hit as f64 / found as f64
+    }
+}
+
+fn escape_xml(text: &str) -> String {
+    text.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+        .replace('\'', "&apos;")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::ledger_delta::LedgerSnapshot;
+    use crate::teal::{Emulator, assemble, disassemble};
+    use algod_client::models::{SimulateTransactionResult, SimulationOpcodeTraceUnit};
+    use algokit_test_artifacts::arc56_struct_operations;
+    use algokit_transact::test_utils::{AccountMother, TransactionHeaderMother};
+    use algokit_transact::{AppCallTransactionBuilder, OnApplicationComplete};
+
+    fn unit(pc: u64) -> SimulationOpcodeTraceUnit {
+        SimulationOpcodeTraceUnit {
+            pc,
+            ..Default::default()
+        }
+    }
+
+    fn disassembly_map(program: &[u8]) -> ProgramSourceMap {
+        ProgramSourceMap::from_disassembly(&disassemble(program).unwrap().lines())
+    }
+
+    #[test]
+    fn test_counts_lines_across_traces_and_inner_transactions() {
+        // #pragma version 10, pushint 1, pushint 1, return
+        let program = [10u8, 0x81, 0x01, 0x81, 0x01, 0x43];
+        let hash = program_hash(&program);
+        let mut collector =
+            CoverageCollector::new().with_program("app.teal", &hash, disassembly_map(&program));
+
+        let trace = SimulationTransactionExecTrace {
+            approval_program_trace: Some(vec![unit(1), unit(3)]),
+            approval_program_hash: Some(hash.to_vec()),
+            inner_trace: Some(vec![SimulationTransactionExecTrace {
+                approval_program_trace: Some(vec![unit(1)]),
+                approval_program_hash: Some(hash.to_vec()),
+                ..Default::default()
+            }]),
+            ..Default::default()
+        };
+        let mut group = SimulateTransactionGroupResult::default();
+        group.txn_results.push(SimulateTransactionResult {
+            exec_trace: Some(trace),
+            ..Default::default()
+        });
+        collector.record_simulation(&group);
+
+        let report = collector.report();
+        assert_eq!(
+            report.files[0].lines,
+            BTreeMap::from([(2, 2), (3, 1), (4, 0)])
+        );
+        assert_eq!((report.lines_hit(), report.lines_found()), (2, 3));
+        let expected = [
+            "TN:",
+            "SF:app.teal",
+            "DA:2,2",
+            "DA:3,1",
+            "DA:4,0",
+            "LF:3",
+            "LH:2",
+            "end_of_record",
+            "",
+        ];
+        assert_eq!(report.to_lcov(), expected.join("\n"));
+    }
+
+    #[test]
+    fn test_writes_cobertura_xml() {
+        let report = CoverageReport {
+            files: vec![FileCoverage {
+                name: "a<b>.teal".to_string(),
+                lines: BTreeMap::from([(1, 3), (2, 0)]),
+            }],
+        };
+        let expected = [
+            r#"<?xml version="1.0" ?>"#,
+            r#"<coverage line-rate="0.5000" branch-rate="0" lines-covered="1" lines-valid="2" branches-covered="0" branches-valid="0" complexity="0" version="1" timestamp="0">"#,
+            "  <packages>",
+            r#"    <package name="teal" line-rate="0.5000" branch-rate="0" complexity="0">"#,
+            "      <classes>",
+            r#"        <class name="a&lt;b&gt;.teal" filename="a&lt;b&gt;.teal" line-rate="0.5000" branch-rate="0" complexity="0">"#,
+            "          <methods/>",
+            "          <lines>",
+            r#"            <line number="1" hits="3"/>"#,
+            r#"            <line number="2" hits="0"/>"#,
+            "          </lines>",
+            "        </class>",
+            "      </classes>",
+            "    </package>",
+            "  </packages>",
+            "</coverage>",
+            "",
+        ];
+        assert_eq!(report.to_cobertura(), expected.join("\n"));
+    }
+
+    #[test]
+    fn test_maps_arc56_source_info() {
+        let contract =
+            Arc56Contract::from_json(arc56_struct_operations::APPLICATION_ARC56).unwrap();
+        // #pragma version 10, intcblock 1 5, bytecblock 0x626f784b6579, txn ApplicationID
+        let mut approval = vec![10, 0x20, 2, 1, 5, 0x26, 1, 6];
+        approval.extend(b"boxKey");
+        approval.extend([0x31, 0x18]);
+        let clear = [10u8, 0x81, 0x01];
+
+        let mut collector = CoverageCollector::new()
+            .with_arc56(&contract, &approval, &clear)
+            .unwrap();
+        collector.record(&SimulationTransactionExecTrace {
+            approval_program_trace: Some(vec![unit(14)]),
+            approval_program_hash: Some(program_hash(&approval).to_vec()),
+            ..Default::default()
+        });
+
+        let report = collector.report();
+        let names = report.files.iter().map(|file| file.name.as_str());
+        assert_eq!(
+            names.collect::<Vec<_>>(),
+            vec![
+                "Arc56StructOperations.approval.teal",
+                "Arc56StructOperations.clear.teal"
+            ]
+        );
+        assert_eq!(report.files[0].lines.get(&15), Some(&1));
+        assert_eq!(report.files[1].lines_hit(), 0);
+    }
+
+    #[test]
+    fn test_measures_emulated_app_calls() {
+        let approval = assemble(
+            "#pragma version 10\ntxn ApplicationID\nbz create\nint 1\nreturn\ncreate:\nint 1",
+        )
+        .unwrap()
+        .program;
+        let clear = assemble("#pragma version 10\nint 1").unwrap().program;
+        let mut emulator = Emulator::new(LedgerSnapshot::default());
+        emulator.fund(&AccountMother::account().address(), 1_000_000);
+        let mut collector = CoverageCollector::new().with_program(
+            "approval.teal",
+            &program_hash(&approval),
+            disassembly_map(&approval),
+        );
+
+        let create = AppCallTransactionBuilder::default()
+            .header(TransactionHeaderMother::simple_testnet().build().unwrap())
+            .app_id(0)
+            .on_complete(OnApplicationComplete::NoOp)
+            .approval_program(approval.clone())
+            .clear_state_program(clear)
+            .build()
+            .unwrap();
+        let group = emulator.execute(&[create]).unwrap();
+        assert!(group.is_success());
+        collector.record(&group.transactions[0].exec_trace);
+
+        let report = collector.report();
+        let file = &report.files[0];
+        assert!(file.lines_hit() < file.lines_found());
+        assert!(file.line_rate() > 0.5);
+    }
+}
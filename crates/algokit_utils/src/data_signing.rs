@@ -0,0 +1,348 @@
+//! ARC-60 signing of arbitrary data with Algorand keys.
+//!
+//! [ARC-60](https://arc.algorand.foundation/ARCs/arc-0060) lets an account sign payloads that
+//! are not transactions, such as login challenges, without the risk of the signature also
+//! authorizing something on chain. The signed bytes are `authenticationData ‖ SHA-256(data)`,
+//! where the authentication data starts with the SHA-256 of the requesting domain, and requests
+//! whose data begins with one of the protocol's domain separation prefixes are rejected.
+//!
+//! Key handling is left to the caller through [`DataSigner`] and [`SignatureVerifier`], so that
+//! wallets, KMDs and hardware keys can all be used.
+
+use crate::error::AlgoKitUtilsError;
+use algokit_transact::{ALGORAND_SIGNATURE_BYTE_LENGTH, Address};
+use serde_json::{Map, Value};
+use sha2::{Digest, Sha256};
+
+/// Domain separation prefixes used by the Algorand protocol when hashing or signing.
+///
+/// Data starting with any of these could be mistaken for a protocol object, so it is never signed.
+/// The list mirrors the `HashID` constants of go-algorand's `protocol/hash.go`, plus the
+/// `MultisigAddr` prefix that `crypto/multisig.go` uses to derive multisig addresses.
+pub const PROTOCOL_PREFIXES: &[&str] = &[
+    "appID",
+    "arc",
+    "aB",
+    "aD",
+    "aO",
+    "aP",
+    "aS",
+    "AS",
+    "B256",
+    "BH",
+    "BR",
+    "CR",
+    "GE",
+    "HB",
+    "KP",
+    "MA",
+    "MB",
+    "MX",
+    "NIC",
+    "NIR",
+    "NIV",
+    "NPR",
+    "OT1",
+    "OT2",
+    "PF",
+    "PL",
+    "Program",
+    "ProgData",
+    "PS",
+    "PK",
+    "SD",
+    "SpecialAddr",
+    "STIB",
+    "spc",
+    "spm",
+    "spp",
+    "sps",
+    "spv",
+    "TE",
+    "TG",
+    "TL",
+    "TX",
+    "VO",
+    // Not a hash ID, but used to derive multisig addresses.
+    "MultisigAddr",
+];
+
+/// The minimum length of authentication data: a 32 byte domain hash, a flags byte, and a 4 byte
+/// signature counter.
+const MIN_AUTHENTICATION_DATA_LENGTH: usize = 37;
+
+/// The purpose of an ARC-60 signing request, which determines how its data is validated.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ScopeType {
+    /// Authentication, for example answering a login challenge. The data must be a JSON object.
+    Auth = 1,
+}
+
+/// The data an account is asked to sign under ARC-60.
+#[derive(Debug, Clone, PartialEq)]
+pub struct StdSigData {
+    /// The payload to sign.
+    pub data: Vec<u8>,
+    /// The account expected to sign.
+    pub signer: Address,
+    /// The domain requesting the signature.
+    pub domain: String,
+    /// Authenticator data, starting with the SHA-256 of the domain.
+    pub authentication_data: Vec<u8>,
+}
+
+/// Signs bytes with the private key of a single account.
+pub trait DataSigner {
+    /// Returns the address of the account the signer signs for.
+    fn address(&self) -> Address;
+
+    /// Signs `message` with the account's Ed25519 key.
+    fn sign(
+        &self,
+        message: &[u8],
+    ) -> Result<[u8; ALGORAND_SIGNATURE_BYTE_LENGTH], AlgoKitUtilsError>;
+}
+
+/// Verifies Ed25519 signatures.
+pub trait SignatureVerifier {
+    /// Verifies `signature` over `message` by the key behind `address`.
+    fn verify(
+        &self,
+        address: &Address,
+        message: &[u8],
+        signature: &[u8; ALGORAND_SIGNATURE_BYTE_LENGTH],
+    ) -> Result<(), AlgoKitUtilsError>;
+}
+
+impl StdSigData {
+    /// Creates signing data for `signer`, deriving minimal authentication data from the domain.
+    pub fn new(data: Vec<u8>, signer: Address, domain: impl Into<String>) -> Self {
+        let domain = domain.into();
+        let mut authentication_data = Sha256::digest(domain.as_bytes()).to_vec();
+        authentication_data.resize(MIN_AUTHENTICATION_DATA_LENGTH, 0);
+        Self {
+            data,
+            signer,
+            domain,
+            authentication_data,
+        }
+    }
+
+    /// Replaces the authentication data, for example with the flags and counter of an authenticator.
+    pub fn with_authentication_data(mut self, authentication_data: Vec<u8>) -> Self {
+        self.authentication_data = authentication_data;
+        self
+    }
+
+    /// Validates the request and returns the bytes to sign.
+    ///
+    /// # Errors
+    ///
+    /// Returns [`AlgoKitUtilsError::InputError`] if:
+    /// - the data starts with one of the [`PROTOCOL_PREFIXES`],
+    /// - the data does not match the scope,
+    /// - the authentication data is too short or does not start with the SHA-256 of the domain.
+    pub fn bytes_to_sign(&self, scope: ScopeType) -> Result<Vec<u8>, AlgoKitUtilsError> {
+        reject_protocol_prefix(&self.data)?;
+        let data = match scope {
+            ScopeType::Auth => canonical_json(&self.data)?,
+        };
+
+        if self.authentication_data.len() < MIN_AUTHENTICATION_DATA_LENGTH {
+            return Err(input_error(format!(
+                "Authentication data must be at least {} bytes",
+                MIN_AUTHENTICATION_DATA_LENGTH
+            )));
+        }
+        if self.authentication_data[..32] != Sha256::digest(self.domain.as_bytes())[..] {
+            return Err(input_error(format!(
+                "Authentication data does not belong to domain {}",
+                self.domain
+            )));
+        }
+
+        let bytes = [
+            self.authentication_data.as_slice(),
+            Sha256::digest(&data).as_slice(),
+        ]
+        .concat();
+        reject_protocol_prefix(&bytes)?;
+        Ok(bytes)
+    }
+}
+
+/// Signs ARC-60 data with `signer`, which must be the account the data names.
+pub fn sign_data(
+    data: &StdSigData,
+    scope: ScopeType,
+    signer: &impl DataSigner,
+) -> Result<[u8; ALGORAND_SIGNATURE_BYTE_LENGTH], AlgoKitUtilsError> {
+    if signer.address() != data.signer {
+        return Err(input_error(format!(
+            "Data must be signed by {} but the signer is {}",
+            data.signer,
+            signer.address()
+        )));
+    }
+    signer.sign(&data.bytes_to_sign(scope)?)
+}
+
+/// Verifies an ARC-60 signature by the account the data names.
+pub fn verify_data(
+    data: &StdSigData,
+    scope: ScopeType,
+    signature: &[u8; ALGORAND_SIGNATURE_BYTE_LENGTH],
+    verifier: &impl SignatureVerifier,
+) -> Result<(), AlgoKitUtilsError> {
+    verifier.verify(&data.signer, &data.bytes_to_sign(scope)?, signature)
+}
+
+fn reject_protocol_prefix(bytes: &[u8]) -> Result<(), AlgoKitUtilsError> {
+    match PROTOCOL_PREFIXES
+        .iter()
+        .find(|prefix| bytes.starts_with(prefix.as_bytes()))
+    {
+        Some(prefix) => Err(input_error(format!(
+            "Data must not start with the protocol prefix \"{}\"",
+            prefix
+        ))),
+        None => Ok(()),
+    }
+}
+
+/// Parses `data` as a JSON object and re-encodes it with sorted keys and no whitespace.
+fn canonical_json(data: &[u8]) -> Result<Vec<u8>, AlgoKitUtilsError> {
+    let value: Value = serde_json::from_slice(data)
+        .map_err(|e| input_error(format!("Data must be valid JSON: {}", e)))?;
+    if !value.is_object() {
+        return Err(input_error("Data must be a JSON object".to_string()));
+    }
+    serde_json::to_vec(&sort_keys(value))
+        .map_err(|e| input_error(format!("Failed to encode JSON: {}", e)))
+}
+
+fn sort_keys(value: Value) -> Value {
+    match value {
+        Value::Object(map) => {
+            let mut entries: Vec<_> = map.into_iter().collect();
+            entries.sort_by(|(a, _), (b, _)| a.cmp(b));
+            Value::Object(
+                entries
+                    .into_iter()
+                    .map(|(key, value)| (key, sort_keys(value)))
+                    .collect::<Map<_, _>>(),
+            )
+        }
+        Value::Array(values) => Value::Array(values.into_iter().map(sort_keys).collect()),
+        other => other,
+    }
+}
+
+fn input_error(err_msg: String) -> AlgoKitUtilsError {
+    AlgoKitUtilsError::InputError { err_msg }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use ed25519_dalek::{Signature, Signer, SigningKey, Verifier, VerifyingKey};
+
+    struct KeySigner(SigningKey);
+
+    impl DataSigner for KeySigner {
+        fn address(&self) -> Address {
+            Address(self.0.verifying_key().to_bytes())
+        }
+
+        fn sign(
+            &self,
+            message: &[u8],
+        ) -> Result<[u8; ALGORAND_SIGNATURE_BYTE_LENGTH], AlgoKitUtilsError> {
+            Ok(self.0.sign(message).to_bytes())
+        }
+    }
+
+    struct Ed25519Verifier;
+
+    impl SignatureVerifier for Ed25519Verifier {
+        fn verify(
+            &self,
+            address: &Address,
+            message: &[u8],
+            signature: &[u8; ALGORAND_SIGNATURE_BYTE_LENGTH],
+        ) -> Result<(), AlgoKitUtilsError> {
+            VerifyingKey::from_bytes(address.as_bytes())
+                .and_then(|key| key.verify(message, &Signature::from_bytes(signature)))
+                .map_err(|e| input_error(e.to_string()))
+        }
+    }
+
+    fn signer() -> KeySigner {
+        KeySigner(SigningKey::from_bytes(&[7; 32]))
+    }
+
+    #[test]
+    fn test_sign_and_verify() {
+        let signer = signer();
+        let data = StdSigData::new(
+            br#"{"type":"arc60.create","challenge":"abc"}"#.to_vec(),
+            signer.address(),
+            "arc60.io",
+        );
+        let signature = sign_data(&data, ScopeType::Auth, &signer).unwrap();
+        assert!(verify_data(&data, ScopeType::Auth, &signature, &Ed25519Verifier).is_ok());
+
+        let reordered = StdSigData {
+            data: br#"{ "challenge": "abc", "type": "arc60.create" }"#.to_vec(),
+            ..data.clone()
+        };
+        assert!(verify_data(&reordered, ScopeType::Auth, &signature, &Ed25519Verifier).is_ok());
+
+        let tampered = StdSigData {
+            data: br#"{"type":"arc60.create","challenge":"abd"}"#.to_vec(),
+            ..data
+        };
+        assert!(verify_data(&tampered, ScopeType::Auth, &signature, &Ed25519Verifier).is_err());
+    }
+
+    #[test]
+    fn test_rejects_protocol_prefixes() {
+        let signer = signer();
+        for data in [
+            &b"TX\x81"[..],
+            b"TG",
+            b"MX{}",
+            b"Program\x01",
+            b"B256",
+            b"NIR",
+            b"NIV",
+        ] {
+            let request = StdSigData::new(data.to_vec(), signer.address(), "arc60.io");
+            let err = sign_data(&request, ScopeType::Auth, &signer).unwrap_err();
+            assert!(err.to_string().contains("protocol prefix"), "{}", err);
+        }
+    }
+
+    #[test]
+    fn test_rejects_invalid_requests() {
+        let signer = signer();
+        let data = StdSigData::new(br#"{"challenge":"abc"}"#.to_vec(), signer.address(), "a.io");
+
+        let not_object = StdSigData {
+            data: b"[1, 2]".to_vec(),
+            ..data.clone()
+        };
+        assert!(not_object.bytes_to_sign(ScopeType::Auth).is_err());
+
+        let other_domain = data.clone().with_authentication_data(
+            StdSigData::new(vec![], signer.address(), "b.io").authentication_data,
+        );
+        assert!(other_domain.bytes_to_sign(ScopeType::Auth).is_err());
+
+        let other_signer = StdSigData {
+            signer: KeySigner(SigningKey::from_bytes(&[8; 32])).address(),
+            ..data
+        };
+        assert!(sign_data(&other_signer, ScopeType::Auth, &signer).is_err());
+    }
+}
@@ -0,0 +1,578 @@
+//! Source maps and trace bundles for the AVM debugger.
+//!
+//! The AVM debugger steps through a simulate execution trace using [source map v3] files that
+//! map each program counter of a compiled program to a line of its TEAL, the same format algod
+//! produces when compiling with source maps. [`SourceMapV3`] reads and writes those files and
+//! converts them to and from the ARC-56 [`ProgramSourceInfo`] of a contract. A
+//! [`DebuggerBundle`] collects the programs and simulate responses of a test run and writes them
+//! out in the layout the debugger expects:
+//!
+//! - `sources/sources.avm.json`, which lists the source map of each program by its hash.
+//! - `sources/<name>.teal` and `sources/<name>.teal.map` for each program.
+//! - `traces/<name>.trace.avm.json` for each simulate response.
+//!
+//! [source map v3]: https://sourcemaps.info/spec.html
+
+use crate::error::AlgoKitUtilsError;
+use crate::simulate_trace::{constant_block_offset, program_hash};
+use algod_client::models::{PendingTransactionResponse, SimulateTransaction};
+use algokit_abi::{PcOffsetMethod, ProgramSourceInfo, SourceInfo};
+use base64::{Engine, engine::general_purpose::STANDARD as BASE64};
+use serde::{Deserialize, Serialize};
+use serde_json::{Value, json};
+use std::collections::BTreeMap;
+use std::path::Path;
+
+const SOURCE_MAP_VERSION: u32 = 3;
+
+const VLQ_ALPHABET: &[u8; 64] = b"ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz0123456789+/";
+const VLQ_CONTINUATION_BIT: u64 = 32;
+
+/// The fields of a simulate response, outside of its transactions, that algod encodes as base64
+/// in JSON.
+const BYTES_FIELDS: &[&str] = &[
+    "approval-program-hash",
+    "clear-state-program-hash",
+    "logic-sig-hash",
+    "key",
+    "name",
+];
+
+/// A source map in the v3 format, where each generated line is a program counter.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct SourceMapV3 {
+    pub version: u32,
+    /// The names of the TEAL files the program was compiled from.
+    pub sources: Vec<String>,
+    pub names: Vec<String>,
+    /// The Base64 VLQ encoded mapping of each program counter, separated by `;`.
+    pub mappings: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub file: Option<String>,
+}
+
+impl SourceMapV3 {
+    /// Parses a source map from its JSON.
+    pub fn from_json(json: &str) -> Result<Self, AlgoKitUtilsError> {
+        let source_map: Self =
+            serde_json::from_str(json).map_err(|e| AlgoKitUtilsError::InputError {
+                err_msg: format!("Invalid source map: {}", e),
+            })?;
+        if source_map.version != SOURCE_MAP_VERSION {
+            return Err(AlgoKitUtilsError::InputError {
+                err_msg: format!(
+                    "Unsupported source map version {}, expected {}",
+                    source_map.version, SOURCE_MAP_VERSION
+                ),
+            });
+        }
+        Ok(source_map)
+    }
+
+    /// Serializes the source map to JSON.
+    pub fn to_json(&self) -> String {
+        serde_json::to_string(self).expect("source maps always serialize")
+    }
+
+    /// Builds the source map of a program compiled from a single TEAL file, given the 1-based
+    /// TEAL line of each program counter that starts an instruction.
+    pub fn from_pc_lines(source: &str, lines: &BTreeMap<u64, u64>) -> Self {
+        let last_pc = lines.keys().next_back().copied();
+        let mut previous_line = 0i64;
+        let mappings = (0..last_pc.map_or(0, |pc| pc + 1))
+            .map(|pc| match lines.get(&pc) {
+                Some(line) => {
+                    let line = *line as i64 - 1;
+                    let segment = [0, 0, line - previous_line, 0]
+                        .into_iter()
+                        .map(encode_vlq)
+                        .collect::<String>();
+                    previous_line = line;
+                    segment
+                }
+                None => String::new(),
+            })
+            .collect::<Vec<_>>()
+            .join(";");
+
+        Self {
+            version: SOURCE_MAP_VERSION,
+            sources: vec![source.to_string()],
+            names: Vec::new(),
+            mappings,
+            file: None,
+        }
+    }
+
+    /// Builds the source map of an ARC-56 program from its `sourceInfo`.
+    ///
+    /// When the program counters are relative to the constant blocks (the `cblocks` offset
+    /// method), the compiled program is needed to find where they end. Error messages and the
+    /// locations in the higher level source are not representable in a source map and are
+    /// dropped.
+    pub fn from_program_source_info(
+        info: &ProgramSourceInfo,
+        source: &str,
+        compiled: Option<&[u8]>,
+    ) -> Result<Self, AlgoKitUtilsError> {
+        let offset = match info.pc_offset_method {
+            PcOffsetMethod::None => 0,
+            PcOffsetMethod::Cblocks => {
+                let compiled = compiled.ok_or_else(|| AlgoKitUtilsError::InputError {
+                    err_msg: "The compiled program is needed to map source info relative to its constant blocks".to_string(),
+                })?;
+                constant_block_offset(compiled)?
+            }
+        };
+        let lines = info
+            .source_info
+            .iter()
+            .filter_map(|entry| Some((entry.teal?, &entry.pc)))
+            .flat_map(|(line, pcs)| {
+                pcs.iter()
+                    .map(move |pc| (u64::from(*pc) + offset, u64::from(line)))
+            })
+            .collect();
+        Ok(Self::from_pc_lines(source, &lines))
+    }
+
+    /// The 1-based TEAL line mapped to each program counter.
+    pub fn pc_lines(&self) -> Result<BTreeMap<u64, u64>, AlgoKitUtilsError> {
+        let mut lines = BTreeMap::new();
+        let mut source_line = 0i64;
+        for (pc, generated_line) in (0u64..).zip(self.mappings.split(';')) {
+            let mut line = None;
+            for segment in generated_line
+                .split(',')
+                .filter(|segment| !segment.is_empty())
+            {
+                let fields = decode_vlq_segment(segment)?;
+                if let [_, _, line_delta, ..] = fields[..] {
+                    source_line += line_delta;
+                    line.get_or_insert(source_line);
+                }
+            }
+            if let Some(line) = line {
+                let line = u64::try_from(line + 1).map_err(|_| AlgoKitUtilsError::InputError {
+                    err_msg: format!("Source map maps pc {} to a negative line", pc),
+                })?;
+                lines.insert(pc, line);
+            }
+        }
+        Ok(lines)
+    }
+
+    /// Converts the source map to ARC-56 `sourceInfo`, with one entry per TEAL line.
+    pub fn to_program_source_info(&self) -> Result<ProgramSourceInfo, AlgoKitUtilsError> {
+        let mut pcs = BTreeMap::<u64, Vec<u32>>::new();
+        for (pc, line) in self.pc_lines()? {
+            pcs.entry(line).or_default().push(pc as u32);
+        }
+        Ok(ProgramSourceInfo {
+            pc_offset_method: PcOffsetMethod::None,
+            source_info: pcs
+                .into_iter()
+                .map(|(line, pc)| SourceInfo {
+                    pc,
+                    error_message: None,
+                    source: None,
+                    teal: Some(line as u32),
+                })
+                .collect(),
+        })
+    }
+}
+
+fn encode_vlq(value: i64) -> String {
+    let mut remaining = if value < 0 {
+        (value.unsigned_abs() << 1) | 1
+    } else {
+        (value as u64) << 1
+    };
+    let mut encoded = String::new();
+    loop {
+        let mut digit = remaining & (VLQ_CONTINUATION_BIT - 1);
+        remaining >>= 5;
+        if remaining > 0 {
+            digit |= VLQ_CONTINUATION_BIT;
+        }
+        encoded.push(VLQ_ALPHABET[digit as usize] as char);
+        if remaining == 0 {
+            return encoded;
+        }
+    }
+}
+
+fn decode_vlq_segment(segment: &str) -> Result<Vec<i64>, AlgoKitUtilsError> {
+    let mut values = Vec::new();
+    let mut value = 0u64;
+    let mut shift = 0;
+    for char in segment.bytes() {
+        let digit = VLQ_ALPHABET
+            .iter()
+            .position(|candidate| *candidate == char)
+            .filter(|_| shift < 60)
+            .ok_or_else(|| AlgoKitUtilsError::InputError {
+                err_msg: format!("Invalid source map segment {:?}", segment),
+            })? as u64;
+        value |= (digit & (VLQ_CONTINUATION_BIT - 1)) << shift;
+        if digit & VLQ_CONTINUATION_BIT == 0 {
+            let magnitude = (value >> 1) as i64;
+            values.push(if value & 1 == 1 {
+                -magnitude
+            } else {
+                magnitude
+            });
+            value = 0;
+            shift = 0;
+        } else {
+            shift += 5;
+        }
+    }
+    if shift > 0 {
+        return Err(AlgoKitUtilsError::InputError {
+            err_msg: format!("Truncated source map segment {:?}", segment),
+        });
+    }
+    Ok(values)
+}
+
+/// A program included in a [`DebuggerBundle`].
+#[derive(Debug, Clone)]
+struct BundledProgram {
+    name: String,
+    hash: [u8; 32],
+    teal: String,
+    source_map: SourceMapV3,
+}
+
+/// The files the AVM debugger needs to step through simulated transactions.
+#[derive(Debug, Clone, Default)]
+pub struct DebuggerBundle {
+    programs: Vec<BundledProgram>,
+    traces: Vec<(String, String)>,
+}
+
+impl DebuggerBundle {
+    /// Creates an empty bundle.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Adds a compiled program with its TEAL and source map, written as `<name>.teal` and
+    /// `<name>.teal.map`. The source map's `sources` are pointed at the bundled TEAL file.
+    pub fn with_program(
+        mut self,
+        name: &str,
+        program: &[u8],
+        teal: &str,
+        source_map: SourceMapV3,
+    ) -> Self {
+        let source_map = SourceMapV3 {
+            sources: vec![format!("{}.teal", name)],
+            ..source_map
+        };
+        self.programs.push(BundledProgram {
+            name: name.to_string(),
+            hash: program_hash(program),
+            teal: teal.to_string(),
+            source_map,
+        });
+        self
+    }
+
+    /// Adds a simulate response, written as `<name>.trace.avm.json`.
+    ///
+    /// The response is written as algod returns it as JSON: program hashes, state keys, box
+    /// names and logs are base64 encoded, and signed transactions use algod's JSON encoding.
+    ///
+    /// # Errors
+    ///
+    /// Returns [`AlgoKitUtilsError::TransactError`] if a transaction in the response cannot be
+    /// encoded.
+    pub fn with_simulation(
+        mut self,
+        name: &str,
+        response: &SimulateTransaction,
+    ) -> Result<Self, AlgoKitUtilsError> {
+        let mut json = serde_json::to_value(response).expect("simulate responses always serialize");
+        encode_bytes_fields(&mut json);
+        for (group, json_group) in response
+            .txn_groups
+            .iter()
+            .zip(array_mut(&mut json["txn-groups"]))
+        {
+            for (result, json_result) in group
+                .txn_results
+                .iter()
+                .zip(array_mut(&mut json_group["txn-results"]))
+            {
+                encode_transactions(&result.txn_result, &mut json_result["txn-result"])?;
+            }
+        }
+        let json = serde_json::to_string_pretty(&json).expect("JSON values always serialize");
+        self.traces.push((name.to_string(), json));
+        Ok(self)
+    }
+
+    /// The contents of each file in the bundle, keyed by its path relative to the bundle's root.
+    pub fn files(&self) -> BTreeMap<String, String> {
+        let mut files = BTreeMap::new();
+        let sources = self
+            .programs
+            .iter()
+            .map(|program| {
+                json!({
+                    "sourcemap-location": format!("{}.teal.map", program.name),
+                    "hash": BASE64.encode(program.hash),
+                })
+            })
+            .collect::<Vec<_>>();
+        files.insert(
+            "sources/sources.avm.json".to_string(),
+            serde_json::to_string_pretty(&json!({ "txn-group-sources": sources }))
+                .expect("JSON values always serialize"),
+        );
+        for program in &self.programs {
+            files.insert(
+                format!("sources/{}.teal", program.name),
+                program.teal.clone(),
+            );
+            files.insert(
+                format!("sources/{}.teal.map", program.name),
+                program.source_map.to_json(),
+            );
+        }
+        for (name, json) in &self.traces {
+            files.insert(format!("traces/{}.trace.avm.json", name), json.clone());
+        }
+        files
+    }
+
+    /// Writes the bundle's files under a directory, creating any directories needed.
+    pub fn write_to(&self, directory: &Path) -> Result<(), AlgoKitUtilsError> {
+        for (relative, contents) in self.files() {
+            let path = directory.join(relative);
+            let io_error = |source| AlgoKitUtilsError::IoError {
+                path: path.display().to_string(),
+                source,
+            };
+            if let Some(parent) = path.parent() {
+                std::fs::create_dir_all(parent).map_err(io_error)?;
+            }
+            std::fs::write(&path, contents).map_err(io_error)?;
+        }
+        Ok(())
+    }
+}
+
+/// Replaces the signed transaction of a transaction result and of its inner transactions with
+/// algod's JSON encoding of them.
+fn encode_transactions(
+    result: &PendingTransactionResponse,
+    json: &mut Value,
+) -> Result<(), AlgoKitUtilsError> {
+    json["txn"] = result.txn.to_algod_json()?;
+    let inner_results = result.inner_txns.iter().flatten();
+    for (inner, json_inner) in inner_results.zip(array_mut(&mut json["inner-txns"])) {
+        encode_transactions(inner, json_inner)?;
+    }
+    Ok(())
+}
+
+/// The elements of a JSON array, or none if the value is not an array.
+fn array_mut(value: &mut Value) -> impl Iterator<Item = &mut Value> {
+    value.as_array_mut().into_iter().flatten()
+}
+
+/// Replaces the byte arrays that serde writes for binary fields with the base64 strings algod
+/// uses.
+fn encode_bytes_fields(value: &mut Value) {
+    let as_bytes = |value: &Value| -> Option<Vec<u8>> {
+        value
+            .as_array()?
+            .iter()
+            .map(|byte| u8::try_from(byte.as_u64()?).ok())
+            .collect()
+    };
+    match value {
+        Value::Object(fields) => {
+            for (name, field) in fields.iter_mut() {
+                if BYTES_FIELDS.contains(&name.as_str()) {
+                    if let Some(bytes) = as_bytes(field) {
+                        *field = Value::String(BASE64.encode(bytes));
+                        continue;
+                    }
+                }
+                if name == "logs" {
+                    if let Some(logs) = field.as_array_mut() {
+                        for log in logs {
+                            if let Some(bytes) = as_bytes(log) {
+                                *log = Value::String(BASE64.encode(bytes));
+                            }
+                        }
+                        continue;
+                    }
+                }
+                encode_bytes_fields(field);
+            }
+        }
+        Value::Array(values) => values.iter_mut().for_each(encode_bytes_fields),
+        _ => {}
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::teal::assemble;
+    use algod_client::models::{
+        SimulateTransactionGroupResult, SimulateTransactionResult, SimulationTransactionExecTrace,
+    };
+    use algokit_transact::test_utils::TestDataMother;
+    use algokit_transact::{AlgorandMsgpack, SignedTransaction};
+
+    #[test]
+    fn test_encodes_and_decodes_pc_lines() {
+        let lines = BTreeMap::from([(1, 2), (3, 3), (4, 2), (5, 18)]);
+        let source_map = SourceMapV3::from_pc_lines("approval.teal", &lines);
+        assert_eq!(source_map.mappings, ";AACA;;AACA;AADA;AAgBA");
+        assert_eq!(
+            source_map.to_json(),
+            r#"{"version":3,"sources":["approval.teal"],"names":[],"mappings":";AACA;;AACA;AADA;AAgBA"}"#
+        );
+
+        let parsed = SourceMapV3::from_json(&source_map.to_json()).unwrap();
+        assert_eq!(parsed.pc_lines().unwrap(), lines);
+        assert!(
+            SourceMapV3::from_json(r#"{"version":2,"sources":[],"names":[],"mappings":""}"#)
+                .is_err()
+        );
+        let truncated = SourceMapV3 {
+            mappings: "AAg".to_string(),
+            ..parsed
+        };
+        assert!(truncated.pc_lines().is_err());
+    }
+
+    #[test]
+    fn test_converts_assembler_source_info() {
+        let assembly = assemble("#pragma version 10\nint 1\nbyte 0x01\npop\n").unwrap();
+        let info = assembly.source_info();
+        let source_map = SourceMapV3::from_program_source_info(&info, "a.teal", None).unwrap();
+        let pc_lines = source_map.pc_lines().unwrap();
+        for (pc, line) in &pc_lines {
+            assert_eq!(assembly.line(*pc), Some(*line));
+        }
+
+        let round_trip = source_map.to_program_source_info().unwrap();
+        assert_eq!(
+            SourceMapV3::from_program_source_info(&round_trip, "a.teal", None).unwrap(),
+            source_map
+        );
+    }
+
+    #[test]
+    fn test_bundles_sources_and_traces() {
+        let program = [10u8, 0x81, 0x01];
+        let hash = program_hash(&program);
+        let source_map = SourceMapV3::from_pc_lines("ignored.teal", &BTreeMap::from([(1, 2)]));
+        let trace = SimulationTransactionExecTrace {
+            approval_program_hash: Some(hash.to_vec()),
+            ..Default::default()
+        };
+        let response = SimulateTransaction {
+            version: 2,
+            last_round: 10,
+            txn_groups: vec![SimulateTransactionGroupResult {
+                txn_results: vec![SimulateTransactionResult {
+                    exec_trace: Some(trace),
+                    ..Default::default()
+                }],
+                ..Default::default()
+            }],
+            ..Default::default()
+        };
+        let bundle = DebuggerBundle::new()
+            .with_program(
+                "approval",
+                &program,
+                "#pragma version 10\npushint 1",
+                source_map,
+            )
+            .with_simulation("call", &response)
+            .unwrap();
+
+        let files = bundle.files();
+        assert_eq!(
+            files.keys().map(String::as_str).collect::<Vec<_>>(),
+            vec![
+                "sources/approval.teal",
+                "sources/approval.teal.map",
+                "sources/sources.avm.json",
+                "traces/call.trace.avm.json",
+            ]
+        );
+        let sources: Value = serde_json::from_str(&files["sources/sources.avm.json"]).unwrap();
+        assert_eq!(
+            sources,
+            json!({"txn-group-sources": [{
+                "sourcemap-location": "approval.teal.map",
+                "hash": BASE64.encode(hash),
+            }]})
+        );
+        let map = SourceMapV3::from_json(&files["sources/approval.teal.map"]).unwrap();
+        assert_eq!(map.sources, vec!["approval.teal"]);
+        let trace: Value = serde_json::from_str(&files["traces/call.trace.avm.json"]).unwrap();
+        assert_eq!(
+            trace["txn-groups"][0]["txn-results"][0]["exec-trace"]["approval-program-hash"],
+            json!(BASE64.encode(hash))
+        );
+    }
+
+    #[test]
+    fn test_writes_transactions_in_algod_json() {
+        let signed =
+            SignedTransaction::decode(&TestDataMother::simple_payment().signed_bytes).unwrap();
+        let inner = SignedTransaction {
+            transaction: TestDataMother::simple_asset_transfer().transaction,
+            signature: None,
+            auth_address: None,
+            multisignature: None,
+        };
+        let mut result = PendingTransactionResponse::new(String::new(), signed.clone());
+        result.logs = Some(vec![b"hello".to_vec()]);
+        result.inner_txns = Some(vec![PendingTransactionResponse::new(
+            String::new(),
+            inner.clone(),
+        )]);
+        let response = SimulateTransaction {
+            version: 2,
+            last_round: 10,
+            txn_groups: vec![SimulateTransactionGroupResult {
+                txn_results: vec![SimulateTransactionResult::new(result)],
+                ..Default::default()
+            }],
+            ..Default::default()
+        };
+
+        let files = DebuggerBundle::new()
+            .with_simulation("pay", &response)
+            .unwrap()
+            .files();
+
+        let trace: Value = serde_json::from_str(&files["traces/pay.trace.avm.json"]).unwrap();
+        let result = &trace["txn-groups"][0]["txn-results"][0]["txn-result"];
+        assert_eq!(result["txn"], signed.to_algod_json().unwrap());
+        assert_eq!(
+            result["txn"]["txn"]["snd"],
+            json!(signed.transaction.sender().as_str())
+        );
+        assert!(result["txn"]["sig"].is_string());
+        assert_eq!(
+            result["inner-txns"][0]["txn"],
+            inner.to_algod_json().unwrap()
+        );
+        assert_eq!(result["logs"], json!([BASE64.encode(b"hello")]));
+    }
+}
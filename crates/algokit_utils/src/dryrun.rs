@@ -0,0 +1,539 @@
+//! Dryrun requests and trace rendering.
+//!
+//! The `teal_dryrun` endpoint evaluates a transaction group against the ledger state supplied in
+//! the request rather than the node's own state, so every app and account the group reads must be
+//! included, along with the creators of the assets it uses since asset params are only resolved
+//! from the creator's account. [`DryrunRequestBuilder`] gathers those from algod for a group of signed transactions,
+//! and [`render_trace`] turns the resulting program traces into tables of the program counter,
+//! source line, stack and scratch space changes at each step.
+
+use crate::error::AlgoKitUtilsError;
+use algod_client::AlgodClient;
+use algod_client::models::{DryrunRequest, DryrunSource, DryrunState, DryrunTxnResult, TealValue};
+use algokit_transact::{Address, SignedTransaction, Transaction};
+use futures_util::future::try_join_all;
+use std::collections::HashSet;
+use std::fmt::Write;
+
+const TEAL_BYTES_TYPE: u64 = 1;
+
+/// Builds a [`DryrunRequest`] for a transaction group from the current state of the ledger.
+pub struct DryrunRequestBuilder {
+    client: AlgodClient,
+    transactions: Vec<SignedTransaction>,
+    sources: Vec<DryrunSource>,
+}
+
+impl DryrunRequestBuilder {
+    /// Creates a builder for a group of signed transactions.
+    pub fn new(client: AlgodClient, transactions: Vec<SignedTransaction>) -> Self {
+        Self {
+            client,
+            transactions,
+            sources: Vec::new(),
+        }
+    }
+
+    /// Adds TEAL source to compile and run in place of a program, such as an app's approval
+    /// program or a transaction's logic signature.
+    pub fn with_source(mut self, source: DryrunSource) -> Self {
+        self.sources.push(source);
+        self
+    }
+
+    /// The IDs of the apps called or referenced by the group, in order of first use.
+    fn app_ids(&self) -> Vec<u64> {
+        let mut seen = HashSet::new();
+        self.transactions
+            .iter()
+            .filter_map(|stx| match &stx.transaction {
+                Transaction::AppCall(fields) => Some(fields),
+                _ => None,
+            })
+            .flat_map(|fields| {
+                std::iter::once(fields.app_id)
+                    .chain(fields.app_references.iter().flatten().copied())
+            })
+            .filter(|app_id| *app_id != 0 && seen.insert(*app_id))
+            .collect()
+    }
+
+    /// The IDs of the assets transferred, frozen, configured or referenced by the group, in order
+    /// of first use.
+    fn asset_ids(&self) -> Vec<u64> {
+        let mut seen = HashSet::new();
+        self.transactions
+            .iter()
+            .flat_map(|stx| match &stx.transaction {
+                Transaction::AssetTransfer(fields) => vec![fields.asset_id],
+                Transaction::AssetFreeze(fields) => vec![fields.asset_id],
+                Transaction::AssetConfig(fields) => vec![fields.asset_id],
+                Transaction::AppCall(fields) => fields.asset_references.clone().unwrap_or_default(),
+                _ => Vec::new(),
+            })
+            .filter(|asset_id| *asset_id != 0 && seen.insert(*asset_id))
+            .collect()
+    }
+
+    /// The addresses of the accounts that the group sends from, pays or references, followed by
+    /// the accounts and creators of the given apps and the creators of the given assets, in order
+    /// of first use.
+    fn addresses(&self, apps: &[(u64, String)], asset_creators: &[String]) -> Vec<String> {
+        let mut addresses = Vec::new();
+        for stx in &self.transactions {
+            let transaction = &stx.transaction;
+            addresses.push(transaction.header().sender.clone());
+            match transaction {
+                Transaction::Payment(fields) => {
+                    addresses.push(fields.receiver.clone());
+                    addresses.extend(fields.close_remainder_to.clone());
+                }
+                Transaction::AssetTransfer(fields) => {
+                    addresses.push(fields.receiver.clone());
+                    addresses.extend(fields.asset_sender.clone());
+                    addresses.extend(fields.close_remainder_to.clone());
+                }
+                Transaction::AssetFreeze(fields) => {
+                    addresses.push(fields.freeze_target.clone());
+                }
+                Transaction::AppCall(fields) => {
+                    addresses.extend(fields.account_references.iter().flatten().cloned());
+                }
+                _ => {}
+            }
+        }
+        let mut addresses = addresses.iter().map(Address::to_string).collect::<Vec<_>>();
+        for (app_id, creator) in apps {
+            addresses.push(Address::from_app_id(app_id).to_string());
+            addresses.push(creator.clone());
+        }
+        addresses.extend(asset_creators.iter().cloned());
+
+        let mut seen = HashSet::new();
+        addresses.retain(|address| seen.insert(address.clone()));
+        addresses
+    }
+
+    /// Fetches the referenced apps, asset creators and accounts and the latest round, and
+    /// assembles the request.
+    pub async fn build(&self) -> Result<DryrunRequest, AlgoKitUtilsError> {
+        let client = &self.client;
+        let apps = try_join_all(
+            self.app_ids()
+                .into_iter()
+                .map(|app_id| client.get_application_by_id(app_id)),
+        )
+        .await?;
+
+        let app_creators = apps
+            .iter()
+            .map(|app| (app.id, app.params.creator.clone()))
+            .collect::<Vec<_>>();
+        let asset_creators = try_join_all(
+            self.asset_ids()
+                .into_iter()
+                .map(|asset_id| client.get_asset_by_id(asset_id)),
+        )
+        .await?
+        .into_iter()
+        .map(|asset| asset.params.creator)
+        .collect::<Vec<_>>();
+        let accounts = try_join_all(
+            self.addresses(&app_creators, &asset_creators)
+                .into_iter()
+                .map(|address| async move { client.account_information(&address, None).await }),
+        )
+        .await?;
+
+        let status = client.get_status().await?;
+        let latest_block = client.get_block(status.last_round, Some(true)).await?;
+
+        Ok(DryrunRequest {
+            txns: self.transactions.clone(),
+            accounts,
+            apps,
+            protocol_version: status.last_version,
+            round: status.last_round,
+            latest_timestamp: latest_block.block.timestamp.unwrap_or_default(),
+            sources: self.sources.clone(),
+        })
+    }
+}
+
+fn render_value(value: &TealValue) -> String {
+    if value.r#type == TEAL_BYTES_TYPE {
+        hex(&value.bytes)
+    } else {
+        value.uint.to_string()
+    }
+}
+
+/// Renders bytes as `0x`-prefixed hex.
+pub(crate) fn hex(bytes: &[u8]) -> String {
+    bytes.iter().fold(String::from("0x"), |mut hex, b| {
+        let _ = write!(hex, "{:02x}", b);
+        hex
+    })
+}
+
+/// Whether a scratch slot holds the zero value that unused slots start with.
+fn is_unset(value: &TealValue) -> bool {
+    value.bytes.is_empty() && value.uint == 0
+}
+
+/// Describes the scratch slots whose value differs from the previous step.
+fn scratch_changes(previous: Option<&DryrunState>, state: &DryrunState) -> String {
+    let empty = Vec::new();
+    let before = previous
+        .and_then(|previous| previous.scratch.as_ref())
+        .unwrap_or(&empty);
+    let after = state.scratch.as_ref().unwrap_or(&empty);
+
+    after
+        .iter()
+        .enumerate()
+        .filter(|(slot, value)| match before.get(*slot) {
+            Some(before) => before != *value,
+            None => !is_unset(value),
+        })
+        .map(|(slot, value)| format!("{} = {}", slot, render_value(value)))
+        .collect::<Vec<_>>()
+        .join(", ")
+}
+
+/// Renders a program trace as a table with one row per step.
+///
+/// Each row shows the program counter, the disassembled source line, the stack as the line is
+/// reached and the scratch slots changed by the previous line. A step that failed has its error
+/// in place of the stack.
+pub fn render_trace(trace: &[DryrunState], disassembly: &[String]) -> String {
+    let header = ["pc", "line", "source", "stack", "scratch"].map(String::from);
+    let mut rows = vec![header];
+    for (step, state) in trace.iter().enumerate() {
+        let stack = match &state.error {
+            Some(error) => format!("error: {}", error),
+            None => {
+                let values = state.stack.iter().map(render_value).collect::<Vec<_>>();
+                format!("[{}]", values.join(", "))
+            }
+        };
+        let previous = step.checked_sub(1).map(|previous| &trace[previous]);
+        rows.push([
+            state.pc.to_string(),
+            state.line.to_string(),
+            disassembly
+                .get(state.line as usize)
+                .map(|line| line.trim().to_string())
+                .unwrap_or_default(),
+            stack,
+            scratch_changes(previous, state),
+        ]);
+    }
+
+    format_table(&rows)
+}
+
+/// Lays out rows as a table with padded columns, separating the first row as the header.
+pub(crate) fn format_table<const N: usize>(rows: &[[String; N]]) -> String {
+    let widths = (0..N)
+        .map(|column| {
+            rows.iter()
+                .map(|row| row[column].chars().count())
+                .max()
+                .unwrap_or_default()
+        })
+        .collect::<Vec<_>>();
+    let format_row = |row: &[String]| {
+        row.iter()
+            .zip(&widths)
+            .map(|(cell, width)| format!("{:<width$}", cell, width = width))
+            .collect::<Vec<_>>()
+            .join(" | ")
+            .trim_end()
+            .to_string()
+    };
+
+    let mut lines = vec![format_row(&rows[0])];
+    lines.push(
+        widths
+            .iter()
+            .map(|width| "-".repeat(*width))
+            .collect::<Vec<_>>()
+            .join("-+-"),
+    );
+    lines.extend(rows[1..].iter().map(|row| format_row(row)));
+    lines.join("\n")
+}
+
+/// Renders the app call trace of a dryrun result, if the transaction was an app call.
+pub fn render_app_call_trace(result: &DryrunTxnResult) -> Option<String> {
+    result
+        .app_call_trace
+        .as_ref()
+        .map(|trace| render_trace(trace, &result.disassembly))
+}
+
+/// Renders the logic signature trace of a dryrun result, if the transaction was signed by one.
+pub fn render_logic_sig_trace(result: &DryrunTxnResult) -> Option<String> {
+    result.logic_sig_trace.as_ref().map(|trace| {
+        render_trace(
+            trace,
+            result.logic_sig_disassembly.as_deref().unwrap_or_default(),
+        )
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use algod_client::models::{
+        Account, Application, ApplicationParams, Asset, AssetParams, Block, GetBlock,
+    };
+    use algokit_http_client::{HttpClient, HttpError, HttpMethod, HttpResponse};
+    use algokit_transact::AlgorandMsgpack;
+    use algokit_transact::test_utils::{AppCallTransactionMother, TransactionMother};
+    use async_trait::async_trait;
+    use std::collections::HashMap;
+    use std::sync::{Arc, Mutex};
+
+    fn creator() -> Address {
+        Address([7; 32])
+    }
+
+    fn asset_creator(asset_id: u64) -> Address {
+        Address([asset_id as u8; 32])
+    }
+
+    #[derive(Default)]
+    struct MockAlgod {
+        paths: Mutex<Vec<String>>,
+    }
+
+    #[async_trait]
+    impl HttpClient for MockAlgod {
+        async fn request(
+            &self,
+            _method: HttpMethod,
+            path: String,
+            _query: Option<HashMap<String, String>>,
+            _body: Option<Vec<u8>>,
+            _headers: Option<HashMap<String, String>>,
+        ) -> Result<HttpResponse, HttpError> {
+            self.paths.lock().unwrap().push(path.clone());
+            let (body, content_type) = if let Some(id) = path.strip_prefix("/v2/applications/") {
+                let app = Application::new(
+                    id.parse().unwrap(),
+                    ApplicationParams::new(creator().to_string(), vec![6, 129, 1], vec![6]),
+                );
+                (serde_json::to_vec(&app).unwrap(), "application/json")
+            } else if let Some(id) = path.strip_prefix("/v2/assets/") {
+                let id = id.parse().unwrap();
+                let asset = Asset::new(id, AssetParams::new(asset_creator(id).to_string(), 0, 1));
+                (serde_json::to_vec(&asset).unwrap(), "application/json")
+            } else if let Some(address) = path.strip_prefix("/v2/accounts/") {
+                let account = Account {
+                    address: address.to_string(),
+                    amount: 1_000_000,
+                    ..Default::default()
+                };
+                (serde_json::to_vec(&account).unwrap(), "application/json")
+            } else if path.starts_with("/v2/blocks/") {
+                let block = GetBlock::new(Block {
+                    timestamp: Some(1_700_000_000),
+                    ..Default::default()
+                });
+                (block.encode().unwrap(), "application/msgpack")
+            } else {
+                let status = r#"{"catchup-time":0,"last-round":500,"last-version":"future","next-version":"future","next-version-round":501,"next-version-supported":true,"stopped-at-unsupported-round":false,"time-since-last-round":0}"#;
+                (status.as_bytes().to_vec(), "application/json")
+            };
+            Ok(HttpResponse {
+                body,
+                headers: HashMap::from([("content-type".to_string(), content_type.to_string())]),
+            })
+        }
+    }
+
+    fn signed(transaction: Transaction) -> SignedTransaction {
+        SignedTransaction {
+            transaction,
+            signature: Some([1; 64]),
+            auth_address: None,
+            multisignature: None,
+        }
+    }
+
+    #[tokio::test]
+    async fn test_builds_request_from_ledger() {
+        let app_call = AppCallTransactionMother::app_call()
+            .app_references(vec![84366825, 5])
+            .build()
+            .unwrap();
+        let payment = TransactionMother::simple_payment().build().unwrap();
+        let Transaction::Payment(payment_fields) = &payment else {
+            unreachable!()
+        };
+        let expected_addresses = [
+            app_call.header().sender.clone(),
+            payment_fields.header.sender.clone(),
+            payment_fields.receiver.clone(),
+            Address::from_app_id(&84366825),
+            creator(),
+            Address::from_app_id(&5),
+            asset_creator(84366776),
+        ];
+        let mock = Arc::new(MockAlgod::default());
+        let source = DryrunSource::new(
+            "approv".to_string(),
+            "#pragma version 10".to_string(),
+            0,
+            84366825,
+        );
+
+        let request = DryrunRequestBuilder::new(
+            AlgodClient::new(mock.clone()),
+            vec![signed(app_call), signed(payment)],
+        )
+        .with_source(source.clone())
+        .build()
+        .await
+        .unwrap();
+
+        assert_eq!(
+            request.apps.iter().map(|app| app.id).collect::<Vec<_>>(),
+            vec![84366825, 5]
+        );
+        assert_eq!(
+            request
+                .accounts
+                .iter()
+                .map(|account| account.address.clone())
+                .collect::<Vec<_>>(),
+            expected_addresses
+                .iter()
+                .map(Address::to_string)
+                .collect::<Vec<_>>()
+        );
+        assert_eq!(request.txns.len(), 2);
+        assert_eq!(request.round, 500);
+        assert_eq!(request.protocol_version, "future");
+        assert_eq!(request.latest_timestamp, 1_700_000_000);
+        assert_eq!(request.sources, vec![source]);
+        assert!(
+            mock.paths
+                .lock()
+                .unwrap()
+                .contains(&"/v2/blocks/500".to_string())
+        );
+    }
+
+    #[tokio::test]
+    async fn test_includes_asset_creators() {
+        let app_call = AppCallTransactionMother::app_call()
+            .asset_references(vec![84366776, 11, 12])
+            .build()
+            .unwrap();
+        let asset_transfer = TransactionMother::simple_asset_transfer()
+            .asset_id(13)
+            .build()
+            .unwrap();
+        let Transaction::AssetTransfer(transfer_fields) = &asset_transfer else {
+            unreachable!()
+        };
+        let expected_addresses = [
+            app_call.header().sender.clone(),
+            transfer_fields.header.sender.clone(),
+            transfer_fields.receiver.clone(),
+            Address::from_app_id(&84366825),
+            creator(),
+            asset_creator(84366776),
+            asset_creator(11),
+            asset_creator(12),
+            asset_creator(13),
+        ];
+        let mock = Arc::new(MockAlgod::default());
+
+        let request = DryrunRequestBuilder::new(
+            AlgodClient::new(mock.clone()),
+            vec![signed(app_call), signed(asset_transfer)],
+        )
+        .build()
+        .await
+        .unwrap();
+
+        assert_eq!(
+            request
+                .accounts
+                .iter()
+                .map(|account| account.address.clone())
+                .collect::<Vec<_>>(),
+            expected_addresses
+                .iter()
+                .map(Address::to_string)
+                .collect::<Vec<_>>()
+        );
+        let paths = mock.paths.lock().unwrap();
+        for asset_id in [11, 12, 13] {
+            assert!(paths.contains(&format!("/v2/assets/{}", asset_id)));
+        }
+    }
+
+    fn uint(value: u64) -> TealValue {
+        TealValue {
+            r#type: 2,
+            bytes: Vec::new(),
+            uint: value,
+        }
+    }
+
+    fn state(pc: u64, line: u64, stack: Vec<TealValue>, scratch: Vec<TealValue>) -> DryrunState {
+        DryrunState {
+            line,
+            pc,
+            stack,
+            scratch: Some(scratch),
+            error: None,
+        }
+    }
+
+    #[test]
+    fn test_renders_trace_table() {
+        let disassembly = [
+            "#pragma version 10",
+            "pushbytes 0x6869 // \"hi\"",
+            "store 1",
+            "intc_0 // 1",
+            "return",
+        ]
+        .map(String::from);
+        let bytes = TealValue {
+            r#type: 1,
+            bytes: b"hi".to_vec(),
+            uint: 0,
+        };
+        let trace = vec![
+            state(1, 1, vec![], vec![uint(0); 2]),
+            state(5, 2, vec![bytes.clone()], vec![uint(0); 2]),
+            state(7, 3, vec![], vec![uint(0), bytes.clone()]),
+            DryrunState {
+                error: Some("invalid program".to_string()),
+                ..state(8, 4, vec![uint(1)], vec![uint(0), bytes])
+            },
+        ];
+
+        let table = render_trace(&trace, &disassembly);
+
+        assert_eq!(
+            table,
+            [
+                "pc | line | source                   | stack                  | scratch",
+                "---+------+--------------------------+------------------------+-----------",
+                "1  | 1    | pushbytes 0x6869 // \"hi\" | []                     |",
+                "5  | 2    | store 1                  | [0x6869]               |",
+                "7  | 3    | intc_0 // 1              | []                     | 1 = 0x6869",
+                "8  | 4    | return                   | error: invalid program |",
+            ]
+            .join("\n")
+        );
+    }
+}
@@ -0,0 +1,72 @@
+//! Error types for the AlgoKit Core utils module.
+
+use snafu::Snafu;
+
+/// Represents errors that can occur when using the AlgoKit Core utilities.
+#[derive(Debug, Snafu)]
+pub enum AlgoKitUtilsError {
+    #[snafu(display("{err_msg}"))]
+    InputError { err_msg: String },
+
+    #[snafu(display("{source}"))]
+    TransactError {
+        source: algokit_transact::AlgoKitTransactError,
+    },
+
+    #[snafu(display("Invalid proof: {err_msg}"))]
+    InvalidProof { err_msg: String },
+
+    #[snafu(display("Block {round} does not link to the previous block: {err_msg}"))]
+    BrokenChainLink { round: u64, err_msg: String },
+
+    #[snafu(display("Algod request failed: {source}"))]
+    AlgodError { source: algod_client::apis::Error },
+
+    #[snafu(display("Indexer request failed: {source}"))]
+    IndexerError { source: indexer_client::apis::Error },
+
+    #[snafu(display("{source}"))]
+    AbiError { source: algokit_abi::ABIError },
+
+    #[snafu(display("Invalid program at pc {pc}: {err_msg}"))]
+    InvalidProgram { pc: u64, err_msg: String },
+
+    #[snafu(display("Invalid TEAL at line {line}: {err_msg}"))]
+    TealSyntax { line: u64, err_msg: String },
+
+    #[snafu(display("Failed to write {path}: {source}"))]
+    IoError {
+        path: String,
+        source: std::io::Error,
+    },
+
+    #[snafu(display("Invalid address {address}: {source}"))]
+    InvalidAddress {
+        address: String,
+        source: algokit_transact::AlgoKitTransactError,
+    },
+}
+
+impl From<algokit_transact::AlgoKitTransactError> for AlgoKitUtilsError {
+    fn from(source: algokit_transact::AlgoKitTransactError) -> Self {
+        AlgoKitUtilsError::TransactError { source }
+    }
+}
+
+impl From<algod_client::apis::Error> for AlgoKitUtilsError {
+    fn from(source: algod_client::apis::Error) -> Self {
+        AlgoKitUtilsError::AlgodError { source }
+    }
+}
+
+impl From<indexer_client::apis::Error> for AlgoKitUtilsError {
+    fn from(source: indexer_client::apis::Error) -> Self {
+        AlgoKitUtilsError::IndexerError { source }
+    }
+}
+
+impl From<algokit_abi::ABIError> for AlgoKitUtilsError {
+    fn from(source: algokit_abi::ABIError) -> Self {
+        AlgoKitUtilsError::AbiError { source }
+    }
+}
@@ -0,0 +1,262 @@
+//! Verification of the deterministic Falcon-1024 signatures used by state proof participants.
+//!
+//! go-algorand signs with the deterministic variant of Falcon-1024 from `algorand/falcon`. A
+//! signature is a header byte, a salt version byte and the compressed `s2` polynomial; the
+//! random 40 byte nonce of standard Falcon is replaced by a salt derived from the salt version,
+//! so the message is hashed to a point as `SHAKE256(salt || message)`. The fixed-length (CT)
+//! form of a signature, which is what state proofs commit to, encodes every coefficient of `s2`
+//! in 12 bits instead.
+
+use crate::error::AlgoKitUtilsError;
+use crate::merkle::invalid_proof;
+use sha3::Shake256;
+use sha3::digest::{ExtendableOutput, Update, XofReader};
+
+const LOGN: u8 = 10;
+const N: usize = 1 << LOGN;
+const Q: i64 = 12289;
+/// The maximum squared norm of a valid signature vector `(s1, s2)`.
+const L2_BOUND: i64 = 70265242;
+
+const PUBLIC_KEY_HEADER: u8 = LOGN;
+const PUBLIC_KEY_SIZE: usize = 1 + N * 14 / 8;
+const COMPRESSED_SIGNATURE_HEADER: u8 = 0x80 | 0x30 | LOGN;
+const CT_SIGNATURE_HEADER: u8 = 0x80 | 0x50 | LOGN;
+const CT_COEFFICIENT_BITS: u32 = 12;
+const SALT_SIZE: usize = 40;
+const SALT_TAG: &[u8] = b"FALCON_DET";
+
+/// A decoded compressed signature.
+struct Signature {
+    salt_version: u8,
+    s2: Vec<i16>,
+}
+
+impl Signature {
+    fn decode(signature: &[u8]) -> Result<Self, AlgoKitUtilsError> {
+        match signature {
+            [COMPRESSED_SIGNATURE_HEADER, salt_version, compressed @ ..] => Ok(Self {
+                salt_version: *salt_version,
+                s2: decode_compressed(compressed)?,
+            }),
+            _ => Err(invalid_proof(
+                "Falcon signature has an unexpected header".to_string(),
+            )),
+        }
+    }
+
+    /// Returns the salt that replaces the nonce when hashing the message.
+    fn salt(&self) -> [u8; SALT_SIZE] {
+        let mut salt = [0u8; SALT_SIZE];
+        salt[0] = self.salt_version;
+        salt[1] = LOGN;
+        salt[2..2 + SALT_TAG.len()].copy_from_slice(SALT_TAG);
+        salt
+    }
+}
+
+/// Converts a compressed signature into its fixed-length (CT) encoding.
+pub(crate) fn ct_signature(signature: &[u8]) -> Result<Vec<u8>, AlgoKitUtilsError> {
+    let signature = Signature::decode(signature)?;
+
+    let mut bytes = vec![CT_SIGNATURE_HEADER, signature.salt_version];
+    let mask = (1u32 << CT_COEFFICIENT_BITS) - 1;
+    let (mut acc, mut acc_len) = (0u32, 0u32);
+    for coefficient in signature.s2 {
+        acc = (acc << CT_COEFFICIENT_BITS) | (coefficient as u16 as u32 & mask);
+        acc_len += CT_COEFFICIENT_BITS;
+        while acc_len >= 8 {
+            acc_len -= 8;
+            bytes.push((acc >> acc_len) as u8);
+        }
+    }
+    Ok(bytes)
+}
+
+/// Verifies a compressed signature of `message` under `public_key`.
+pub(crate) fn verify(
+    public_key: &[u8],
+    message: &[u8],
+    signature: &[u8],
+) -> Result<(), AlgoKitUtilsError> {
+    let h = decode_public_key(public_key)?;
+    let signature = Signature::decode(signature)?;
+    let c = hash_to_point(&signature.salt(), message);
+
+    // s1 = c - s2 * h mod (x^N + 1, q)
+    let mut product = vec![0i64; N];
+    for (i, s2) in signature.s2.iter().enumerate() {
+        if *s2 == 0 {
+            continue;
+        }
+        for (j, h) in h.iter().enumerate() {
+            let term = *s2 as i64 * h;
+            if i + j < N {
+                product[i + j] += term;
+            } else {
+                product[i + j - N] -= term;
+            }
+        }
+    }
+
+    let mut norm = 0i64;
+    for ((c, product), s2) in c.iter().zip(product).zip(&signature.s2) {
+        let mut s1 = (c - product).rem_euclid(Q);
+        if s1 > Q / 2 {
+            s1 -= Q;
+        }
+        norm += s1 * s1 + *s2 as i64 * *s2 as i64;
+    }
+    if norm > L2_BOUND {
+        return Err(invalid_proof("Falcon signature is invalid".to_string()));
+    }
+    Ok(())
+}
+
+fn decode_public_key(public_key: &[u8]) -> Result<Vec<i64>, AlgoKitUtilsError> {
+    if public_key.len() != PUBLIC_KEY_SIZE || public_key[0] != PUBLIC_KEY_HEADER {
+        return Err(invalid_proof(
+            "Falcon public key is not a Falcon-1024 key".to_string(),
+        ));
+    }
+
+    let mut coefficients = Vec::with_capacity(N);
+    let (mut acc, mut acc_len) = (0u32, 0u32);
+    for byte in &public_key[1..] {
+        acc = (acc << 8) | *byte as u32;
+        acc_len += 8;
+        if acc_len >= 14 {
+            acc_len -= 14;
+            let coefficient = ((acc >> acc_len) & 0x3fff) as i64;
+            if coefficient >= Q {
+                return Err(invalid_proof(
+                    "Falcon public key has a coefficient out of range".to_string(),
+                ));
+            }
+            coefficients.push(coefficient);
+        }
+    }
+    Ok(coefficients)
+}
+
+/// Decodes the compressed encoding of `s2`: per coefficient a sign bit, the 7 low bits of the
+/// magnitude and the remaining bits of the magnitude in unary.
+fn decode_compressed(bytes: &[u8]) -> Result<Vec<i16>, AlgoKitUtilsError> {
+    let malformed = || invalid_proof("Falcon signature is malformed".to_string());
+    let mut position = 0usize;
+    let mut next_bit = || {
+        let byte = bytes.get(position / 8).ok_or_else(malformed)?;
+        let bit = (byte >> (7 - position % 8)) & 1 == 1;
+        position += 1;
+        Ok::<bool, AlgoKitUtilsError>(bit)
+    };
+
+    let mut coefficients = Vec::with_capacity(N);
+    for _ in 0..N {
+        let negative = next_bit()?;
+        let mut magnitude = 0u16;
+        for _ in 0..7 {
+            magnitude = (magnitude << 1) | next_bit()? as u16;
+        }
+        while !next_bit()? {
+            magnitude += 128;
+            if magnitude > 2047 {
+                return Err(malformed());
+            }
+        }
+        if negative && magnitude == 0 {
+            return Err(malformed());
+        }
+        coefficients.push(if negative {
+            -(magnitude as i16)
+        } else {
+            magnitude as i16
+        });
+    }
+
+    // The encoding must end in the last byte, padded with zero bits.
+    let unused_bits = (8 - position % 8) % 8;
+    if position.div_ceil(8) != bytes.len() || bytes[bytes.len() - 1] & ((1 << unused_bits) - 1) != 0
+    {
+        return Err(malformed());
+    }
+    Ok(coefficients)
+}
+
+/// Hashes a salted message to a polynomial with coefficients modulo q.
+fn hash_to_point(salt: &[u8], message: &[u8]) -> Vec<i64> {
+    let mut shake = Shake256::default();
+    shake.update(salt);
+    shake.update(message);
+    let mut reader = shake.finalize_xof();
+
+    let mut point = Vec::with_capacity(N);
+    while point.len() < N {
+        let mut bytes = [0u8; 2];
+        reader.read(&mut bytes);
+        let value = u16::from_be_bytes(bytes) as i64;
+        if value < 5 * Q {
+            point.push(value % Q);
+        }
+    }
+    point
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::state_proof::state_proof_message_hash;
+    use algokit_transact::test_utils::TestDataMother;
+    use algokit_transact::{FalconSignatureStruct, Transaction};
+
+    /// Returns the hash of the testnet state proof message and a signature revealed in its proof.
+    fn artifact_signature() -> (Vec<u8>, FalconSignatureStruct) {
+        let Transaction::StateProof(fields) = TestDataMother::state_proof().transaction else {
+            panic!("expected a state proof transaction");
+        };
+        let message_hash = state_proof_message_hash(&fields.message.unwrap()).unwrap();
+        let (_, reveal) = fields.state_proof.unwrap().reveals.pop_first().unwrap();
+        (message_hash.to_vec(), reveal.sigslot.sig)
+    }
+
+    #[test]
+    fn test_verifies_participant_signature() {
+        let (message_hash, signature) = artifact_signature();
+
+        verify(
+            &signature.verifying_key.public_key,
+            &message_hash,
+            &signature.signature,
+        )
+        .unwrap();
+    }
+
+    #[test]
+    fn test_rejects_signature_of_other_message() {
+        let (mut message_hash, signature) = artifact_signature();
+        message_hash[0] ^= 1;
+
+        let result = verify(
+            &signature.verifying_key.public_key,
+            &message_hash,
+            &signature.signature,
+        );
+
+        assert!(
+            result
+                .unwrap_err()
+                .to_string()
+                .contains("Falcon signature is invalid")
+        );
+    }
+
+    #[test]
+    fn test_ct_signature_has_fixed_length() {
+        let (_, signature) = artifact_signature();
+
+        let ct = ct_signature(&signature.signature).unwrap();
+
+        assert_eq!(ct.len(), 2 + N * CT_COEFFICIENT_BITS as usize / 8);
+        assert_eq!(ct[..2], [CT_SIGNATURE_HEADER, signature.signature[1]]);
+    }
+}
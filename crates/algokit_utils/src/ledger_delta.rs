@@ -0,0 +1,1016 @@
+//! Typed ledger state deltas.
+//!
+//! algod's `/v2/deltas` endpoints return the ledger's internal state delta for a round or for a
+//! transaction group, which the generated `LedgerStateDelta` model does not describe. This module
+//! decodes the msgpack response into a [`LedgerDelta`] of account, asset, app and box changes, and
+//! [`LedgerSnapshot`] applies those changes to an in-memory copy of the accounts they touch, so
+//! that balances can be kept up to date from algod alone.
+//!
+//! Unlike block apply data, a state delta records the complete new value of every account and
+//! resource it changes, so applying a delta replaces values rather than adjusting them.
+
+use crate::error::AlgoKitUtilsError;
+use crate::typed_block::{is_false, is_zero, is_zero_address, is_zero_bytes};
+use algod_client::apis::Error as AlgodError;
+use algokit_http_client::{HttpClient, HttpMethod};
+use algokit_transact::{Address, AlgorandMsgpack, Byte32};
+use serde::{Deserialize, Serialize};
+use serde_with::{Bytes, serde_as};
+use std::collections::{BTreeMap, HashMap};
+
+const BOX_KEY_PREFIX: &[u8] = b"bx:";
+const TEAL_BYTES_TYPE: u8 = 1;
+const TEAL_UINT_TYPE: u8 = 2;
+
+fn is_zero_u32(value: &u32) -> bool {
+    *value == 0
+}
+
+/// The number of integer and byte slice values allowed in an app's state.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct StateSchema {
+    #[serde(rename = "nui", default, skip_serializing_if = "is_zero")]
+    pub num_uints: u64,
+
+    #[serde(rename = "nbs", default, skip_serializing_if = "is_zero")]
+    pub num_byte_slices: u64,
+}
+
+/// The balance, participation and resource totals of an account.
+#[serde_as]
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct AccountData {
+    /// The participation status: 0 offline, 1 online and 2 not participating.
+    #[serde(rename = "Status", default)]
+    pub status: u8,
+
+    /// The balance in microalgos.
+    #[serde(rename = "MicroAlgos", default, skip_serializing_if = "is_zero")]
+    pub balance: u64,
+
+    /// The rewards level the account last received rewards at.
+    #[serde(rename = "RewardsBase", default, skip_serializing_if = "is_zero")]
+    pub rewards_base: u64,
+
+    /// The total rewards the account has received.
+    #[serde(
+        rename = "RewardedMicroAlgos",
+        default,
+        skip_serializing_if = "is_zero"
+    )]
+    pub rewards: u64,
+
+    /// The account authorized to sign for this one, or the zero address if it is not rekeyed.
+    #[serde(rename = "AuthAddr", default, skip_serializing_if = "is_zero_address")]
+    pub auth_address: Address,
+
+    /// Whether the account is eligible for block incentives.
+    #[serde(
+        rename = "IncentiveEligible",
+        default,
+        skip_serializing_if = "is_false"
+    )]
+    pub incentive_eligible: bool,
+
+    /// The combined state schema of the apps the account created or opted in to.
+    #[serde(rename = "TotalAppSchema", default)]
+    pub total_app_schema: StateSchema,
+
+    #[serde(
+        rename = "TotalExtraAppPages",
+        default,
+        skip_serializing_if = "is_zero_u32"
+    )]
+    pub total_extra_app_pages: u32,
+
+    #[serde(rename = "TotalAppParams", default, skip_serializing_if = "is_zero")]
+    pub total_created_apps: u64,
+
+    #[serde(
+        rename = "TotalAppLocalStates",
+        default,
+        skip_serializing_if = "is_zero"
+    )]
+    pub total_apps_opted_in: u64,
+
+    #[serde(rename = "TotalAssetParams", default, skip_serializing_if = "is_zero")]
+    pub total_created_assets: u64,
+
+    #[serde(rename = "TotalAssets", default, skip_serializing_if = "is_zero")]
+    pub total_assets_opted_in: u64,
+
+    #[serde(rename = "TotalBoxes", default, skip_serializing_if = "is_zero")]
+    pub total_boxes: u64,
+
+    #[serde(rename = "TotalBoxBytes", default, skip_serializing_if = "is_zero")]
+    pub total_box_bytes: u64,
+
+    /// The last round the account proposed a block in.
+    #[serde(rename = "LastProposed", default, skip_serializing_if = "is_zero")]
+    pub last_proposed: u64,
+
+    /// The last round the account sent a heartbeat in.
+    #[serde(rename = "LastHeartbeat", default, skip_serializing_if = "is_zero")]
+    pub last_heartbeat: u64,
+
+    #[serde(rename = "VoteID", default, skip_serializing_if = "is_zero_bytes")]
+    #[serde_as(as = "Bytes")]
+    pub vote_id: Byte32,
+
+    #[serde(rename = "SelectionID", default, skip_serializing_if = "is_zero_bytes")]
+    #[serde_as(as = "Bytes")]
+    pub selection_id: Byte32,
+
+    /// The 64 byte commitment to the account's state proof keys.
+    #[serde(
+        rename = "StateProofID",
+        default,
+        skip_serializing_if = "Vec::is_empty"
+    )]
+    #[serde_as(as = "Bytes")]
+    pub state_proof_id: Vec<u8>,
+
+    #[serde(rename = "VoteFirstValid", default, skip_serializing_if = "is_zero")]
+    pub vote_first_valid: u64,
+
+    #[serde(rename = "VoteLastValid", default, skip_serializing_if = "is_zero")]
+    pub vote_last_valid: u64,
+
+    #[serde(rename = "VoteKeyDilution", default, skip_serializing_if = "is_zero")]
+    pub vote_key_dilution: u64,
+}
+
+/// A value in app global or local state.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(try_from = "TealValueWire", into = "TealValueWire")]
+pub enum TealValue {
+    Bytes(Vec<u8>),
+    Uint(u64),
+}
+
+#[serde_as]
+#[derive(Serialize, Deserialize)]
+struct TealValueWire {
+    #[serde(rename = "tt", default)]
+    value_type: u8,
+
+    #[serde(rename = "tb", default, skip_serializing_if = "Vec::is_empty")]
+    #[serde_as(as = "Bytes")]
+    bytes: Vec<u8>,
+
+    #[serde(rename = "ui", default, skip_serializing_if = "is_zero")]
+    uint: u64,
+}
+
+impl TryFrom<TealValueWire> for TealValue {
+    type Error = String;
+
+    fn try_from(value: TealValueWire) -> Result<Self, Self::Error> {
+        match value.value_type {
+            TEAL_BYTES_TYPE => Ok(TealValue::Bytes(value.bytes)),
+            TEAL_UINT_TYPE => Ok(TealValue::Uint(value.uint)),
+            value_type => Err(format!("Unknown TEAL value type {}", value_type)),
+        }
+    }
+}
+
+impl From<TealValue> for TealValueWire {
+    fn from(value: TealValue) -> Self {
+        match value {
+            TealValue::Bytes(bytes) => TealValueWire {
+                value_type: TEAL_BYTES_TYPE,
+                bytes,
+                uint: 0,
+            },
+            TealValue::Uint(uint) => TealValueWire {
+                value_type: TEAL_UINT_TYPE,
+                bytes: Vec::new(),
+                uint,
+            },
+        }
+    }
+}
+
+/// The parameters of an app, held by its creator.
+#[serde_as]
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct AppParams {
+    #[serde(rename = "approv", default, skip_serializing_if = "Vec::is_empty")]
+    #[serde_as(as = "Bytes")]
+    pub approval_program: Vec<u8>,
+
+    #[serde(rename = "clearp", default, skip_serializing_if = "Vec::is_empty")]
+    #[serde_as(as = "Bytes")]
+    pub clear_state_program: Vec<u8>,
+
+    #[serde(rename = "gs", default, skip_serializing_if = "BTreeMap::is_empty")]
+    #[serde_as(as = "BTreeMap<Bytes, _>")]
+    pub global_state: BTreeMap<Vec<u8>, TealValue>,
+
+    #[serde(rename = "lsch", default)]
+    pub local_state_schema: StateSchema,
+
+    #[serde(rename = "gsch", default)]
+    pub global_state_schema: StateSchema,
+
+    #[serde(rename = "epp", default, skip_serializing_if = "is_zero_u32")]
+    pub extra_program_pages: u32,
+
+    /// The number of times the app has been updated.
+    #[serde(rename = "v", default, skip_serializing_if = "is_zero")]
+    pub version: u64,
+}
+
+/// The local state of an account that has opted in to an app.
+#[serde_as]
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct AppLocalState {
+    #[serde(rename = "hsch", default)]
+    pub schema: StateSchema,
+
+    #[serde(rename = "tkv", default, skip_serializing_if = "BTreeMap::is_empty")]
+    #[serde_as(as = "BTreeMap<Bytes, _>")]
+    pub key_values: BTreeMap<Vec<u8>, TealValue>,
+}
+
+/// The parameters of an asset, held by its creator.
+#[serde_as]
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct AssetParams {
+    #[serde(rename = "t", default, skip_serializing_if = "is_zero")]
+    pub total: u64,
+
+    #[serde(rename = "dc", default, skip_serializing_if = "is_zero_u32")]
+    pub decimals: u32,
+
+    #[serde(rename = "df", default, skip_serializing_if = "is_false")]
+    pub default_frozen: bool,
+
+    #[serde(rename = "un", default, skip_serializing_if = "String::is_empty")]
+    pub unit_name: String,
+
+    #[serde(rename = "an", default, skip_serializing_if = "String::is_empty")]
+    pub asset_name: String,
+
+    #[serde(rename = "au", default, skip_serializing_if = "String::is_empty")]
+    pub url: String,
+
+    #[serde(rename = "am", default, skip_serializing_if = "is_zero_bytes")]
+    #[serde_as(as = "Bytes")]
+    pub metadata_hash: Byte32,
+
+    #[serde(rename = "m", default, skip_serializing_if = "is_zero_address")]
+    pub manager: Address,
+
+    #[serde(rename = "r", default, skip_serializing_if = "is_zero_address")]
+    pub reserve: Address,
+
+    #[serde(rename = "f", default, skip_serializing_if = "is_zero_address")]
+    pub freeze: Address,
+
+    #[serde(rename = "c", default, skip_serializing_if = "is_zero_address")]
+    pub clawback: Address,
+}
+
+/// An account's holding of an asset it has opted in to.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct AssetHolding {
+    #[serde(rename = "a", default, skip_serializing_if = "is_zero")]
+    pub amount: u64,
+
+    #[serde(rename = "f", default, skip_serializing_if = "is_false")]
+    pub frozen: bool,
+}
+
+/// The new value of an account resource changed by a delta.
+#[derive(Debug, Clone, PartialEq)]
+pub enum ResourceChange<T> {
+    /// The resource was created or updated to the given value.
+    Set(T),
+    /// The resource was deleted.
+    Deleted,
+}
+
+/// The new data of an account changed by a delta.
+#[derive(Debug, Clone, PartialEq)]
+pub struct AccountChange {
+    pub address: Address,
+    /// The new account data, which is empty if the account was closed.
+    pub data: AccountData,
+}
+
+/// A change to an account's relationship with an app.
+#[derive(Debug, Clone, PartialEq)]
+pub struct AppResourceChange {
+    pub address: Address,
+    pub app_id: u64,
+    /// The new parameters, including global state, of an app created by the account.
+    pub params: Option<ResourceChange<AppParams>>,
+    /// The new local state of the account in the app.
+    pub local_state: Option<ResourceChange<AppLocalState>>,
+}
+
+/// A change to an account's relationship with an asset.
+#[derive(Debug, Clone, PartialEq)]
+pub struct AssetResourceChange {
+    pub address: Address,
+    pub asset_id: u64,
+    /// The new parameters of an asset created by the account.
+    pub params: Option<ResourceChange<AssetParams>>,
+    /// The new holding of the account, which is deleted when the account opts out.
+    pub holding: Option<ResourceChange<AssetHolding>>,
+}
+
+/// A change to the contents of an app box.
+#[derive(Debug, Clone, PartialEq)]
+pub struct BoxChange {
+    pub app_id: u64,
+    pub name: Vec<u8>,
+    /// The new contents of the box, or `None` if it was deleted.
+    pub value: Option<Vec<u8>>,
+    /// The contents of the box before the change, or `None` if it did not exist.
+    pub previous_value: Option<Vec<u8>>,
+}
+
+/// The changes to the ledger made by a round or a transaction group.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct LedgerDelta {
+    /// The round the delta was produced by, if the delta includes its block header.
+    pub round: Option<u64>,
+    pub accounts: Vec<AccountChange>,
+    pub apps: Vec<AppResourceChange>,
+    pub assets: Vec<AssetResourceChange>,
+    pub boxes: Vec<BoxChange>,
+}
+
+/// The ledger delta of a transaction group, with the IDs of the transactions in the group.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct TransactionGroupLedgerDelta {
+    pub ids: Vec<String>,
+    pub delta: LedgerDelta,
+}
+
+#[serde_as]
+#[derive(Debug, Default, Serialize, Deserialize)]
+struct StateDeltaWire {
+    #[serde(rename = "Accts", default)]
+    accounts: AccountDeltasWire,
+
+    #[serde(rename = "KvMods", default, skip_serializing_if = "Option::is_none")]
+    #[serde_as(as = "Option<BTreeMap<Bytes, _>>")]
+    kv_mods: Option<BTreeMap<Vec<u8>, KvValueDeltaWire>>,
+
+    #[serde(rename = "Hdr", default, skip_serializing_if = "Option::is_none")]
+    header: Option<HeaderWire>,
+}
+
+impl AlgorandMsgpack for StateDeltaWire {}
+
+#[derive(Debug, Default, Serialize, Deserialize)]
+struct HeaderWire {
+    #[serde(rename = "rnd", default)]
+    round: u64,
+}
+
+#[derive(Debug, Default, Serialize, Deserialize)]
+struct AccountDeltasWire {
+    #[serde(rename = "Accts", default, skip_serializing_if = "Option::is_none")]
+    accounts: Option<Vec<BalanceRecordWire>>,
+
+    #[serde(
+        rename = "AppResources",
+        default,
+        skip_serializing_if = "Option::is_none"
+    )]
+    apps: Option<Vec<AppResourceRecordWire>>,
+
+    #[serde(
+        rename = "AssetResources",
+        default,
+        skip_serializing_if = "Option::is_none"
+    )]
+    assets: Option<Vec<AssetResourceRecordWire>>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+struct BalanceRecordWire {
+    #[serde(rename = "Addr")]
+    address: Address,
+
+    #[serde(flatten)]
+    data: AccountData,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+struct AppResourceRecordWire {
+    #[serde(rename = "Aidx")]
+    app_id: u64,
+
+    #[serde(rename = "Addr")]
+    address: Address,
+
+    #[serde(rename = "Params", default)]
+    params: ResourceDeltaWire<AppParams>,
+
+    #[serde(rename = "State", default)]
+    local_state: LocalStateDeltaWire,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+struct AssetResourceRecordWire {
+    #[serde(rename = "Aidx")]
+    asset_id: u64,
+
+    #[serde(rename = "Addr")]
+    address: Address,
+
+    #[serde(rename = "Params", default)]
+    params: ResourceDeltaWire<AssetParams>,
+
+    #[serde(rename = "Holding", default)]
+    holding: HoldingDeltaWire,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+struct ResourceDeltaWire<T> {
+    #[serde(rename = "Params", default = "Option::default")]
+    value: Option<T>,
+
+    #[serde(rename = "Deleted", default)]
+    deleted: bool,
+}
+
+impl<T> Default for ResourceDeltaWire<T> {
+    fn default() -> Self {
+        Self {
+            value: None,
+            deleted: false,
+        }
+    }
+}
+
+#[derive(Debug, Default, Serialize, Deserialize)]
+struct LocalStateDeltaWire {
+    #[serde(rename = "LocalState", default)]
+    value: Option<AppLocalState>,
+
+    #[serde(rename = "Deleted", default)]
+    deleted: bool,
+}
+
+#[derive(Debug, Default, Serialize, Deserialize)]
+struct HoldingDeltaWire {
+    #[serde(rename = "Holding", default)]
+    value: Option<AssetHolding>,
+
+    #[serde(rename = "Deleted", default)]
+    deleted: bool,
+}
+
+#[serde_as]
+#[derive(Debug, Default, Serialize, Deserialize)]
+struct KvValueDeltaWire {
+    #[serde(rename = "Data", default)]
+    #[serde_as(as = "Option<Bytes>")]
+    data: Option<Vec<u8>>,
+
+    #[serde(rename = "OldData", default)]
+    #[serde_as(as = "Option<Bytes>")]
+    old_data: Option<Vec<u8>>,
+}
+
+#[derive(Debug, Default, Serialize, Deserialize)]
+struct TransactionGroupDeltasWire {
+    #[serde(rename = "Deltas", default)]
+    deltas: Option<Vec<TransactionGroupDeltaWire>>,
+}
+
+impl AlgorandMsgpack for TransactionGroupDeltasWire {}
+
+#[derive(Debug, Default, Serialize, Deserialize)]
+struct TransactionGroupDeltaWire {
+    #[serde(rename = "Delta", default)]
+    delta: StateDeltaWire,
+
+    #[serde(rename = "Ids", default)]
+    ids: Option<Vec<String>>,
+}
+
+fn resource_change<T>(value: Option<T>, deleted: bool) -> Option<ResourceChange<T>> {
+    match (value, deleted) {
+        (_, true) => Some(ResourceChange::Deleted),
+        (Some(value), false) => Some(ResourceChange::Set(value)),
+        (None, false) => None,
+    }
+}
+
+/// Splits a `bx:` key-value store key into the app ID and box name.
+fn box_key(key: &[u8]) -> Option<(u64, Vec<u8>)> {
+    let key = key.strip_prefix(BOX_KEY_PREFIX)?;
+    let (app_id, name) = key.split_at_checked(8)?;
+    Some((u64::from_be_bytes(app_id.try_into().ok()?), name.to_vec()))
+}
+
+impl From<StateDeltaWire> for LedgerDelta {
+    fn from(wire: StateDeltaWire) -> Self {
+        let accounts = wire
+            .accounts
+            .accounts
+            .into_iter()
+            .flatten()
+            .map(|record| AccountChange {
+                address: record.address,
+                data: record.data,
+            })
+            .collect();
+
+        let apps = wire
+            .accounts
+            .apps
+            .into_iter()
+            .flatten()
+            .map(|record| AppResourceChange {
+                address: record.address,
+                app_id: record.app_id,
+                params: resource_change(record.params.value, record.params.deleted),
+                local_state: resource_change(record.local_state.value, record.local_state.deleted),
+            })
+            .collect();
+
+        let assets = wire
+            .accounts
+            .assets
+            .into_iter()
+            .flatten()
+            .map(|record| AssetResourceChange {
+                address: record.address,
+                asset_id: record.asset_id,
+                params: resource_change(record.params.value, record.params.deleted),
+                holding: resource_change(record.holding.value, record.holding.deleted),
+            })
+            .collect();
+
+        let boxes = wire
+            .kv_mods
+            .into_iter()
+            .flatten()
+            .filter_map(|(key, value)| {
+                let (app_id, name) = box_key(&key)?;
+                Some(BoxChange {
+                    app_id,
+                    name,
+                    value: value.data,
+                    previous_value: value.old_data,
+                })
+            })
+            .collect();
+
+        Self {
+            round: wire.header.map(|header| header.round),
+            accounts,
+            apps,
+            assets,
+            boxes,
+        }
+    }
+}
+
+impl LedgerDelta {
+    /// Decodes the msgpack response of `/v2/deltas/{round}` or `/v2/deltas/txn/group/{id}`.
+    pub fn from_msgpack(bytes: &[u8]) -> Result<Self, AlgoKitUtilsError> {
+        Ok(StateDeltaWire::decode(bytes)?.into())
+    }
+}
+
+impl TransactionGroupLedgerDelta {
+    /// Decodes the msgpack response of `/v2/deltas/{round}/txn/group`.
+    pub fn from_msgpack(bytes: &[u8]) -> Result<Vec<Self>, AlgoKitUtilsError> {
+        Ok(TransactionGroupDeltasWire::decode(bytes)?
+            .deltas
+            .into_iter()
+            .flatten()
+            .map(|group| Self {
+                ids: group.ids.unwrap_or_default(),
+                delta: group.delta.into(),
+            })
+            .collect())
+    }
+}
+
+async fn get_msgpack(http_client: &dyn HttpClient, path: String) -> Result<Vec<u8>, AlgodError> {
+    let response = http_client
+        .request(
+            HttpMethod::Get,
+            path,
+            Some(HashMap::from([(
+                "format".to_string(),
+                "msgpack".to_string(),
+            )])),
+            None,
+            Some(HashMap::from([(
+                "Accept".to_string(),
+                "application/msgpack".to_string(),
+            )])),
+        )
+        .await
+        .map_err(|source| AlgodError::Http { source })?;
+    Ok(response.body)
+}
+
+/// Fetches the ledger delta of a round from algod.
+///
+/// This requests the delta directly rather than through `AlgodClient::get_ledger_state_delta`,
+/// whose response model does not retain the delta's contents.
+pub async fn fetch_ledger_delta(
+    http_client: &dyn HttpClient,
+    round: u64,
+) -> Result<LedgerDelta, AlgoKitUtilsError> {
+    let body = get_msgpack(http_client, format!("/v2/deltas/{}", round)).await?;
+    LedgerDelta::from_msgpack(&body)
+}
+
+/// Fetches the ledger deltas of each transaction group in a round from algod.
+pub async fn fetch_transaction_group_ledger_deltas(
+    http_client: &dyn HttpClient,
+    round: u64,
+) -> Result<Vec<TransactionGroupLedgerDelta>, AlgoKitUtilsError> {
+    let body = get_msgpack(http_client, format!("/v2/deltas/{}/txn/group", round)).await?;
+    TransactionGroupLedgerDelta::from_msgpack(&body)
+}
+
+/// An account and the resources it holds.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct AccountSnapshot {
+    pub data: AccountData,
+    /// Holdings of the assets the account has opted in to, by asset ID.
+    pub assets: BTreeMap<u64, AssetHolding>,
+    /// Parameters of the assets the account created, by asset ID.
+    pub created_assets: BTreeMap<u64, AssetParams>,
+    /// Local state of the apps the account has opted in to, by app ID.
+    pub apps_local_state: BTreeMap<u64, AppLocalState>,
+    /// Parameters of the apps the account created, by app ID.
+    pub created_apps: BTreeMap<u64, AppParams>,
+}
+
+impl AccountSnapshot {
+    fn is_empty(&self) -> bool {
+        self.data == AccountData::default()
+            && self.assets.is_empty()
+            && self.created_assets.is_empty()
+            && self.apps_local_state.is_empty()
+            && self.created_apps.is_empty()
+    }
+}
+
+/// An in-memory copy of the accounts and boxes touched by the deltas applied to it.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct LedgerSnapshot {
+    pub accounts: HashMap<Address, AccountSnapshot>,
+    /// Box contents by app ID and box name.
+    pub boxes: BTreeMap<(u64, Vec<u8>), Vec<u8>>,
+}
+
+fn apply_resource<T: Clone>(
+    resources: &mut BTreeMap<u64, T>,
+    id: u64,
+    change: &Option<ResourceChange<T>>,
+) {
+    match change {
+        Some(ResourceChange::Set(value)) => {
+            resources.insert(id, value.clone());
+        }
+        Some(ResourceChange::Deleted) => {
+            resources.remove(&id);
+        }
+        None => {}
+    }
+}
+
+impl LedgerSnapshot {
+    /// Creates an empty snapshot.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Returns the balance of an account in microalgos, which is 0 for unknown accounts.
+    pub fn balance(&self, address: &Address) -> u64 {
+        self.accounts
+            .get(address)
+            .map_or(0, |account| account.data.balance)
+    }
+
+    /// Applies a delta, replacing the values it changes. Accounts left with no balance and no
+    /// resources are removed.
+    pub fn apply(&mut self, delta: &LedgerDelta) {
+        for change in &delta.accounts {
+            self.accounts
+                .entry(change.address.clone())
+                .or_default()
+                .data = change.data.clone();
+        }
+        for change in &delta.assets {
+            let account = self.accounts.entry(change.address.clone()).or_default();
+            apply_resource(&mut account.created_assets, change.asset_id, &change.params);
+            apply_resource(&mut account.assets, change.asset_id, &change.holding);
+        }
+        for change in &delta.apps {
+            let account = self.accounts.entry(change.address.clone()).or_default();
+            apply_resource(&mut account.created_apps, change.app_id, &change.params);
+            apply_resource(
+                &mut account.apps_local_state,
+                change.app_id,
+                &change.local_state,
+            );
+        }
+        self.accounts.retain(|_, account| !account.is_empty());
+
+        for change in &delta.boxes {
+            let key = (change.app_id, change.name.clone());
+            match &change.value {
+                Some(value) => {
+                    self.boxes.insert(key, value.clone());
+                }
+                None => {
+                    self.boxes.remove(&key);
+                }
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use algokit_http_client::{HttpError, HttpResponse};
+    use async_trait::async_trait;
+    use std::sync::Mutex;
+
+    fn box_key_bytes(app_id: u64, name: &[u8]) -> Vec<u8> {
+        [BOX_KEY_PREFIX, &app_id.to_be_bytes(), name].concat()
+    }
+
+    fn round_delta() -> StateDeltaWire {
+        StateDeltaWire {
+            accounts: AccountDeltasWire {
+                accounts: Some(vec![BalanceRecordWire {
+                    address: Address([1; 32]),
+                    data: AccountData {
+                        status: 1,
+                        balance: 5_000_000,
+                        auth_address: Address([2; 32]),
+                        total_assets_opted_in: 1,
+                        vote_id: [3; 32],
+                        state_proof_id: vec![4; 64],
+                        ..Default::default()
+                    },
+                }]),
+                apps: Some(vec![AppResourceRecordWire {
+                    app_id: 1234,
+                    address: Address([1; 32]),
+                    params: ResourceDeltaWire {
+                        value: Some(AppParams {
+                            approval_program: vec![6, 129, 1],
+                            global_state: BTreeMap::from([
+                                (b"count".to_vec(), TealValue::Uint(7)),
+                                (b"owner".to_vec(), TealValue::Bytes(vec![1; 32])),
+                            ]),
+                            global_state_schema: StateSchema {
+                                num_uints: 1,
+                                num_byte_slices: 1,
+                            },
+                            ..Default::default()
+                        }),
+                        deleted: false,
+                    },
+                    local_state: LocalStateDeltaWire {
+                        value: None,
+                        deleted: true,
+                    },
+                }]),
+                assets: Some(vec![AssetResourceRecordWire {
+                    asset_id: 99,
+                    address: Address([1; 32]),
+                    params: ResourceDeltaWire::default(),
+                    holding: HoldingDeltaWire {
+                        value: Some(AssetHolding {
+                            amount: 10,
+                            frozen: false,
+                        }),
+                        deleted: false,
+                    },
+                }]),
+            },
+            kv_mods: Some(BTreeMap::from([
+                (
+                    box_key_bytes(1234, b"name"),
+                    KvValueDeltaWire {
+                        data: Some(b"value".to_vec()),
+                        old_data: None,
+                    },
+                ),
+                (b"other".to_vec(), KvValueDeltaWire::default()),
+            ])),
+            header: Some(HeaderWire { round: 42 }),
+        }
+    }
+
+    #[test]
+    fn test_decodes_round_delta() {
+        let delta = LedgerDelta::from_msgpack(&round_delta().encode().unwrap()).unwrap();
+
+        assert_eq!(delta.round, Some(42));
+        assert_eq!(delta.accounts.len(), 1);
+        let account = &delta.accounts[0];
+        assert_eq!(account.address, Address([1; 32]));
+        assert_eq!(account.data.balance, 5_000_000);
+        assert_eq!(account.data.auth_address, Address([2; 32]));
+        assert_eq!(account.data.state_proof_id, vec![4; 64]);
+
+        let Some(ResourceChange::Set(params)) = &delta.apps[0].params else {
+            panic!("expected app params");
+        };
+        assert_eq!(params.global_state[b"count".as_slice()], TealValue::Uint(7));
+        assert_eq!(params.global_state_schema.num_byte_slices, 1);
+        assert_eq!(delta.apps[0].local_state, Some(ResourceChange::Deleted));
+
+        assert_eq!(delta.assets[0].params, None);
+        assert_eq!(
+            delta.assets[0].holding,
+            Some(ResourceChange::Set(AssetHolding {
+                amount: 10,
+                frozen: false
+            }))
+        );
+
+        assert_eq!(
+            delta.boxes,
+            vec![BoxChange {
+                app_id: 1234,
+                name: b"name".to_vec(),
+                value: Some(b"value".to_vec()),
+                previous_value: None,
+            }]
+        );
+    }
+
+    /// algod encodes Go string map keys as msgpack strings even when they are not valid UTF-8.
+    #[test]
+    fn test_decodes_non_utf8_string_keys() {
+        fn raw_str(bytes: &[u8]) -> rmpv::Value {
+            let encoded = [&[0xa0 | bytes.len() as u8], bytes].concat();
+            rmpv::decode::read_value(&mut encoded.as_slice()).unwrap()
+        }
+        let key = box_key_bytes(0xd2, &[0xff]);
+        let value = rmpv::Value::Map(vec![
+            (
+                "Accts".into(),
+                rmpv::Value::Map(vec![(
+                    "AppResources".into(),
+                    rmpv::Value::Array(vec![rmpv::Value::Map(vec![
+                        ("Aidx".into(), 1.into()),
+                        ("Addr".into(), rmpv::Value::Binary(vec![1; 32])),
+                        (
+                            "State".into(),
+                            rmpv::Value::Map(vec![(
+                                "LocalState".into(),
+                                rmpv::Value::Map(vec![(
+                                    "tkv".into(),
+                                    rmpv::Value::Map(vec![(
+                                        raw_str(&[0xfe, 0x01]),
+                                        rmpv::Value::Map(vec![
+                                            ("tt".into(), 2.into()),
+                                            ("ui".into(), 5.into()),
+                                        ]),
+                                    )]),
+                                )]),
+                            )]),
+                        ),
+                    ])]),
+                )]),
+            ),
+            (
+                "KvMods".into(),
+                rmpv::Value::Map(vec![(
+                    raw_str(&key),
+                    rmpv::Value::Map(vec![
+                        ("Data".into(), rmpv::Value::Nil),
+                        ("OldData".into(), rmpv::Value::Binary(vec![9])),
+                    ]),
+                )]),
+            ),
+        ]);
+        let mut bytes = Vec::new();
+        rmpv::encode::write_value(&mut bytes, &value).unwrap();
+
+        let delta = LedgerDelta::from_msgpack(&bytes).unwrap();
+
+        let Some(ResourceChange::Set(local_state)) = &delta.apps[0].local_state else {
+            panic!("expected local state");
+        };
+        assert_eq!(
+            local_state.key_values,
+            BTreeMap::from([(vec![0xfe, 0x01], TealValue::Uint(5))])
+        );
+        assert_eq!(
+            delta.boxes,
+            vec![BoxChange {
+                app_id: 0xd2,
+                name: vec![0xff],
+                value: None,
+                previous_value: Some(vec![9]),
+            }]
+        );
+    }
+
+    #[test]
+    fn test_snapshot_apply() {
+        let address = Address([1; 32]);
+        let mut snapshot = LedgerSnapshot::new();
+
+        snapshot.apply(&round_delta().into());
+
+        assert_eq!(snapshot.balance(&address), 5_000_000);
+        let account = &snapshot.accounts[&address];
+        assert_eq!(account.assets[&99].amount, 10);
+        assert!(account.created_apps.contains_key(&1234));
+        assert_eq!(snapshot.boxes[&(1234, b"name".to_vec())], b"value".to_vec());
+
+        let closing = LedgerDelta {
+            accounts: vec![AccountChange {
+                address: address.clone(),
+                data: AccountData::default(),
+            }],
+            apps: vec![AppResourceChange {
+                address: address.clone(),
+                app_id: 1234,
+                params: Some(ResourceChange::Deleted),
+                local_state: None,
+            }],
+            assets: vec![AssetResourceChange {
+                address: address.clone(),
+                asset_id: 99,
+                params: None,
+                holding: Some(ResourceChange::Deleted),
+            }],
+            boxes: vec![BoxChange {
+                app_id: 1234,
+                name: b"name".to_vec(),
+                value: None,
+                previous_value: Some(b"value".to_vec()),
+            }],
+            ..Default::default()
+        };
+        snapshot.apply(&closing);
+
+        assert_eq!(snapshot, LedgerSnapshot::default());
+    }
+
+    #[derive(Default)]
+    struct MockAlgod {
+        /// The path and `format` query parameter of each request.
+        requests: Mutex<Vec<(String, String)>>,
+    }
+
+    #[async_trait]
+    impl HttpClient for MockAlgod {
+        async fn request(
+            &self,
+            _method: HttpMethod,
+            path: String,
+            query: Option<HashMap<String, String>>,
+            _body: Option<Vec<u8>>,
+            _headers: Option<HashMap<String, String>>,
+        ) -> Result<HttpResponse, HttpError> {
+            self.requests
+                .lock()
+                .unwrap()
+                .push((path, query.unwrap()["format"].clone()));
+            let response = TransactionGroupDeltasWire {
+                deltas: Some(vec![TransactionGroupDeltaWire {
+                    delta: round_delta(),
+                    ids: Some(vec!["TXID".to_string()]),
+                }]),
+            };
+            Ok(HttpResponse {
+                body: response.encode().unwrap(),
+                headers: HashMap::new(),
+            })
+        }
+    }
+
+    #[tokio::test]
+    async fn test_fetches_transaction_group_deltas() {
+        let algod = MockAlgod::default();
+
+        let deltas = fetch_transaction_group_ledger_deltas(&algod, 42)
+            .await
+            .unwrap();
+
+        assert_eq!(deltas.len(), 1);
+        assert_eq!(deltas[0].ids, vec!["TXID".to_string()]);
+        assert_eq!(deltas[0].delta, LedgerDelta::from(round_delta()));
+        let requests = algod.requests.lock().unwrap();
+        assert_eq!(requests[0].0, "/v2/deltas/42/txn/group");
+        assert_eq!(requests[0].1, "msgpack");
+    }
+}
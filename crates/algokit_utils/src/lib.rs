@@ -0,0 +1,64 @@
+//! Utilities that combine the transaction model from `algokit_transact` with the data
+//! returned by the algod and indexer API clients.
+pub mod block;
+pub mod block_header;
+pub mod chain_follower;
+pub mod coverage;
+pub mod data_signing;
+pub mod debugger;
+pub mod dryrun;
+pub mod error;
+mod falcon;
+pub mod ledger_delta;
+pub mod logic_sig_template;
+mod merkle;
+pub mod min_balance;
+pub mod simulate_trace;
+pub mod state_proof;
+pub mod subscriber;
+mod sumhash;
+pub mod teal;
+pub mod transaction_proof;
+pub mod typed_block;
+
+pub use block::{ApplyData, BlockTransaction, EvalDelta, ValueDelta, block_transactions};
+pub use block_header::{BlockHeader, verify_chain};
+pub use chain_follower::ChainFollower;
+pub use coverage::{CoverageCollector, CoverageReport, FileCoverage};
+pub use data_signing::{
+    DataSigner, PROTOCOL_PREFIXES, ScopeType, SignatureVerifier, StdSigData, sign_data, verify_data,
+};
+pub use debugger::{DebuggerBundle, SourceMapV3};
+pub use dryrun::{
+    DryrunRequestBuilder, render_app_call_trace, render_logic_sig_trace, render_trace,
+};
+pub use error::AlgoKitUtilsError;
+pub use ledger_delta::{
+    AccountChange, AccountData, AccountSnapshot, AppLocalState, AppParams, AppResourceChange,
+    AssetHolding, AssetParams, AssetResourceChange, BoxChange, LedgerDelta, LedgerSnapshot,
+    ResourceChange, StateSchema, TealValue, TransactionGroupLedgerDelta, fetch_ledger_delta,
+    fetch_transaction_group_ledger_deltas,
+};
+pub use logic_sig_template::{
+    LogicSigArg, LogicSigTemplate, TemplateVariable, inject_template_values, logic_sig_address,
+};
+pub use min_balance::{MinBalanceCalculator, MinBalanceChange, MinBalanceShortfall};
+pub use simulate_trace::{
+    ProgramSourceMap, SourceLine, TraceProgram, TraceRenderer, TraceStep, program_hash,
+};
+pub use state_proof::{
+    DefaultStateProofPrimitives, LightBlockHeader, StateProofPrimitives, StateProofVerifier,
+    decode_state_proof, state_proof_message_hash, verify_light_block_header_proof, verify_weights,
+};
+pub use subscriber::{
+    Arc28Event, SubscribedTransaction, SubscribedTransactionData, TransactionFilter,
+    TransactionSubscriber,
+};
+pub use teal::{Assembler, Assembly, Disassembly, Emulator, TemplateValue, assemble, disassemble};
+pub use transaction_proof::{
+    transaction_proof_root, verify_transaction_in_block, verify_transaction_proof,
+};
+pub use typed_block::{
+    ParticipationUpdates, RewardsState, StateProofTracking, TypedBlock, TypedBlockResponse,
+    UpgradeState, UpgradeVote,
+};
@@ -0,0 +1,387 @@
+//! ARC-47 logic signature templates.
+//!
+//! An ARC-47 template describes a logic signature whose TEAL contains `TMPL_` variables, each
+//! with an ABI type, along with the ABI types of the arguments the logic signature expects.
+//! [`LogicSigTemplate::compile`] substitutes typed values for the variables and compiles the
+//! result with the offline [`Assembler`], and [`logic_sig_address`] gives the escrow address of
+//! the compiled program. Templates distributed as bytecode with placeholders at known offsets
+//! can be filled in with [`inject_template_values`] instead.
+
+use crate::error::AlgoKitUtilsError;
+use crate::teal::assembler::{write_bytes, write_uvarint};
+use crate::teal::disassembler::{read_bytes, read_uvarint};
+use crate::teal::{Assembler, Assembly, TemplateValue};
+use algokit_abi::{ABIType, ABIValue};
+use algokit_transact::{Address, PROGRAM_DOMAIN_SEPARATOR};
+use base64::{Engine, engine::general_purpose::STANDARD as BASE64};
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha512_256};
+use std::collections::HashMap;
+use std::str::FromStr;
+
+const TEMPLATE_VARIABLE_PREFIX: &str = "TMPL_";
+
+/// An ARC-47 logic signature description.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct LogicSigTemplate {
+    pub name: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub description: Option<String>,
+    /// The base64 encoded TEAL of the logic signature.
+    pub program: String,
+    #[serde(default)]
+    pub variables: Vec<TemplateVariable>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub args: Vec<LogicSigArg>,
+}
+
+/// A template variable that must be given a value before the program is compiled.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct TemplateVariable {
+    /// The variable as it appears in the TEAL, such as `TMPL_RECEIVER`.
+    pub variable: String,
+    pub name: String,
+    /// The ABI type of the variable's value.
+    #[serde(rename = "type")]
+    pub variable_type: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub description: Option<String>,
+}
+
+/// An argument the logic signature expects when it is used to sign.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct LogicSigArg {
+    pub name: String,
+    /// The ABI type of the argument.
+    #[serde(rename = "type")]
+    pub arg_type: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub description: Option<String>,
+}
+
+impl LogicSigTemplate {
+    /// Parses a template from its ARC-47 JSON.
+    pub fn from_json(json: &str) -> Result<Self, AlgoKitUtilsError> {
+        let template: Self =
+            serde_json::from_str(json).map_err(|e| AlgoKitUtilsError::InputError {
+                err_msg: format!("Invalid ARC-47 template: {}", e),
+            })?;
+        for variable in &template.variables {
+            if !variable.variable.starts_with(TEMPLATE_VARIABLE_PREFIX) {
+                return Err(AlgoKitUtilsError::InputError {
+                    err_msg: format!(
+                        "Template variable {} does not start with {}",
+                        variable.variable, TEMPLATE_VARIABLE_PREFIX
+                    ),
+                });
+            }
+            ABIType::from_str(&variable.variable_type)?;
+        }
+        for arg in &template.args {
+            ABIType::from_str(&arg.arg_type)?;
+        }
+        Ok(template)
+    }
+
+    /// Serializes the template to its ARC-47 JSON.
+    pub fn to_json(&self) -> String {
+        serde_json::to_string(self).expect("templates always serialize")
+    }
+
+    /// The TEAL of the logic signature with its template variables in place.
+    pub fn teal(&self) -> Result<String, AlgoKitUtilsError> {
+        let bytes = BASE64
+            .decode(&self.program)
+            .map_err(|e| AlgoKitUtilsError::InputError {
+                err_msg: format!("Template program is not valid base64: {}", e),
+            })?;
+        String::from_utf8(bytes).map_err(|e| AlgoKitUtilsError::InputError {
+            err_msg: format!("Template program is not valid UTF-8: {}", e),
+        })
+    }
+
+    /// Compiles the logic signature with a value for each template variable, keyed by the
+    /// variable's name.
+    ///
+    /// `uint64` values are substituted as integers and values of every other type as their ABI
+    /// encoding.
+    pub fn compile(
+        &self,
+        values: &HashMap<String, ABIValue>,
+    ) -> Result<Assembly, AlgoKitUtilsError> {
+        if let Some(unknown) = values.keys().find(|name| {
+            !self
+                .variables
+                .iter()
+                .any(|variable| variable.name == **name)
+        }) {
+            return Err(AlgoKitUtilsError::InputError {
+                err_msg: format!("{} is not a variable of template {}", unknown, self.name),
+            });
+        }
+
+        let mut assembler = Assembler::new();
+        for variable in &self.variables {
+            let value =
+                values
+                    .get(&variable.name)
+                    .ok_or_else(|| AlgoKitUtilsError::InputError {
+                        err_msg: format!("No value for template variable {}", variable.name),
+                    })?;
+            let value = template_value(&ABIType::from_str(&variable.variable_type)?, value)?;
+            let name = &variable.variable[TEMPLATE_VARIABLE_PREFIX.len()..];
+            assembler = assembler.with_template_variable(name, value);
+        }
+        assembler.assemble(&self.teal()?)
+    }
+
+    /// ABI encodes the arguments for signing with the logic signature, in the order of
+    /// [`args`](Self::args).
+    pub fn encode_args(&self, values: &[ABIValue]) -> Result<Vec<Vec<u8>>, AlgoKitUtilsError> {
+        self.check_arg_count(values.len())?;
+        self.args
+            .iter()
+            .zip(values)
+            .map(|(arg, value)| Ok(ABIType::from_str(&arg.arg_type)?.encode(value)?))
+            .collect()
+    }
+
+    /// Checks that each argument is a canonical ABI encoding of the type the template declares
+    /// for it.
+    pub fn validate_args(&self, args: &[Vec<u8>]) -> Result<(), AlgoKitUtilsError> {
+        self.check_arg_count(args.len())?;
+        for (arg, bytes) in self.args.iter().zip(args) {
+            let abi_type = ABIType::from_str(&arg.arg_type)?;
+            let canonical = abi_type
+                .decode(bytes)
+                .and_then(|value| abi_type.encode(&value));
+            if canonical.as_ref().ok() != Some(bytes) {
+                return Err(AlgoKitUtilsError::InputError {
+                    err_msg: format!(
+                        "Argument {} is not a valid {} value",
+                        arg.name, arg.arg_type
+                    ),
+                });
+            }
+        }
+        Ok(())
+    }
+
+    fn check_arg_count(&self, count: usize) -> Result<(), AlgoKitUtilsError> {
+        if count != self.args.len() {
+            return Err(AlgoKitUtilsError::InputError {
+                err_msg: format!(
+                    "Template {} expects {} arguments, got {}",
+                    self.name,
+                    self.args.len(),
+                    count
+                ),
+            });
+        }
+        Ok(())
+    }
+}
+
+fn template_value(
+    abi_type: &ABIType,
+    value: &ABIValue,
+) -> Result<TemplateValue, AlgoKitUtilsError> {
+    let is_uint64 = match abi_type {
+        ABIType::Uint(bits) => bits.value() == 64,
+        ABIType::AVMUint64 => true,
+        _ => false,
+    };
+    if !is_uint64 {
+        return Ok(TemplateValue::Bytes(abi_type.encode(value)?));
+    }
+    match value {
+        ABIValue::Uint(value) => u64::try_from(value).map(TemplateValue::Uint).map_err(|_| {
+            AlgoKitUtilsError::InputError {
+                err_msg: format!("{} does not fit in a uint64", value),
+            }
+        }),
+        _ => Err(AlgoKitUtilsError::InputError {
+            err_msg: format!("Expected a uint64 value, got {:?}", value),
+        }),
+    }
+}
+
+/// The escrow address of a compiled logic signature program.
+pub fn logic_sig_address(program: &[u8]) -> Address {
+    let mut hasher = Sha512_256::new();
+    hasher.update(PROGRAM_DOMAIN_SEPARATOR.as_bytes());
+    hasher.update(program);
+    Address(hasher.finalize().into())
+}
+
+/// Replaces the placeholder values of a compiled template.
+///
+/// Each placeholder is given by its offset in the original program and the value to put
+/// there: a [`TemplateValue::Uint`] replaces the varuint at that offset and a
+/// [`TemplateValue::Bytes`] replaces the length-prefixed bytes at that offset. Later bytes shift
+/// when a value encodes to a different length than its placeholder, so placeholders must not be
+/// crossed by branches.
+pub fn inject_template_values(
+    program: &[u8],
+    placeholders: &[(u64, TemplateValue)],
+) -> Result<Vec<u8>, AlgoKitUtilsError> {
+    let mut placeholders = placeholders.iter().collect::<Vec<_>>();
+    placeholders.sort_by_key(|(offset, _)| *offset);
+
+    let mut injected = Vec::with_capacity(program.len());
+    let mut position = 0;
+    for (offset, value) in placeholders {
+        let offset = usize::try_from(*offset).unwrap_or(usize::MAX);
+        if offset < position || offset >= program.len() {
+            return Err(AlgoKitUtilsError::InputError {
+                err_msg: format!("Placeholder offset {} is out of range or overlaps", offset),
+            });
+        }
+        injected.extend(&program[position..offset]);
+        position = offset;
+        match value {
+            TemplateValue::Uint(value) => {
+                read_uvarint(program, &mut position)?;
+                write_uvarint(&mut injected, *value);
+            }
+            TemplateValue::Bytes(bytes) => {
+                read_bytes(program, &mut position)?;
+                write_bytes(&mut injected, bytes);
+            }
+        }
+    }
+    injected.extend(&program[position..]);
+    Ok(injected)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::teal::assemble;
+    use algokit_transact::test_utils::AccountMother;
+    use num_bigint::BigUint;
+
+    const HASHED_TIMELOCK: &str = r#"#pragma version 10
+txn CloseRemainderTo
+addr TMPL_RECEIVER
+==
+arg 0
+sha256
+byte TMPL_HASH
+==
+&&
+txn FirstValid
+int TMPL_TIMEOUT
+>
+txn CloseRemainderTo
+addr TMPL_OWNER
+==
+&&
+||
+"#;
+
+    fn template() -> LogicSigTemplate {
+        let json = serde_json::json!({
+            "name": "HashedTimelock",
+            "description": "Pays the receiver given the preimage of a hash, or the owner after a timeout",
+            "program": BASE64.encode(HASHED_TIMELOCK),
+            "variables": [
+                {"variable": "TMPL_RECEIVER", "name": "receiver", "type": "address"},
+                {"variable": "TMPL_OWNER", "name": "owner", "type": "address"},
+                {"variable": "TMPL_HASH", "name": "hash", "type": "byte[32]"},
+                {"variable": "TMPL_TIMEOUT", "name": "timeout", "type": "uint64"},
+            ],
+            "args": [{"name": "preimage", "type": "byte[32]"}],
+        });
+        LogicSigTemplate::from_json(&json.to_string()).unwrap()
+    }
+
+    #[test]
+    fn test_compiles_template_with_typed_values() {
+        let template = template();
+        let receiver = AccountMother::account().address();
+        let owner = AccountMother::neil().address();
+        let hash = [7u8; 32];
+        let values = HashMap::from([
+            (
+                "receiver".to_string(),
+                ABIValue::Address(receiver.to_string()),
+            ),
+            ("owner".to_string(), ABIValue::Address(owner.to_string())),
+            (
+                "hash".to_string(),
+                ABIValue::Array(hash.iter().map(|byte| ABIValue::Byte(*byte)).collect()),
+            ),
+            (
+                "timeout".to_string(),
+                ABIValue::Uint(BigUint::from(5000u64)),
+            ),
+        ]);
+        let assembly = template.compile(&values).unwrap();
+
+        let expected = HASHED_TIMELOCK
+            .replace("TMPL_RECEIVER", &receiver.to_string())
+            .replace("TMPL_OWNER", &owner.to_string())
+            .replace("TMPL_HASH", &format!("0x{}", "07".repeat(32)))
+            .replace("TMPL_TIMEOUT", "5000");
+        assert_eq!(assembly.program, assemble(&expected).unwrap().program);
+
+        // #pragma version 1, int 1
+        assert_eq!(
+            logic_sig_address(&[0x01, 0x20, 0x01, 0x01, 0x22]).to_string(),
+            "6Z3C3LDVWGMX23BMSYMANACQOSINPFIRF77H7N3AWJZYV6OH6GWTJKVMXY"
+        );
+
+        let mut missing = values.clone();
+        missing.remove("timeout");
+        assert!(template.compile(&missing).is_err());
+        let mut wrong_type = values;
+        wrong_type.insert("timeout".to_string(), ABIValue::Bool(true));
+        assert!(template.compile(&wrong_type).is_err());
+    }
+
+    #[test]
+    fn test_validates_arguments() {
+        let template = template();
+        let preimage = ABIValue::Array(vec![ABIValue::Byte(1); 32]);
+        let args = template.encode_args(&[preimage]).unwrap();
+        assert_eq!(args, vec![vec![1u8; 32]]);
+        template.validate_args(&args).unwrap();
+
+        assert!(template.validate_args(&[vec![1u8; 31]]).is_err());
+        assert!(template.validate_args(&[]).is_err());
+    }
+
+    #[test]
+    fn test_rejects_invalid_templates() {
+        let mut json = serde_json::to_value(template()).unwrap();
+        json["variables"][0]["variable"] = "RECEIVER".into();
+        assert!(LogicSigTemplate::from_json(&json.to_string()).is_err());
+
+        let mut json = serde_json::to_value(template()).unwrap();
+        json["args"][0]["type"] = "notatype".into();
+        assert!(LogicSigTemplate::from_json(&json.to_string()).is_err());
+    }
+
+    #[test]
+    fn test_injects_values_into_bytecode() {
+        let placeholder = assemble("#pragma version 10\npushint 0\npushbytes 0x\n&&\npushint 1")
+            .unwrap()
+            .program;
+        let injected = inject_template_values(
+            &placeholder,
+            &[
+                (4, TemplateValue::Bytes(b"abc".to_vec())),
+                (2, TemplateValue::Uint(300)),
+            ],
+        )
+        .unwrap();
+        let expected =
+            assemble("#pragma version 10\npushint 300\npushbytes 0x616263\n&&\npushint 1")
+                .unwrap()
+                .program;
+        assert_eq!(injected, expected);
+
+        assert!(inject_template_values(&placeholder, &[(99, TemplateValue::Uint(1))]).is_err());
+    }
+}
@@ -0,0 +1,225 @@
+//! Merkle array and vector commitment helpers shared by the proof verifiers.
+//!
+//! Inner nodes of an Algorand Merkle array are hashed as `"MA" || left || right`, where a
+//! missing child is replaced by a zero digest. In a vector commitment the leaf for index `i` is
+//! placed at the bit-reversed position of `i`, so that proofs can be produced for trees whose
+//! size is not known in advance.
+
+use crate::error::AlgoKitUtilsError;
+use algod_client::apis::parameter_enums::Hashtype;
+use sha2::{Digest, Sha256, Sha512_256};
+
+const MERKLE_ARRAY_NODE_PREFIX: &[u8] = b"MA";
+
+/// The maximum depth of a Merkle array supported by the protocol.
+pub(crate) const MAX_TREE_DEPTH: u64 = 64;
+
+/// Hashes the concatenation of `parts` with the hash function of `hashtype`.
+pub(crate) fn hash_parts(hashtype: Hashtype, parts: &[&[u8]]) -> Vec<u8> {
+    match hashtype {
+        Hashtype::Sha512256 => {
+            let mut hasher = Sha512_256::new();
+            parts.iter().for_each(|part| hasher.update(part));
+            hasher.finalize().to_vec()
+        }
+        Hashtype::Sha256 => {
+            let mut hasher = Sha256::new();
+            parts.iter().for_each(|part| hasher.update(part));
+            hasher.finalize().to_vec()
+        }
+    }
+}
+
+pub(crate) fn invalid_proof(err_msg: String) -> AlgoKitUtilsError {
+    AlgoKitUtilsError::InvalidProof { err_msg }
+}
+
+/// Maps the index of a leaf to its position in a vector commitment tree of the given depth.
+pub(crate) fn vector_commitment_index(
+    index: u64,
+    tree_depth: u64,
+) -> Result<u64, AlgoKitUtilsError> {
+    if tree_depth >= MAX_TREE_DEPTH || index >= 1 << tree_depth {
+        return Err(invalid_proof(format!(
+            "index {} is out of range for a tree of depth {}",
+            index, tree_depth
+        )));
+    }
+    if tree_depth == 0 {
+        return Ok(0);
+    }
+    Ok(index.reverse_bits() >> (64 - tree_depth))
+}
+
+/// Computes the root of a vector commitment from the hashes of some of its leaves and the
+/// sibling hashes of a (possibly multi-leaf) proof.
+///
+/// `leaves` maps leaf indices to leaf hashes, which are placed at their bit-reversed positions
+/// before calling [`merkle_array_root`].
+pub(crate) fn vector_commitment_root<F>(
+    leaves: impl IntoIterator<Item = (u64, Vec<u8>)>,
+    path: &[Vec<u8>],
+    tree_depth: u64,
+    digest_size: usize,
+    hash: F,
+) -> Result<Vec<u8>, AlgoKitUtilsError>
+where
+    F: Fn(&[&[u8]]) -> Result<Vec<u8>, AlgoKitUtilsError>,
+{
+    let leaves = leaves
+        .into_iter()
+        .map(|(index, leaf)| Ok((vector_commitment_index(index, tree_depth)?, leaf)))
+        .collect::<Result<Vec<_>, AlgoKitUtilsError>>()?;
+    merkle_array_root(leaves, path, tree_depth, digest_size, hash)
+}
+
+/// Computes the root of a Merkle array from the hashes of some of its leaves and the sibling
+/// hashes of a (possibly multi-leaf) proof.
+///
+/// `leaves` maps leaf positions to leaf hashes. Siblings that cannot be derived from other leaves
+/// are consumed from `path` level by level, from the leaves up, in increasing position order.
+/// `hash` hashes the concatenation of its arguments with the function of the tree.
+pub(crate) fn merkle_array_root<F>(
+    leaves: impl IntoIterator<Item = (u64, Vec<u8>)>,
+    path: &[Vec<u8>],
+    tree_depth: u64,
+    digest_size: usize,
+    hash: F,
+) -> Result<Vec<u8>, AlgoKitUtilsError>
+where
+    F: Fn(&[&[u8]]) -> Result<Vec<u8>, AlgoKitUtilsError>,
+{
+    if tree_depth >= MAX_TREE_DEPTH {
+        return Err(invalid_proof(format!(
+            "tree depth {} is too large",
+            tree_depth
+        )));
+    }
+    let mut layer: Vec<(u64, Vec<u8>)> = leaves.into_iter().collect();
+    if let Some((position, _)) = layer
+        .iter()
+        .find(|(position, _)| *position >= 1 << tree_depth)
+    {
+        return Err(invalid_proof(format!(
+            "position {} is out of range for a tree of depth {}",
+            position, tree_depth
+        )));
+    }
+    if layer.is_empty() {
+        return Err(invalid_proof("no leaves to verify".to_string()));
+    }
+    layer.sort_by_key(|(position, _)| *position);
+
+    let mut hints = path.iter();
+    for _ in 0..tree_depth {
+        let mut next = Vec::with_capacity(layer.len().div_ceil(2));
+        let mut items = layer.into_iter().peekable();
+        while let Some((position, node)) = items.next() {
+            let sibling = match items.next_if(|(next, _)| *next == position ^ 1) {
+                Some((_, sibling)) => sibling,
+                None => hints
+                    .next()
+                    .ok_or_else(|| invalid_proof("proof is missing sibling hashes".to_string()))?
+                    .clone(),
+            };
+            let (left, right) = if position & 1 == 0 {
+                (node, sibling)
+            } else {
+                (sibling, node)
+            };
+            next.push((
+                position / 2,
+                hash(&[
+                    MERKLE_ARRAY_NODE_PREFIX,
+                    &fixed_length(left, digest_size),
+                    &fixed_length(right, digest_size),
+                ])?,
+            ));
+        }
+        layer = next;
+    }
+
+    if hints.next().is_some() {
+        return Err(invalid_proof("proof has unused sibling hashes".to_string()));
+    }
+    if layer.len() != 1 {
+        return Err(invalid_proof(
+            "leaves did not reduce to a single root".to_string(),
+        ));
+    }
+    Ok(layer.remove(0).1)
+}
+
+/// Pads a missing or short digest with zeros so that inner nodes have a fixed-length encoding.
+fn fixed_length(mut digest: Vec<u8>, digest_size: usize) -> Vec<u8> {
+    if digest.len() < digest_size {
+        digest.resize(digest_size, 0);
+    }
+    digest
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn sha256(parts: &[&[u8]]) -> Result<Vec<u8>, AlgoKitUtilsError> {
+        Ok(hash_parts(Hashtype::Sha256, parts))
+    }
+
+    #[test]
+    fn test_vector_commitment_index() {
+        assert_eq!(vector_commitment_index(0, 0).unwrap(), 0);
+        assert_eq!(vector_commitment_index(1, 2).unwrap(), 2);
+        assert_eq!(vector_commitment_index(3, 3).unwrap(), 6);
+        assert!(vector_commitment_index(4, 2).is_err());
+    }
+
+    #[test]
+    fn test_multi_leaf_root_matches_single_leaf_roots() {
+        let leaves: Vec<Vec<u8>> = (0..4u8).map(|i| sha256(&[&[i]]).unwrap()).collect();
+        let positioned: Vec<&Vec<u8>> = (0..4)
+            .map(|position| {
+                let index = (0..4)
+                    .find(|i| vector_commitment_index(*i, 2).unwrap() == position)
+                    .unwrap();
+                &leaves[index as usize]
+            })
+            .collect();
+        let left = sha256(&[b"MA", positioned[0], positioned[1]]).unwrap();
+        let right = sha256(&[b"MA", positioned[2], positioned[3]]).unwrap();
+        let root = sha256(&[b"MA", &left, &right]).unwrap();
+
+        // Index 0 and 2 sit at positions 0 and 1, so only the right subtree is needed.
+        let multi = vector_commitment_root(
+            [(0, leaves[0].clone()), (2, leaves[2].clone())],
+            std::slice::from_ref(&right),
+            2,
+            32,
+            sha256,
+        )
+        .unwrap();
+        assert_eq!(multi, root);
+
+        // Index 1 sits at position 2 with index 3 as its sibling.
+        let single = vector_commitment_root(
+            [(1, leaves[1].clone())],
+            &[leaves[3].clone(), left],
+            2,
+            32,
+            sha256,
+        )
+        .unwrap();
+        assert_eq!(single, root);
+    }
+
+    #[test]
+    fn test_rejects_wrong_path_length() {
+        let leaf = sha256(&[b"leaf"]).unwrap();
+
+        assert!(vector_commitment_root([(0, leaf.clone())], &[], 1, 32, sha256).is_err());
+        assert!(
+            vector_commitment_root([(0, leaf.clone())], &[leaf.clone(), leaf], 1, 32, sha256)
+                .is_err()
+        );
+    }
+}
@@ -0,0 +1,615 @@
+//! Minimum balance requirement calculation.
+//!
+//! Every Algorand account must hold a minimum balance that grows with the assets it holds,
+//! the apps it has created or opted into, the state schema those apps reserve and the boxes
+//! its app account stores. This module provides the [`MinBalanceCalculator`], which computes
+//! the current requirement of an algod [`Account`] and the change in requirement that a
+//! transaction group would cause, so that callers can warn before submitting a group that
+//! would be rejected by the network.
+
+use crate::error::AlgoKitUtilsError;
+use algod_client::models::{
+    Account, Application, ApplicationParams, ApplicationStateSchema, Asset,
+};
+use algokit_transact::{Address, ConsensusParams, OnApplicationComplete, Transaction};
+use std::collections::{HashMap, HashSet};
+use std::str::FromStr;
+
+/// The change in minimum balance requirement for a single account.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct MinBalanceChange {
+    /// The account whose minimum balance changes.
+    pub address: Address,
+    /// The change in microALGO; negative when the requirement decreases.
+    pub delta: i64,
+}
+
+/// An account whose balance would fall below its minimum balance requirement.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct MinBalanceShortfall {
+    /// The account that would be under-funded.
+    pub address: Address,
+    /// The current balance of the account in microALGO.
+    pub balance: u64,
+    /// The minimum balance the account would require in microALGO.
+    pub required: u64,
+}
+
+#[derive(Debug, Clone)]
+struct BoxCreation {
+    app_id: u64,
+    name_len: u64,
+    size: u64,
+}
+
+/// Calculates minimum balance requirements using a set of consensus parameters.
+///
+/// The effect of opting into, closing out of or deleting an existing app depends on that
+/// app's schema, so the parameters of those apps must be registered with
+/// [`MinBalanceCalculator::with_app`]. Destroying an asset refunds its creator rather than the
+/// manager sending the transaction, so destroyed assets must be registered with
+/// [`MinBalanceCalculator::with_asset`]. Boxes are created by app logic rather than by the
+/// transaction itself, so expected box creations are registered with
+/// [`MinBalanceCalculator::with_box_creation`].
+#[derive(Debug, Clone, Default)]
+pub struct MinBalanceCalculator {
+    params: ConsensusParams,
+    apps: HashMap<u64, ApplicationParams>,
+    assets: HashMap<u64, String>,
+    boxes: Vec<BoxCreation>,
+}
+
+impl MinBalanceCalculator {
+    /// Creates a calculator for the supplied consensus parameters.
+    pub fn new(params: ConsensusParams) -> Self {
+        Self {
+            params,
+            apps: HashMap::new(),
+            assets: HashMap::new(),
+            boxes: Vec::new(),
+        }
+    }
+
+    /// Registers the parameters of an existing app referenced by the transaction group.
+    pub fn with_app(mut self, app: &Application) -> Self {
+        self.apps.insert(app.id, app.params.clone());
+        self
+    }
+
+    /// Registers an existing asset referenced by the transaction group.
+    pub fn with_asset(mut self, asset: &Asset) -> Self {
+        self.assets
+            .insert(asset.index, asset.params.creator.clone());
+        self
+    }
+
+    /// Registers a box that the transaction group is expected to create.
+    ///
+    /// The box cost is charged to the app account of `app_id`.
+    pub fn with_box_creation(mut self, app_id: u64, name: &[u8], size: u64) -> Self {
+        self.boxes.push(BoxCreation {
+            app_id,
+            name_len: name.len() as u64,
+            size,
+        });
+        self
+    }
+
+    /// Returns the minimum balance requirement of an account.
+    pub fn account_min_balance(&self, account: &Account) -> u64 {
+        let p = &self.params;
+        let schema_cost = account
+            .apps_total_schema
+            .as_ref()
+            .map_or(0, |schema| self.schema_cost(schema));
+
+        p.min_balance
+            + p.min_balance * account.total_assets_opted_in
+            + p.app_flat_params_min_balance * account.total_created_apps
+            + p.app_flat_params_min_balance * account.apps_total_extra_pages.unwrap_or(0)
+            + p.app_flat_opt_in_min_balance * account.total_apps_opted_in
+            + schema_cost
+            + p.box_flat_min_balance * account.total_boxes.unwrap_or(0)
+            + p.box_byte_min_balance * account.total_box_bytes.unwrap_or(0)
+    }
+
+    /// Returns the minimum balance increase for a box with the given name length and size.
+    pub fn box_min_balance(&self, name_len: u64, size: u64) -> u64 {
+        self.params.box_flat_min_balance + self.params.box_byte_min_balance * (name_len + size)
+    }
+
+    /// Computes the change in minimum balance requirement that a transaction group would cause.
+    ///
+    /// `accounts` provides the current state of the accounts involved, which is used to
+    /// ignore opt-ins to assets and apps the account already holds and to release the full
+    /// requirement of accounts that are closed. Accounts that are not supplied are assumed
+    /// not to hold the assets and apps being opted into.
+    ///
+    /// # Returns
+    /// The non-zero changes per account, in the order the accounts are first affected, or an
+    /// error if the effect of a transaction depends on an app that has not been registered.
+    pub fn group_changes(
+        &self,
+        txs: &[Transaction],
+        accounts: &[Account],
+    ) -> Result<Vec<MinBalanceChange>, AlgoKitUtilsError> {
+        let accounts = index_accounts(accounts)?;
+        let mut assets_held: HashSet<(Address, u64)> = HashSet::new();
+        let mut apps_opted_in: HashSet<(Address, u64)> = HashSet::new();
+        for (address, account) in &accounts {
+            for holding in account.assets.iter().flatten() {
+                assets_held.insert((address.clone(), holding.asset_id));
+            }
+            for local_state in account.apps_local_state.iter().flatten() {
+                apps_opted_in.insert((address.clone(), local_state.id));
+            }
+        }
+
+        let mut changes = Changes::default();
+        let p = &self.params;
+
+        for tx in txs {
+            let sender = tx.sender().clone();
+            match tx {
+                Transaction::Payment(payment) => {
+                    if payment.close_remainder_to.is_some() {
+                        if let Some(account) = accounts.get(&sender) {
+                            changes.add(&sender, -(self.account_min_balance(account) as i64));
+                        }
+                    }
+                }
+                Transaction::AssetTransfer(transfer) => {
+                    let key = (sender.clone(), transfer.asset_id);
+                    if transfer.close_remainder_to.is_some() {
+                        if assets_held.remove(&key) {
+                            changes.add(&sender, -(p.min_balance as i64));
+                        }
+                    } else if transfer.receiver == sender
+                        && transfer.asset_sender.is_none()
+                        && transfer.amount == 0
+                        && assets_held.insert(key)
+                    {
+                        changes.add(&sender, p.min_balance as i64);
+                    }
+                }
+                Transaction::AssetConfig(config) => {
+                    if config.asset_id == 0 {
+                        changes.add(&sender, p.min_balance as i64);
+                    } else if is_asset_destroy(config) {
+                        let creator = parse_address(self.asset_creator(config.asset_id)?)?;
+                        changes.add(&creator, -(p.min_balance as i64));
+                    }
+                }
+                Transaction::AppCall(call) => {
+                    if call.app_id == 0 {
+                        let global = call.global_state_schema.as_ref().map_or(0, |s| {
+                            self.schema_cost(&ApplicationStateSchema::new(
+                                s.num_uints,
+                                s.num_byte_slices,
+                            ))
+                        });
+                        let pages = 1 + call.extra_program_pages.unwrap_or(0) as u64;
+                        changes.add(
+                            &sender,
+                            (p.app_flat_params_min_balance * pages + global) as i64,
+                        );
+                        if call.on_complete == OnApplicationComplete::OptIn {
+                            let local = call.local_state_schema.as_ref().map_or(0, |s| {
+                                self.schema_cost(&ApplicationStateSchema::new(
+                                    s.num_uints,
+                                    s.num_byte_slices,
+                                ))
+                            });
+                            changes.add(&sender, (p.app_flat_opt_in_min_balance + local) as i64);
+                        }
+                        continue;
+                    }
+
+                    let key = (sender.clone(), call.app_id);
+                    match call.on_complete {
+                        OnApplicationComplete::OptIn => {
+                            if apps_opted_in.insert(key) {
+                                let cost = self.opt_in_cost(call.app_id)?;
+                                changes.add(&sender, cost as i64);
+                            }
+                        }
+                        OnApplicationComplete::CloseOut | OnApplicationComplete::ClearState => {
+                            if apps_opted_in.remove(&key) {
+                                let cost = self.opt_in_cost(call.app_id)?;
+                                changes.add(&sender, -(cost as i64));
+                            }
+                        }
+                        OnApplicationComplete::DeleteApplication => {
+                            let app = self.app(call.app_id)?;
+                            let creator = parse_address(&app.creator)?;
+                            let global = app
+                                .global_state_schema
+                                .as_ref()
+                                .map_or(0, |s| self.schema_cost(s));
+                            let pages = 1 + app.extra_program_pages.unwrap_or(0) as u64;
+                            changes.add(
+                                &creator,
+                                -((p.app_flat_params_min_balance * pages + global) as i64),
+                            );
+                        }
+                        OnApplicationComplete::NoOp | OnApplicationComplete::UpdateApplication => {}
+                    }
+                }
+                Transaction::AssetFreeze(_)
+                | Transaction::KeyRegistration(_)
+                | Transaction::Heartbeat(_)
+                | Transaction::StateProof(_) => {}
+            }
+        }
+
+        for box_creation in &self.boxes {
+            changes.add(
+                &Address::from_app_id(&box_creation.app_id),
+                self.box_min_balance(box_creation.name_len, box_creation.size) as i64,
+            );
+        }
+
+        Ok(changes.into_vec())
+    }
+
+    /// Finds the accounts whose balance would not cover their minimum balance requirement
+    /// after the transaction group is applied.
+    ///
+    /// Only the change in requirement is considered; amounts moved and fees paid by the group
+    /// are not deducted from the balances.
+    pub fn shortfalls(
+        &self,
+        txs: &[Transaction],
+        accounts: &[Account],
+    ) -> Result<Vec<MinBalanceShortfall>, AlgoKitUtilsError> {
+        let changes = self.group_changes(txs, accounts)?;
+        let mut shortfalls = Vec::new();
+
+        for account in accounts {
+            let address = parse_address(&account.address)?;
+            let delta: i64 = changes
+                .iter()
+                .filter(|change| change.address == address)
+                .map(|change| change.delta)
+                .sum();
+            let required = (self.account_min_balance(account) as i64 + delta).max(0) as u64;
+            let closed = txs.iter().any(|tx| {
+                matches!(tx, Transaction::Payment(payment)
+                    if payment.header.sender == address && payment.close_remainder_to.is_some())
+            });
+
+            if !closed && account.amount < required {
+                shortfalls.push(MinBalanceShortfall {
+                    address,
+                    balance: account.amount,
+                    required,
+                });
+            }
+        }
+
+        Ok(shortfalls)
+    }
+
+    fn schema_cost(&self, schema: &ApplicationStateSchema) -> u64 {
+        let p = &self.params;
+        schema.num_uint as u64 * (p.schema_min_balance_per_entry + p.schema_uint_min_balance)
+            + schema.num_byte_slice as u64
+                * (p.schema_min_balance_per_entry + p.schema_bytes_min_balance)
+    }
+
+    fn opt_in_cost(&self, app_id: u64) -> Result<u64, AlgoKitUtilsError> {
+        let local = self
+            .app(app_id)?
+            .local_state_schema
+            .as_ref()
+            .map_or(0, |s| self.schema_cost(s));
+        Ok(self.params.app_flat_opt_in_min_balance + local)
+    }
+
+    fn app(&self, app_id: u64) -> Result<&ApplicationParams, AlgoKitUtilsError> {
+        self.apps
+            .get(&app_id)
+            .ok_or_else(|| AlgoKitUtilsError::InputError {
+                err_msg: format!(
+                    "App {} must be registered to calculate its minimum balance effect",
+                    app_id
+                ),
+            })
+    }
+
+    fn asset_creator(&self, asset_id: u64) -> Result<&str, AlgoKitUtilsError> {
+        self.assets
+            .get(&asset_id)
+            .map(String::as_str)
+            .ok_or_else(|| AlgoKitUtilsError::InputError {
+                err_msg: format!(
+                    "Asset {} must be registered to calculate its minimum balance effect",
+                    asset_id
+                ),
+            })
+    }
+}
+
+#[derive(Default)]
+struct Changes {
+    order: Vec<Address>,
+    deltas: HashMap<Address, i64>,
+}
+
+impl Changes {
+    fn add(&mut self, address: &Address, delta: i64) {
+        if !self.deltas.contains_key(address) {
+            self.order.push(address.clone());
+        }
+        *self.deltas.entry(address.clone()).or_default() += delta;
+    }
+
+    fn into_vec(self) -> Vec<MinBalanceChange> {
+        self.order
+            .into_iter()
+            .filter_map(|address| {
+                let delta = self.deltas[&address];
+                (delta != 0).then_some(MinBalanceChange { address, delta })
+            })
+            .collect()
+    }
+}
+
+fn is_asset_destroy(config: &algokit_transact::AssetConfigTransactionFields) -> bool {
+    config.total.is_none()
+        && config.decimals.is_none()
+        && config.default_frozen.is_none()
+        && config.asset_name.is_none()
+        && config.unit_name.is_none()
+        && config.url.is_none()
+        && config.metadata_hash.is_none()
+        && config.manager.is_none()
+        && config.reserve.is_none()
+        && config.freeze.is_none()
+        && config.clawback.is_none()
+}
+
+fn parse_address(address: &str) -> Result<Address, AlgoKitUtilsError> {
+    Address::from_str(address).map_err(|source| AlgoKitUtilsError::InvalidAddress {
+        address: address.to_string(),
+        source,
+    })
+}
+
+fn index_accounts(accounts: &[Account]) -> Result<HashMap<Address, &Account>, AlgoKitUtilsError> {
+    accounts
+        .iter()
+        .map(|account| Ok((parse_address(&account.address)?, account)))
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use algod_client::models::{ApplicationLocalState, AssetHolding, AssetParams};
+    use algokit_transact::test_utils::{
+        AccountMother, AppCallTransactionMother, AssetConfigTransactionMother,
+        TransactionHeaderMother, TransactionMother,
+    };
+    use algokit_transact::{AppCallTransactionBuilder, StateSchema};
+
+    fn account(address: &Address, amount: u64) -> Account {
+        Account {
+            address: address.to_string(),
+            amount,
+            ..Default::default()
+        }
+    }
+
+    fn app(id: u64, creator: &Address) -> Application {
+        Application {
+            id,
+            params: ApplicationParams {
+                creator: creator.to_string(),
+                extra_program_pages: Some(1),
+                local_state_schema: Some(ApplicationStateSchema::new(1, 1)),
+                global_state_schema: Some(ApplicationStateSchema::new(2, 0)),
+                ..Default::default()
+            },
+        }
+    }
+
+    fn app_call(app_id: u64, on_complete: OnApplicationComplete) -> Transaction {
+        AppCallTransactionBuilder::default()
+            .header(TransactionHeaderMother::simple_testnet().build().unwrap())
+            .app_id(app_id)
+            .on_complete(on_complete)
+            .build()
+            .unwrap()
+    }
+
+    #[test]
+    fn test_account_min_balance() {
+        let account = Account {
+            total_assets_opted_in: 2,
+            total_created_apps: 1,
+            apps_total_extra_pages: Some(1),
+            total_apps_opted_in: 1,
+            apps_total_schema: Some(ApplicationStateSchema::new(2, 1)),
+            total_boxes: Some(3),
+            total_box_bytes: Some(100),
+            ..account(&AccountMother::account().address(), 0)
+        };
+
+        let calculator = MinBalanceCalculator::default();
+
+        assert_eq!(calculator.account_min_balance(&account), 754_500);
+        assert_eq!(calculator.account_min_balance(&Account::default()), 100_000);
+    }
+
+    #[test]
+    fn test_asset_opt_in_and_opt_out() {
+        let sender = AccountMother::neil().address();
+        let opt_in = TransactionMother::opt_in_asset_transfer().build().unwrap();
+        let mut opt_out = opt_in.clone();
+        if let Transaction::AssetTransfer(ref mut fields) = opt_out {
+            fields.close_remainder_to = Some(AccountMother::account().address());
+        }
+        let calculator = MinBalanceCalculator::default();
+
+        let changes = calculator.group_changes(&[opt_in.clone()], &[]).unwrap();
+        assert_eq!(
+            changes,
+            vec![MinBalanceChange {
+                address: sender.clone(),
+                delta: 100_000
+            }]
+        );
+
+        let holder = Account {
+            assets: Some(vec![AssetHolding::new(0, 107686045, false)]),
+            ..account(&sender, 0)
+        };
+        assert!(
+            calculator
+                .group_changes(&[opt_in.clone()], &[holder.clone()])
+                .unwrap()
+                .is_empty()
+        );
+        assert_eq!(
+            calculator.group_changes(&[opt_out], &[holder]).unwrap(),
+            vec![MinBalanceChange {
+                address: sender,
+                delta: -100_000
+            }]
+        );
+    }
+
+    #[test]
+    fn test_asset_destroy_refunds_creator() {
+        let creator = AccountMother::account().address();
+        let destroy = AssetConfigTransactionMother::asset_destroy()
+            .build()
+            .unwrap();
+        let manager = destroy.sender().clone();
+        assert_ne!(manager, creator);
+
+        let asset = Asset {
+            index: 917559,
+            params: AssetParams::new(creator.to_string(), 0, 1),
+        };
+        let calculator = MinBalanceCalculator::default().with_asset(&asset);
+
+        assert_eq!(
+            calculator.group_changes(&[destroy.clone()], &[]).unwrap(),
+            vec![MinBalanceChange {
+                address: creator,
+                delta: -100_000
+            }]
+        );
+
+        let error = MinBalanceCalculator::default()
+            .group_changes(&[destroy], &[])
+            .unwrap_err();
+        assert_eq!(
+            error.to_string(),
+            "Asset 917559 must be registered to calculate its minimum balance effect"
+        );
+    }
+
+    #[test]
+    fn test_app_create_with_opt_in() {
+        let mut create = AppCallTransactionMother::app_create().build().unwrap();
+        if let Transaction::AppCall(ref mut fields) = create {
+            fields.on_complete = OnApplicationComplete::OptIn;
+            fields.extra_program_pages = Some(2);
+            fields.global_state_schema = Some(StateSchema {
+                num_uints: 1,
+                num_byte_slices: 1,
+            });
+            fields.local_state_schema = Some(StateSchema {
+                num_uints: 0,
+                num_byte_slices: 2,
+            });
+        }
+        let sender = create.sender().clone();
+
+        let changes = MinBalanceCalculator::default()
+            .group_changes(&[create], &[])
+            .unwrap();
+
+        // 3 pages + global schema (28_500 + 50_000) + opt-in + local schema (2 * 50_000)
+        assert_eq!(
+            changes,
+            vec![MinBalanceChange {
+                address: sender,
+                delta: 300_000 + 78_500 + 100_000 + 100_000
+            }]
+        );
+    }
+
+    #[test]
+    fn test_app_opt_in_close_out_and_delete() {
+        let sender = AccountMother::account().address();
+        let calculator = MinBalanceCalculator::default().with_app(&app(42, &sender));
+
+        let opt_in = app_call(42, OnApplicationComplete::OptIn);
+        assert_eq!(
+            calculator.group_changes(&[opt_in], &[]).unwrap(),
+            vec![MinBalanceChange {
+                address: sender.clone(),
+                delta: 100_000 + 28_500 + 50_000
+            }]
+        );
+
+        let opted_in = Account {
+            apps_local_state: Some(vec![ApplicationLocalState::new(
+                42,
+                ApplicationStateSchema::new(1, 1),
+            )]),
+            ..account(&sender, 0)
+        };
+        let close_out = app_call(42, OnApplicationComplete::CloseOut);
+        let delete = app_call(42, OnApplicationComplete::DeleteApplication);
+        assert_eq!(
+            calculator
+                .group_changes(&[close_out, delete], &[opted_in])
+                .unwrap(),
+            vec![MinBalanceChange {
+                address: sender,
+                delta: -(100_000 + 28_500 + 50_000) - (200_000 + 57_000)
+            }]
+        );
+
+        let unknown = app_call(7, OnApplicationComplete::OptIn);
+        let error = calculator.group_changes(&[unknown], &[]).unwrap_err();
+        assert_eq!(
+            error.to_string(),
+            "App 7 must be registered to calculate its minimum balance effect"
+        );
+    }
+
+    #[test]
+    fn test_box_creation_and_shortfalls() {
+        let sender = AccountMother::account().address();
+        let app_address = Address::from_app_id(&42);
+        let calculator = MinBalanceCalculator::default()
+            .with_app(&app(42, &sender))
+            .with_box_creation(42, b"box", 1021);
+        let opt_in = app_call(42, OnApplicationComplete::OptIn);
+
+        let accounts = [account(&sender, 250_000), account(&app_address, 100_000)];
+        let shortfalls = calculator.shortfalls(&[opt_in], &accounts).unwrap();
+
+        assert_eq!(
+            shortfalls,
+            vec![
+                MinBalanceShortfall {
+                    address: sender,
+                    balance: 250_000,
+                    required: 278_500,
+                },
+                MinBalanceShortfall {
+                    address: app_address,
+                    balance: 100_000,
+                    required: 100_000 + 2_500 + 1024 * 400,
+                },
+            ]
+        );
+    }
+}
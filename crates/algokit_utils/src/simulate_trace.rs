@@ -0,0 +1,547 @@
+//! Human-readable execution traces from simulate.
+//!
+//! When simulate is asked for an execution trace, each transaction result carries a
+//! [`SimulationTransactionExecTrace`] with one unit per opcode executed by its logic signature,
+//! approval or clear state program, and a nested trace for each inner transaction. A
+//! [`TraceRenderer`] walks that tree in execution order and, given a [`ProgramSourceMap`] for the
+//! programs involved, labels each step with its TEAL line, original source and named scratch
+//! slots.
+
+use crate::dryrun::{format_table, hex};
+use crate::error::AlgoKitUtilsError;
+use crate::teal::Instructions;
+use algod_client::models::{
+    ApplicationStateOperation, AvmValue, SimulationOpcodeTraceUnit, SimulationTransactionExecTrace,
+};
+use algokit_abi::{Arc56Contract, PcOffsetMethod};
+use base64::{Engine, engine::general_purpose::STANDARD as BASE64};
+use serde::Serialize;
+use sha2::{Digest, Sha512_256};
+use std::collections::{BTreeMap, HashMap, HashSet};
+
+const AVM_BYTES_TYPE: u64 = 1;
+
+/// The program that a trace step was executed by.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum TraceProgram {
+    LogicSig,
+    Approval,
+    ClearState,
+}
+
+impl TraceProgram {
+    fn name(self) -> &'static str {
+        match self {
+            TraceProgram::LogicSig => "logic sig",
+            TraceProgram::Approval => "approval",
+            TraceProgram::ClearState => "clear state",
+        }
+    }
+}
+
+/// Where an instruction of a program came from.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize)]
+pub struct SourceLine {
+    /// The 1-based line of the instruction in the TEAL program.
+    pub teal_line: Option<u64>,
+    /// The text of the TEAL line.
+    pub teal: Option<String>,
+    /// The location in the higher level language the TEAL was compiled from.
+    pub source: Option<String>,
+    /// The error message the program associates with failing at this instruction.
+    pub error_message: Option<String>,
+}
+
+/// Maps the program counters of a compiled program back to its source.
+#[derive(Debug, Clone, Default)]
+pub struct ProgramSourceMap {
+    lines: BTreeMap<u64, SourceLine>,
+    scratch_names: HashMap<u64, String>,
+}
+
+impl ProgramSourceMap {
+    /// Builds the source map of an ARC-56 contract's approval or clear state program from its
+    /// `sourceInfo`, naming scratch slots from its `scratchVariables`.
+    ///
+    /// When the contract's program counters are relative to the constant blocks (the `cblocks`
+    /// offset method), the compiled program is needed to find where they end.
+    pub fn from_arc56(
+        contract: &Arc56Contract,
+        program: TraceProgram,
+        compiled: Option<&[u8]>,
+    ) -> Result<Self, AlgoKitUtilsError> {
+        let source_info =
+            contract
+                .source_info
+                .as_ref()
+                .ok_or_else(|| AlgoKitUtilsError::InputError {
+                    err_msg: format!("Contract {} has no source info", contract.name),
+                })?;
+        let (program_info, teal) = match program {
+            TraceProgram::Approval => (&source_info.approval, contract.decoded_teal()?.0),
+            TraceProgram::ClearState => (&source_info.clear, contract.decoded_teal()?.1),
+            TraceProgram::LogicSig => {
+                return Err(AlgoKitUtilsError::InputError {
+                    err_msg: "ARC-56 contracts only describe approval and clear state programs"
+                        .to_string(),
+                });
+            }
+        };
+        let offset = match program_info.pc_offset_method {
+            PcOffsetMethod::None => 0,
+            PcOffsetMethod::Cblocks => {
+                let compiled = compiled.ok_or_else(|| AlgoKitUtilsError::InputError {
+                    err_msg: format!(
+                        "The {} program is needed to map source info relative to its constant blocks",
+                        program.name()
+                    ),
+                })?;
+                constant_block_offset(compiled)?
+            }
+        };
+        let teal_lines = teal.lines().collect::<Vec<_>>();
+
+        let mut lines = BTreeMap::new();
+        for info in &program_info.source_info {
+            let line = SourceLine {
+                teal_line: info.teal.map(u64::from),
+                teal: info
+                    .teal
+                    .and_then(|line| teal_lines.get((line as usize).checked_sub(1)?))
+                    .map(|line| line.trim().to_string()),
+                source: info.source.clone(),
+                error_message: info.error_message.clone(),
+            };
+            for pc in &info.pc {
+                lines.insert(u64::from(*pc) + offset, line.clone());
+            }
+        }
+        let scratch_names = contract
+            .scratch_variables
+            .iter()
+            .flatten()
+            .map(|(name, variable)| (u64::from(variable.slot), name.clone()))
+            .collect();
+
+        Ok(Self {
+            lines,
+            scratch_names,
+        })
+    }
+
+    /// Builds a source map from lines of TEAL, each with the program counter of its instruction
+    /// if it has one, such as those of a [`Disassembly`](crate::teal::Disassembly).
+    pub fn from_disassembly(lines: &[(Option<u64>, String)]) -> Self {
+        Self {
+            lines: (1u64..)
+                .zip(lines)
+                .filter_map(|(number, (pc, text))| {
+                    let line = SourceLine {
+                        teal_line: Some(number),
+                        teal: Some(text.trim().to_string()),
+                        ..Default::default()
+                    };
+                    Some(((*pc)?, line))
+                })
+                .collect(),
+            scratch_names: HashMap::new(),
+        }
+    }
+
+    /// Names scratch slots, replacing any names already given to the same slots.
+    pub fn with_scratch_names(mut self, names: HashMap<u64, String>) -> Self {
+        self.scratch_names.extend(names);
+        self
+    }
+
+    /// The source of the instruction at a program counter.
+    pub fn line(&self, pc: u64) -> Option<&SourceLine> {
+        self.lines.get(&pc)
+    }
+
+    /// The source of every mapped program counter, in program order.
+    pub fn lines(&self) -> impl Iterator<Item = (u64, &SourceLine)> {
+        self.lines.iter().map(|(pc, line)| (*pc, line))
+    }
+
+    fn scratch_slot(&self, slot: u64) -> String {
+        self.scratch_names
+            .get(&slot)
+            .cloned()
+            .unwrap_or_else(|| slot.to_string())
+    }
+}
+
+/// The hash that simulate identifies a traced program by.
+pub fn program_hash(program: &[u8]) -> [u8; 32] {
+    Sha512_256::digest(program).into()
+}
+
+/// The program counter of the last byte of the constant blocks at the start of a program, or
+/// zero if it does not start with any.
+pub(crate) fn constant_block_offset(program: &[u8]) -> Result<u64, AlgoKitUtilsError> {
+    let mut offset = 0;
+    for instruction in Instructions::new(program)? {
+        let instruction = instruction?;
+        if !matches!(instruction.op.name, "intcblock" | "bytecblock") {
+            break;
+        }
+        offset = instruction.pc + instruction.size - 1;
+    }
+    Ok(offset)
+}
+
+/// One opcode executed during simulation.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct TraceStep {
+    /// The indexes of the inner transactions leading from the traced transaction to the one that
+    /// executed this step, empty for the traced transaction itself.
+    pub path: Vec<u64>,
+    pub program: TraceProgram,
+    pub pc: u64,
+    /// The source of the instruction, if a source map was given for the program.
+    pub source: Option<SourceLine>,
+    pub stack_pop_count: u64,
+    pub stack_additions: Vec<String>,
+    /// The scratch slots written, as `slot = value`, using the slot's name if it has one.
+    pub scratch_changes: Vec<String>,
+    /// The app state written or deleted, such as `global["counter"] = 1`.
+    pub state_changes: Vec<String>,
+    /// The indexes of the inner transactions submitted by this step.
+    pub spawned_inners: Vec<u64>,
+}
+
+/// Turns simulate execution traces into step-by-step logs.
+#[derive(Debug, Clone, Default)]
+pub struct TraceRenderer {
+    programs: HashMap<Vec<u8>, ProgramSourceMap>,
+}
+
+impl TraceRenderer {
+    /// Creates a renderer with no source maps, which labels steps by program counter only.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Uses a source map for the program with the given hash, as computed by [`program_hash`].
+    pub fn with_program(mut self, hash: &[u8], source_map: ProgramSourceMap) -> Self {
+        self.programs.insert(hash.to_vec(), source_map);
+        self
+    }
+
+    /// The steps of a transaction's trace in the order they were executed, with the steps of
+    /// each inner transaction following the step that submitted it.
+    pub fn steps(&self, trace: &SimulationTransactionExecTrace) -> Vec<TraceStep> {
+        let mut steps = Vec::new();
+        self.collect_steps(trace, &[], &mut steps);
+        steps
+    }
+
+    fn collect_steps(
+        &self,
+        trace: &SimulationTransactionExecTrace,
+        path: &[u64],
+        steps: &mut Vec<TraceStep>,
+    ) {
+        let programs = [
+            (
+                TraceProgram::LogicSig,
+                &trace.logic_sig_trace,
+                &trace.logic_sig_hash,
+            ),
+            (
+                TraceProgram::Approval,
+                &trace.approval_program_trace,
+                &trace.approval_program_hash,
+            ),
+            (
+                TraceProgram::ClearState,
+                &trace.clear_state_program_trace,
+                &trace.clear_state_program_hash,
+            ),
+        ];
+        let inners = trace.inner_trace.as_deref().unwrap_or_default();
+        let mut visited = HashSet::new();
+
+        for (program, units, hash) in programs {
+            let source_map = hash.as_ref().and_then(|hash| self.programs.get(hash));
+            for unit in units.iter().flatten() {
+                steps.push(step(path, program, unit, source_map));
+                for index in unit.spawned_inners.iter().flatten() {
+                    if let Some(inner) = inners.get(*index as usize) {
+                        visited.insert(*index);
+                        self.collect_steps(inner, &[path, &[*index]].concat(), steps);
+                    }
+                }
+            }
+        }
+        for (index, inner) in (0u64..).zip(inners) {
+            if !visited.contains(&index) {
+                self.collect_steps(inner, &[path, &[index]].concat(), steps);
+            }
+        }
+    }
+
+    /// Renders a transaction's trace as a table with one row per step.
+    ///
+    /// Each row shows the transaction that executed the step as a path of inner transaction
+    /// indexes from `root`, the program and its program counter, the TEAL line and source if
+    /// known, the stack values popped and pushed, and the scratch and state written.
+    pub fn render(&self, trace: &SimulationTransactionExecTrace) -> String {
+        let header = [
+            "txn", "program", "pc", "line", "teal", "source", "stack", "changes",
+        ]
+        .map(String::from);
+        let mut rows = vec![header];
+        for step in self.steps(trace) {
+            let source = step.source.clone().unwrap_or_default();
+            let mut stack = Vec::new();
+            if step.stack_pop_count > 0 {
+                stack.push(format!("-{}", step.stack_pop_count));
+            }
+            if !step.stack_additions.is_empty() {
+                stack.push(format!("+[{}]", step.stack_additions.join(", ")));
+            }
+            let mut changes = step.scratch_changes.clone();
+            changes.extend(step.state_changes.iter().cloned());
+            if !step.spawned_inners.is_empty() {
+                let inners = step.spawned_inners.iter().map(u64::to_string);
+                changes.push(format!("inners {}", inners.collect::<Vec<_>>().join(", ")));
+            }
+            rows.push([
+                std::iter::once("root".to_string())
+                    .chain(step.path.iter().map(u64::to_string))
+                    .collect::<Vec<_>>()
+                    .join("."),
+                step.program.name().to_string(),
+                step.pc.to_string(),
+                source
+                    .teal_line
+                    .map(|line| line.to_string())
+                    .unwrap_or_default(),
+                source.teal.unwrap_or_default(),
+                source.source.unwrap_or_default(),
+                stack.join(" "),
+                changes.join(", "),
+            ]);
+        }
+        format_table(&rows)
+    }
+}
+
+fn step(
+    path: &[u64],
+    program: TraceProgram,
+    unit: &SimulationOpcodeTraceUnit,
+    source_map: Option<&ProgramSourceMap>,
+) -> TraceStep {
+    TraceStep {
+        path: path.to_vec(),
+        program,
+        pc: unit.pc,
+        source: source_map.and_then(|map| map.line(unit.pc)).cloned(),
+        stack_pop_count: unit.stack_pop_count.unwrap_or_default(),
+        stack_additions: unit
+            .stack_additions
+            .iter()
+            .flatten()
+            .map(render_value)
+            .collect(),
+        scratch_changes: unit
+            .scratch_changes
+            .iter()
+            .flatten()
+            .map(|change| {
+                let slot = match source_map {
+                    Some(map) => map.scratch_slot(change.slot),
+                    None => change.slot.to_string(),
+                };
+                format!("scratch[{}] = {}", slot, render_value(&change.new_value))
+            })
+            .collect(),
+        state_changes: unit
+            .state_changes
+            .iter()
+            .flatten()
+            .map(render_state_change)
+            .collect(),
+        spawned_inners: unit.spawned_inners.clone().unwrap_or_default(),
+    }
+}
+
+/// The bytes of an AVM value, which simulate returns base64 encoded.
+fn value_bytes(bytes: &str) -> Vec<u8> {
+    BASE64
+        .decode(bytes)
+        .unwrap_or_else(|_| bytes.as_bytes().to_vec())
+}
+
+fn render_value(value: &AvmValue) -> String {
+    if value.r#type == AVM_BYTES_TYPE {
+        hex(&value_bytes(value.bytes.as_deref().unwrap_or_default()))
+    } else {
+        value.uint.unwrap_or_default().to_string()
+    }
+}
+
+/// Renders a state key as a quoted string if it is printable, otherwise as hex.
+fn render_key(key: &[u8]) -> String {
+    match std::str::from_utf8(key) {
+        Ok(key) if !key.chars().any(char::is_control) => format!("{:?}", key),
+        _ => hex(key),
+    }
+}
+
+fn render_state_change(change: &ApplicationStateOperation) -> String {
+    let target = match change.app_state_type.as_str() {
+        "g" => format!("global[{}]", render_key(&change.key)),
+        "l" => format!(
+            "local({})[{}]",
+            change.account.as_deref().unwrap_or_default(),
+            render_key(&change.key)
+        ),
+        "b" => format!("box[{}]", render_key(&change.key)),
+        other => format!("{}[{}]", other, render_key(&change.key)),
+    };
+    match (change.operation.as_str(), &change.new_value) {
+        ("d", _) | (_, None) => format!("delete {}", target),
+        (_, Some(value)) => format!("{} = {}", target, render_value(value)),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use algod_client::models::ScratchChange;
+    use algokit_test_artifacts::arc56_struct_operations;
+
+    fn uint(value: u64) -> AvmValue {
+        AvmValue {
+            r#type: 2,
+            bytes: None,
+            uint: Some(value),
+        }
+    }
+
+    fn unit(pc: u64) -> SimulationOpcodeTraceUnit {
+        SimulationOpcodeTraceUnit {
+            pc,
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn test_maps_arc56_source_info_past_constant_blocks() {
+        let contract =
+            Arc56Contract::from_json(arc56_struct_operations::APPLICATION_ARC56).unwrap();
+        // #pragma version 10, intcblock 1 5, bytecblock 0x626f784b6579, txn ApplicationID
+        let mut program = vec![10, 0x20, 2, 1, 5, 0x26, 1, 6];
+        program.extend(b"boxKey");
+        program.extend([0x31, 0x18]);
+        let hash = program_hash(&program);
+
+        assert!(
+            ProgramSourceMap::from_arc56(&contract, TraceProgram::Approval, None).is_err(),
+            "cblocks offsets need the compiled program"
+        );
+        let source_map =
+            ProgramSourceMap::from_arc56(&contract, TraceProgram::Approval, Some(&program))
+                .unwrap();
+        let line = source_map.line(14).unwrap();
+        assert_eq!(line.teal_line, Some(15));
+        assert_eq!(line.teal.as_deref(), Some("txn ApplicationID"));
+        assert_eq!(
+            line.source.as_deref(),
+            Some("examples/arc56_test/arc56_test.algo.ts:11")
+        );
+
+        let trace = SimulationTransactionExecTrace {
+            approval_program_trace: Some(vec![SimulationOpcodeTraceUnit {
+                pc: 14,
+                stack_additions: Some(vec![uint(0)]),
+                scratch_changes: Some(vec![ScratchChange {
+                    slot: 200,
+                    new_value: uint(5),
+                }]),
+                ..Default::default()
+            }]),
+            approval_program_hash: Some(hash.to_vec()),
+            ..Default::default()
+        };
+        let steps = TraceRenderer::new()
+            .with_program(&hash, source_map)
+            .steps(&trace);
+        assert_eq!(steps.len(), 1);
+        assert_eq!(steps[0].source.as_ref().unwrap().teal_line, Some(15));
+        assert_eq!(steps[0].stack_additions, vec!["0"]);
+        assert_eq!(steps[0].scratch_changes, vec!["scratch[someNumber] = 5"]);
+    }
+
+    #[test]
+    fn test_renders_inner_transactions_after_the_step_that_spawned_them() {
+        let program = [10u8, 0x81, 0x01, 0xb3];
+        let hash = program_hash(&program);
+        let source_map = ProgramSourceMap::from_disassembly(&[
+            (None, "#pragma version 10".to_string()),
+            (Some(1), "pushint 1".to_string()),
+            (Some(3), "itxn_submit".to_string()),
+        ]);
+        let inner = SimulationTransactionExecTrace {
+            approval_program_trace: Some(vec![SimulationOpcodeTraceUnit {
+                pc: 1,
+                state_changes: Some(vec![ApplicationStateOperation {
+                    operation: "w".to_string(),
+                    app_state_type: "g".to_string(),
+                    key: b"count".to_vec(),
+                    new_value: Some(AvmValue {
+                        r#type: 1,
+                        bytes: Some(BASE64.encode([0xca, 0xfe])),
+                        uint: None,
+                    }),
+                    account: None,
+                }]),
+                ..Default::default()
+            }]),
+            ..Default::default()
+        };
+        let trace = SimulationTransactionExecTrace {
+            approval_program_trace: Some(vec![
+                SimulationOpcodeTraceUnit {
+                    stack_additions: Some(vec![uint(1)]),
+                    ..unit(1)
+                },
+                SimulationOpcodeTraceUnit {
+                    stack_pop_count: Some(1),
+                    spawned_inners: Some(vec![0]),
+                    ..unit(3)
+                },
+                unit(4),
+            ]),
+            approval_program_hash: Some(hash.to_vec()),
+            inner_trace: Some(vec![inner]),
+            ..Default::default()
+        };
+
+        let renderer = TraceRenderer::new().with_program(&hash, source_map);
+        let paths = renderer
+            .steps(&trace)
+            .into_iter()
+            .map(|step| (step.path, step.pc))
+            .collect::<Vec<_>>();
+        assert_eq!(
+            paths,
+            vec![(vec![], 1), (vec![], 3), (vec![0], 1), (vec![], 4)]
+        );
+
+        let expected = [
+            "txn    | program  | pc | line | teal        | source | stack | changes",
+            "-------+----------+----+------+-------------+--------+-------+-------------------------",
+            "root   | approval | 1  | 2    | pushint 1   |        | +[1]  |",
+            "root   | approval | 3  | 3    | itxn_submit |        | -1    | inners 0",
+            "root.0 | approval | 1  |      |             |        |       | global[\"count\"] = 0xcafe",
+            "root   | approval | 4  |      |             |        |       |",
+        ];
+        assert_eq!(renderer.render(&trace), expected.join("\n"));
+    }
+}
@@ -0,0 +1,1058 @@
+//! Verification of state proofs and light block header proofs.
+//!
+//! A state proof attests to a [`StateProofMessage`] for an interval of rounds. It is verified
+//! against the participants commitment and the natural log of the proven weight (`voters_commitment`
+//! and `ln_proven_weight`) of the message of the *previous* interval, so a light client only needs
+//! to trust a single starting message. Once a message is trusted, a [`LightBlockHeaderProof`]
+//! returned by algod proves that a block header is committed to by its `block_headers_commitment`.
+//!
+//! Participant keys and signatures use Sumhash512 and deterministic Falcon-1024. Both are
+//! implemented in this crate and used by [`DefaultStateProofPrimitives`], but they are reached
+//! through [`StateProofPrimitives`] so that a faster or audited implementation can be supplied.
+
+use crate::block_header::BlockHeader;
+use crate::error::AlgoKitUtilsError;
+use crate::falcon;
+use crate::merkle::{hash_parts, invalid_proof, vector_commitment_root};
+use crate::sumhash::sumhash512;
+use algod_client::apis::parameter_enums::Hashtype;
+use algod_client::models::LightBlockHeaderProof;
+use algokit_transact::{
+    AlgorandMsgpack, Byte32, FalconSignatureStruct, MerkleArrayProof, MerkleSignatureVerifier,
+    Participant, StateProof, StateProofMessage,
+};
+use num_bigint::BigUint;
+use serde::{Deserialize, Serialize};
+use serde_with::{Bytes, serde_as};
+use sha2::{Digest, Sha256};
+use sha3::Shake256;
+use sha3::digest::{ExtendableOutput, Update, XofReader};
+
+/// Hash type identifier for SHA-512/256.
+pub const HASH_TYPE_SHA512_256: u64 = 0;
+/// Hash type identifier for Sumhash512.
+pub const HASH_TYPE_SUMHASH512: u64 = 1;
+/// Hash type identifier for SHA-256.
+pub const HASH_TYPE_SHA256: u64 = 2;
+
+/// The security strength, in bits, required of state proofs by the protocol.
+pub const STRENGTH_TARGET: u64 = 256;
+/// The maximum number of positions a state proof may reveal.
+pub const MAX_REVEALS: u64 = 640;
+
+const MAX_STATE_PROOF_TREE_DEPTH: u64 = 20;
+const MAX_ENCODED_TREE_DEPTH: u64 = 16;
+const SALT_VERSION: u64 = 0;
+const COIN_GENERATOR_VERSION: u8 = 0;
+const CRYPTO_PRIMITIVES_ID: u16 = 0;
+/// ln(2) in fixed point with 16 bits of precision, rounded up.
+const LN2_FIXED_POINT: u64 = 45427;
+const PRECISION_BITS: u64 = 16;
+
+const COIN_PREFIX: &[u8] = b"spc";
+const PARTICIPANT_PREFIX: &[u8] = b"spp";
+const SIGNATURE_PREFIX: &[u8] = b"sps";
+const EPHEMERAL_KEY_PREFIX: &[u8] = b"KP";
+const LIGHT_BLOCK_HEADER_DIGEST_SIZE: usize = 32;
+
+/// Cryptographic primitives used to verify the signatures of state proof participants.
+///
+/// Every method defaults to the implementation in this crate.
+pub trait StateProofPrimitives {
+    /// Hashes the concatenation of `parts` with the function identified by `hash_type`.
+    fn hash(&self, hash_type: u64, parts: &[&[u8]]) -> Result<Vec<u8>, AlgoKitUtilsError> {
+        match hash_type {
+            HASH_TYPE_SHA512_256 => Ok(hash_parts(Hashtype::Sha512256, parts)),
+            HASH_TYPE_SUMHASH512 => Ok(sumhash512(parts)),
+            HASH_TYPE_SHA256 => Ok(hash_parts(Hashtype::Sha256, parts)),
+            _ => Err(AlgoKitUtilsError::InputError {
+                err_msg: format!("Unsupported hash type {}", hash_type),
+            }),
+        }
+    }
+
+    /// Converts a compressed Falcon signature into its fixed-length (CT) representation.
+    fn falcon_ct_signature(&self, signature: &[u8]) -> Result<Vec<u8>, AlgoKitUtilsError> {
+        falcon::ct_signature(signature)
+    }
+
+    /// Verifies a compressed Falcon signature of `message` under `public_key`.
+    fn verify_falcon_signature(
+        &self,
+        public_key: &[u8],
+        message: &[u8],
+        signature: &[u8],
+    ) -> Result<(), AlgoKitUtilsError> {
+        falcon::verify(public_key, message, signature)
+    }
+}
+
+/// The state proof primitives implemented by this crate.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct DefaultStateProofPrimitives;
+
+impl StateProofPrimitives for DefaultStateProofPrimitives {}
+
+/// Returns the digest size in bytes of the hash function identified by `hash_type`.
+fn digest_size(hash_type: u64) -> Result<usize, AlgoKitUtilsError> {
+    match hash_type {
+        HASH_TYPE_SHA512_256 | HASH_TYPE_SHA256 => Ok(32),
+        HASH_TYPE_SUMHASH512 => Ok(64),
+        _ => Err(invalid_proof(format!("unknown hash type {}", hash_type))),
+    }
+}
+
+fn is_zero(value: &u64) -> bool {
+    *value == 0
+}
+
+fn is_zero_bytes(bytes: &[u8]) -> bool {
+    bytes.iter().all(|b| *b == 0)
+}
+
+/// The subset of a block header that is committed to by a state proof message.
+#[serde_as]
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct LightBlockHeader {
+    #[serde(rename = "0")]
+    #[serde(default)]
+    #[serde(skip_serializing_if = "is_zero_bytes")]
+    #[serde_as(as = "Bytes")]
+    pub seed: Byte32,
+
+    #[serde(rename = "r")]
+    #[serde(default)]
+    #[serde(skip_serializing_if = "is_zero")]
+    pub round: u64,
+
+    #[serde(rename = "gh")]
+    #[serde(default)]
+    #[serde(skip_serializing_if = "is_zero_bytes")]
+    #[serde_as(as = "Bytes")]
+    pub genesis_hash: Byte32,
+
+    #[serde(rename = "tc")]
+    #[serde(default)]
+    #[serde(skip_serializing_if = "is_zero_bytes")]
+    #[serde_as(as = "Bytes")]
+    pub sha256_transactions_commitment: Vec<u8>,
+}
+
+impl AlgorandMsgpack for LightBlockHeader {
+    const PREFIX: &'static [u8] = b"B256";
+}
+
+impl LightBlockHeader {
+    /// Extracts the light header of a block header.
+    pub fn from_block_header(header: &BlockHeader) -> Self {
+        Self {
+            seed: header.bytes32("seed").unwrap_or_default(),
+            round: header.round(),
+            genesis_hash: header.genesis_hash().unwrap_or_default(),
+            sha256_transactions_commitment: header
+                .field("txn256")
+                .and_then(|v| v.as_slice())
+                .map(<[u8]>::to_vec)
+                .unwrap_or_default(),
+        }
+    }
+
+    /// Computes the leaf hash of the header in a block headers commitment.
+    pub fn hash(&self) -> Result<Byte32, AlgoKitUtilsError> {
+        Ok(Sha256::digest(self.encode()?).into())
+    }
+}
+
+/// Verifies that a proof shows `header` is committed to by the `block_headers_commitment` of
+/// `message`.
+pub fn verify_light_block_header_proof(
+    proof: &LightBlockHeaderProof,
+    header: &LightBlockHeader,
+    message: &StateProofMessage,
+) -> Result<(), AlgoKitUtilsError> {
+    if header.round < message.first_attested_round || header.round > message.last_attested_round {
+        return Err(invalid_proof(format!(
+            "round {} is not attested to by the message for rounds {} to {}",
+            header.round, message.first_attested_round, message.last_attested_round
+        )));
+    }
+    let index = header.round - message.first_attested_round;
+    if proof.index != index {
+        return Err(invalid_proof(format!(
+            "proof is for index {} but round {} is at index {}",
+            proof.index, header.round, index
+        )));
+    }
+    let proof_length = proof
+        .treedepth
+        .checked_mul(LIGHT_BLOCK_HEADER_DIGEST_SIZE as u64)
+        .ok_or_else(|| invalid_proof(format!("tree depth {} is too large", proof.treedepth)))?;
+    if proof.proof.len() as u64 != proof_length {
+        return Err(invalid_proof(format!(
+            "expected {} bytes of proof for a tree of depth {}, got {}",
+            proof_length,
+            proof.treedepth,
+            proof.proof.len()
+        )));
+    }
+
+    let path: Vec<Vec<u8>> = proof
+        .proof
+        .chunks(LIGHT_BLOCK_HEADER_DIGEST_SIZE)
+        .map(<[u8]>::to_vec)
+        .collect();
+    let root = vector_commitment_root(
+        [(index, header.hash()?.to_vec())],
+        &path,
+        proof.treedepth,
+        LIGHT_BLOCK_HEADER_DIGEST_SIZE,
+        |parts| Ok(hash_parts(Hashtype::Sha256, parts)),
+    )?;
+
+    if root != message.block_headers_commitment {
+        return Err(invalid_proof(format!(
+            "block {} is not committed to by the block headers commitment",
+            header.round
+        )));
+    }
+    Ok(())
+}
+
+#[derive(Serialize, Deserialize)]
+#[serde(transparent)]
+struct MessageRepresentation(StateProofMessage);
+
+impl AlgorandMsgpack for MessageRepresentation {
+    const PREFIX: &'static [u8] = b"spm";
+}
+
+/// Computes the hash of a state proof message, which is what participants sign.
+pub fn state_proof_message_hash(message: &StateProofMessage) -> Result<Byte32, AlgoKitUtilsError> {
+    let encoded = MessageRepresentation(message.clone()).encode()?;
+    Ok(Sha256::digest(encoded).into())
+}
+
+/// Converts a state proof returned by algod into the message and the decoded proof.
+pub fn decode_state_proof(
+    state_proof: &algod_client::models::StateProof,
+) -> Result<(StateProofMessage, StateProof), AlgoKitUtilsError> {
+    let message = &state_proof.message;
+    let proof = rmp_serde::from_slice(&state_proof.state_proof).map_err(|e| {
+        AlgoKitUtilsError::InputError {
+            err_msg: format!("Failed to decode state proof: {}", e),
+        }
+    })?;
+
+    Ok((
+        StateProofMessage {
+            block_headers_commitment: message.block_headers_commitment.clone(),
+            voters_commitment: message.voters_commitment.clone(),
+            ln_proven_weight: message.ln_proven_weight,
+            first_attested_round: message.first_attested_round,
+            last_attested_round: message.last_attested_round,
+        },
+        proof,
+    ))
+}
+
+/// Checks that `num_reveals` revealed positions are enough for `signed_weight` to prove, with
+/// `strength_target` bits of security, that the proven weight was signed.
+///
+/// `ln_proven_weight` is the natural log of the proven weight in fixed point with 16 bits of
+/// precision, as found in [`StateProofMessage::ln_proven_weight`].
+pub fn verify_weights(
+    signed_weight: u64,
+    ln_proven_weight: u64,
+    num_reveals: u64,
+    strength_target: u64,
+) -> Result<(), AlgoKitUtilsError> {
+    if num_reveals > MAX_REVEALS {
+        return Err(invalid_proof(format!(
+            "{} reveals exceeds the maximum of {}",
+            num_reveals, MAX_REVEALS
+        )));
+    }
+    if signed_weight == 0 {
+        return Err(invalid_proof("signed weight is zero".to_string()));
+    }
+
+    // With d such that 2^d <= signed weight < 2^(d+1), the proof is sufficient when
+    //   reveals * (x + w * y) >= (strength target * T + reveals * ln proven weight) * y
+    // where T = ln(2) in fixed point and
+    //   y = 2^(2d) + 2^(d+2) * signed weight + signed weight^2
+    //   x = 3 * 2^b * (signed weight^2 - 2^(2d))
+    //   w = d * (T - 1)
+    let d = 63 - signed_weight.leading_zeros() as u64;
+    let weight = BigUint::from(signed_weight);
+    let weight_squared = &weight * &weight;
+    let two_pow_2d = BigUint::from(1u8) << (2 * d);
+    let y = &two_pow_2d + ((BigUint::from(1u8) << (d + 2)) * &weight) + &weight_squared;
+    let x =
+        BigUint::from(3u8) * (BigUint::from(1u8) << PRECISION_BITS) * (weight_squared - two_pow_2d);
+    let w = BigUint::from(d) * BigUint::from(LN2_FIXED_POINT - 1);
+
+    let reveals = BigUint::from(num_reveals);
+    let lhs = &reveals * (x + w * &y);
+    let rhs = (BigUint::from(strength_target) * BigUint::from(LN2_FIXED_POINT)
+        + &reveals * BigUint::from(ln_proven_weight))
+        * y;
+
+    if lhs < rhs {
+        return Err(invalid_proof(format!(
+            "signed weight {} with {} reveals is insufficient to prove the proven weight",
+            signed_weight, num_reveals
+        )));
+    }
+    Ok(())
+}
+
+/// Generates the pseudo-random coins that select which positions a state proof must reveal.
+struct CoinGenerator {
+    reader: <Shake256 as ExtendableOutput>::Reader,
+    signed_weight: u64,
+    threshold: u128,
+}
+
+impl CoinGenerator {
+    fn new(
+        participants_commitment: &[u8],
+        ln_proven_weight: u64,
+        proof: &StateProof,
+        message_hash: &Byte32,
+    ) -> Self {
+        let mut shake = Shake256::default();
+        shake.update(COIN_PREFIX);
+        shake.update(&[COIN_GENERATOR_VERSION]);
+        shake.update(participants_commitment);
+        shake.update(&ln_proven_weight.to_le_bytes());
+        shake.update(&proof.sig_commit);
+        shake.update(&proof.signed_weight.to_le_bytes());
+        shake.update(message_hash);
+
+        // Rejection sampling keeps coins uniform over [0, signed weight).
+        let signed_weight = proof.signed_weight;
+        let threshold = (1u128 << 64) / signed_weight as u128 * signed_weight as u128;
+
+        Self {
+            reader: shake.finalize_xof(),
+            signed_weight,
+            threshold,
+        }
+    }
+
+    fn next_coin(&mut self) -> u64 {
+        loop {
+            let mut bytes = [0u8; 8];
+            self.reader.read(&mut bytes);
+            let value = u64::from_le_bytes(bytes);
+            if (value as u128) < self.threshold {
+                return value % self.signed_weight;
+            }
+        }
+    }
+}
+
+/// Verifies state proofs against a trusted participants commitment and proven weight.
+#[derive(Debug, Clone, PartialEq)]
+pub struct StateProofVerifier {
+    participants_commitment: Vec<u8>,
+    ln_proven_weight: u64,
+    strength_target: u64,
+}
+
+impl StateProofVerifier {
+    pub fn new(participants_commitment: Vec<u8>, ln_proven_weight: u64) -> Self {
+        Self {
+            participants_commitment,
+            ln_proven_weight,
+            strength_target: STRENGTH_TARGET,
+        }
+    }
+
+    /// Creates a verifier for the state proof that follows `previous`.
+    pub fn from_message(previous: &StateProofMessage) -> Self {
+        Self::new(
+            previous.voters_commitment.clone(),
+            previous.ln_proven_weight,
+        )
+    }
+
+    pub fn with_strength_target(mut self, strength_target: u64) -> Self {
+        self.strength_target = strength_target;
+        self
+    }
+
+    /// Verifies that `proof` proves `message` was signed by enough of the participants.
+    pub fn verify(
+        &self,
+        message: &StateProofMessage,
+        proof: &StateProof,
+        primitives: &impl StateProofPrimitives,
+    ) -> Result<(), AlgoKitUtilsError> {
+        let num_reveals = proof.positions_to_reveal.len() as u64;
+        verify_weights(
+            proof.signed_weight,
+            self.ln_proven_weight,
+            num_reveals,
+            self.strength_target,
+        )?;
+
+        for (name, tree) in [("sig", &proof.sig_proofs), ("part", &proof.part_proofs)] {
+            if tree.tree_depth > MAX_STATE_PROOF_TREE_DEPTH {
+                return Err(invalid_proof(format!(
+                    "{} proof tree depth {} exceeds the maximum of {}",
+                    name, tree.tree_depth, MAX_STATE_PROOF_TREE_DEPTH
+                )));
+            }
+        }
+        if proof.merkle_signature_salt_version != SALT_VERSION {
+            return Err(invalid_proof(format!(
+                "unsupported salt version {}",
+                proof.merkle_signature_salt_version
+            )));
+        }
+
+        let message_hash = state_proof_message_hash(message)?;
+        let sig_hash_type = proof.sig_proofs.hash_factory.hash_type;
+        let part_hash_type = proof.part_proofs.hash_factory.hash_type;
+        let mut sig_leaves = Vec::with_capacity(proof.reveals.len());
+        let mut part_leaves = Vec::with_capacity(proof.reveals.len());
+
+        for (position, reveal) in &proof.reveals {
+            let sigslot = &reveal.sigslot;
+            let signature = hashable_signature(&sigslot.sig, primitives)?;
+            sig_leaves.push((
+                *position,
+                primitives.hash(
+                    sig_hash_type,
+                    &[
+                        SIGNATURE_PREFIX,
+                        &sigslot.lower_sig_weight.to_le_bytes(),
+                        &signature,
+                    ],
+                )?,
+            ));
+            part_leaves.push((
+                *position,
+                participant_hash(&reveal.participant, part_hash_type, primitives)?,
+            ));
+
+            verify_participant_signature(
+                &reveal.participant.verifier,
+                message.last_attested_round,
+                &message_hash,
+                &sigslot.sig,
+                primitives,
+            )
+            .map_err(|e| invalid_proof(format!("reveal at position {}: {}", position, e)))?;
+        }
+
+        if tree_root(sig_leaves, &proof.sig_proofs, primitives)? != proof.sig_commit {
+            return Err(invalid_proof(
+                "revealed signatures are not committed to by the signature commitment".to_string(),
+            ));
+        }
+        if tree_root(part_leaves, &proof.part_proofs, primitives)? != self.participants_commitment {
+            return Err(invalid_proof(
+                "revealed participants are not committed to by the participants commitment"
+                    .to_string(),
+            ));
+        }
+
+        let mut coins = CoinGenerator::new(
+            &self.participants_commitment,
+            self.ln_proven_weight,
+            proof,
+            &message_hash,
+        );
+        for position in &proof.positions_to_reveal {
+            let coin = coins.next_coin();
+            let reveal = proof
+                .reveals
+                .get(position)
+                .ok_or_else(|| invalid_proof(format!("position {} is not revealed", position)))?;
+            let lower = reveal.sigslot.lower_sig_weight;
+            if coin < lower || coin - lower >= reveal.participant.weight {
+                return Err(invalid_proof(format!(
+                    "coin {} does not select position {}",
+                    coin, position
+                )));
+            }
+        }
+
+        Ok(())
+    }
+}
+
+fn tree_root(
+    leaves: Vec<(u64, Vec<u8>)>,
+    proof: &MerkleArrayProof,
+    primitives: &impl StateProofPrimitives,
+) -> Result<Vec<u8>, AlgoKitUtilsError> {
+    let hash_type = proof.hash_factory.hash_type;
+    vector_commitment_root(
+        leaves,
+        &proof.path,
+        proof.tree_depth,
+        digest_size(hash_type)?,
+        |parts| primitives.hash(hash_type, parts),
+    )
+}
+
+fn participant_hash(
+    participant: &Participant,
+    hash_type: u64,
+    primitives: &impl StateProofPrimitives,
+) -> Result<Vec<u8>, AlgoKitUtilsError> {
+    primitives.hash(
+        hash_type,
+        &[
+            PARTICIPANT_PREFIX,
+            &participant.weight.to_le_bytes(),
+            &participant.verifier.key_lifetime.to_le_bytes(),
+            &participant.verifier.commitment,
+        ],
+    )
+}
+
+/// Encodes a Merkle signature into the fixed-length form that is committed to by a state proof.
+fn hashable_signature(
+    signature: &FalconSignatureStruct,
+    primitives: &impl StateProofPrimitives,
+) -> Result<Vec<u8>, AlgoKitUtilsError> {
+    let proof = &signature.proof;
+    if proof.tree_depth > MAX_ENCODED_TREE_DEPTH || proof.path.len() as u64 != proof.tree_depth {
+        return Err(invalid_proof(format!(
+            "signature proof of depth {} with {} path elements cannot be encoded",
+            proof.tree_depth,
+            proof.path.len()
+        )));
+    }
+    let zero_digest = vec![0u8; digest_size(proof.hash_factory.hash_type)?];
+
+    let mut bytes = CRYPTO_PRIMITIVES_ID.to_le_bytes().to_vec();
+    bytes.extend(primitives.falcon_ct_signature(&signature.signature)?);
+    bytes.extend(&signature.verifying_key.public_key);
+    bytes.extend(signature.vector_commitment_index.to_le_bytes());
+    bytes.push(proof.tree_depth as u8);
+    for _ in proof.tree_depth..MAX_ENCODED_TREE_DEPTH {
+        bytes.extend(&zero_digest);
+    }
+    for node in &proof.path {
+        bytes.extend(if node.is_empty() { &zero_digest } else { node });
+    }
+    Ok(bytes)
+}
+
+/// Verifies a participant's signature of `message_hash` for `round`, including that the
+/// ephemeral key used is committed to by the participant's key commitment.
+fn verify_participant_signature(
+    verifier: &MerkleSignatureVerifier,
+    round: u64,
+    message_hash: &Byte32,
+    signature: &FalconSignatureStruct,
+    primitives: &impl StateProofPrimitives,
+) -> Result<(), AlgoKitUtilsError> {
+    if verifier.key_lifetime == 0 {
+        return Err(invalid_proof("key lifetime is zero".to_string()));
+    }
+    if round % verifier.key_lifetime != 0 {
+        return Err(invalid_proof(format!(
+            "round {} is not a multiple of the key lifetime {}",
+            round, verifier.key_lifetime
+        )));
+    }
+
+    let hash_type = signature.proof.hash_factory.hash_type;
+    let key_leaf = primitives.hash(
+        hash_type,
+        &[
+            EPHEMERAL_KEY_PREFIX,
+            &CRYPTO_PRIMITIVES_ID.to_le_bytes(),
+            &round.to_le_bytes(),
+            &signature.verifying_key.public_key,
+        ],
+    )?;
+    let key_root = tree_root(
+        vec![(signature.vector_commitment_index, key_leaf)],
+        &signature.proof,
+        primitives,
+    )?;
+    if key_root != verifier.commitment {
+        return Err(invalid_proof(
+            "signing key is not committed to by the participant".to_string(),
+        ));
+    }
+
+    primitives.verify_falcon_signature(
+        &signature.verifying_key.public_key,
+        message_hash,
+        &signature.signature,
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use algod_client::models::Block;
+    use algokit_transact::test_utils::TestDataMother;
+    use algokit_transact::{FalconVerifier, HashFactory, Reveal, SigslotCommit, Transaction};
+    use sha2::Sha512;
+    use std::collections::BTreeMap;
+
+    const ROUND: u64 = 512;
+
+    /// Stands in for Sumhash512 with SHA-512 and for Falcon with a SHA-256 "signature" of the
+    /// public key and message.
+    struct MockPrimitives;
+
+    impl StateProofPrimitives for MockPrimitives {
+        fn hash(&self, hash_type: u64, parts: &[&[u8]]) -> Result<Vec<u8>, AlgoKitUtilsError> {
+            if hash_type == HASH_TYPE_SUMHASH512 {
+                let mut hasher = Sha512::new();
+                parts
+                    .iter()
+                    .for_each(|part| sha2::Digest::update(&mut hasher, part));
+                return Ok(hasher.finalize().to_vec());
+            }
+            Ok(hash_parts(Hashtype::Sha256, parts))
+        }
+
+        fn falcon_ct_signature(&self, signature: &[u8]) -> Result<Vec<u8>, AlgoKitUtilsError> {
+            Ok(signature.to_vec())
+        }
+
+        fn verify_falcon_signature(
+            &self,
+            public_key: &[u8],
+            message: &[u8],
+            signature: &[u8],
+        ) -> Result<(), AlgoKitUtilsError> {
+            if mock_sign(public_key, message) != signature {
+                return Err(invalid_proof("bad signature".to_string()));
+            }
+            Ok(())
+        }
+    }
+
+    fn mock_sign(public_key: &[u8], message: &[u8]) -> Vec<u8> {
+        hash_parts(Hashtype::Sha256, &[public_key, message])
+    }
+
+    fn message() -> StateProofMessage {
+        StateProofMessage {
+            block_headers_commitment: vec![1; 32],
+            voters_commitment: vec![2; 64],
+            ln_proven_weight: 10,
+            first_attested_round: ROUND - 255,
+            last_attested_round: ROUND,
+        }
+    }
+
+    fn depth_two_root(leaves: &[Vec<u8>]) -> Vec<u8> {
+        let mut positioned = vec![Vec::new(); 4];
+        for (index, leaf) in leaves.iter().enumerate() {
+            positioned[crate::merkle::vector_commitment_index(index as u64, 2).unwrap() as usize] =
+                leaf.clone();
+        }
+        let sumhash = |parts: &[&[u8]]| MockPrimitives.hash(HASH_TYPE_SUMHASH512, parts).unwrap();
+        let left = sumhash(&[b"MA", &positioned[0], &positioned[1]]);
+        let right = sumhash(&[b"MA", &positioned[2], &positioned[3]]);
+        sumhash(&[b"MA", &left, &right])
+    }
+
+    /// Builds a state proof for `message` in which four participants sign and all of them are
+    /// revealed, returning it with the participants commitment.
+    fn signed_state_proof(
+        message: &StateProofMessage,
+        ln_proven_weight: u64,
+    ) -> (StateProof, Vec<u8>) {
+        let weights = [1000u64, 2000, 3000, 4000];
+        let message_hash = state_proof_message_hash(message).unwrap();
+        let no_path = MerkleArrayProof {
+            path: vec![],
+            hash_factory: HashFactory {
+                hash_type: HASH_TYPE_SUMHASH512,
+            },
+            tree_depth: 0,
+        };
+
+        let mut lower_sig_weight = 0;
+        let mut reveals = BTreeMap::new();
+        for (position, weight) in weights.iter().enumerate() {
+            let public_key = vec![position as u8 + 1; 8];
+            let key_leaf = MockPrimitives
+                .hash(
+                    HASH_TYPE_SUMHASH512,
+                    &[b"KP", &[0, 0], &ROUND.to_le_bytes(), &public_key],
+                )
+                .unwrap();
+            let sig = FalconSignatureStruct {
+                signature: mock_sign(&public_key, &message_hash),
+                vector_commitment_index: 0,
+                proof: no_path.clone(),
+                verifying_key: FalconVerifier { public_key },
+            };
+            let participant = Participant {
+                verifier: MerkleSignatureVerifier {
+                    commitment: key_leaf.try_into().unwrap(),
+                    key_lifetime: 256,
+                },
+                weight: *weight,
+            };
+            reveals.insert(
+                position as u64,
+                Reveal {
+                    sigslot: SigslotCommit {
+                        sig,
+                        lower_sig_weight,
+                    },
+                    participant,
+                },
+            );
+            lower_sig_weight += weight;
+        }
+
+        let sig_leaves: Vec<Vec<u8>> = reveals
+            .values()
+            .map(|r| {
+                let signature = hashable_signature(&r.sigslot.sig, &MockPrimitives).unwrap();
+                MockPrimitives
+                    .hash(
+                        HASH_TYPE_SUMHASH512,
+                        &[
+                            b"sps",
+                            &r.sigslot.lower_sig_weight.to_le_bytes(),
+                            &signature,
+                        ],
+                    )
+                    .unwrap()
+            })
+            .collect();
+        let part_leaves: Vec<Vec<u8>> = reveals
+            .values()
+            .map(|r| {
+                participant_hash(&r.participant, HASH_TYPE_SUMHASH512, &MockPrimitives).unwrap()
+            })
+            .collect();
+        let participants_commitment = depth_two_root(&part_leaves);
+        let tree_proof = MerkleArrayProof {
+            tree_depth: 2,
+            ..no_path
+        };
+
+        let mut proof = StateProof {
+            sig_commit: depth_two_root(&sig_leaves),
+            signed_weight: lower_sig_weight,
+            sig_proofs: tree_proof.clone(),
+            part_proofs: tree_proof,
+            merkle_signature_salt_version: 0,
+            reveals,
+            positions_to_reveal: vec![],
+        };
+
+        let mut coins = CoinGenerator::new(
+            &participants_commitment,
+            ln_proven_weight,
+            &proof,
+            &message_hash,
+        );
+        proof.positions_to_reveal = (0..20)
+            .map(|_| {
+                let coin = coins.next_coin();
+                *proof
+                    .reveals
+                    .iter()
+                    .find(|(_, r)| {
+                        r.sigslot.lower_sig_weight <= coin
+                            && coin < r.sigslot.lower_sig_weight + r.participant.weight
+                    })
+                    .unwrap()
+                    .0
+            })
+            .collect();
+
+        (proof, participants_commitment)
+    }
+
+    fn artifact() -> (StateProofMessage, StateProof) {
+        let Transaction::StateProof(fields) = TestDataMother::state_proof().transaction else {
+            panic!("expected a state proof transaction");
+        };
+        (fields.message.unwrap(), fields.state_proof.unwrap())
+    }
+
+    #[test]
+    fn test_artifact_reveals_minimum_positions_for_signed_weight() {
+        let (message, proof) = artifact();
+
+        // The proven weight of consecutive intervals barely changes, so the message's own weight
+        // stands in for the previous interval's.
+        let reveals = proof.positions_to_reveal.len() as u64;
+        assert_eq!(reveals, 148);
+        verify_weights(
+            proof.signed_weight,
+            message.ln_proven_weight,
+            reveals,
+            STRENGTH_TARGET,
+        )
+        .unwrap();
+        assert!(
+            verify_weights(
+                proof.signed_weight,
+                message.ln_proven_weight,
+                reveals - 1,
+                STRENGTH_TARGET
+            )
+            .is_err()
+        );
+    }
+
+    /// The voters commitment of the interval before the artifact's, which the artifact's proof is
+    /// verified against. It is not part of the artifact, but the coins that select the 148
+    /// revealed positions are derived from it, so no other value verifies.
+    const ARTIFACT_PARTICIPANTS_COMMITMENT: &str = "4ad0a891b20be6921c63e12521c0b706137809f110ea1af321e1479864db4708f770153cdce38a6c7ad51e61295e6df0a4ee126342819b50e8de3c6a054c9d40";
+
+    fn artifact_verifier(ln_proven_weight: u64) -> StateProofVerifier {
+        let commitment = (0..ARTIFACT_PARTICIPANTS_COMMITMENT.len())
+            .step_by(2)
+            .map(|i| u8::from_str_radix(&ARTIFACT_PARTICIPANTS_COMMITMENT[i..i + 2], 16).unwrap())
+            .collect();
+        StateProofVerifier::new(commitment, ln_proven_weight)
+    }
+
+    #[test]
+    fn test_verifies_testnet_artifact() {
+        let (message, proof) = artifact();
+        let verifier = artifact_verifier(message.ln_proven_weight);
+
+        verifier
+            .verify(&message, &proof, &DefaultStateProofPrimitives)
+            .unwrap();
+
+        let other_message = StateProofMessage {
+            last_attested_round: message.last_attested_round + 256,
+            ..message.clone()
+        };
+        assert!(
+            verifier
+                .verify(&other_message, &proof, &DefaultStateProofPrimitives)
+                .is_err()
+        );
+        assert!(
+            artifact_verifier(message.ln_proven_weight + 1)
+                .verify(&message, &proof, &DefaultStateProofPrimitives)
+                .is_err()
+        );
+    }
+
+    #[test]
+    fn test_decode_state_proof_response() {
+        let (message, proof) = artifact();
+        let response = algod_client::models::StateProof::new(
+            algod_client::models::StateProofMessage::new(
+                message.block_headers_commitment.clone(),
+                message.voters_commitment.clone(),
+                message.ln_proven_weight,
+                message.first_attested_round,
+                message.last_attested_round,
+            ),
+            rmp_serde::to_vec_named(&proof).unwrap(),
+        );
+
+        assert_eq!(decode_state_proof(&response).unwrap(), (message, proof));
+    }
+
+    #[test]
+    fn test_verify_state_proof() {
+        let message = message();
+        let ln_proven_weight = 558182; // ln(5000) in 16 bit fixed point
+        let (proof, participants_commitment) = signed_state_proof(&message, ln_proven_weight);
+        let verifier = StateProofVerifier::new(participants_commitment, ln_proven_weight)
+            .with_strength_target(16);
+
+        verifier.verify(&message, &proof, &MockPrimitives).unwrap();
+    }
+
+    #[test]
+    fn test_rejects_invalid_state_proofs() {
+        let message = message();
+        let ln_proven_weight = 558182;
+        let (proof, participants_commitment) = signed_state_proof(&message, ln_proven_weight);
+        let verifier = StateProofVerifier::new(participants_commitment.clone(), ln_proven_weight)
+            .with_strength_target(16);
+
+        let other_message = StateProofMessage {
+            block_headers_commitment: vec![3; 32],
+            ..message.clone()
+        };
+        assert!(
+            verifier
+                .verify(&other_message, &proof, &MockPrimitives)
+                .unwrap_err()
+                .to_string()
+                .contains("bad signature")
+        );
+
+        let other_participants =
+            StateProofVerifier::new(vec![0; 64], ln_proven_weight).with_strength_target(16);
+        assert_eq!(
+            other_participants
+                .verify(&message, &proof, &MockPrimitives)
+                .unwrap_err()
+                .to_string(),
+            "Invalid proof: revealed participants are not committed to by the participants commitment"
+        );
+
+        let mut wrong_position = proof.clone();
+        wrong_position.positions_to_reveal[0] = (proof.positions_to_reveal[0] + 1) % 4;
+        assert!(
+            verifier
+                .verify(&message, &wrong_position, &MockPrimitives)
+                .unwrap_err()
+                .to_string()
+                .contains("does not select position")
+        );
+
+        let mut tampered_weight = proof.clone();
+        tampered_weight
+            .reveals
+            .get_mut(&0)
+            .unwrap()
+            .sigslot
+            .lower_sig_weight = 1;
+        assert!(
+            verifier
+                .verify(&message, &tampered_weight, &MockPrimitives)
+                .is_err()
+        );
+
+        let mut too_few = proof.clone();
+        too_few.positions_to_reveal.truncate(5);
+        assert!(
+            verifier
+                .verify(&message, &too_few, &MockPrimitives)
+                .unwrap_err()
+                .to_string()
+                .contains("insufficient")
+        );
+    }
+
+    fn light_headers(message: &StateProofMessage) -> Vec<LightBlockHeader> {
+        (0..4)
+            .map(|i| LightBlockHeader {
+                seed: [i as u8; 32],
+                round: message.first_attested_round + i,
+                genesis_hash: [7; 32],
+                sha256_transactions_commitment: vec![i as u8 + 1; 32],
+            })
+            .collect()
+    }
+
+    /// Commits to the headers in a depth two vector commitment and returns a proof for each.
+    fn commit_headers(
+        message: &mut StateProofMessage,
+        headers: &[LightBlockHeader],
+    ) -> Vec<LightBlockHeaderProof> {
+        let mut level = vec![vec![0u8; 32]; 4];
+        for (index, header) in headers.iter().enumerate() {
+            level[crate::merkle::vector_commitment_index(index as u64, 2).unwrap() as usize] =
+                header.hash().unwrap().to_vec();
+        }
+        let mut levels = vec![level];
+        while levels.last().unwrap().len() > 1 {
+            let next = levels
+                .last()
+                .unwrap()
+                .chunks(2)
+                .map(|pair| hash_parts(Hashtype::Sha256, &[b"MA", &pair[0], &pair[1]]))
+                .collect();
+            levels.push(next);
+        }
+        message.block_headers_commitment = levels.last().unwrap()[0].clone();
+
+        (0..headers.len() as u64)
+            .map(|index| {
+                let mut position =
+                    crate::merkle::vector_commitment_index(index, 2).unwrap() as usize;
+                let mut path = Vec::new();
+                for level in &levels[..levels.len() - 1] {
+                    path.extend(&level[position ^ 1]);
+                    position >>= 1;
+                }
+                LightBlockHeaderProof::new(index, 2, path)
+            })
+            .collect()
+    }
+
+    #[test]
+    fn test_verify_light_block_header_proofs() {
+        let mut message = message();
+        let headers = light_headers(&message);
+        let proofs = commit_headers(&mut message, &headers);
+
+        for (proof, header) in proofs.iter().zip(&headers) {
+            verify_light_block_header_proof(proof, header, &message).unwrap();
+        }
+
+        let tampered = LightBlockHeader {
+            seed: [9; 32],
+            ..headers[1].clone()
+        };
+        assert_eq!(
+            verify_light_block_header_proof(&proofs[1], &tampered, &message)
+                .unwrap_err()
+                .to_string(),
+            format!(
+                "Invalid proof: block {} is not committed to by the block headers commitment",
+                tampered.round
+            )
+        );
+        assert!(verify_light_block_header_proof(&proofs[0], &headers[1], &message).is_err());
+
+        let unattested = LightBlockHeader {
+            round: message.last_attested_round + 1,
+            ..headers[0].clone()
+        };
+        assert!(verify_light_block_header_proof(&proofs[0], &unattested, &message).is_err());
+
+        let mut too_deep = proofs[0].clone();
+        too_deep.treedepth = u64::MAX;
+        assert_eq!(
+            verify_light_block_header_proof(&too_deep, &headers[0], &message)
+                .unwrap_err()
+                .to_string(),
+            format!("Invalid proof: tree depth {} is too large", u64::MAX)
+        );
+    }
+
+    #[test]
+    fn test_light_block_header_from_block() {
+        let block = Block {
+            round: Some(ROUND),
+            seed: Some(vec![4; 32]),
+            genesis_hash: Some(vec![5; 32]),
+            transactions_root_sha256: Some(vec![6; 32]),
+            transactions_root: Some(vec![8; 32]),
+            ..Default::default()
+        };
+        let header = BlockHeader::from_msgpack(&block.encode_raw().unwrap()).unwrap();
+
+        let light = LightBlockHeader::from_block_header(&header);
+
+        assert_eq!(
+            light,
+            LightBlockHeader {
+                seed: [4; 32],
+                round: ROUND,
+                genesis_hash: [5; 32],
+                sha256_transactions_commitment: vec![6; 32],
+            }
+        );
+        assert_eq!(&light.encode().unwrap()[..4], b"B256");
+        assert_eq!(
+            LightBlockHeader::decode(&light.encode().unwrap()).unwrap(),
+            light
+        );
+    }
+}
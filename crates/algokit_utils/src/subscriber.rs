@@ -0,0 +1,1296 @@
+//! Subscribing to transactions that match declarative filters.
+//!
+//! [`TransactionSubscriber`] yields every transaction from a starting round onwards that matches
+//! at least one of its [`TransactionFilter`]s, including inner transactions issued by app calls.
+//! When the starting round is far behind the tip of the chain and an indexer is configured, the
+//! subscriber first catches up by searching the indexer in windows of rounds, and then switches to
+//! following new blocks from algod with a [`ChainFollower`]. Catching up stops at the last round
+//! the indexer has processed, so rounds it has not seen yet are read from algod instead.
+
+use crate::block::{BlockTransaction, block_transactions};
+use crate::chain_follower::ChainFollower;
+use crate::error::AlgoKitUtilsError;
+use algod_client::AlgodClient;
+use algod_client::models::GetBlock;
+use algokit_abi::{ABIType, ABIValue, Arc56Contract, Event};
+use algokit_transact::{Address, Transaction};
+use base64::Engine;
+use base64::engine::general_purpose::STANDARD as BASE64;
+use futures_util::{Stream, StreamExt};
+use indexer_client::IndexerClient;
+use indexer_client::apis::parameter_enums::{AddressRole, TxType};
+use indexer_client::models::Transaction as IndexerTransaction;
+use sha2::{Digest, Sha512_256};
+use std::collections::{BTreeMap, VecDeque};
+use std::pin::Pin;
+use std::str::FromStr;
+
+const DEFAULT_MAX_ROUNDS_BEHIND: u64 = 1000;
+const DEFAULT_CATCH_UP_WINDOW: u64 = 1000;
+const INDEXER_PAGE_SIZE: u64 = 1000;
+const SELECTOR_LENGTH: usize = 4;
+
+type Selector = [u8; SELECTOR_LENGTH];
+type BlockStream = Pin<Box<dyn Stream<Item = Result<GetBlock, AlgoKitUtilsError>> + Send>>;
+
+/// An ARC-28 event that a filter looks for in transaction logs.
+#[derive(Debug, Clone)]
+struct Arc28EventSpec {
+    name: String,
+    signature: String,
+    selector: Selector,
+    args_type: ABIType,
+}
+
+impl Arc28EventSpec {
+    fn from_event(event: &Event) -> Result<Self, AlgoKitUtilsError> {
+        let arg_types = event
+            .args
+            .iter()
+            .map(|arg| arg.arg_type.as_str())
+            .collect::<Vec<_>>()
+            .join(",");
+        let signature = format!("{}({})", event.name, arg_types);
+        let args_type = ABIType::from_str(&format!("({})", arg_types))?;
+
+        Ok(Self {
+            name: event.name.clone(),
+            selector: selector(&signature),
+            signature,
+            args_type,
+        })
+    }
+
+    fn decode(&self, log: &[u8]) -> Option<Arc28Event> {
+        if log.len() < SELECTOR_LENGTH || log[..SELECTOR_LENGTH] != self.selector {
+            return None;
+        }
+        let args = match self.args_type.decode(&log[SELECTOR_LENGTH..]).ok()? {
+            ABIValue::Array(args) => args,
+            _ => return None,
+        };
+
+        Some(Arc28Event {
+            name: self.name.clone(),
+            signature: self.signature.clone(),
+            args,
+        })
+    }
+}
+
+/// An ARC-28 event decoded from the logs of a transaction.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Arc28Event {
+    /// The name of the event.
+    pub name: String,
+    /// The signature of the event, such as `Transfer(address,uint64)`.
+    pub signature: String,
+    /// The decoded event arguments, in order.
+    pub args: Vec<ABIValue>,
+}
+
+/// Criteria that a transaction must match.
+///
+/// Every criterion that is set must match; a filter with no criteria matches every transaction.
+#[derive(Debug, Clone, Default)]
+pub struct TransactionFilter {
+    sender: Option<String>,
+    receiver: Option<String>,
+    transaction_type: Option<TxType>,
+    app_id: Option<u64>,
+    asset_id: Option<u64>,
+    min_amount: Option<u64>,
+    max_amount: Option<u64>,
+    method_selectors: Vec<Selector>,
+    arc28_events: Vec<Arc28EventSpec>,
+    note_prefix: Option<Vec<u8>>,
+}
+
+impl TransactionFilter {
+    /// Creates a filter that matches every transaction.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Only matches transactions sent by `sender`.
+    pub fn with_sender(mut self, sender: &Address) -> Self {
+        self.sender = Some(sender.to_string());
+        self
+    }
+
+    /// Only matches payments and asset transfers to `receiver`.
+    pub fn with_receiver(mut self, receiver: &Address) -> Self {
+        self.receiver = Some(receiver.to_string());
+        self
+    }
+
+    /// Only matches transactions of the given type.
+    pub fn with_transaction_type(mut self, transaction_type: TxType) -> Self {
+        self.transaction_type = Some(transaction_type);
+        self
+    }
+
+    /// Only matches app calls to, or creations of, the app with the given ID.
+    pub fn with_app_id(mut self, app_id: u64) -> Self {
+        self.app_id = Some(app_id);
+        self
+    }
+
+    /// Only matches asset transfers, configurations and freezes of the asset with the given ID.
+    pub fn with_asset_id(mut self, asset_id: u64) -> Self {
+        self.asset_id = Some(asset_id);
+        self
+    }
+
+    /// Only matches payments and asset transfers of at least `min_amount`.
+    pub fn with_min_amount(mut self, min_amount: u64) -> Self {
+        self.min_amount = Some(min_amount);
+        self
+    }
+
+    /// Only matches payments and asset transfers of at most `max_amount`.
+    pub fn with_max_amount(mut self, max_amount: u64) -> Self {
+        self.max_amount = Some(max_amount);
+        self
+    }
+
+    /// Only matches app calls whose first argument is the selector of one of the given methods.
+    ///
+    /// Can be called repeatedly to match any of several methods.
+    pub fn with_method_signature(mut self, signature: &str) -> Self {
+        self.method_selectors.push(selector(signature));
+        self
+    }
+
+    /// Only matches app calls that emit at least one of the named ARC-28 events.
+    ///
+    /// Events are looked up in the contract's `events` and in the events of its methods. The
+    /// matching events are decoded and returned with each subscribed transaction.
+    pub fn with_arc28_events(
+        mut self,
+        contract: &Arc56Contract,
+        names: &[&str],
+    ) -> Result<Self, AlgoKitUtilsError> {
+        let events = contract
+            .events
+            .iter()
+            .flatten()
+            .chain(
+                contract
+                    .methods
+                    .iter()
+                    .flat_map(|method| method.events.iter().flatten()),
+            )
+            .collect::<Vec<_>>();
+
+        for name in names {
+            let mut found = false;
+            for event in events.iter().filter(|event| event.name == *name) {
+                let spec = Arc28EventSpec::from_event(event)?;
+                found = true;
+                if !self
+                    .arc28_events
+                    .iter()
+                    .any(|existing| existing.signature == spec.signature)
+                {
+                    self.arc28_events.push(spec);
+                }
+            }
+            if !found {
+                return Err(AlgoKitUtilsError::InputError {
+                    err_msg: format!("Contract {} has no event named {}", contract.name, name),
+                });
+            }
+        }
+        Ok(self)
+    }
+
+    /// Only matches transactions whose note starts with `prefix`.
+    pub fn with_note_prefix(mut self, prefix: &[u8]) -> Self {
+        self.note_prefix = Some(prefix.to_vec());
+        self
+    }
+
+    fn matches(&self, view: &TransactionView) -> bool {
+        let amount_in_range = |amount: Option<u64>| {
+            amount.is_some_and(|amount| {
+                self.min_amount.is_none_or(|min| amount >= min)
+                    && self.max_amount.is_none_or(|max| amount <= max)
+            })
+        };
+
+        self.sender
+            .as_ref()
+            .is_none_or(|sender| *sender == view.sender)
+            && self
+                .receiver
+                .as_ref()
+                .is_none_or(|receiver| view.receiver.as_ref() == Some(receiver))
+            && self
+                .transaction_type
+                .is_none_or(|tx_type| view.transaction_type == Some(tx_type))
+            && self.app_id.is_none_or(|id| view.app_id == Some(id))
+            && self.asset_id.is_none_or(|id| view.asset_id == Some(id))
+            && ((self.min_amount.is_none() && self.max_amount.is_none())
+                || amount_in_range(view.amount))
+            && (self.method_selectors.is_empty()
+                || view.first_app_arg.as_ref().is_some_and(|arg| {
+                    self.method_selectors
+                        .iter()
+                        .any(|selector| arg.as_slice() == selector)
+                }))
+            && (self.arc28_events.is_empty() || !self.events(view).is_empty())
+            && self
+                .note_prefix
+                .as_ref()
+                .is_none_or(|prefix| view.note.starts_with(prefix))
+    }
+
+    fn events(&self, view: &TransactionView) -> Vec<Arc28Event> {
+        view.logs
+            .iter()
+            .filter_map(|log| self.arc28_events.iter().find_map(|spec| spec.decode(log)))
+            .collect()
+    }
+
+    /// The search parameters that narrow indexer results down to candidates for this filter.
+    fn indexer_query(&self) -> IndexerQuery<'_> {
+        let (address, address_role) = match (&self.sender, &self.receiver) {
+            (Some(sender), _) => (Some(sender.as_str()), Some(AddressRole::Sender)),
+            (None, Some(receiver)) => (Some(receiver.as_str()), Some(AddressRole::Receiver)),
+            (None, None) => (None, None),
+        };
+
+        IndexerQuery {
+            tx_type: self.transaction_type,
+            asset_id: self.asset_id,
+            app_id: self.app_id,
+            address,
+            address_role,
+        }
+    }
+}
+
+struct IndexerQuery<'a> {
+    tx_type: Option<TxType>,
+    asset_id: Option<u64>,
+    app_id: Option<u64>,
+    address: Option<&'a str>,
+    address_role: Option<AddressRole>,
+}
+
+/// The fields of a transaction that filters match against, independent of where it came from.
+struct TransactionView {
+    sender: String,
+    receiver: Option<String>,
+    transaction_type: Option<TxType>,
+    app_id: Option<u64>,
+    asset_id: Option<u64>,
+    amount: Option<u64>,
+    note: Vec<u8>,
+    first_app_arg: Option<Vec<u8>>,
+    logs: Vec<Vec<u8>>,
+}
+
+/// A transaction as returned by the source it was found in.
+#[derive(Debug, Clone, PartialEq)]
+pub enum SubscribedTransactionData {
+    /// A transaction found in an algod block.
+    Block(BlockTransaction),
+    /// A transaction found by searching the indexer.
+    Indexer(Box<IndexerTransaction>),
+}
+
+/// A transaction that matched at least one filter of a [`TransactionSubscriber`].
+#[derive(Debug, Clone, PartialEq)]
+pub struct SubscribedTransaction {
+    /// The on-chain ID of a top level transaction, or `{parent id}/inner/{n}` for the nth inner
+    /// transaction of a top level transaction, counting depth first from 1.
+    pub id: String,
+    /// The ID of the top level transaction, if this is an inner transaction.
+    pub parent_id: Option<String>,
+    /// The round the transaction was confirmed in.
+    pub round: u64,
+    /// The transaction itself.
+    pub transaction: SubscribedTransactionData,
+    /// The names of the filters that the transaction matched.
+    pub filters_matched: Vec<String>,
+    /// The ARC-28 events of the matched filters decoded from the transaction's logs.
+    pub arc28_events: Vec<Arc28Event>,
+}
+
+/// A transaction that can be matched against filters.
+trait FilterSource: Sized {
+    fn view(&self) -> TransactionView;
+    fn inner_transactions(&self) -> &[Self];
+    fn data(&self) -> SubscribedTransactionData;
+}
+
+impl FilterSource for BlockTransaction {
+    fn view(&self) -> TransactionView {
+        let transaction = &self.signed_transaction.transaction;
+        let apply_data = &self.apply_data;
+        let (transaction_type, receiver, amount, app_id, asset_id, first_app_arg) =
+            match transaction {
+                Transaction::Payment(fields) => (
+                    TxType::Pay,
+                    Some(fields.receiver.to_string()),
+                    Some(fields.amount),
+                    None,
+                    None,
+                    None,
+                ),
+                Transaction::AssetTransfer(fields) => (
+                    TxType::Axfer,
+                    Some(fields.receiver.to_string()),
+                    Some(fields.amount),
+                    None,
+                    Some(fields.asset_id),
+                    None,
+                ),
+                Transaction::AssetConfig(fields) => (
+                    TxType::Acfg,
+                    None,
+                    None,
+                    None,
+                    non_zero(fields.asset_id).or(apply_data.created_asset_id),
+                    None,
+                ),
+                Transaction::AppCall(fields) => (
+                    TxType::Appl,
+                    None,
+                    None,
+                    non_zero(fields.app_id).or(apply_data.created_app_id),
+                    None,
+                    fields.args.as_ref().and_then(|args| args.first().cloned()),
+                ),
+                Transaction::AssetFreeze(fields) => {
+                    (TxType::Afrz, None, None, None, Some(fields.asset_id), None)
+                }
+                Transaction::KeyRegistration(_) => (TxType::Keyreg, None, None, None, None, None),
+                Transaction::Heartbeat(_) => (TxType::Hb, None, None, None, None, None),
+                Transaction::StateProof(_) => (TxType::Stpf, None, None, None, None, None),
+            };
+
+        TransactionView {
+            sender: transaction.header().sender.to_string(),
+            receiver,
+            transaction_type: Some(transaction_type),
+            app_id,
+            asset_id,
+            amount,
+            note: transaction.header().note.clone().unwrap_or_default(),
+            first_app_arg,
+            logs: apply_data
+                .eval_delta
+                .iter()
+                .flat_map(|delta| delta.logs.iter())
+                .map(|log| log.as_bytes().to_vec())
+                .collect(),
+        }
+    }
+
+    fn inner_transactions(&self) -> &[Self] {
+        self.apply_data
+            .eval_delta
+            .as_ref()
+            .map_or(&[], |delta| delta.inner_transactions.as_slice())
+    }
+
+    fn data(&self) -> SubscribedTransactionData {
+        SubscribedTransactionData::Block(self.clone())
+    }
+}
+
+impl FilterSource for IndexerTransaction {
+    fn view(&self) -> TransactionView {
+        let (receiver, amount) = match (&self.payment_transaction, &self.asset_transfer_transaction)
+        {
+            (Some(payment), _) => (Some(payment.receiver.clone()), Some(payment.amount)),
+            (None, Some(transfer)) => (Some(transfer.receiver.clone()), Some(transfer.amount)),
+            (None, None) => (None, None),
+        };
+        let asset_id = self
+            .asset_transfer_transaction
+            .as_ref()
+            .map(|transfer| transfer.asset_id)
+            .or_else(|| {
+                self.asset_config_transaction
+                    .as_ref()
+                    .and_then(|config| config.asset_id.and_then(non_zero))
+                    .or(self.created_asset_index)
+            })
+            .or_else(|| {
+                self.asset_freeze_transaction
+                    .as_ref()
+                    .map(|freeze| freeze.asset_id)
+            });
+        let app_id = self
+            .application_transaction
+            .as_ref()
+            .and_then(|app| non_zero(app.application_id).or(self.created_application_index));
+        let first_app_arg = self
+            .application_transaction
+            .as_ref()
+            .and_then(|app| app.application_args.as_ref())
+            .and_then(|args| args.first())
+            .and_then(|arg| BASE64.decode(arg).ok());
+
+        TransactionView {
+            sender: self.sender.clone(),
+            receiver,
+            transaction_type: TxType::from_str(&self.tx_type).ok(),
+            app_id,
+            asset_id,
+            amount,
+            note: self.note.clone().unwrap_or_default(),
+            first_app_arg,
+            logs: self.logs.clone().unwrap_or_default(),
+        }
+    }
+
+    fn inner_transactions(&self) -> &[Self] {
+        self.inner_txns.as_deref().unwrap_or_default()
+    }
+
+    fn data(&self) -> SubscribedTransactionData {
+        SubscribedTransactionData::Indexer(Box::new(self.clone()))
+    }
+}
+
+fn non_zero(id: u64) -> Option<u64> {
+    (id != 0).then_some(id)
+}
+
+fn selector(signature: &str) -> Selector {
+    let hash = Sha512_256::digest(signature.as_bytes());
+    let mut selector = [0; SELECTOR_LENGTH];
+    selector.copy_from_slice(&hash[..SELECTOR_LENGTH]);
+    selector
+}
+
+/// Matches a top level transaction and its inner transactions against the filters, appending
+/// the matches to `matches` in depth first order.
+fn match_transaction<T: FilterSource>(
+    filters: &[(String, TransactionFilter)],
+    transaction: &T,
+    id: String,
+    round: u64,
+    matches: &mut Vec<SubscribedTransaction>,
+) {
+    let mut inner_index = 0;
+    match_tree(
+        filters,
+        transaction,
+        &id,
+        None,
+        round,
+        &mut inner_index,
+        matches,
+    );
+}
+
+fn match_tree<T: FilterSource>(
+    filters: &[(String, TransactionFilter)],
+    transaction: &T,
+    root_id: &str,
+    id: Option<String>,
+    round: u64,
+    inner_index: &mut u64,
+    matches: &mut Vec<SubscribedTransaction>,
+) {
+    let view = transaction.view();
+    let matched = filters
+        .iter()
+        .filter(|(_, filter)| filter.matches(&view))
+        .collect::<Vec<_>>();
+
+    if !matched.is_empty() {
+        let mut arc28_events: Vec<Arc28Event> = Vec::new();
+        for event in matched.iter().flat_map(|(_, filter)| filter.events(&view)) {
+            if !arc28_events.contains(&event) {
+                arc28_events.push(event);
+            }
+        }
+        matches.push(SubscribedTransaction {
+            parent_id: id.as_ref().map(|_| root_id.to_string()),
+            id: id.unwrap_or_else(|| root_id.to_string()),
+            round,
+            transaction: transaction.data(),
+            filters_matched: matched.iter().map(|(name, _)| name.clone()).collect(),
+            arc28_events,
+        });
+    }
+
+    for inner in transaction.inner_transactions() {
+        *inner_index += 1;
+        let inner_id = format!("{}/inner/{}", root_id, inner_index);
+        match_tree(
+            filters,
+            inner,
+            root_id,
+            Some(inner_id),
+            round,
+            inner_index,
+            matches,
+        );
+    }
+}
+
+/// Subscribes to the transactions that match a set of named filters.
+pub struct TransactionSubscriber {
+    algod: AlgodClient,
+    indexer: Option<IndexerClient>,
+    start_round: u64,
+    filters: Vec<(String, TransactionFilter)>,
+    max_rounds_behind: u64,
+    catch_up_window: u64,
+    batch_size: Option<u64>,
+}
+
+impl TransactionSubscriber {
+    /// Creates a subscriber that starts with the transactions of `start_round`.
+    pub fn new(algod: AlgodClient, start_round: u64) -> Self {
+        Self {
+            algod,
+            indexer: None,
+            start_round,
+            filters: Vec::new(),
+            max_rounds_behind: DEFAULT_MAX_ROUNDS_BEHIND,
+            catch_up_window: DEFAULT_CATCH_UP_WINDOW,
+            batch_size: None,
+        }
+    }
+
+    /// Adds a filter, whose name is reported with each transaction that matches it.
+    pub fn with_filter(mut self, name: &str, filter: TransactionFilter) -> Self {
+        self.filters.push((name.to_string(), filter));
+        self
+    }
+
+    /// Catches up using the indexer when the start round is at least `max_rounds_behind` rounds
+    /// behind the tip of the chain.
+    pub fn with_indexer(mut self, indexer: IndexerClient, max_rounds_behind: u64) -> Self {
+        self.indexer = Some(indexer);
+        self.max_rounds_behind = max_rounds_behind;
+        self
+    }
+
+    /// Sets how many rounds are searched at a time while catching up with the indexer.
+    pub fn with_catch_up_window(mut self, catch_up_window: u64) -> Self {
+        self.catch_up_window = catch_up_window.max(1);
+        self
+    }
+
+    /// Sets the maximum number of blocks fetched concurrently while following algod.
+    pub fn with_batch_size(mut self, batch_size: u64) -> Self {
+        self.batch_size = Some(batch_size);
+        self
+    }
+
+    /// Turns the subscriber into a stream of matching transactions.
+    ///
+    /// The stream ends after yielding an error.
+    pub fn into_stream(
+        self,
+    ) -> impl Stream<Item = Result<SubscribedTransaction, AlgoKitUtilsError>> + Send {
+        let state = SubscriberState {
+            phase: Phase::Start,
+            subscriber: self,
+            buffer: VecDeque::new(),
+            done: false,
+        };
+
+        futures_util::stream::unfold(state, |mut state| async move {
+            if state.done {
+                return None;
+            }
+            match state.next_transaction().await {
+                Ok(transaction) => Some((Ok(transaction), state)),
+                Err(e) => {
+                    state.done = true;
+                    Some((Err(e), state))
+                }
+            }
+        })
+    }
+
+    fn follow(&self, start_round: u64) -> BlockStream {
+        let mut follower = ChainFollower::new(self.algod.clone(), start_round);
+        if let Some(batch_size) = self.batch_size {
+            follower = follower.with_batch_size(batch_size);
+        }
+        Box::pin(follower.into_stream())
+    }
+
+    /// Searches the indexer for the top level transactions in `min_round..=max_round` that may
+    /// match a filter, ordered as they were confirmed, along with the lowest round the indexer
+    /// reported as its current round.
+    async fn search_indexer(
+        &self,
+        indexer: &IndexerClient,
+        min_round: u64,
+        max_round: u64,
+    ) -> Result<(Vec<IndexerTransaction>, Option<u64>), AlgoKitUtilsError> {
+        let mut transactions = BTreeMap::new();
+        let mut current_round: Option<u64> = None;
+        for (_, filter) in &self.filters {
+            let query = filter.indexer_query();
+            let mut next_token: Option<String> = None;
+            loop {
+                let page = indexer
+                    .search_for_transactions(
+                        Some(INDEXER_PAGE_SIZE),
+                        next_token.as_deref(),
+                        None,
+                        query.tx_type,
+                        None,
+                        None,
+                        None,
+                        None,
+                        Some(min_round),
+                        Some(max_round),
+                        query.asset_id,
+                        None,
+                        None,
+                        None,
+                        None,
+                        query.address,
+                        query.address_role,
+                        None,
+                        None,
+                        query.app_id,
+                    )
+                    .await?;
+                current_round = Some(
+                    current_round.map_or(page.current_round, |round| round.min(page.current_round)),
+                );
+                if page.transactions.is_empty() {
+                    break;
+                }
+                for transaction in page.transactions {
+                    let key = (
+                        transaction.confirmed_round.unwrap_or_default(),
+                        transaction.intra_round_offset.unwrap_or_default(),
+                        transaction.id.clone(),
+                    );
+                    transactions.entry(key).or_insert(transaction);
+                }
+                match page.next_token {
+                    Some(token) => next_token = Some(token),
+                    None => break,
+                }
+            }
+        }
+        Ok((transactions.into_values().collect(), current_round))
+    }
+}
+
+enum Phase {
+    Start,
+    CatchUp { next_round: u64, tip: u64 },
+    Follow(BlockStream),
+}
+
+struct SubscriberState {
+    subscriber: TransactionSubscriber,
+    phase: Phase,
+    buffer: VecDeque<SubscribedTransaction>,
+    done: bool,
+}
+
+impl SubscriberState {
+    async fn next_transaction(&mut self) -> Result<SubscribedTransaction, AlgoKitUtilsError> {
+        loop {
+            if let Some(transaction) = self.buffer.pop_front() {
+                return Ok(transaction);
+            }
+
+            let subscriber = &self.subscriber;
+            let mut matches = Vec::new();
+            match &mut self.phase {
+                Phase::Start => {
+                    let start_round = subscriber.start_round;
+                    let tip = subscriber.algod.get_status().await?.last_round;
+                    self.phase = if subscriber.indexer.is_some()
+                        && tip.saturating_sub(start_round) >= subscriber.max_rounds_behind
+                    {
+                        Phase::CatchUp {
+                            next_round: start_round,
+                            tip,
+                        }
+                    } else {
+                        Phase::Follow(subscriber.follow(start_round))
+                    };
+                }
+                Phase::CatchUp { next_round, tip } => {
+                    let min_round = *next_round;
+                    if min_round > *tip {
+                        self.phase = Phase::Follow(subscriber.follow(min_round));
+                        continue;
+                    }
+                    let mut max_round = min_round
+                        .saturating_add(subscriber.catch_up_window - 1)
+                        .min(*tip);
+                    if let Some(indexer) = &subscriber.indexer {
+                        let (transactions, indexer_round) = subscriber
+                            .search_indexer(indexer, min_round, max_round)
+                            .await?;
+                        // Rounds the indexer has not processed yet are read from algod.
+                        if let Some(indexer_round) = indexer_round {
+                            *tip = (*tip).min(indexer_round);
+                            max_round = max_round.min(indexer_round);
+                        }
+                        for transaction in transactions.iter().filter(|transaction| {
+                            transaction.confirmed_round.unwrap_or_default() <= max_round
+                        }) {
+                            match_transaction(
+                                &subscriber.filters,
+                                transaction,
+                                transaction.id.clone().unwrap_or_default(),
+                                transaction.confirmed_round.unwrap_or_default(),
+                                &mut matches,
+                            );
+                        }
+                    }
+                    *next_round = (*next_round).max(max_round + 1);
+                }
+                Phase::Follow(blocks) => {
+                    let Some(block) = blocks.next().await else {
+                        return Err(AlgoKitUtilsError::InputError {
+                            err_msg: "Block stream ended unexpectedly".to_string(),
+                        });
+                    };
+                    let block = block?.block;
+                    let round = block.round.unwrap_or_default();
+                    for transaction in block_transactions(&block)? {
+                        match_transaction(
+                            &subscriber.filters,
+                            &transaction,
+                            transaction.id()?,
+                            round,
+                            &mut matches,
+                        );
+                    }
+                }
+            }
+            self.buffer.extend(matches);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::block::{ApplyData, EvalDelta};
+    use algod_client::models::{Block, SignedTxnInBlock};
+    use algokit_http_client::{HttpClient, HttpError, HttpMethod, HttpResponse};
+    use algokit_transact::test_utils::{
+        AppCallTransactionMother, TestDataMother, TransactionMother,
+    };
+    use algokit_transact::{AlgorandMsgpack, SignedTransaction, TransactionId};
+    use async_trait::async_trait;
+    use indexer_client::models::{SearchForTransactions, TransactionPayment};
+    use num_bigint::BigUint;
+    use std::collections::HashMap;
+    use std::sync::{Arc, Mutex};
+
+    fn block_transaction(transaction: Transaction, apply_data: ApplyData) -> BlockTransaction {
+        BlockTransaction {
+            signed_transaction: SignedTransaction {
+                transaction,
+                signature: Some([1; 64]),
+                auth_address: None,
+                multisignature: None,
+            },
+            logic_signature: None,
+            apply_data,
+        }
+    }
+
+    fn payment() -> Transaction {
+        TransactionMother::simple_payment().build().unwrap()
+    }
+
+    fn matched(
+        filters: &[(&str, TransactionFilter)],
+        transaction: &BlockTransaction,
+    ) -> Vec<(String, Vec<String>)> {
+        let filters = filters
+            .iter()
+            .map(|(name, filter)| (name.to_string(), filter.clone()))
+            .collect::<Vec<_>>();
+        let mut matches = Vec::new();
+        match_transaction(&filters, transaction, "root".to_string(), 1, &mut matches);
+        matches
+            .into_iter()
+            .map(|matched| (matched.id, matched.filters_matched))
+            .collect()
+    }
+
+    #[test]
+    fn test_matches_payment_criteria() {
+        let transaction = payment();
+        let Transaction::Payment(fields) = &transaction else {
+            unreachable!()
+        };
+        let (sender, receiver, amount) = (
+            fields.header.sender.clone(),
+            fields.receiver.clone(),
+            fields.amount,
+        );
+        let txn = block_transaction(transaction, ApplyData::default());
+
+        let filters = [
+            (
+                "payments",
+                TransactionFilter::new()
+                    .with_transaction_type(TxType::Pay)
+                    .with_sender(&sender)
+                    .with_receiver(&receiver)
+                    .with_min_amount(amount)
+                    .with_max_amount(amount),
+            ),
+            (
+                "too small",
+                TransactionFilter::new().with_min_amount(amount + 1),
+            ),
+            (
+                "transfers",
+                TransactionFilter::new().with_transaction_type(TxType::Axfer),
+            ),
+            (
+                "from receiver",
+                TransactionFilter::new().with_sender(&receiver),
+            ),
+            ("all", TransactionFilter::new()),
+        ];
+
+        assert_eq!(
+            matched(&filters, &txn),
+            vec![(
+                "root".to_string(),
+                vec!["payments".to_string(), "all".to_string()]
+            )]
+        );
+    }
+
+    #[test]
+    fn test_matches_inner_transactions_and_methods() {
+        let signature = "mint(uint64)void";
+        let app_call = AppCallTransactionMother::app_call()
+            .args(vec![selector(signature).to_vec()])
+            .build()
+            .unwrap();
+        let inner = |transaction| block_transaction(transaction, ApplyData::default());
+        let txn = block_transaction(
+            app_call,
+            ApplyData {
+                eval_delta: Some(EvalDelta {
+                    inner_transactions: vec![
+                        block_transaction(
+                            AppCallTransactionMother::app_call().build().unwrap(),
+                            ApplyData {
+                                eval_delta: Some(EvalDelta {
+                                    inner_transactions: vec![inner(payment())],
+                                    ..Default::default()
+                                }),
+                                ..Default::default()
+                            },
+                        ),
+                        inner(payment()),
+                    ],
+                    ..Default::default()
+                }),
+                ..Default::default()
+            },
+        );
+
+        let filters = [
+            (
+                "mint",
+                TransactionFilter::new()
+                    .with_app_id(84366825)
+                    .with_method_signature("burn(uint64)void")
+                    .with_method_signature(signature),
+            ),
+            (
+                "payments",
+                TransactionFilter::new().with_transaction_type(TxType::Pay),
+            ),
+        ];
+        let matches = matched(&filters, &txn);
+
+        assert_eq!(
+            matches,
+            vec![
+                ("root".to_string(), vec!["mint".to_string()]),
+                ("root/inner/2".to_string(), vec!["payments".to_string()]),
+                ("root/inner/3".to_string(), vec!["payments".to_string()]),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_decodes_arc28_events() {
+        let contract =
+            Arc56Contract::from_json(algokit_test_artifacts::reti::APPLICATION_ARC56).unwrap();
+        let filter = TransactionFilter::new()
+            .with_arc28_events(&contract, &["retiOP_stakeAdded"])
+            .unwrap();
+        let staker = TransactionMother::simple_payment()
+            .build()
+            .unwrap()
+            .header()
+            .sender
+            .to_string();
+        let args = vec![
+            ABIValue::Uint(BigUint::from(1u8)),
+            ABIValue::Uint(BigUint::from(2u8)),
+            ABIValue::Uint(BigUint::from(3u8)),
+            ABIValue::Address(staker),
+            ABIValue::Uint(BigUint::from(4u8)),
+        ];
+        let signature = "retiOP_stakeAdded(uint64,uint16,uint64,address,uint64)";
+        let mut log = selector(signature).to_vec();
+        log.extend(
+            ABIType::from_str("(uint64,uint16,uint64,address,uint64)")
+                .unwrap()
+                .encode(&ABIValue::Array(args.clone()))
+                .unwrap(),
+        );
+        let transaction = IndexerTransaction {
+            tx_type: "appl".to_string(),
+            logs: Some(vec![b"unrelated".to_vec(), log]),
+            ..Default::default()
+        };
+        let filters = vec![("stake".to_string(), filter)];
+
+        let mut matches = Vec::new();
+        match_transaction(&filters, &transaction, "root".to_string(), 1, &mut matches);
+
+        assert_eq!(matches.len(), 1);
+        assert_eq!(
+            matches[0].arc28_events,
+            vec![Arc28Event {
+                name: "retiOP_stakeAdded".to_string(),
+                signature: signature.to_string(),
+                args,
+            }]
+        );
+
+        let mut matches = Vec::new();
+        let quiet = IndexerTransaction {
+            logs: None,
+            ..transaction
+        };
+        match_transaction(&filters, &quiet, "root".to_string(), 1, &mut matches);
+        assert!(matches.is_empty());
+    }
+
+    #[test]
+    fn test_unknown_arc28_event() {
+        let contract =
+            Arc56Contract::from_json(algokit_test_artifacts::reti::APPLICATION_ARC56).unwrap();
+
+        let error = TransactionFilter::new()
+            .with_arc28_events(&contract, &["missing"])
+            .unwrap_err();
+
+        assert!(error.to_string().ends_with("has no event named missing"));
+    }
+
+    fn response(body: Vec<u8>, content_type: &str) -> HttpResponse {
+        HttpResponse {
+            body,
+            headers: HashMap::from([("content-type".to_string(), content_type.to_string())]),
+        }
+    }
+
+    /// A node at round 20 that produces a new round each time it is waited on.
+    struct MockAlgod {
+        last_round: Mutex<u64>,
+        blocks: HashMap<u64, Transaction>,
+    }
+
+    #[async_trait]
+    impl HttpClient for MockAlgod {
+        async fn request(
+            &self,
+            _method: HttpMethod,
+            path: String,
+            _query: Option<HashMap<String, String>>,
+            _body: Option<Vec<u8>>,
+            _headers: Option<HashMap<String, String>>,
+        ) -> Result<HttpResponse, HttpError> {
+            let mut last_round = self.last_round.lock().unwrap();
+            if let Some(round) = path.strip_prefix("/v2/blocks/") {
+                let round: u64 = round.parse().unwrap();
+                let transaction = self.blocks.get(&round);
+                let header = transaction.map(|transaction| transaction.header().clone());
+                // Stored the way algod stores them, without genesis fields or the `hgh` flag.
+                let transactions = transaction.map(|transaction| {
+                    let mut stripped = transaction.clone();
+                    let stripped_header = stripped.header_mut();
+                    let has_genesis_id = stripped_header.genesis_id.take().is_some();
+                    stripped_header.genesis_hash = None;
+                    vec![SignedTxnInBlock {
+                        signed_transaction: block_transaction(stripped, ApplyData::default())
+                            .signed_transaction,
+                        has_genesis_id: has_genesis_id.then_some(true),
+                        ..Default::default()
+                    }]
+                });
+                let block = GetBlock::new(Block {
+                    round: Some(round),
+                    genesis_id: header.as_ref().and_then(|header| header.genesis_id.clone()),
+                    genesis_hash: header
+                        .as_ref()
+                        .and_then(|header| header.genesis_hash.map(|hash| hash.to_vec())),
+                    transactions,
+                    ..Default::default()
+                });
+                return Ok(response(block.encode().unwrap(), "application/msgpack"));
+            }
+
+            if let Some(round) = path.strip_prefix("/v2/status/wait-for-block-after/") {
+                *last_round = (*last_round).max(round.parse::<u64>().unwrap() + 1);
+            }
+            let body = format!(
+                r#"{{"catchup-time":0,"last-round":{},"last-version":"v1","next-version":"v1","next-version-round":0,"next-version-supported":true,"stopped-at-unsupported-round":false,"time-since-last-round":0}}"#,
+                *last_round
+            );
+            Ok(response(body.into_bytes(), "application/json"))
+        }
+    }
+
+    /// An indexer at `current_round` that returns one transaction per page.
+    struct MockIndexer {
+        current_round: u64,
+        transactions: Vec<IndexerTransaction>,
+        queries: Mutex<Vec<HashMap<String, String>>>,
+    }
+
+    #[async_trait]
+    impl HttpClient for MockIndexer {
+        async fn request(
+            &self,
+            _method: HttpMethod,
+            _path: String,
+            query: Option<HashMap<String, String>>,
+            _body: Option<Vec<u8>>,
+            _headers: Option<HashMap<String, String>>,
+        ) -> Result<HttpResponse, HttpError> {
+            let query = query.unwrap();
+            self.queries.lock().unwrap().push(query.clone());
+            let param = |name: &str| query.get(name).map(|value| value.parse::<u64>().unwrap());
+            let (min_round, max_round) = (param("min-round").unwrap(), param("max-round").unwrap());
+            let skip = param("next").unwrap_or_default() as usize;
+
+            let transactions = self
+                .transactions
+                .iter()
+                .filter(|transaction| {
+                    (min_round..=max_round).contains(&transaction.confirmed_round.unwrap())
+                })
+                .skip(skip)
+                .take(1)
+                .cloned()
+                .collect::<Vec<_>>();
+            let search = SearchForTransactions {
+                next_token: (!transactions.is_empty()).then(|| (skip + 1).to_string()),
+                ..SearchForTransactions::new(self.current_round, transactions)
+            };
+            Ok(response(
+                serde_json::to_vec(&search).unwrap(),
+                "application/json",
+            ))
+        }
+    }
+
+    fn indexer_payment(round: u64, receiver: &str) -> IndexerTransaction {
+        IndexerTransaction {
+            id: Some(format!("TXN{}", round)),
+            confirmed_round: Some(round),
+            tx_type: "pay".to_string(),
+            payment_transaction: Some(TransactionPayment {
+                receiver: receiver.to_string(),
+                amount: 1000,
+                ..Default::default()
+            }),
+            ..Default::default()
+        }
+    }
+
+    #[tokio::test]
+    async fn test_catches_up_with_indexer_then_follows_algod() {
+        let transaction = payment();
+        let receiver = match &transaction {
+            Transaction::Payment(fields) => fields.receiver.clone(),
+            _ => unreachable!(),
+        };
+        let algod = AlgodClient::new(Arc::new(MockAlgod {
+            last_round: Mutex::new(20),
+            blocks: HashMap::from([(22, transaction.clone())]),
+        }));
+        let mock_indexer = Arc::new(MockIndexer {
+            current_round: 20,
+            transactions: vec![
+                indexer_payment(3, &receiver.to_string()),
+                indexer_payment(15, &receiver.to_string()),
+                indexer_payment(17, "OTHER"),
+            ],
+            queries: Mutex::new(Vec::new()),
+        });
+
+        let transactions = TransactionSubscriber::new(algod, 2)
+            .with_indexer(IndexerClient::new(mock_indexer.clone()), 10)
+            .with_catch_up_window(10)
+            .with_filter(
+                "received",
+                TransactionFilter::new()
+                    .with_transaction_type(TxType::Pay)
+                    .with_receiver(&receiver),
+            )
+            .into_stream()
+            .take(3)
+            .collect::<Vec<_>>()
+            .await
+            .into_iter()
+            .map(|transaction| transaction.unwrap())
+            .collect::<Vec<_>>();
+
+        assert_eq!(
+            transactions
+                .iter()
+                .map(|transaction| (transaction.round, transaction.id.clone()))
+                .collect::<Vec<_>>(),
+            vec![
+                (3, "TXN3".to_string()),
+                (15, "TXN15".to_string()),
+                (22, transaction.id().unwrap()),
+            ]
+        );
+        assert!(matches!(
+            transactions[2].transaction,
+            SubscribedTransactionData::Block(_)
+        ));
+
+        let queries = mock_indexer.queries.lock().unwrap();
+        let windows = queries
+            .iter()
+            .map(|query| (query["min-round"].clone(), query["max-round"].clone()))
+            .collect::<Vec<_>>();
+        assert_eq!(windows.first(), Some(&("2".to_string(), "11".to_string())));
+        assert_eq!(windows.last(), Some(&("12".to_string(), "20".to_string())));
+        assert_eq!(queries[0]["address-role"], "receiver");
+        assert_eq!(queries[0]["tx-type"], "pay");
+    }
+
+    #[tokio::test]
+    async fn test_hands_over_to_algod_where_a_lagging_indexer_stops() {
+        let transaction = payment();
+        let receiver = match &transaction {
+            Transaction::Payment(fields) => fields.receiver.clone(),
+            _ => unreachable!(),
+        };
+        let algod = AlgodClient::new(Arc::new(MockAlgod {
+            last_round: Mutex::new(20),
+            blocks: HashMap::from([(15, transaction.clone())]),
+        }));
+        let mock_indexer = Arc::new(MockIndexer {
+            current_round: 14,
+            transactions: vec![indexer_payment(3, &receiver.to_string())],
+            queries: Mutex::new(Vec::new()),
+        });
+
+        let transactions = TransactionSubscriber::new(algod, 2)
+            .with_indexer(IndexerClient::new(mock_indexer.clone()), 10)
+            .with_catch_up_window(10)
+            .with_filter(
+                "received",
+                TransactionFilter::new().with_receiver(&receiver),
+            )
+            .into_stream()
+            .take(2)
+            .collect::<Vec<_>>()
+            .await
+            .into_iter()
+            .map(|transaction| transaction.unwrap())
+            .collect::<Vec<_>>();
+
+        assert_eq!(
+            transactions
+                .iter()
+                .map(|transaction| (transaction.round, transaction.id.clone()))
+                .collect::<Vec<_>>(),
+            vec![(3, "TXN3".to_string()), (15, transaction.id().unwrap())]
+        );
+        assert!(matches!(
+            transactions[1].transaction,
+            SubscribedTransactionData::Block(_)
+        ));
+        let queries = mock_indexer.queries.lock().unwrap();
+        assert_eq!(
+            queries
+                .last()
+                .map(|query| (&query["min-round"][..], &query["max-round"][..])),
+            Some(("12", "14"))
+        );
+    }
+
+    #[tokio::test]
+    async fn test_indexer_and_algod_report_the_same_id() {
+        // A testnet state proof transaction and the ID the indexer reports for it.
+        let data = TestDataMother::state_proof();
+        let round = 24098947;
+        let indexer_transaction = IndexerTransaction {
+            id: Some("6D3MLKOASKUXHFTTWYUG563UBKZ5RW3FFKN6ZUUWBCY47RZT3HIA".to_string()),
+            confirmed_round: Some(round),
+            tx_type: "stpf".to_string(),
+            sender: data.transaction.header().sender.to_string(),
+            ..Default::default()
+        };
+        let node = || {
+            AlgodClient::new(Arc::new(MockAlgod {
+                last_round: Mutex::new(round + 20),
+                blocks: HashMap::from([(round, data.transaction.clone())]),
+            }))
+        };
+        let filter = || TransactionFilter::new().with_transaction_type(TxType::Stpf);
+
+        let caught_up = TransactionSubscriber::new(node(), round - 5)
+            .with_indexer(
+                IndexerClient::new(Arc::new(MockIndexer {
+                    current_round: round + 20,
+                    transactions: vec![indexer_transaction],
+                    queries: Mutex::new(Vec::new()),
+                })),
+                10,
+            )
+            .with_filter("state proofs", filter());
+        let caught_up = Box::pin(caught_up.into_stream())
+            .next()
+            .await
+            .unwrap()
+            .unwrap();
+        let followed =
+            TransactionSubscriber::new(node(), round - 5).with_filter("state proofs", filter());
+        let followed = Box::pin(followed.into_stream())
+            .next()
+            .await
+            .unwrap()
+            .unwrap();
+
+        assert!(matches!(
+            caught_up.transaction,
+            SubscribedTransactionData::Indexer(_)
+        ));
+        assert!(matches!(
+            followed.transaction,
+            SubscribedTransactionData::Block(_)
+        ));
+        assert_eq!(followed.round, round);
+        assert_eq!(followed.id, caught_up.id);
+    }
+}
@@ -0,0 +1,124 @@
+//! Sumhash512, the subset-sum hash function used by state proofs.
+//!
+//! The compression function maps 128 input bytes to 64 output bytes: each of the 8 rows of a
+//! fixed pseudo-random matrix of 64-bit words contributes the wrapping sum of the words selected
+//! by the set bits of the input. Inputs are absorbed in 64 byte blocks, each compressed together
+//! with the previous output, and finished with Merkle–Damgård padding. This matches the unsalted
+//! `sumhash.New512` of `go-sumhash` that go-algorand uses.
+
+use sha3::Shake256;
+use sha3::digest::{ExtendableOutput, Update, XofReader};
+use std::sync::OnceLock;
+
+const ROWS: usize = 8;
+const COLUMNS: usize = 1024;
+const WORD_BITS: u16 = 64;
+const MATRIX_SEED: &[u8] = b"Algorand";
+
+/// The size in bytes of a Sumhash512 digest.
+pub(crate) const OUTPUT_SIZE: usize = ROWS * 8;
+const BLOCK_SIZE: usize = COLUMNS / 8 - OUTPUT_SIZE;
+const LENGTH_SIZE: usize = 16;
+
+type Matrix = [[u64; COLUMNS]; ROWS];
+
+/// Returns the matrix derived from SHAKE256 of its dimensions and the "Algorand" seed.
+fn matrix() -> &'static Matrix {
+    static MATRIX: OnceLock<Box<Matrix>> = OnceLock::new();
+    MATRIX.get_or_init(|| {
+        let mut shake = Shake256::default();
+        shake.update(&WORD_BITS.to_le_bytes());
+        shake.update(&(ROWS as u16).to_le_bytes());
+        shake.update(&(COLUMNS as u16).to_le_bytes());
+        shake.update(MATRIX_SEED);
+        let mut reader = shake.finalize_xof();
+
+        let mut matrix = Box::new([[0u64; COLUMNS]; ROWS]);
+        for row in matrix.iter_mut() {
+            for word in row.iter_mut() {
+                let mut bytes = [0u8; 8];
+                reader.read(&mut bytes);
+                *word = u64::from_le_bytes(bytes);
+            }
+        }
+        matrix
+    })
+}
+
+/// Compresses the chaining value and a block into the next chaining value.
+fn compress(chain: &[u8; OUTPUT_SIZE], block: &[u8]) -> [u8; OUTPUT_SIZE] {
+    let matrix = matrix();
+    let input = chain.iter().chain(block);
+    let mut sums = [0u64; ROWS];
+    for (byte_index, byte) in input.enumerate() {
+        for bit in 0..8 {
+            if (byte >> bit) & 1 == 1 {
+                let column = byte_index * 8 + bit;
+                for (sum, row) in sums.iter_mut().zip(matrix.iter()) {
+                    *sum = sum.wrapping_add(row[column]);
+                }
+            }
+        }
+    }
+
+    let mut output = [0u8; OUTPUT_SIZE];
+    for (chunk, sum) in output.chunks_exact_mut(8).zip(sums) {
+        chunk.copy_from_slice(&sum.to_le_bytes());
+    }
+    output
+}
+
+/// Computes the Sumhash512 digest of the concatenation of `parts`.
+pub(crate) fn sumhash512(parts: &[&[u8]]) -> Vec<u8> {
+    let length: usize = parts.iter().map(|part| part.len()).sum();
+    let mut data = Vec::with_capacity(length + 2 * BLOCK_SIZE);
+    parts.iter().for_each(|part| data.extend_from_slice(part));
+
+    let remainder = length % BLOCK_SIZE;
+    let padding = if remainder < BLOCK_SIZE - LENGTH_SIZE {
+        BLOCK_SIZE - LENGTH_SIZE - remainder
+    } else {
+        2 * BLOCK_SIZE - LENGTH_SIZE - remainder
+    };
+    data.push(0x01);
+    data.resize(length + padding, 0);
+    let bit_length = (length as u128) << 3;
+    data.extend_from_slice(&bit_length.to_le_bytes());
+
+    let mut chain = [0u8; OUTPUT_SIZE];
+    for block in data.chunks_exact(BLOCK_SIZE) {
+        chain = compress(&chain, block);
+    }
+    chain.to_vec()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parts_are_concatenated() {
+        let data: Vec<u8> = (0..200u8).collect();
+
+        let whole = sumhash512(&[&data]);
+
+        assert_eq!(whole.len(), OUTPUT_SIZE);
+        assert_eq!(
+            sumhash512(&[&data[..47], &data[47..130], &data[130..]]),
+            whole
+        );
+    }
+
+    #[test]
+    fn test_padding_distinguishes_lengths() {
+        // 48 and 64 bytes straddle the point where padding needs an extra block.
+        let digests: Vec<Vec<u8>> = [0usize, 1, 47, 48, 63, 64, 65]
+            .iter()
+            .map(|length| sumhash512(&[&vec![0u8; *length]]))
+            .collect();
+
+        for (i, digest) in digests.iter().enumerate() {
+            assert!(digests[i + 1..].iter().all(|other| other != digest));
+        }
+    }
+}
@@ -0,0 +1,789 @@
+//! Static analysis of compiled programs.
+//!
+//! [`analyze`] splits a program into basic blocks and prices each with the opcode costs of its
+//! version, then reports the most expensive path through the program when it has no loops or
+//! recursion. It also reports which of the transaction fields a logic signature should constrain
+//! the program compares with the zero address, and the app state keys and box names it references
+//! through constants.
+
+use super::disassembler::{Immediate, Instruction, disassemble};
+use crate::error::AlgoKitUtilsError;
+use std::collections::{BTreeMap, BTreeSet, HashMap, HashSet};
+
+/// The cost of an opcode.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct OpCost {
+    /// The cost, or for dynamically priced opcodes the cost with the smallest possible inputs.
+    pub base: u64,
+    /// Whether the cost grows with the size of the opcode's inputs.
+    pub dynamic: bool,
+}
+
+impl OpCost {
+    const fn fixed(base: u64) -> Self {
+        Self {
+            base,
+            dynamic: false,
+        }
+    }
+
+    const fn dynamic(base: u64) -> Self {
+        Self {
+            base,
+            dynamic: true,
+        }
+    }
+}
+
+/// The cost of an instruction in a program of the given version.
+pub fn opcode_cost(instruction: &Instruction, version: u64) -> OpCost {
+    let field = instruction
+        .immediates
+        .iter()
+        .find_map(|immediate| match immediate {
+            Immediate::Field(name) => Some(*name),
+            _ => None,
+        });
+    // Costs of the opcodes that take an elliptic curve group, in the order BN254g1, BN254g2,
+    // BLS12_381g1, BLS12_381g2.
+    let by_group = |costs: [u64; 4]| match field {
+        Some("BN254g1") => costs[0],
+        Some("BN254g2") => costs[1],
+        Some("BLS12_381g1") => costs[2],
+        _ => costs[3],
+    };
+    match instruction.op.name {
+        "sha256" if version == 1 => OpCost::fixed(7),
+        "sha256" => OpCost::fixed(35),
+        "keccak256" if version == 1 => OpCost::fixed(26),
+        "keccak256" => OpCost::fixed(130),
+        "sha512_256" if version == 1 => OpCost::fixed(9),
+        "sha512_256" => OpCost::fixed(45),
+        "sha3_256" => OpCost::fixed(130),
+        "ed25519verify" | "ed25519verify_bare" => OpCost::fixed(1900),
+        "ecdsa_verify" if field == Some("Secp256r1") => OpCost::fixed(2500),
+        "ecdsa_verify" => OpCost::fixed(1700),
+        "ecdsa_pk_decompress" if field == Some("Secp256r1") => OpCost::fixed(2400),
+        "ecdsa_pk_decompress" => OpCost::fixed(650),
+        "ecdsa_pk_recover" => OpCost::fixed(2000),
+        "vrf_verify" => OpCost::fixed(5700),
+        "falcon_verify" => OpCost::fixed(1700),
+        "divmodw" => OpCost::fixed(20),
+        "sqrt" => OpCost::fixed(4),
+        "expw" => OpCost::fixed(10),
+        "bsqrt" => OpCost::fixed(40),
+        "b+" | "b-" => OpCost::fixed(10),
+        "b*" | "b/" | "b%" => OpCost::fixed(20),
+        "b|" | "b&" | "b^" => OpCost::fixed(6),
+        "b~" => OpCost::fixed(4),
+        "ec_add" => OpCost::fixed(by_group([125, 170, 205, 290])),
+        "ec_scalar_mul" => OpCost::fixed(by_group([1810, 3430, 2950, 6530])),
+        "ec_subgroup_check" => OpCost::fixed(by_group([20, 3100, 1850, 2340])),
+        "ec_map_to" => OpCost::fixed(by_group([630, 3300, 1950, 8150])),
+        "ec_pairing_check" => OpCost::dynamic(by_group([8000, 8000, 13000, 13000])),
+        "ec_multi_scalar_mul" => OpCost::dynamic(by_group([3600, 7200, 6500, 14850])),
+        "base64_decode" => OpCost::dynamic(1),
+        "json_ref" => OpCost::dynamic(25),
+        "sumhash512" => OpCost::dynamic(150),
+        "mimc" => OpCost::dynamic(10),
+        _ => OpCost::fixed(1),
+    }
+}
+
+/// A run of instructions that is only entered at its first and only left after its last.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct BasicBlock {
+    /// The program counter of the first instruction.
+    pub start: u64,
+    /// The program counter just past the last instruction.
+    pub end: u64,
+    /// The total cost of the instructions.
+    pub cost: u64,
+    /// Whether any instruction has a cost that depends on its inputs.
+    pub dynamic_cost: bool,
+    /// The blocks execution may continue with, by their start.
+    pub successors: Vec<u64>,
+    /// The subroutine the block calls before continuing, by its start.
+    pub calls: Option<u64>,
+}
+
+/// Whether a program checks the transaction fields a logic signature must constrain so that it
+/// cannot be used to rekey or close out the account it signs for.
+///
+/// A field is only reported as checked when the program compares its value in the transaction
+/// being evaluated, read with `txn` or with `gtxns` at `txn GroupIndex`, with `==` against
+/// `global ZeroAddress` or a zero address constant. Reads of other transactions in the group
+/// and other comparisons are not counted, and how the result of the comparison is used is not
+/// followed, so a `false` field needs review while a `true` one is only likely to be safe.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct LogicSigChecks {
+    pub rekey_to: bool,
+    pub close_remainder_to: bool,
+    pub asset_close_to: bool,
+}
+
+/// The kinds of app storage a key can refer to.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum StateKind {
+    Global,
+    Local,
+    Box,
+}
+
+/// A global or local state key or box name that a program uses as a constant.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct StateKeyReference {
+    pub kind: StateKind,
+    pub key: Vec<u8>,
+    /// The program counters of the instructions that access it.
+    pub pcs: Vec<u64>,
+}
+
+/// The results of [`analyze`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ProgramAnalysis {
+    pub version: u64,
+    pub blocks: Vec<BasicBlock>,
+    /// The highest cost of any path through the program, counting subroutine calls and pricing
+    /// dynamic opcodes at their base cost, or `None` if it has loops or recursion.
+    pub max_path_cost: Option<u64>,
+    pub logic_sig_checks: LogicSigChecks,
+    pub state_keys: Vec<StateKeyReference>,
+    /// The program counters of state and box accesses whose key is not a constant.
+    pub dynamic_state_accesses: Vec<u64>,
+}
+
+/// Analyzes a compiled program.
+pub fn analyze(program: &[u8]) -> Result<ProgramAnalysis, AlgoKitUtilsError> {
+    let disassembly = disassemble(program)?;
+    let version = disassembly.version;
+    let instructions = &disassembly.instructions;
+    let blocks = basic_blocks(instructions, version, program.len() as u64);
+    let max_path_cost = match blocks.first() {
+        Some(entry) => PathCosts::new(&blocks).max_cost(entry.start),
+        None => Some(0),
+    };
+
+    let logic_sig_checks = logic_sig_checks(instructions, &blocks);
+    let (state_keys, dynamic_state_accesses) = state_references(instructions, &blocks);
+    Ok(ProgramAnalysis {
+        version,
+        blocks,
+        max_path_cost,
+        logic_sig_checks,
+        state_keys,
+        dynamic_state_accesses,
+    })
+}
+
+/// A value compared by an `==` in a logic signature check.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum CheckOperand {
+    /// A field of the transaction being evaluated.
+    OwnField(&'static str),
+    ZeroAddress,
+}
+
+/// Finds the fields of the transaction being evaluated that are compared with the zero address.
+fn logic_sig_checks(instructions: &[Instruction], blocks: &[BasicBlock]) -> LogicSigChecks {
+    let starts = blocks
+        .iter()
+        .map(|block| block.start)
+        .collect::<HashSet<_>>();
+    let mut byte_constants: &[Vec<u8>] = &[];
+    let mut checks = LogicSigChecks::default();
+
+    for (index, instruction) in instructions.iter().enumerate() {
+        match (instruction.op.name, instruction.immediates.first()) {
+            ("bytecblock", Some(Immediate::ByteList(values))) => byte_constants = values,
+            ("==", _) => {
+                let Some((second, second_start)) = index
+                    .checked_sub(1)
+                    .and_then(|end| check_operand(instructions, end, byte_constants))
+                else {
+                    continue;
+                };
+                let Some((first, first_start)) = second_start
+                    .checked_sub(1)
+                    .and_then(|end| check_operand(instructions, end, byte_constants))
+                else {
+                    continue;
+                };
+                // Both operands must be pushed by the block that compares them.
+                if instructions[first_start + 1..=index]
+                    .iter()
+                    .any(|instruction| starts.contains(&instruction.pc))
+                {
+                    continue;
+                }
+                let field = match (first, second) {
+                    (CheckOperand::OwnField(field), CheckOperand::ZeroAddress)
+                    | (CheckOperand::ZeroAddress, CheckOperand::OwnField(field)) => field,
+                    _ => continue,
+                };
+                match field {
+                    "RekeyTo" => checks.rekey_to = true,
+                    "CloseRemainderTo" => checks.close_remainder_to = true,
+                    "AssetCloseTo" => checks.asset_close_to = true,
+                    _ => {}
+                }
+            }
+            _ => {}
+        }
+    }
+    checks
+}
+
+/// Decodes the check operand pushed by the instructions ending at `end`, returning it with the
+/// index of the first of those instructions.
+fn check_operand(
+    instructions: &[Instruction],
+    end: usize,
+    byte_constants: &[Vec<u8>],
+) -> Option<(CheckOperand, usize)> {
+    let instruction = &instructions[end];
+    let is_zero_address = |bytes: Option<&Vec<u8>>| {
+        bytes.is_some_and(|bytes| bytes.len() == 32 && bytes.iter().all(|byte| *byte == 0))
+    };
+    let bytec_index = match (instruction.op.name, instruction.immediates.first()) {
+        ("bytec", Some(Immediate::Uint(index))) => Some(*index as usize),
+        (name, _) => name
+            .strip_prefix("bytec_")
+            .and_then(|index| index.parse().ok()),
+    };
+    match (instruction.op.name, instruction.immediates.first()) {
+        ("txn", Some(Immediate::Field(field))) => Some((CheckOperand::OwnField(field), end)),
+        ("gtxns", Some(Immediate::Field(field))) => {
+            let group_index = &instructions[end.checked_sub(1)?];
+            (group_index.op.name == "txn"
+                && group_index.immediates.first() == Some(&Immediate::Field("GroupIndex")))
+            .then_some((CheckOperand::OwnField(field), end - 1))
+        }
+        ("global", Some(Immediate::Field("ZeroAddress"))) => Some((CheckOperand::ZeroAddress, end)),
+        ("pushbytes", Some(Immediate::Bytes(bytes))) if is_zero_address(Some(bytes)) => {
+            Some((CheckOperand::ZeroAddress, end))
+        }
+        _ if is_zero_address(bytec_index.and_then(|index| byte_constants.get(index))) => {
+            Some((CheckOperand::ZeroAddress, end))
+        }
+        _ => None,
+    }
+}
+
+fn branch_targets(instruction: &Instruction) -> Vec<u64> {
+    instruction
+        .immediates
+        .iter()
+        .flat_map(|immediate| match immediate {
+            Immediate::Label(target) => vec![*target],
+            Immediate::Labels(targets) => targets.clone(),
+            _ => Vec::new(),
+        })
+        .collect()
+}
+
+/// Whether execution never continues with the next instruction.
+fn ends_flow(instruction: &Instruction) -> bool {
+    matches!(instruction.op.name, "b" | "return" | "retsub" | "err")
+}
+
+fn basic_blocks(instructions: &[Instruction], version: u64, length: u64) -> Vec<BasicBlock> {
+    let mut leaders = BTreeSet::new();
+    if let Some(first) = instructions.first() {
+        leaders.insert(first.pc);
+    }
+    for instruction in instructions {
+        let targets = branch_targets(instruction);
+        if !targets.is_empty() || ends_flow(instruction) {
+            leaders.insert(instruction.pc + instruction.size);
+            leaders.extend(targets);
+        }
+    }
+    leaders.remove(&length);
+
+    let mut blocks = Vec::<BasicBlock>::new();
+    for instruction in instructions {
+        if leaders.contains(&instruction.pc) {
+            blocks.push(BasicBlock {
+                start: instruction.pc,
+                end: instruction.pc,
+                cost: 0,
+                dynamic_cost: false,
+                successors: Vec::new(),
+                calls: None,
+            });
+        }
+        let block = blocks
+            .last_mut()
+            .expect("the first instruction starts a block");
+        let cost = opcode_cost(instruction, version);
+        block.end = instruction.pc + instruction.size;
+        block.cost += cost.base;
+        block.dynamic_cost |= cost.dynamic;
+    }
+
+    for block in &mut blocks {
+        let last = instructions
+            .iter()
+            .rfind(|instruction| instruction.pc < block.end)
+            .expect("blocks are not empty");
+        let mut successors = Vec::new();
+        if last.op.name == "callsub" {
+            block.calls = branch_targets(last).first().copied();
+        } else {
+            successors.extend(branch_targets(last));
+        }
+        if !ends_flow(last) && block.end < length {
+            successors.push(block.end);
+        }
+        successors.retain(|target| *target < length);
+        successors.dedup();
+        block.successors = successors;
+    }
+    blocks
+}
+
+/// Finds the most expensive path from a block to the end of the program or its subroutine.
+struct PathCosts<'a> {
+    blocks: HashMap<u64, &'a BasicBlock>,
+    costs: HashMap<u64, Option<u64>>,
+    visiting: HashSet<u64>,
+}
+
+impl<'a> PathCosts<'a> {
+    fn new(blocks: &'a [BasicBlock]) -> Self {
+        Self {
+            blocks: blocks.iter().map(|block| (block.start, block)).collect(),
+            costs: HashMap::new(),
+            visiting: HashSet::new(),
+        }
+    }
+
+    fn max_cost(&mut self, start: u64) -> Option<u64> {
+        if let Some(cost) = self.costs.get(&start) {
+            return *cost;
+        }
+        // Reaching a block that is already on the path means there is a loop or recursion.
+        if !self.visiting.insert(start) {
+            return None;
+        }
+        let block = self.blocks[&start];
+        let mut cost = Some(block.cost);
+        if let Some(subroutine) = block.calls {
+            cost = cost.zip(self.max_cost(subroutine)).map(|(a, b)| a + b);
+        }
+        let mut successors = Some(0);
+        for successor in &block.successors {
+            successors = successors
+                .zip(self.max_cost(*successor))
+                .map(|(a, b)| a.max(b));
+        }
+        let cost = cost.zip(successors).map(|(a, b)| a + b);
+        self.visiting.remove(&start);
+        self.costs.insert(start, cost);
+        cost
+    }
+}
+
+/// The stack position of the key or box name for each opcode that accesses app storage, counted
+/// from the top.
+fn state_key_position(name: &str) -> Option<(StateKind, usize)> {
+    Some(match name {
+        "app_global_get" | "app_global_get_ex" | "app_global_del" => (StateKind::Global, 0),
+        "app_global_put" => (StateKind::Global, 1),
+        "app_local_get" | "app_local_get_ex" | "app_local_del" => (StateKind::Local, 0),
+        "app_local_put" => (StateKind::Local, 1),
+        "box_del" | "box_len" | "box_get" => (StateKind::Box, 0),
+        "box_create" | "box_put" | "box_resize" => (StateKind::Box, 1),
+        "box_extract" | "box_replace" => (StateKind::Box, 2),
+        "box_splice" => (StateKind::Box, 3),
+        _ => return None,
+    })
+}
+
+/// The number of values an opcode pops and pushes, for those that do not rearrange the stack.
+fn stack_effect(instruction: &Instruction) -> Option<(usize, usize)> {
+    let list_length = || match instruction.immediates.first() {
+        Some(Immediate::Uints(values)) => values.len(),
+        Some(Immediate::ByteList(values)) => values.len(),
+        Some(Immediate::Labels(labels)) => labels.len(),
+        _ => 0,
+    };
+    Some(match instruction.op.name {
+        "intcblock" | "bytecblock" | "b" | "err" | "itxn_begin" | "itxn_submit" | "itxn_next" => {
+            (0, 0)
+        }
+        "txn" | "global" | "gtxn" | "txna" | "gtxna" | "itxn" | "itxna" | "gitxn" | "gitxna"
+        | "load" | "intc" | "intc_0" | "intc_1" | "intc_2" | "intc_3" | "bytec" | "bytec_0"
+        | "bytec_1" | "bytec_2" | "bytec_3" | "arg" | "arg_0" | "arg_1" | "arg_2" | "arg_3"
+        | "pushint" | "pushbytes" | "gload" | "gaid" | "online_stake" | "frame_dig" => (0, 1),
+        "pushints" | "pushbytess" => (0, list_length()),
+        "pop" | "bnz" | "bz" | "assert" | "return" | "store" | "log" | "app_global_del"
+        | "itxn_field" | "switch" | "frame_bury" => (1, 0),
+        "match" => (list_length() + 1, 0),
+        "sha256" | "keccak256" | "sha512_256" | "sha3_256" | "!" | "len" | "itob" | "btoi"
+        | "~" | "sqrt" | "bitlen" | "bsqrt" | "b~" | "bzero" | "gtxns" | "gtxnsa" | "gloads"
+        | "gaids" | "loads" | "txnas" | "gtxnas" | "itxnas" | "gitxnas" | "args" | "substring"
+        | "extract" | "base64_decode" | "balance" | "min_balance" | "app_global_get"
+        | "box_del" | "ec_map_to" | "ec_subgroup_check" | "mimc" | "sumhash512" | "block" => (1, 1),
+        "asset_params_get"
+        | "app_params_get"
+        | "acct_params_get"
+        | "voter_params_get"
+        | "box_len"
+        | "box_get"
+        | "ecdsa_pk_decompress" => (1, 2),
+        "+"
+        | "-"
+        | "/"
+        | "*"
+        | "<"
+        | ">"
+        | "<="
+        | ">="
+        | "&&"
+        | "||"
+        | "=="
+        | "!="
+        | "%"
+        | "|"
+        | "&"
+        | "^"
+        | "concat"
+        | "getbit"
+        | "getbyte"
+        | "extract_uint16"
+        | "extract_uint32"
+        | "extract_uint64"
+        | "exp"
+        | "shl"
+        | "shr"
+        | "b+"
+        | "b-"
+        | "b/"
+        | "b*"
+        | "b<"
+        | "b>"
+        | "b<="
+        | "b>="
+        | "b=="
+        | "b!="
+        | "b%"
+        | "b|"
+        | "b&"
+        | "b^"
+        | "app_opted_in"
+        | "app_local_get"
+        | "gtxnsas"
+        | "gloadss"
+        | "replace2"
+        | "ec_add"
+        | "ec_scalar_mul"
+        | "ec_pairing_check"
+        | "ec_multi_scalar_mul"
+        | "box_create"
+        | "json_ref" => (2, 1),
+        "app_global_put" | "app_local_del" | "box_put" | "box_resize" | "stores" => (2, 0),
+        "app_global_get_ex" | "asset_holding_get" | "mulw" | "addw" | "expw" => (2, 2),
+        "substring3" | "extract3" | "replace3" | "setbit" | "setbyte" | "select" | "divw"
+        | "ed25519verify" | "ed25519verify_bare" | "falcon_verify" | "box_extract" => (3, 1),
+        "app_local_put" | "box_replace" => (3, 0),
+        "app_local_get_ex" | "vrf_verify" => (3, 2),
+        "box_splice" => (4, 0),
+        "divmodw" => (4, 4),
+        "ecdsa_pk_recover" => (4, 2),
+        "ecdsa_verify" => (5, 1),
+        _ => return None,
+    })
+}
+
+/// Finds the constant keys used by state and box accesses by following the constants on the
+/// stack through each basic block.
+fn state_references(
+    instructions: &[Instruction],
+    blocks: &[BasicBlock],
+) -> (Vec<StateKeyReference>, Vec<u64>) {
+    let starts = blocks
+        .iter()
+        .map(|block| block.start)
+        .collect::<HashSet<_>>();
+    let mut keys = BTreeMap::<(StateKind, Vec<u8>), Vec<u64>>::new();
+    let mut dynamic = Vec::new();
+    let mut byte_constants: &[Vec<u8>] = &[];
+    // The values known to be byte constants, with the top of the stack last. Values below those
+    // tracked are unknown.
+    let mut stack = Vec::<Option<Vec<u8>>>::new();
+
+    for instruction in instructions {
+        if starts.contains(&instruction.pc) {
+            stack.clear();
+        }
+        let name = instruction.op.name;
+        if let Some((kind, position)) = state_key_position(name) {
+            match stack
+                .len()
+                .checked_sub(position + 1)
+                .and_then(|index| stack[index].clone())
+            {
+                Some(key) => keys.entry((kind, key)).or_default().push(instruction.pc),
+                None => dynamic.push(instruction.pc),
+            }
+        }
+
+        let pushed = match (name, instruction.immediates.first()) {
+            ("bytecblock", Some(Immediate::ByteList(values))) => {
+                byte_constants = values;
+                None
+            }
+            ("pushbytes", Some(Immediate::Bytes(bytes))) => Some(vec![Some(bytes.clone())]),
+            ("pushbytess", Some(Immediate::ByteList(values))) => {
+                Some(values.iter().cloned().map(Some).collect())
+            }
+            ("bytec", Some(Immediate::Uint(index))) => {
+                Some(vec![byte_constants.get(*index as usize).cloned()])
+            }
+            _ => match name.strip_prefix("bytec_") {
+                Some(index) => {
+                    let index = index.parse::<usize>().unwrap_or(usize::MAX);
+                    Some(vec![byte_constants.get(index).cloned()])
+                }
+                None => None,
+            },
+        };
+        if let Some(values) = pushed {
+            stack.extend(values);
+            continue;
+        }
+
+        let depth = match instruction.immediates.first() {
+            Some(Immediate::Uint(depth)) => *depth as usize,
+            _ => 0,
+        };
+        let from_top = |stack: &Vec<Option<Vec<u8>>>, depth: usize| {
+            stack
+                .len()
+                .checked_sub(depth + 1)
+                .and_then(|index| stack[index].clone())
+        };
+        match name {
+            "dup" => stack.push(from_top(&stack, 0)),
+            "dup2" => {
+                let (second, top) = (from_top(&stack, 1), from_top(&stack, 0));
+                stack.extend([second, top]);
+            }
+            "dig" => stack.push(from_top(&stack, depth)),
+            "dupn" => {
+                let top = from_top(&stack, 0);
+                stack.extend(std::iter::repeat_n(top, depth));
+            }
+            "popn" => stack.truncate(stack.len().saturating_sub(depth)),
+            "swap" | "cover" | "uncover" | "bury" => {
+                let depth = if name == "swap" { 1 } else { depth };
+                if stack.len() <= depth {
+                    // The values involved are not all tracked.
+                    stack.clear();
+                    continue;
+                }
+                let top = stack.len() - 1;
+                match name {
+                    "swap" => stack.swap(top, top - 1),
+                    "cover" => {
+                        let value = stack.remove(top);
+                        stack.insert(top - depth, value);
+                    }
+                    "uncover" => {
+                        let value = stack.remove(top - depth);
+                        stack.push(value);
+                    }
+                    _ => {
+                        let value = stack.pop().flatten();
+                        stack[top - depth] = value;
+                    }
+                }
+            }
+            _ => match stack_effect(instruction) {
+                Some((pops, pushes)) => {
+                    stack.truncate(stack.len().saturating_sub(pops));
+                    // Values below the tracked ones become reachable again, so keep the
+                    // unknowns that are pushed in their place.
+                    stack.extend(std::iter::repeat_n(None, pushes));
+                }
+                None => stack.clear(),
+            },
+        }
+    }
+
+    let keys = keys
+        .into_iter()
+        .map(|((kind, key), pcs)| StateKeyReference { kind, key, pcs })
+        .collect();
+    (keys, dynamic)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::teal::assemble;
+    use algokit_abi::Arc56Contract;
+    use algokit_test_artifacts::zero_coupon_bond;
+    use base64::{Engine, engine::general_purpose::STANDARD as BASE64};
+
+    #[test]
+    fn test_prices_blocks_and_the_most_expensive_path() {
+        let source = r#"#pragma version 8
+txn NumAppArgs
+bz cheap
+txna ApplicationArgs 0
+callsub hash
+pop
+int 1
+return
+cheap:
+int 1
+return
+hash:
+sha256
+keccak256
+retsub
+"#;
+        let analysis = analyze(&assemble(source).unwrap().program).unwrap();
+        let summary = analysis
+            .blocks
+            .iter()
+            .map(|block| (block.cost, block.successors.len(), block.calls.is_some()))
+            .collect::<Vec<_>>();
+        assert_eq!(
+            summary,
+            vec![
+                (3, 2, false),
+                (2, 1, true),
+                (3, 0, false),
+                (2, 0, false),
+                (166, 0, false)
+            ]
+        );
+        assert_eq!(analysis.max_path_cost, Some(3 + 2 + 166 + 3));
+
+        let looping = "#pragma version 8\nloop:\nint 1\nbnz loop\nint 1";
+        let analysis = analyze(&assemble(looping).unwrap().program).unwrap();
+        assert_eq!(analysis.max_path_cost, None);
+    }
+
+    fn logic_sig_checks_of(source: &str) -> LogicSigChecks {
+        analyze(&assemble(source).unwrap().program)
+            .unwrap()
+            .logic_sig_checks
+    }
+
+    #[test]
+    fn test_finds_logic_sig_checks() {
+        let source = r#"#pragma version 8
+bytecblock 0x0000000000000000000000000000000000000000000000000000000000000000
+txn RekeyTo
+global ZeroAddress
+==
+global ZeroAddress
+txn GroupIndex
+gtxns CloseRemainderTo
+==
+&&
+txn AssetCloseTo
+bytec_0
+==
+&&
+"#;
+        assert_eq!(
+            logic_sig_checks_of(source),
+            LogicSigChecks {
+                rekey_to: true,
+                close_remainder_to: true,
+                asset_close_to: true,
+            }
+        );
+    }
+
+    #[test]
+    fn test_ignores_reads_that_are_not_zero_address_checks() {
+        let read_only = "#pragma version 8\ntxn RekeyTo\npop\nint 1";
+        assert_eq!(logic_sig_checks_of(read_only), LogicSigChecks::default());
+
+        // The logic signature may be evaluated for any transaction of the group.
+        let other_transaction = r#"#pragma version 8
+gtxn 0 CloseRemainderTo
+global ZeroAddress
+==
+txn RekeyTo
+txn Sender
+==
+||
+"#;
+        assert_eq!(
+            logic_sig_checks_of(other_transaction),
+            LogicSigChecks::default()
+        );
+    }
+
+    #[test]
+    fn test_lists_constant_state_keys() {
+        let source = r#"#pragma version 10
+bytecblock "counter" "owner"
+bytec_0
+bytec_0
+app_global_get
+int 1
++
+app_global_put
+txn Sender
+pushbytes "balance"
+app_local_get
+pop
+pushbytess "a" "b"
+swap
+int 8
+box_create
+pop
+pop
+txn ApplicationArgs 1
+box_get
+"#;
+        let analysis = analyze(&assemble(source).unwrap().program).unwrap();
+        let keys = analysis
+            .state_keys
+            .iter()
+            .map(|reference| {
+                (
+                    reference.kind,
+                    reference.key.as_slice(),
+                    reference.pcs.len(),
+                )
+            })
+            .collect::<Vec<_>>();
+        assert_eq!(
+            keys,
+            vec![
+                (StateKind::Global, b"counter".as_slice(), 2),
+                (StateKind::Local, b"balance".as_slice(), 1),
+                (StateKind::Box, b"a".as_slice(), 1),
+            ]
+        );
+        assert_eq!(analysis.dynamic_state_accesses.len(), 1);
+    }
+
+    #[test]
+    fn test_analyzes_compiled_contract() {
+        let contract = Arc56Contract::from_json(zero_coupon_bond::APPLICATION_ARC56).unwrap();
+        let program = BASE64
+            .decode(&contract.byte_code.as_ref().unwrap().approval)
+            .unwrap();
+        let analysis = analyze(&program).unwrap();
+        let covered = analysis
+            .blocks
+            .iter()
+            .map(|block| block.end - block.start)
+            .sum::<u64>();
+        assert_eq!(covered, program.len() as u64 - 1);
+        // The contract loops while it has opcode budget to spend.
+        assert_eq!(analysis.max_path_cost, None);
+        assert!(!analysis.state_keys.is_empty());
+    }
+}
@@ -0,0 +1,930 @@
+//! Compilation of TEAL source into program bytes.
+
+use super::opcodes::{FieldGroup, ImmediateKind, MAX_PROGRAM_VERSION, OpSpec, op_spec_by_name};
+use crate::error::AlgoKitUtilsError;
+use algokit_abi::{PcOffsetMethod, ProgramSourceInfo, SourceInfo};
+use algokit_transact::Address;
+use base64::{
+    Engine,
+    engine::general_purpose::{STANDARD as BASE64, URL_SAFE as BASE64_URL},
+};
+use sha2::{Digest, Sha512_256};
+use std::collections::{BTreeMap, HashMap};
+use std::str::FromStr;
+
+/// The version assembled when the source has no `#pragma version` and no target is given.
+const DEFAULT_VERSION: u64 = 1;
+/// The first program version in which branches may jump backwards.
+const BACK_BRANCH_VERSION: u64 = 4;
+/// The first program version in which constants used once are pushed rather than put in a
+/// constant block.
+const OPTIMIZE_CONSTANTS_VERSION: u64 = 4;
+
+/// Named values accepted by `int` in place of a number.
+const NAMED_INTS: &[(&str, u64)] = &[
+    ("NoOp", 0),
+    ("OptIn", 1),
+    ("CloseOut", 2),
+    ("ClearState", 3),
+    ("UpdateApplication", 4),
+    ("DeleteApplication", 5),
+    ("unknown", 0),
+    ("pay", 1),
+    ("keyreg", 2),
+    ("acfg", 3),
+    ("axfer", 4),
+    ("afrz", 5),
+    ("appl", 6),
+];
+
+/// The value substituted for a `TMPL_` template variable.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum TemplateValue {
+    Uint(u64),
+    Bytes(Vec<u8>),
+}
+
+/// A compiled program and the TEAL line each of its instructions came from.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Assembly {
+    pub version: u64,
+    pub program: Vec<u8>,
+    /// The program counter of each instruction and its 1-based TEAL line.
+    lines: BTreeMap<u64, u64>,
+}
+
+impl Assembly {
+    /// The 1-based TEAL line of the instruction that the byte at a program counter belongs to.
+    pub fn line(&self, pc: u64) -> Option<u64> {
+        if pc >= self.program.len() as u64 {
+            return None;
+        }
+        self.lines.range(..=pc).next_back().map(|(_, line)| *line)
+    }
+
+    /// The program counters of each TEAL line, in the form of an ARC-56 `sourceInfo` entry.
+    pub fn source_info(&self) -> ProgramSourceInfo {
+        let mut pcs = BTreeMap::<u64, Vec<u32>>::new();
+        for (pc, line) in &self.lines {
+            pcs.entry(*line).or_default().push(*pc as u32);
+        }
+        ProgramSourceInfo {
+            pc_offset_method: PcOffsetMethod::None,
+            source_info: pcs
+                .into_iter()
+                .map(|(line, pc)| SourceInfo {
+                    pc,
+                    error_message: None,
+                    source: None,
+                    teal: Some(line as u32),
+                })
+                .collect(),
+        }
+    }
+}
+
+/// Compiles TEAL source without a node.
+///
+/// `int`, `byte`, `addr` and `method` constants are collected into constant blocks at the start of
+/// the program unless the source declares its own `intcblock` or `bytecblock`.
+#[derive(Debug, Clone, Default)]
+pub struct Assembler {
+    version: Option<u64>,
+    templates: HashMap<String, TemplateValue>,
+}
+
+/// Compiles TEAL source with the default [`Assembler`].
+pub fn assemble(source: &str) -> Result<Assembly, AlgoKitUtilsError> {
+    Assembler::new().assemble(source)
+}
+
+impl Assembler {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Assembles for a program version, which the source's `#pragma version` must match if it
+    /// has one.
+    pub fn with_version(mut self, version: u64) -> Self {
+        self.version = Some(version);
+        self
+    }
+
+    /// Substitutes a value for the template variable `TMPL_{name}`.
+    pub fn with_template_variable(mut self, name: &str, value: TemplateValue) -> Self {
+        self.templates.insert(format!("TMPL_{}", name), value);
+        self
+    }
+
+    pub fn assemble(&self, source: &str) -> Result<Assembly, AlgoKitUtilsError> {
+        let (version, items) = self.parse(source)?;
+        Encoder::new(version, &items)?.encode(&items)
+    }
+
+    fn parse(&self, source: &str) -> Result<(u64, Vec<(u64, Item)>), AlgoKitUtilsError> {
+        let mut declared = None;
+        let mut lines = Vec::new();
+        for (number, text) in (1u64..).zip(source.lines()) {
+            let tokens = tokenize(text, number)?;
+            match tokens.first().map(String::as_str) {
+                None => continue,
+                Some("#pragma") => match tokens.get(1).map(String::as_str) {
+                    Some("version") => {
+                        let mut args = Tokens::new(&tokens[2..], number, &self.templates);
+                        let version = args.uint()?;
+                        args.finish()?;
+                        if !lines.is_empty() || declared.is_some() {
+                            return Err(syntax(number, "#pragma version must come first"));
+                        }
+                        declared = Some(version);
+                    }
+                    Some("typetrack") => {}
+                    other => {
+                        return Err(syntax(
+                            number,
+                            format!("unknown pragma {}", other.unwrap_or_default()),
+                        ));
+                    }
+                },
+                Some(_) => lines.push((number, tokens)),
+            }
+        }
+
+        let version = match (declared, self.version) {
+            (Some(declared), Some(target)) if declared != target => {
+                return Err(syntax(
+                    1,
+                    format!(
+                        "program declares version {} but version {} was requested",
+                        declared, target
+                    ),
+                ));
+            }
+            (declared, target) => declared.or(target).unwrap_or(DEFAULT_VERSION),
+        };
+        if version == 0 || version > MAX_PROGRAM_VERSION {
+            return Err(syntax(
+                1,
+                format!("unsupported program version {}", version),
+            ));
+        }
+
+        let mut items = Vec::new();
+        for (number, tokens) in lines {
+            let mut tokens = tokens.as_slice();
+            if let Some(label) = tokens[0].strip_suffix(':') {
+                items.push((number, Item::Label(label.to_string())));
+                tokens = &tokens[1..];
+                if tokens.is_empty() {
+                    continue;
+                }
+            }
+            let item = self.parse_op(version, number, &tokens[0], &tokens[1..])?;
+            items.push((number, item));
+        }
+        Ok((version, items))
+    }
+
+    fn parse_op(
+        &self,
+        version: u64,
+        number: u64,
+        name: &str,
+        args: &[String],
+    ) -> Result<Item, AlgoKitUtilsError> {
+        let mut args = Tokens::new(args, number, &self.templates);
+        let pseudo = match name {
+            "int" => Some(Item::Int(args.uint()?)),
+            "byte" => Some(Item::Bytes(args.bytes()?)),
+            "addr" => Some(Item::Bytes(args.address()?)),
+            "method" => {
+                let signature = parse_bytes_token(args.next()?, number)?;
+                Some(Item::Bytes(Sha512_256::digest(&signature)[..4].to_vec()))
+            }
+            _ => None,
+        };
+        if let Some(item) = pseudo {
+            args.finish()?;
+            return Ok(item);
+        }
+
+        let name = match (name, args.remaining()) {
+            ("txn" | "gtxns" | "itxn", 2) | ("gtxn" | "gitxn", 3) => format!("{}a", name),
+            ("intc" | "bytec" | "arg", 1) => match args.peek_uint() {
+                Some(index) if index < 4 => {
+                    args.next()?;
+                    format!("{}_{}", name, index)
+                }
+                _ => name.to_string(),
+            },
+            _ => name.to_string(),
+        };
+        let op = op_spec_by_name(&name, version).ok_or_else(|| {
+            match op_spec_by_name(&name, MAX_PROGRAM_VERSION) {
+                Some(op) => syntax(
+                    number,
+                    format!(
+                        "{} is not available in version {}, only from {}",
+                        name, version, op.version
+                    ),
+                ),
+                None => syntax(number, format!("unknown opcode {}", name)),
+            }
+        })?;
+
+        let mut operands = Vec::with_capacity(op.immediates.len());
+        for kind in op.immediates {
+            let operand = match kind {
+                ImmediateKind::Uint8 => Operand::Uint(args.uint8()?.into()),
+                ImmediateKind::Int8 => {
+                    let token = args.next()?;
+                    let value = token
+                        .parse::<i8>()
+                        .map_err(|_| syntax(number, format!("{} is not a signed byte", token)))?;
+                    Operand::Int(value)
+                }
+                ImmediateKind::Varuint => Operand::Uint(args.uint()?),
+                ImmediateKind::Bytes => Operand::Bytes(args.bytes()?),
+                ImmediateKind::Label => Operand::Labels(vec![args.next()?.to_string()]),
+                ImmediateKind::Labels => {
+                    let mut labels = Vec::new();
+                    while args.remaining() > 0 {
+                        labels.push(args.next()?.to_string());
+                    }
+                    Operand::Labels(labels)
+                }
+                ImmediateKind::Varuints => {
+                    let mut values = Vec::new();
+                    while args.remaining() > 0 {
+                        values.push(args.uint()?);
+                    }
+                    Operand::Uints(values)
+                }
+                ImmediateKind::ByteList => {
+                    let mut values = Vec::new();
+                    while args.remaining() > 0 {
+                        values.push(args.bytes()?);
+                    }
+                    Operand::ByteList(values)
+                }
+                ImmediateKind::Field(group) => Operand::Uint(args.field(*group, version)?.into()),
+            };
+            operands.push(operand);
+        }
+        args.finish()?;
+        Ok(Item::Op(op, operands))
+    }
+}
+
+fn syntax(line: u64, err_msg: impl Into<String>) -> AlgoKitUtilsError {
+    AlgoKitUtilsError::TealSyntax {
+        line,
+        err_msg: err_msg.into(),
+    }
+}
+
+/// A parsed line of TEAL.
+enum Item {
+    Label(String),
+    Op(&'static OpSpec, Vec<Operand>),
+    /// An `int` pseudo-op.
+    Int(u64),
+    /// A `byte`, `addr` or `method` pseudo-op.
+    Bytes(Vec<u8>),
+}
+
+enum Operand {
+    Uint(u64),
+    Int(i8),
+    Bytes(Vec<u8>),
+    Labels(Vec<String>),
+    Uints(Vec<u64>),
+    ByteList(Vec<Vec<u8>>),
+}
+
+/// Splits a line into tokens, keeping quoted strings whole and dropping comments.
+fn tokenize(line: &str, number: u64) -> Result<Vec<String>, AlgoKitUtilsError> {
+    let mut tokens = Vec::new();
+    let mut chars = line.char_indices().peekable();
+    while let Some((start, c)) = chars.next() {
+        if c.is_whitespace() {
+            continue;
+        }
+        if line[start..].starts_with("//") {
+            break;
+        }
+        let mut end = start + c.len_utf8();
+        let mut quoted = c == '"';
+        let mut escaped = false;
+        while let Some(&(index, c)) = chars.peek() {
+            if quoted {
+                quoted = escaped || c != '"';
+                escaped = !escaped && c == '\\';
+            } else if c.is_whitespace() || line[index..].starts_with("//") {
+                break;
+            } else if c == '"' {
+                quoted = true;
+            }
+            end = index + c.len_utf8();
+            chars.next();
+        }
+        if quoted {
+            return Err(syntax(number, "unterminated string"));
+        }
+        tokens.push(line[start..end].to_string());
+    }
+    Ok(tokens)
+}
+
+/// The arguments of an opcode, consumed as its immediates are parsed.
+struct Tokens<'a> {
+    tokens: &'a [String],
+    position: usize,
+    line: u64,
+    templates: &'a HashMap<String, TemplateValue>,
+}
+
+impl<'a> Tokens<'a> {
+    fn new(tokens: &'a [String], line: u64, templates: &'a HashMap<String, TemplateValue>) -> Self {
+        Self {
+            tokens,
+            position: 0,
+            line,
+            templates,
+        }
+    }
+
+    fn remaining(&self) -> usize {
+        self.tokens.len() - self.position
+    }
+
+    fn next(&mut self) -> Result<&'a str, AlgoKitUtilsError> {
+        let token = self
+            .tokens
+            .get(self.position)
+            .ok_or_else(|| syntax(self.line, "missing argument"))?;
+        self.position += 1;
+        Ok(token)
+    }
+
+    fn peek_uint(&self) -> Option<u64> {
+        parse_uint(self.tokens.get(self.position)?)
+    }
+
+    fn finish(&self) -> Result<(), AlgoKitUtilsError> {
+        match self.tokens.get(self.position) {
+            Some(token) => Err(syntax(self.line, format!("unexpected argument {}", token))),
+            None => Ok(()),
+        }
+    }
+
+    fn template(&self, token: &str) -> Result<Option<&'a TemplateValue>, AlgoKitUtilsError> {
+        if !token.starts_with("TMPL_") {
+            return Ok(None);
+        }
+        self.templates.get(token).map(Some).ok_or_else(|| {
+            syntax(
+                self.line,
+                format!("no value for template variable {}", token),
+            )
+        })
+    }
+
+    fn uint(&mut self) -> Result<u64, AlgoKitUtilsError> {
+        let token = self.next()?;
+        match self.template(token)? {
+            Some(TemplateValue::Uint(value)) => Ok(*value),
+            Some(TemplateValue::Bytes(_)) => Err(syntax(
+                self.line,
+                format!("template variable {} is not an integer", token),
+            )),
+            None => parse_uint(token)
+                .or_else(|| {
+                    NAMED_INTS
+                        .iter()
+                        .find(|(name, _)| *name == token)
+                        .map(|(_, value)| *value)
+                })
+                .ok_or_else(|| syntax(self.line, format!("{} is not an integer", token))),
+        }
+    }
+
+    fn uint8(&mut self) -> Result<u8, AlgoKitUtilsError> {
+        let value = self.uint()?;
+        u8::try_from(value)
+            .map_err(|_| syntax(self.line, format!("{} does not fit in a byte", value)))
+    }
+
+    fn bytes(&mut self) -> Result<Vec<u8>, AlgoKitUtilsError> {
+        let token = self.next()?;
+        if let Some(value) = self.template(token)? {
+            return match value {
+                TemplateValue::Bytes(bytes) => Ok(bytes.clone()),
+                TemplateValue::Uint(_) => Err(syntax(
+                    self.line,
+                    format!("template variable {} is not a byte array", token),
+                )),
+            };
+        }
+        match token {
+            "base64" | "b64" | "base32" | "b32" => {
+                let encoded = self.next()?;
+                parse_bytes_token(&format!("{}({})", token, encoded), self.line)
+            }
+            _ => parse_bytes_token(token, self.line),
+        }
+    }
+
+    fn address(&mut self) -> Result<Vec<u8>, AlgoKitUtilsError> {
+        let token = self.next()?;
+        match self.template(token)? {
+            Some(TemplateValue::Bytes(bytes)) if bytes.len() == 32 => Ok(bytes.clone()),
+            Some(_) => Err(syntax(
+                self.line,
+                format!("template variable {} is not a 32 byte address", token),
+            )),
+            None => Address::from_str(token)
+                .map(|address| address.as_bytes().to_vec())
+                .map_err(|error| {
+                    syntax(self.line, format!("invalid address {}: {}", token, error))
+                }),
+        }
+    }
+
+    fn field(&mut self, group: FieldGroup, version: u64) -> Result<u8, AlgoKitUtilsError> {
+        let name = self.next()?;
+        let (index, field) = group
+            .field_by_name(name)
+            .ok_or_else(|| syntax(self.line, format!("unknown field {}", name)))?;
+        if field.version > version {
+            return Err(syntax(
+                self.line,
+                format!("{} is not available in version {}", name, version),
+            ));
+        }
+        Ok(index)
+    }
+}
+
+/// Parses an unsigned integer in decimal, hex (`0x`), octal (`0o` or a leading `0`) or binary
+/// (`0b`).
+fn parse_uint(token: &str) -> Option<u64> {
+    let (digits, radix) = if let Some(hex) = token.strip_prefix("0x").or(token.strip_prefix("0X")) {
+        (hex, 16)
+    } else if let Some(octal) = token.strip_prefix("0o") {
+        (octal, 8)
+    } else if let Some(binary) = token.strip_prefix("0b") {
+        (binary, 2)
+    } else if token.len() > 1 && token.starts_with('0') {
+        (&token[1..], 8)
+    } else {
+        (token, 10)
+    };
+    if digits.is_empty() || !digits.chars().all(|c| c.is_digit(radix)) {
+        return None;
+    }
+    u64::from_str_radix(digits, radix).ok()
+}
+
+/// Parses a byte array written as hex, a quoted string, or `base64(...)` / `base32(...)`.
+fn parse_bytes_token(token: &str, line: u64) -> Result<Vec<u8>, AlgoKitUtilsError> {
+    let invalid = || syntax(line, format!("{} is not a byte array", token));
+    if let Some(hex) = token.strip_prefix("0x") {
+        if hex.len() % 2 != 0 {
+            return Err(invalid());
+        }
+        return (0..hex.len())
+            .step_by(2)
+            .map(|index| u8::from_str_radix(&hex[index..index + 2], 16).map_err(|_| invalid()))
+            .collect();
+    }
+    if let Some(quoted) = token
+        .strip_prefix('"')
+        .and_then(|rest| rest.strip_suffix('"'))
+    {
+        return parse_string(quoted).ok_or_else(invalid);
+    }
+    let (encoding, encoded) = token
+        .split_once('(')
+        .and_then(|(encoding, rest)| Some((encoding, rest.strip_suffix(')')?)))
+        .ok_or_else(invalid)?;
+    match encoding {
+        "base64" | "b64" => BASE64
+            .decode(encoded)
+            .or_else(|_| BASE64_URL.decode(encoded))
+            .map_err(|_| invalid()),
+        "base32" | "b32" => base32::decode(
+            base32::Alphabet::Rfc4648 { padding: false },
+            encoded.trim_end_matches('='),
+        )
+        .ok_or_else(invalid),
+        _ => Err(invalid()),
+    }
+}
+
+/// Decodes the escapes of a quoted string.
+fn parse_string(quoted: &str) -> Option<Vec<u8>> {
+    let mut bytes = Vec::new();
+    let mut chars = quoted.chars();
+    while let Some(c) = chars.next() {
+        if c != '\\' {
+            let mut buffer = [0; 4];
+            bytes.extend(c.encode_utf8(&mut buffer).as_bytes());
+            continue;
+        }
+        let escaped = match chars.next()? {
+            'n' => b'\n',
+            'r' => b'\r',
+            't' => b'\t',
+            '0' => 0,
+            '\\' => b'\\',
+            '"' => b'"',
+            'x' => {
+                let hex = [chars.next()?, chars.next()?].iter().collect::<String>();
+                u8::from_str_radix(&hex, 16).ok()?
+            }
+            _ => return None,
+        };
+        bytes.push(escaped);
+    }
+    Some(bytes)
+}
+
+/// Lays out the bytes of a program.
+struct Encoder {
+    version: u64,
+    program: Vec<u8>,
+    lines: BTreeMap<u64, u64>,
+    /// The constants `int` pseudo-ops refer to, and whether they are in a constant block.
+    ints: Vec<u64>,
+    bytes: Vec<Vec<u8>>,
+    /// Labels and the program counters they mark.
+    labels: HashMap<String, u64>,
+    /// Branch offsets to fill in: where the offset goes, the end of its instruction, the label
+    /// and the line.
+    fixups: Vec<(usize, u64, String, u64)>,
+}
+
+impl Encoder {
+    /// Chooses the constant blocks and writes them if the program does not declare its own.
+    fn new(version: u64, items: &[(u64, Item)]) -> Result<Self, AlgoKitUtilsError> {
+        let mut encoder = Self {
+            version,
+            program: Vec::new(),
+            lines: BTreeMap::new(),
+            ints: Vec::new(),
+            bytes: Vec::new(),
+            labels: HashMap::new(),
+            fixups: Vec::new(),
+        };
+        write_uvarint(&mut encoder.program, version);
+
+        let declared = |name: &str| {
+            items.iter().find_map(|(_, item)| match item {
+                Item::Op(op, operands) if op.name == name => operands.first(),
+                _ => None,
+            })
+        };
+        match declared("intcblock") {
+            Some(Operand::Uints(values)) => encoder.ints = values.clone(),
+            _ => {
+                let ints = items.iter().filter_map(|(_, item)| match item {
+                    Item::Int(value) => Some(*value),
+                    _ => None,
+                });
+                encoder.ints = encoder.constant_block(ints);
+                if !encoder.ints.is_empty() {
+                    encoder.program.push(0x20);
+                    write_uvarint(&mut encoder.program, encoder.ints.len() as u64);
+                    for value in encoder.ints.clone() {
+                        write_uvarint(&mut encoder.program, value);
+                    }
+                }
+            }
+        }
+        match declared("bytecblock") {
+            Some(Operand::ByteList(values)) => encoder.bytes = values.clone(),
+            _ => {
+                let bytes = items.iter().filter_map(|(_, item)| match item {
+                    Item::Bytes(value) => Some(value.clone()),
+                    _ => None,
+                });
+                encoder.bytes = encoder.constant_block(bytes);
+                if !encoder.bytes.is_empty() {
+                    encoder.program.push(0x26);
+                    write_uvarint(&mut encoder.program, encoder.bytes.len() as u64);
+                    for value in encoder.bytes.clone() {
+                        write_bytes(&mut encoder.program, &value);
+                    }
+                }
+            }
+        }
+        Ok(encoder)
+    }
+
+    /// The constants to put in a block: all of them in order of first use for early versions,
+    /// otherwise those used more than once, most used first.
+    fn constant_block<T: Clone + Eq + std::hash::Hash>(
+        &self,
+        values: impl Iterator<Item = T>,
+    ) -> Vec<T> {
+        let mut counts = Vec::<(T, usize)>::new();
+        let mut indexes = HashMap::new();
+        for value in values {
+            let index = *indexes.entry(value.clone()).or_insert_with(|| {
+                counts.push((value, 0));
+                counts.len() - 1
+            });
+            counts[index].1 += 1;
+        }
+        if self.version >= OPTIMIZE_CONSTANTS_VERSION {
+            counts.retain(|(_, count)| *count > 1);
+            counts.sort_by(|a, b| b.1.cmp(&a.1));
+        }
+        counts.into_iter().map(|(value, _)| value).collect()
+    }
+
+    fn encode(mut self, items: &[(u64, Item)]) -> Result<Assembly, AlgoKitUtilsError> {
+        for (line, item) in items {
+            let start = self.program.len();
+            match item {
+                Item::Label(label) => {
+                    if self.labels.insert(label.clone(), start as u64).is_some() {
+                        return Err(syntax(*line, format!("duplicate label {}", label)));
+                    }
+                    continue;
+                }
+                Item::Int(value) => match self.ints.iter().position(|int| int == value) {
+                    Some(index) => self.constant_reference(0x21, 0x22, index),
+                    None => self.push(0x81, *line, |program| write_uvarint(program, *value))?,
+                },
+                Item::Bytes(value) => match self.bytes.iter().position(|bytes| bytes == value) {
+                    Some(index) => self.constant_reference(0x27, 0x28, index),
+                    None => self.push(0x80, *line, |program| write_bytes(program, value))?,
+                },
+                Item::Op(op, operands) => self.op(*line, op, operands)?,
+            }
+            if self.program.len() > start {
+                self.lines.insert(start as u64, *line);
+            }
+        }
+
+        for (position, end, label, line) in std::mem::take(&mut self.fixups) {
+            let target = *self
+                .labels
+                .get(&label)
+                .ok_or_else(|| syntax(line, format!("reference to undefined label {}", label)))?;
+            let offset = target as i64 - end as i64;
+            if offset < 0 && self.version < BACK_BRANCH_VERSION {
+                return Err(syntax(
+                    line,
+                    format!(
+                        "label {} is behind the branch, which needs version 4",
+                        label
+                    ),
+                ));
+            }
+            let offset = i16::try_from(offset)
+                .map_err(|_| syntax(line, format!("label {} is too far away", label)))?;
+            self.program[position..position + 2].copy_from_slice(&offset.to_be_bytes());
+        }
+
+        Ok(Assembly {
+            version: self.version,
+            program: self.program,
+            lines: self.lines,
+        })
+    }
+
+    fn constant_reference(&mut self, opcode: u8, first_shortcut: u8, index: usize) {
+        if index < 4 {
+            self.program.push(first_shortcut + index as u8);
+        } else {
+            self.program.push(opcode);
+            self.program.push(index as u8);
+        }
+    }
+
+    fn push(
+        &mut self,
+        opcode: u8,
+        line: u64,
+        write: impl FnOnce(&mut Vec<u8>),
+    ) -> Result<(), AlgoKitUtilsError> {
+        if self.version < 3 {
+            return Err(syntax(
+                line,
+                "constants outside the constant blocks need version 3",
+            ));
+        }
+        self.program.push(opcode);
+        write(&mut self.program);
+        Ok(())
+    }
+
+    fn op(
+        &mut self,
+        line: u64,
+        op: &OpSpec,
+        operands: &[Operand],
+    ) -> Result<(), AlgoKitUtilsError> {
+        self.program.push(op.opcode);
+        let mut branches = Vec::new();
+        for (kind, operand) in op.immediates.iter().zip(operands) {
+            match operand {
+                Operand::Uint(value) => match kind {
+                    ImmediateKind::Varuint => write_uvarint(&mut self.program, *value),
+                    _ => self.program.push(*value as u8),
+                },
+                Operand::Int(value) => self.program.push(*value as u8),
+                Operand::Bytes(value) => write_bytes(&mut self.program, value),
+                Operand::Labels(labels) => {
+                    if *kind == ImmediateKind::Labels {
+                        let count = u8::try_from(labels.len()).map_err(|_| {
+                            syntax(line, format!("{} has too many labels", op.name))
+                        })?;
+                        self.program.push(count);
+                    }
+                    for label in labels {
+                        branches.push((self.program.len(), label.clone()));
+                        self.program.extend([0, 0]);
+                    }
+                }
+                Operand::Uints(values) => {
+                    write_uvarint(&mut self.program, values.len() as u64);
+                    for value in values {
+                        write_uvarint(&mut self.program, *value);
+                    }
+                }
+                Operand::ByteList(values) => {
+                    write_uvarint(&mut self.program, values.len() as u64);
+                    for value in values {
+                        write_bytes(&mut self.program, value);
+                    }
+                }
+            }
+        }
+        let end = self.program.len() as u64;
+        self.fixups.extend(
+            branches
+                .into_iter()
+                .map(|(position, label)| (position, end, label, line)),
+        );
+        Ok(())
+    }
+}
+
+pub(crate) fn write_uvarint(program: &mut Vec<u8>, mut value: u64) {
+    while value >= 0x80 {
+        program.push(value as u8 | 0x80);
+        value >>= 7;
+    }
+    program.push(value as u8);
+}
+
+pub(crate) fn write_bytes(program: &mut Vec<u8>, bytes: &[u8]) {
+    write_uvarint(program, bytes.len() as u64);
+    program.extend(bytes);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::teal::disassemble;
+    use algokit_abi::Arc56Contract;
+    use algokit_test_artifacts::{sandbox, void_return_test, zero_coupon_bond};
+
+    #[test]
+    fn test_assembles_compiled_contracts() {
+        let assembler = Assembler::new()
+            .with_template_variable("UPDATABLE", TemplateValue::Uint(0))
+            .with_template_variable("DELETABLE", TemplateValue::Uint(0));
+        for spec in [
+            sandbox::APPLICATION_ARC56,
+            void_return_test::APPLICATION_ARC56,
+            zero_coupon_bond::APPLICATION_ARC56,
+        ] {
+            let contract = Arc56Contract::from_json(spec).unwrap();
+            let (approval, clear) = contract.decoded_teal().unwrap();
+            let byte_code = contract.byte_code.as_ref().unwrap();
+            for (teal, compiled) in [(approval, &byte_code.approval), (clear, &byte_code.clear)] {
+                let assembly = assembler.assemble(&teal).unwrap();
+                assert_eq!(
+                    BASE64.encode(&assembly.program),
+                    *compiled,
+                    "{}",
+                    contract.name
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn test_builds_constant_blocks_and_resolves_labels() {
+        let source = r#"#pragma version 8
+int 1
+byte "hi"
+int 1 // counted twice
+byte 0x6869
+bnz done
+txn ApplicationArgs 0
+method "add(uint64,uint64)uint64"
+int 7
+pop
+done:
+  b done
+"#;
+        let assembly = assemble(source).unwrap();
+        let expected = [
+            "#pragma version 8",
+            "intcblock 1",
+            "bytecblock 0x6869",
+            "intc_0 // 1",
+            "bytec_0 // \"hi\"",
+            "intc_0 // 1",
+            "bytec_0 // \"hi\"",
+            "bnz label1",
+            "txna ApplicationArgs 0",
+            "pushbytes 0xfe6bdf69",
+            "pushint 7",
+            "pop",
+            "label1:",
+            "b label1",
+        ];
+        assert_eq!(
+            disassemble(&assembly.program).unwrap().to_string(),
+            expected.join("\n") + "\n"
+        );
+
+        // The constant blocks come from no line, and each instruction from its own.
+        assert_eq!(assembly.line(1), None);
+        assert_eq!(assembly.line(9), Some(2));
+        assert_eq!(assembly.line(13), Some(6));
+        assert_eq!(assembly.line(15), Some(6));
+        let source_info = assembly.source_info();
+        assert_eq!(source_info.source_info[0].teal, Some(2));
+        assert_eq!(source_info.source_info[0].pc, vec![9]);
+    }
+
+    #[test]
+    fn test_source_info_lists_instruction_starts() {
+        let source = "#pragma version 8\nintcblock 1 2\nintc 1\npushbytes 0x6869\npop\npop";
+        let assembly = assemble(source).unwrap();
+
+        let pcs = assembly
+            .source_info()
+            .source_info
+            .into_iter()
+            .map(|info| (info.teal, info.pc))
+            .collect::<Vec<_>>();
+        assert_eq!(
+            pcs,
+            vec![
+                (Some(2), vec![1]),
+                (Some(3), vec![5]),
+                (Some(4), vec![6]),
+                (Some(5), vec![10]),
+                (Some(6), vec![11]),
+            ]
+        );
+        assert_eq!(assembly.line(8), Some(4));
+        assert_eq!(assembly.line(12), None);
+    }
+
+    #[test]
+    fn test_reports_errors_with_lines() {
+        let error = |source: &str| assemble(source).unwrap_err().to_string();
+        assert_eq!(
+            error("#pragma version 8\nfoo"),
+            "Invalid TEAL at line 2: unknown opcode foo"
+        );
+        assert_eq!(
+            error("#pragma version 2\nint 1\nassert"),
+            "Invalid TEAL at line 3: assert is not available in version 2, only from 3"
+        );
+        assert_eq!(
+            error("#pragma version 8\nb missing"),
+            "Invalid TEAL at line 2: reference to undefined label missing"
+        );
+        assert_eq!(
+            error("#pragma version 3\nloop:\nb loop"),
+            "Invalid TEAL at line 3: label loop is behind the branch, which needs version 4"
+        );
+        assert_eq!(
+            error("#pragma version 8\nintcblock TMPL_X"),
+            "Invalid TEAL at line 2: no value for template variable TMPL_X"
+        );
+        assert_eq!(
+            Assembler::new()
+                .with_version(6)
+                .assemble("#pragma version 8")
+                .unwrap_err()
+                .to_string(),
+            "Invalid TEAL at line 1: program declares version 8 but version 6 was requested"
+        );
+        assert_eq!(
+            assemble("int 1").unwrap().program,
+            vec![1, 0x20, 1, 1, 0x22]
+        );
+    }
+}
@@ -0,0 +1,503 @@
+//! Decoding of compiled programs into instructions and TEAL text.
+
+use super::opcodes::{ImmediateKind, MAX_PROGRAM_VERSION, OpSpec, op_spec};
+use crate::dryrun::hex;
+use crate::error::AlgoKitUtilsError;
+use std::collections::{BTreeMap, HashSet};
+use std::fmt;
+
+/// The first program version in which branches may jump backwards.
+const BACK_BRANCH_VERSION: u64 = 4;
+
+/// An immediate argument of an instruction.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Immediate {
+    Uint(u64),
+    Int(i64),
+    Bytes(Vec<u8>),
+    /// The program counter a branch jumps to.
+    Label(u64),
+    /// The program counters a `switch` or `match` may jump to.
+    Labels(Vec<u64>),
+    Uints(Vec<u64>),
+    ByteList(Vec<Vec<u8>>),
+    /// The name of the field selected by the immediate.
+    Field(&'static str),
+}
+
+/// A decoded instruction of a program.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Instruction {
+    /// The offset of the opcode from the start of the program.
+    pub pc: u64,
+    /// The number of bytes taken by the opcode and its immediates.
+    pub size: u64,
+    pub op: &'static OpSpec,
+    pub immediates: Vec<Immediate>,
+}
+
+/// Iterates over the instructions of a program, stopping after the first that cannot be decoded.
+pub struct Instructions<'a> {
+    program: &'a [u8],
+    version: u64,
+    position: usize,
+    failed: bool,
+}
+
+impl<'a> Instructions<'a> {
+    /// Reads the version of a program, ready to decode the instructions that follow it.
+    pub fn new(program: &'a [u8]) -> Result<Self, AlgoKitUtilsError> {
+        let mut position = 0;
+        let version = read_uvarint(program, &mut position)?;
+        if version == 0 || version > MAX_PROGRAM_VERSION {
+            return Err(AlgoKitUtilsError::InvalidProgram {
+                pc: 0,
+                err_msg: format!("unsupported program version {}", version),
+            });
+        }
+        Ok(Self {
+            program,
+            version,
+            position,
+            failed: false,
+        })
+    }
+
+    /// The version of the program.
+    pub fn version(&self) -> u64 {
+        self.version
+    }
+
+    fn decode(&mut self) -> Result<Instruction, AlgoKitUtilsError> {
+        let pc = self.position;
+        let opcode = self.program[pc];
+        let op =
+            op_spec(opcode, self.version).ok_or_else(|| AlgoKitUtilsError::InvalidProgram {
+                pc: pc as u64,
+                err_msg: format!(
+                    "opcode 0x{:02x} is not available in version {}",
+                    opcode, self.version
+                ),
+            })?;
+        self.position += 1;
+
+        let mut immediates = Vec::with_capacity(op.immediates.len());
+        let mut offsets = Vec::new();
+        for kind in op.immediates {
+            let position = &mut self.position;
+            let program = self.program;
+            let immediate = match kind {
+                ImmediateKind::Uint8 => Immediate::Uint(read_u8(program, position)?.into()),
+                ImmediateKind::Int8 => Immediate::Int((read_u8(program, position)? as i8).into()),
+                ImmediateKind::Varuint => Immediate::Uint(read_uvarint(program, position)?),
+                ImmediateKind::Bytes => Immediate::Bytes(read_bytes(program, position)?),
+                ImmediateKind::Label => {
+                    offsets.push((immediates.len(), read_offset(program, position)?));
+                    Immediate::Label(0)
+                }
+                ImmediateKind::Labels => {
+                    let count = read_u8(program, position)?;
+                    let mut labels = Vec::with_capacity(count.into());
+                    for _ in 0..count {
+                        labels.push(read_offset(program, position)?);
+                    }
+                    offsets.extend(labels.into_iter().map(|offset| (immediates.len(), offset)));
+                    Immediate::Labels(Vec::new())
+                }
+                ImmediateKind::Varuints => {
+                    let count = read_uvarint(program, position)?;
+                    let mut values = Vec::new();
+                    for _ in 0..count {
+                        values.push(read_uvarint(program, position)?);
+                    }
+                    Immediate::Uints(values)
+                }
+                ImmediateKind::ByteList => {
+                    let count = read_uvarint(program, position)?;
+                    let mut values = Vec::new();
+                    for _ in 0..count {
+                        values.push(read_bytes(program, position)?);
+                    }
+                    Immediate::ByteList(values)
+                }
+                ImmediateKind::Field(group) => {
+                    let index = read_u8(program, position)?;
+                    let field = group
+                        .fields()
+                        .get(index as usize)
+                        .filter(|field| field.version <= self.version)
+                        .ok_or_else(|| AlgoKitUtilsError::InvalidProgram {
+                            pc: pc as u64,
+                            err_msg: format!(
+                                "{} has no field {} in version {}",
+                                op.name, index, self.version
+                            ),
+                        })?;
+                    Immediate::Field(field.name)
+                }
+            };
+            immediates.push(immediate);
+        }
+
+        // Branch offsets are relative to the end of the whole instruction.
+        let end = self.position as i64;
+        for (index, offset) in offsets {
+            let offset = if self.version >= BACK_BRANCH_VERSION {
+                i64::from(offset as i16)
+            } else {
+                i64::from(offset)
+            };
+            let target = end + offset;
+            if target < 0 || target > self.program.len() as i64 {
+                return Err(AlgoKitUtilsError::InvalidProgram {
+                    pc: pc as u64,
+                    err_msg: format!("{} branches outside the program to {}", op.name, target),
+                });
+            }
+            match &mut immediates[index] {
+                Immediate::Label(label) => *label = target as u64,
+                Immediate::Labels(labels) => labels.push(target as u64),
+                _ => unreachable!("branch offsets are only read for label immediates"),
+            }
+        }
+
+        Ok(Instruction {
+            pc: pc as u64,
+            size: (self.position - pc) as u64,
+            op,
+            immediates,
+        })
+    }
+}
+
+impl Iterator for Instructions<'_> {
+    type Item = Result<Instruction, AlgoKitUtilsError>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.failed || self.position >= self.program.len() {
+            return None;
+        }
+        let instruction = self.decode();
+        self.failed = instruction.is_err();
+        Some(instruction)
+    }
+}
+
+fn truncated(position: usize) -> AlgoKitUtilsError {
+    AlgoKitUtilsError::InvalidProgram {
+        pc: position as u64,
+        err_msg: "program ends inside an instruction".to_string(),
+    }
+}
+
+fn read_u8(program: &[u8], position: &mut usize) -> Result<u8, AlgoKitUtilsError> {
+    let byte = *program.get(*position).ok_or_else(|| truncated(*position))?;
+    *position += 1;
+    Ok(byte)
+}
+
+fn read_offset(program: &[u8], position: &mut usize) -> Result<u16, AlgoKitUtilsError> {
+    Ok(u16::from_be_bytes([
+        read_u8(program, position)?,
+        read_u8(program, position)?,
+    ]))
+}
+
+pub(crate) fn read_uvarint(program: &[u8], position: &mut usize) -> Result<u64, AlgoKitUtilsError> {
+    let start = *position;
+    let mut value = 0u64;
+    for shift in (0..64).step_by(7) {
+        let byte = read_u8(program, position)?;
+        value |= u64::from(byte & 0x7f) << shift;
+        if byte & 0x80 == 0 {
+            return Ok(value);
+        }
+    }
+    Err(AlgoKitUtilsError::InvalidProgram {
+        pc: start as u64,
+        err_msg: "varuint is longer than 64 bits".to_string(),
+    })
+}
+
+pub(crate) fn read_bytes(
+    program: &[u8],
+    position: &mut usize,
+) -> Result<Vec<u8>, AlgoKitUtilsError> {
+    let length = read_uvarint(program, position)?;
+    let end = position
+        .checked_add(usize::try_from(length).unwrap_or(usize::MAX))
+        .filter(|end| *end <= program.len())
+        .ok_or_else(|| truncated(*position))?;
+    let bytes = program[*position..end].to_vec();
+    *position = end;
+    Ok(bytes)
+}
+
+/// A decoded program, with a label for each branch target.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Disassembly {
+    pub version: u64,
+    pub instructions: Vec<Instruction>,
+    labels: BTreeMap<u64, String>,
+    length: u64,
+}
+
+/// Decodes a compiled program.
+///
+/// Branch targets are named `label1`, `label2` and so on in program order, and must fall on an
+/// instruction or the end of the program.
+pub fn disassemble(program: &[u8]) -> Result<Disassembly, AlgoKitUtilsError> {
+    let instructions = Instructions::new(program)?;
+    let version = instructions.version();
+    let instructions = instructions.collect::<Result<Vec<_>, _>>()?;
+
+    let starts = instructions
+        .iter()
+        .map(|instruction| instruction.pc)
+        .chain(std::iter::once(program.len() as u64))
+        .collect::<HashSet<_>>();
+    let mut targets = BTreeMap::new();
+    for instruction in &instructions {
+        for immediate in &instruction.immediates {
+            let pcs = match immediate {
+                Immediate::Label(target) => std::slice::from_ref(target),
+                Immediate::Labels(targets) => targets.as_slice(),
+                _ => continue,
+            };
+            for target in pcs {
+                if !starts.contains(target) {
+                    return Err(AlgoKitUtilsError::InvalidProgram {
+                        pc: instruction.pc,
+                        err_msg: format!(
+                            "{} branches into the middle of an instruction at {}",
+                            instruction.op.name, target
+                        ),
+                    });
+                }
+                targets.insert(*target, String::new());
+            }
+        }
+    }
+    for (index, label) in targets.values_mut().enumerate() {
+        *label = format!("label{}", index + 1);
+    }
+
+    Ok(Disassembly {
+        version,
+        instructions,
+        labels: targets,
+        length: program.len() as u64,
+    })
+}
+
+impl Disassembly {
+    /// The label of a branch target.
+    pub fn label(&self, pc: u64) -> Option<&str> {
+        self.labels.get(&pc).map(String::as_str)
+    }
+
+    /// The lines of TEAL, each with the program counter of its instruction. The version pragma and
+    /// labels have no program counter.
+    pub fn lines(&self) -> Vec<(Option<u64>, String)> {
+        let mut lines = vec![(None, format!("#pragma version {}", self.version))];
+        let mut int_constants: &[u64] = &[];
+        let mut byte_constants: &[Vec<u8>] = &[];
+        for instruction in &self.instructions {
+            if let Some(label) = self.label(instruction.pc) {
+                lines.push((None, format!("{}:", label)));
+            }
+            match (instruction.op.name, instruction.immediates.first()) {
+                ("intcblock", Some(Immediate::Uints(values))) => int_constants = values,
+                ("bytecblock", Some(Immediate::ByteList(values))) => byte_constants = values,
+                _ => {}
+            }
+
+            let mut text = instruction.op.name.to_string();
+            for immediate in &instruction.immediates {
+                text.push(' ');
+                text.push_str(&self.format_immediate(immediate));
+            }
+            if let Some(comment) = constant_comment(instruction, int_constants, byte_constants) {
+                text.push_str(" // ");
+                text.push_str(&comment);
+            }
+            lines.push((Some(instruction.pc), text));
+        }
+        if let Some(label) = self.label(self.length) {
+            lines.push((None, format!("{}:", label)));
+        }
+        lines
+    }
+
+    fn format_immediate(&self, immediate: &Immediate) -> String {
+        let label = |pc: &u64| self.label(*pc).unwrap_or_default().to_string();
+        match immediate {
+            Immediate::Uint(value) => value.to_string(),
+            Immediate::Int(value) => value.to_string(),
+            Immediate::Bytes(bytes) => hex(bytes),
+            Immediate::Label(pc) => label(pc),
+            Immediate::Labels(pcs) => pcs.iter().map(label).collect::<Vec<_>>().join(" "),
+            Immediate::Uints(values) => values
+                .iter()
+                .map(u64::to_string)
+                .collect::<Vec<_>>()
+                .join(" "),
+            Immediate::ByteList(values) => values
+                .iter()
+                .map(|bytes| hex(bytes))
+                .collect::<Vec<_>>()
+                .join(" "),
+            Immediate::Field(name) => name.to_string(),
+        }
+    }
+}
+
+impl fmt::Display for Disassembly {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for (_, line) in self.lines() {
+            writeln!(f, "{}", line)?;
+        }
+        Ok(())
+    }
+}
+
+/// Describes the constant an instruction pushes when it is not written in the instruction itself.
+fn constant_comment(
+    instruction: &Instruction,
+    int_constants: &[u64],
+    byte_constants: &[Vec<u8>],
+) -> Option<String> {
+    let name = instruction.op.name;
+    let index = match (name, instruction.immediates.first()) {
+        ("intc" | "bytec", Some(Immediate::Uint(index))) => *index as usize,
+        ("pushbytes", Some(Immediate::Bytes(bytes))) => return printable(bytes),
+        _ => match name.rsplit_once('_') {
+            Some(("intc" | "bytec", index)) => index.parse().ok()?,
+            _ => return None,
+        },
+    };
+    if name.starts_with("intc") {
+        int_constants.get(index).map(u64::to_string)
+    } else {
+        let bytes = byte_constants.get(index)?;
+        Some(printable(bytes).unwrap_or_else(|| hex(bytes)))
+    }
+}
+
+/// Quotes bytes that are printable ASCII text.
+fn printable(bytes: &[u8]) -> Option<String> {
+    let text = std::str::from_utf8(bytes).ok()?;
+    (!text.is_empty() && text.chars().all(|c| c.is_ascii_graphic() || c == ' '))
+        .then(|| format!("{:?}", text))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use algokit_abi::Arc56Contract;
+    use algokit_test_artifacts::{sandbox, void_return_test, zero_coupon_bond};
+    use base64::{Engine, engine::general_purpose::STANDARD as BASE64};
+
+    #[test]
+    fn test_disassembles_constants_branches_and_fields() {
+        let program = [
+            0x08, // #pragma version 8
+            0x20, 0x02, 0x01, 0xe8, 0x07, // intcblock 1 1000
+            0x26, 0x01, 0x02, 0x68, 0x69, // bytecblock 0x6869
+            0x31, 0x18, // txn ApplicationID
+            0x8d, 0x02, 0x00, 0x03, 0x00, 0x05, // switch label1 label2
+            0x42, 0xff, 0xf5, // b label1
+            0x28, // bytec_0
+            0xb0, // log
+            0x23, // intc_1
+            0x43, // return
+        ];
+        let disassembly = disassemble(&program).unwrap();
+        assert_eq!(disassembly.version, 8);
+        assert_eq!(disassembly.instructions.len(), 9);
+        assert_eq!(
+            disassembly.instructions[3].immediates,
+            vec![Immediate::Labels(vec![22, 24])]
+        );
+
+        let expected = [
+            "#pragma version 8",
+            "intcblock 1 1000",
+            "bytecblock 0x6869",
+            "label1:",
+            "txn ApplicationID",
+            "switch label2 label3",
+            "b label1",
+            "label2:",
+            "bytec_0 // \"hi\"",
+            "log",
+            "label3:",
+            "intc_1 // 1000",
+            "return",
+        ];
+        assert_eq!(disassembly.to_string(), expected.join("\n") + "\n");
+        assert_eq!(
+            disassembly.lines()[4],
+            (Some(11), "txn ApplicationID".into())
+        );
+    }
+
+    #[test]
+    fn test_rejects_invalid_programs() {
+        let error = |program: &[u8]| disassemble(program).unwrap_err().to_string();
+        assert_eq!(
+            error(&[0x02, 0x44]),
+            "Invalid program at pc 1: opcode 0x44 is not available in version 2"
+        );
+        assert_eq!(
+            error(&[0x05, 0x31, 0x3f]),
+            "Invalid program at pc 1: txn has no field 63 in version 5"
+        );
+        assert_eq!(
+            error(&[0x08, 0x80, 0x05, 0x01]),
+            "Invalid program at pc 3: program ends inside an instruction"
+        );
+        assert_eq!(
+            error(&[0x08, 0x42, 0x00, 0x01, 0x81, 0x01]),
+            "Invalid program at pc 1: b branches into the middle of an instruction at 5"
+        );
+        // Offsets are unsigned before backward branches were allowed.
+        assert_eq!(
+            error(&[0x03, 0x42, 0xff, 0xfd]),
+            "Invalid program at pc 1: b branches outside the program to 65537"
+        );
+        assert!(disassemble(&[0x04, 0x42, 0xff, 0xfd]).is_ok());
+    }
+
+    /// The opcodes of a compiled contract match the instructions of the TEAL it was compiled
+    /// from, once labels, comments and pragmas are removed.
+    #[test]
+    fn test_disassembles_compiled_contracts() {
+        for spec in [
+            sandbox::APPLICATION_ARC56,
+            void_return_test::APPLICATION_ARC56,
+            zero_coupon_bond::APPLICATION_ARC56,
+        ] {
+            let contract = Arc56Contract::from_json(spec).unwrap();
+            let (approval, clear) = contract.decoded_teal().unwrap();
+            let byte_code = contract.byte_code.as_ref().unwrap();
+            for (teal, compiled) in [(approval, &byte_code.approval), (clear, &byte_code.clear)] {
+                let expected = teal
+                    .lines()
+                    .map(|line| line.split("//").next().unwrap().trim())
+                    .filter(|line| {
+                        !line.is_empty() && !line.starts_with('#') && !line.ends_with(':')
+                    })
+                    .map(|line| line.split_whitespace().next().unwrap())
+                    .collect::<Vec<_>>();
+                let disassembly = disassemble(&BASE64.decode(compiled).unwrap()).unwrap();
+                let names = disassembly
+                    .instructions
+                    .iter()
+                    .map(|instruction| instruction.op.name)
+                    .collect::<Vec<_>>();
+                assert_eq!(names, expected, "{}", contract.name);
+            }
+        }
+    }
+}
@@ -0,0 +1,1358 @@
+//! Offline execution of transaction groups against an in-memory ledger.
+//!
+//! An [`Emulator`] applies transactions to a [`LedgerSnapshot`] the way algod would: payments,
+//! asset transfers, asset configuration and freezes move balances and holdings, and app calls
+//! run the approval or clear state program of the app, including any inner transactions the
+//! program submits. A group either applies in full or leaves the ledger untouched, and the
+//! opcode budget and fees are pooled across the group.
+//!
+//! Each app call records a [`SimulationTransactionExecTrace`] in the shape simulate returns when
+//! asked for an execution trace, so the trace can be rendered with
+//! [`TraceRenderer`](crate::TraceRenderer) or compared unit by unit with one recorded from algod.
+//!
+//! Programs may only access the accounts, apps, assets and boxes available under the AVM's
+//! resource availability rules, including resources shared across the group from program
+//! version 9, unless [`Emulator::with_allow_unnamed_resources`] lifts the restriction.
+//!
+//! Transactions are assumed to be signed by their sender and logic signatures are not run.
+//! Opcodes that verify signatures or read block data are not supported and fail the program.
+
+use super::interpreter::{Evaluation, Invocation, run};
+use crate::error::AlgoKitUtilsError;
+use crate::ledger_delta::{
+    AccountData, AccountSnapshot, AppLocalState, AppParams, AssetHolding, AssetParams,
+    LedgerSnapshot, StateSchema, TealValue,
+};
+use crate::simulate_trace::program_hash;
+use algod_client::models::SimulationTransactionExecTrace;
+use algokit_transact::{
+    Address, AppCallTransactionFields, AssetConfigTransactionFields, AssetFreezeTransactionFields,
+    AssetTransferTransactionFields, Byte32, ConsensusParams, OnApplicationComplete,
+    PaymentTransactionFields, Transaction,
+};
+use std::collections::{BTreeMap, HashSet};
+
+/// The opcode budget each app call adds to the pool of its group.
+const APP_CALL_BUDGET: u64 = 700;
+
+/// The maximum depth of app calls made through inner transactions.
+const MAX_APP_CALL_DEPTH: usize = 8;
+
+/// The ID given to the first app or asset created on a ledger without any.
+const FIRST_CREATABLE_ID: u64 = 1001;
+
+/// The outcome of a transaction applied by the emulator.
+#[derive(Debug, Clone, PartialEq)]
+pub struct EmulatedTransaction {
+    /// The transaction as applied, with the defaults of inner transactions filled in.
+    pub transaction: Transaction,
+    pub logs: Vec<Vec<u8>>,
+    pub created_app_id: Option<u64>,
+    pub created_asset_id: Option<u64>,
+    pub inner_transactions: Vec<EmulatedTransaction>,
+    /// The opcodes executed by the transaction's program, which is empty for transactions that
+    /// do not call an app.
+    pub exec_trace: SimulationTransactionExecTrace,
+}
+
+impl EmulatedTransaction {
+    fn new(transaction: Transaction) -> Self {
+        Self {
+            transaction,
+            logs: Vec::new(),
+            created_app_id: None,
+            created_asset_id: None,
+            inner_transactions: Vec::new(),
+            exec_trace: SimulationTransactionExecTrace::default(),
+        }
+    }
+}
+
+/// Why a group failed to apply.
+#[derive(Debug, Clone, PartialEq)]
+pub struct EmulationFailure {
+    /// The index of the failed transaction in the group, followed by the index of each inner
+    /// transaction leading to the one that failed. Empty when the group as a whole is invalid.
+    pub path: Vec<u64>,
+    /// The program counter of the opcode that failed, if the failure happened in a program.
+    pub pc: Option<u64>,
+    pub message: String,
+    /// The opcodes executed by the failed top-level transaction up to the failure.
+    pub exec_trace: Option<SimulationTransactionExecTrace>,
+}
+
+/// The outcome of a transaction group applied by the emulator.
+#[derive(Debug, Clone, PartialEq)]
+pub struct EmulatedGroup {
+    /// The transactions applied before the failure, or every transaction in the group.
+    pub transactions: Vec<EmulatedTransaction>,
+    /// Set when the group failed, in which case the ledger was left unchanged.
+    pub failure: Option<EmulationFailure>,
+    /// The opcode budget made available by the app calls of the group.
+    pub app_budget_added: u64,
+    pub app_budget_consumed: u64,
+}
+
+impl EmulatedGroup {
+    /// Returns whether every transaction in the group was applied.
+    pub fn is_success(&self) -> bool {
+        self.failure.is_none()
+    }
+}
+
+/// Applies transaction groups to an in-memory ledger without a node.
+#[derive(Debug, Clone)]
+pub struct Emulator {
+    ledger: LedgerSnapshot,
+    params: ConsensusParams,
+    round: u64,
+    timestamp: u64,
+    genesis_hash: Byte32,
+    next_id: u64,
+    allow_unnamed_resources: bool,
+}
+
+impl Emulator {
+    /// Creates an emulator over a ledger, using the parameters of the latest protocol version.
+    pub fn new(ledger: LedgerSnapshot) -> Self {
+        Self {
+            ledger,
+            params: ConsensusParams::default(),
+            round: 1,
+            timestamp: 0,
+            genesis_hash: [0; 32],
+            next_id: FIRST_CREATABLE_ID,
+            allow_unnamed_resources: false,
+        }
+    }
+
+    /// Sets the consensus parameters that fees, minimum balances and limits are checked against.
+    pub fn with_consensus_params(mut self, params: ConsensusParams) -> Self {
+        self.params = params;
+        self
+    }
+
+    /// Sets the values programs read from `global Round` and `global LatestTimestamp`.
+    pub fn with_round(mut self, round: u64, timestamp: u64) -> Self {
+        self.round = round;
+        self.timestamp = timestamp;
+        self
+    }
+
+    /// Sets the value programs read from `global GenesisHash`.
+    pub fn with_genesis_hash(mut self, genesis_hash: Byte32) -> Self {
+        self.genesis_hash = genesis_hash;
+        self
+    }
+
+    /// Lets programs access accounts, apps, assets and boxes that no transaction in the group
+    /// references, as simulate does with `allow_unnamed_resources`.
+    pub fn with_allow_unnamed_resources(mut self, allow: bool) -> Self {
+        self.allow_unnamed_resources = allow;
+        self
+    }
+
+    /// Returns the ledger with the effects of every group applied so far.
+    pub fn ledger(&self) -> &LedgerSnapshot {
+        &self.ledger
+    }
+
+    /// Returns the ledger for setting up accounts, assets and apps directly.
+    pub fn ledger_mut(&mut self) -> &mut LedgerSnapshot {
+        &mut self.ledger
+    }
+
+    /// Adds microalgos to the balance of an account, creating the account if needed.
+    pub fn fund(&mut self, address: &Address, amount: u64) {
+        let account = self.ledger.accounts.entry(address.clone()).or_default();
+        account.data.balance = account.data.balance.saturating_add(amount);
+    }
+
+    /// Applies a transaction group, committing its effects to the ledger if every transaction
+    /// succeeds.
+    ///
+    /// # Returns
+    /// The outcome of each transaction applied, or an error if the group is empty or larger than
+    /// the consensus parameters allow. A group that fails to apply is reported through
+    /// [`EmulatedGroup::failure`] rather than as an error.
+    pub fn execute(&mut self, group: &[Transaction]) -> Result<EmulatedGroup, AlgoKitUtilsError> {
+        if group.is_empty() || group.len() > self.params.max_tx_group_size {
+            return Err(AlgoKitUtilsError::InputError {
+                err_msg: format!(
+                    "Transaction groups must contain between 1 and {} transactions, got {}",
+                    self.params.max_tx_group_size,
+                    group.len()
+                ),
+            });
+        }
+
+        let app_calls = group
+            .iter()
+            .filter(|tx| matches!(tx, Transaction::AppCall(_)))
+            .count() as u64;
+        let budget = APP_CALL_BUDGET * app_calls;
+        let mut ctx = Context {
+            params: self.params.clone(),
+            round: self.round,
+            timestamp: self.timestamp,
+            genesis_hash: self.genesis_hash,
+            allow_unnamed_resources: self.allow_unnamed_resources,
+            next_id: self.next_id.max(next_creatable_id(&self.ledger)),
+            ledger: self.ledger.clone(),
+            budget,
+            budget_added: budget,
+            budget_consumed: 0,
+            fee_credit: 0,
+            inner_count: 0,
+            app_stack: Vec::new(),
+            touched: HashSet::new(),
+        };
+
+        let fees = group.iter().fold(0u64, |total, tx| {
+            total.saturating_add(tx.header().fee.unwrap_or(0))
+        });
+        let required = self.params.min_txn_fee * group.len() as u64;
+        let (transactions, failure) = if fees < required {
+            let failure = EmulationFailure {
+                path: Vec::new(),
+                pc: None,
+                message: format!(
+                    "txgroup had {} in fees, which is less than the minimum {} * {}",
+                    fees,
+                    group.len(),
+                    self.params.min_txn_fee
+                ),
+                exec_trace: None,
+            };
+            (Vec::new(), Some(failure))
+        } else {
+            ctx.fee_credit = fees - required;
+            let (transactions, fault) = apply_group(&mut ctx, group, None);
+            (transactions, fault.map(EmulationFailure::from))
+        };
+
+        if failure.is_none() {
+            self.ledger = ctx.ledger;
+            self.next_id = ctx.next_id;
+        }
+        Ok(EmulatedGroup {
+            transactions,
+            failure,
+            app_budget_added: ctx.budget_added,
+            app_budget_consumed: ctx.budget_consumed,
+        })
+    }
+}
+
+fn next_creatable_id(ledger: &LedgerSnapshot) -> u64 {
+    ledger
+        .accounts
+        .values()
+        .flat_map(|account| {
+            account
+                .created_apps
+                .keys()
+                .chain(account.created_assets.keys())
+        })
+        .map(|id| id + 1)
+        .max()
+        .unwrap_or(FIRST_CREATABLE_ID)
+        .max(FIRST_CREATABLE_ID)
+}
+
+/// A failure while applying a transaction, unwound to the top of the group.
+#[derive(Debug)]
+pub(super) struct Fault {
+    pub(super) path: Vec<u64>,
+    pub(super) pc: Option<u64>,
+    pub(super) message: String,
+    pub(super) exec_trace: Option<Box<SimulationTransactionExecTrace>>,
+}
+
+impl Fault {
+    pub(super) fn new(message: impl Into<String>) -> Self {
+        Self {
+            path: Vec::new(),
+            pc: None,
+            message: message.into(),
+            exec_trace: None,
+        }
+    }
+}
+
+impl From<Fault> for EmulationFailure {
+    fn from(fault: Fault) -> Self {
+        Self {
+            path: fault.path,
+            pc: fault.pc,
+            message: fault.message,
+            exec_trace: fault.exec_trace.map(|trace| *trace),
+        }
+    }
+}
+
+/// The transactions of a group as seen by the programs in it.
+pub(super) struct GroupView<'g> {
+    pub(super) transactions: &'g [Transaction],
+    /// The outcomes of the transactions applied so far.
+    pub(super) applied: &'g [EmulatedTransaction],
+    /// The final scratch space of each applied transaction that ran a program.
+    pub(super) scratch: &'g [Option<Vec<TealValue>>],
+}
+
+/// The ledger and pooled resources shared by the transactions of a group.
+pub(super) struct Context {
+    pub(super) params: ConsensusParams,
+    pub(super) round: u64,
+    pub(super) timestamp: u64,
+    pub(super) genesis_hash: Byte32,
+    pub(super) allow_unnamed_resources: bool,
+    pub(super) ledger: LedgerSnapshot,
+    next_id: u64,
+    /// The opcode budget left in the pool.
+    pub(super) budget: u64,
+    budget_added: u64,
+    pub(super) budget_consumed: u64,
+    /// Fees paid beyond the minimum, which can pay for inner transactions.
+    pub(super) fee_credit: u64,
+    pub(super) inner_count: usize,
+    /// The apps whose programs are executing, outermost first.
+    app_stack: Vec<u64>,
+    /// Accounts changed by the current top-level transaction.
+    touched: HashSet<Address>,
+}
+
+impl Context {
+    fn allocate_id(&mut self) -> u64 {
+        let id = self.next_id;
+        self.next_id += 1;
+        id
+    }
+
+    /// Returns an account for changing, creating it if needed.
+    pub(super) fn account_mut(&mut self, address: &Address) -> &mut AccountSnapshot {
+        self.touched.insert(address.clone());
+        self.ledger.accounts.entry(address.clone()).or_default()
+    }
+
+    pub(super) fn account_data(&self, address: &Address) -> AccountData {
+        self.ledger
+            .accounts
+            .get(address)
+            .map(|account| account.data.clone())
+            .unwrap_or_default()
+    }
+
+    pub(super) fn debit(&mut self, address: &Address, amount: u64) -> Result<(), Fault> {
+        let balance = self.ledger.balance(address);
+        if balance < amount {
+            return Err(Fault::new(format!(
+                "overspend (account {}, balance {}, tried to spend {})",
+                address, balance, amount
+            )));
+        }
+        self.account_mut(address).data.balance = balance - amount;
+        Ok(())
+    }
+
+    pub(super) fn credit(&mut self, address: &Address, amount: u64) -> Result<(), Fault> {
+        let balance = self
+            .ledger
+            .balance(address)
+            .checked_add(amount)
+            .ok_or_else(|| Fault::new(format!("balance of {} overflowed", address)))?;
+        self.account_mut(address).data.balance = balance;
+        Ok(())
+    }
+
+    /// Returns the minimum balance requirement of an account from the totals in its ledger data.
+    pub(super) fn min_balance(&self, data: &AccountData) -> u64 {
+        let p = &self.params;
+        let schema = &data.total_app_schema;
+
+        p.min_balance
+            + p.min_balance * data.total_assets_opted_in
+            + p.app_flat_params_min_balance * data.total_created_apps
+            + p.app_flat_params_min_balance * data.total_extra_app_pages as u64
+            + p.app_flat_opt_in_min_balance * data.total_apps_opted_in
+            + schema.num_uints * (p.schema_min_balance_per_entry + p.schema_uint_min_balance)
+            + schema.num_byte_slices * (p.schema_min_balance_per_entry + p.schema_bytes_min_balance)
+            + p.box_flat_min_balance * data.total_boxes
+            + p.box_byte_min_balance * data.total_box_bytes
+    }
+
+    pub(super) fn app_params(&self, app_id: u64) -> Option<&AppParams> {
+        find_app(&self.ledger, app_id).map(|(_, params)| params)
+    }
+
+    pub(super) fn app_params_mut(&mut self, app_id: u64) -> Option<&mut AppParams> {
+        self.ledger
+            .accounts
+            .values_mut()
+            .find_map(|account| account.created_apps.get_mut(&app_id))
+    }
+
+    pub(super) fn local_state(&self, address: &Address, app_id: u64) -> Option<&AppLocalState> {
+        self.ledger
+            .accounts
+            .get(address)
+            .and_then(|account| account.apps_local_state.get(&app_id))
+    }
+
+    pub(super) fn local_state_mut(
+        &mut self,
+        address: &Address,
+        app_id: u64,
+    ) -> Option<&mut AppLocalState> {
+        self.ledger
+            .accounts
+            .get_mut(address)
+            .and_then(|account| account.apps_local_state.get_mut(&app_id))
+    }
+
+    pub(super) fn asset_holding(&self, address: &Address, asset_id: u64) -> Option<AssetHolding> {
+        self.ledger
+            .accounts
+            .get(address)
+            .and_then(|account| account.assets.get(&asset_id))
+            .cloned()
+    }
+
+    fn asset(&self, asset_id: u64) -> Result<(Address, AssetParams), Fault> {
+        find_asset(&self.ledger, asset_id)
+            .map(|(creator, params)| (creator.clone(), params.clone()))
+            .ok_or_else(|| Fault::new(format!("asset {} does not exist", asset_id)))
+    }
+
+    /// Checks that every account changed by the current top-level transaction keeps its minimum
+    /// balance, removing accounts that were emptied.
+    fn check_min_balances(&mut self) -> Result<(), Fault> {
+        for address in std::mem::take(&mut self.touched) {
+            let Some(account) = self.ledger.accounts.get(&address) else {
+                continue;
+            };
+            if is_empty_account(account) {
+                self.ledger.accounts.remove(&address);
+                continue;
+            }
+            let min_balance = self.min_balance(&account.data);
+            if account.data.balance < min_balance {
+                return Err(Fault::new(format!(
+                    "account {} balance {} below min {}",
+                    address, account.data.balance, min_balance
+                )));
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Applies the transactions of a group in order, stopping at the first that fails.
+pub(super) fn apply_group(
+    ctx: &mut Context,
+    transactions: &[Transaction],
+    caller_app_id: Option<u64>,
+) -> (Vec<EmulatedTransaction>, Option<Fault>) {
+    let mut applied = Vec::with_capacity(transactions.len());
+    let mut scratch = Vec::with_capacity(transactions.len());
+    for index in 0..transactions.len() {
+        let group = GroupView {
+            transactions,
+            applied: &applied,
+            scratch: &scratch,
+        };
+        match apply_transaction(ctx, &group, index, caller_app_id) {
+            Ok((result, final_scratch)) => {
+                applied.push(result);
+                scratch.push(final_scratch);
+            }
+            Err(mut fault) => {
+                fault.path.insert(0, index as u64);
+                return (applied, Some(fault));
+            }
+        }
+    }
+    (applied, None)
+}
+
+fn apply_transaction(
+    ctx: &mut Context,
+    group: &GroupView<'_>,
+    index: usize,
+    caller_app_id: Option<u64>,
+) -> Result<(EmulatedTransaction, Option<Vec<TealValue>>), Fault> {
+    let tx = &group.transactions[index];
+    let header = tx.header();
+    let sender = &header.sender;
+    ctx.debit(sender, header.fee.unwrap_or(0))?;
+
+    let mut result = EmulatedTransaction::new(tx.clone());
+    let scratch = match tx {
+        Transaction::Payment(fields) => {
+            apply_payment(ctx, fields)?;
+            None
+        }
+        Transaction::AssetTransfer(fields) => {
+            apply_asset_transfer(ctx, fields)?;
+            None
+        }
+        Transaction::AssetConfig(fields) => {
+            result.created_asset_id = apply_asset_config(ctx, fields)?;
+            None
+        }
+        Transaction::AssetFreeze(fields) => {
+            apply_asset_freeze(ctx, fields)?;
+            None
+        }
+        Transaction::AppCall(fields) => Some(apply_app_call(
+            ctx,
+            group,
+            index,
+            fields,
+            caller_app_id,
+            &mut result,
+        )?),
+        Transaction::KeyRegistration(_) => None,
+        Transaction::Heartbeat(_) | Transaction::StateProof(_) => {
+            return Err(Fault::new(
+                "heartbeat and state proof transactions are not supported",
+            ));
+        }
+    };
+
+    if let Some(rekey_to) = &header.rekey_to {
+        let auth_address = if rekey_to == sender {
+            Address::default()
+        } else {
+            rekey_to.clone()
+        };
+        ctx.account_mut(sender).data.auth_address = auth_address;
+    }
+    if caller_app_id.is_none() {
+        ctx.check_min_balances()?;
+    }
+    Ok((result, scratch))
+}
+
+fn apply_payment(ctx: &mut Context, fields: &PaymentTransactionFields) -> Result<(), Fault> {
+    let sender = &fields.header.sender;
+    ctx.debit(sender, fields.amount)?;
+    ctx.credit(&fields.receiver, fields.amount)?;
+
+    if let Some(close_to) = &fields.close_remainder_to {
+        let account = ctx.account_mut(sender);
+        if !account.assets.is_empty()
+            || !account.created_assets.is_empty()
+            || !account.apps_local_state.is_empty()
+            || !account.created_apps.is_empty()
+        {
+            return Err(Fault::new(format!(
+                "cannot close account {} while it holds assets or apps",
+                sender
+            )));
+        }
+        let remainder = account.data.balance;
+        account.data = AccountData::default();
+        ctx.credit(close_to, remainder)?;
+    }
+    Ok(())
+}
+
+fn apply_asset_transfer(
+    ctx: &mut Context,
+    fields: &AssetTransferTransactionFields,
+) -> Result<(), Fault> {
+    let sender = &fields.header.sender;
+    let asset_id = fields.asset_id;
+    let (creator, params) = ctx.asset(asset_id)?;
+
+    let is_opt_in = fields.asset_sender.is_none()
+        && fields.close_remainder_to.is_none()
+        && fields.amount == 0
+        && fields.receiver == *sender
+        && ctx.asset_holding(sender, asset_id).is_none();
+    if is_opt_in {
+        let account = ctx.account_mut(sender);
+        account.assets.insert(
+            asset_id,
+            AssetHolding {
+                amount: 0,
+                frozen: params.default_frozen,
+            },
+        );
+        account.data.total_assets_opted_in += 1;
+        return Ok(());
+    }
+
+    let source = match &fields.asset_sender {
+        Some(asset_sender) => {
+            if *sender != params.clawback {
+                return Err(Fault::new(format!(
+                    "clawback not allowed: sender {} is not the clawback address of asset {}",
+                    sender, asset_id
+                )));
+            }
+            asset_sender.clone()
+        }
+        None => sender.clone(),
+    };
+    let clawback = fields.asset_sender.is_some();
+    move_asset(
+        ctx,
+        asset_id,
+        &source,
+        &fields.receiver,
+        fields.amount,
+        !clawback,
+    )?;
+
+    if let Some(close_to) = &fields.close_remainder_to {
+        if clawback {
+            return Err(Fault::new("cannot close an asset holding with a clawback"));
+        }
+        if source == creator {
+            return Err(Fault::new(format!(
+                "cannot close asset {} holding of its creator",
+                asset_id
+            )));
+        }
+        let remainder = ctx
+            .asset_holding(&source, asset_id)
+            .map_or(0, |holding| holding.amount);
+        move_asset(ctx, asset_id, &source, close_to, remainder, true)?;
+        let account = ctx.account_mut(&source);
+        account.assets.remove(&asset_id);
+        account.data.total_assets_opted_in = account.data.total_assets_opted_in.saturating_sub(1);
+    }
+    Ok(())
+}
+
+fn move_asset(
+    ctx: &mut Context,
+    asset_id: u64,
+    from: &Address,
+    to: &Address,
+    amount: u64,
+    check_frozen: bool,
+) -> Result<(), Fault> {
+    let not_opted_in = |address: &Address| {
+        Fault::new(format!(
+            "account {} has not opted in to asset {}",
+            address, asset_id
+        ))
+    };
+    let from_holding = ctx
+        .asset_holding(from, asset_id)
+        .ok_or_else(|| not_opted_in(from))?;
+    let to_holding = ctx
+        .asset_holding(to, asset_id)
+        .ok_or_else(|| not_opted_in(to))?;
+    for (address, holding) in [(from, &from_holding), (to, &to_holding)] {
+        if check_frozen && holding.frozen {
+            return Err(Fault::new(format!(
+                "asset {} frozen in {}",
+                asset_id, address
+            )));
+        }
+    }
+    if from_holding.amount < amount {
+        return Err(Fault::new(format!(
+            "underflow on subtracting {} from sender amount {}",
+            amount, from_holding.amount
+        )));
+    }
+    if from == to {
+        return Ok(());
+    }
+    let to_amount = to_holding
+        .amount
+        .checked_add(amount)
+        .ok_or_else(|| Fault::new(format!("asset {} balance of {} overflowed", asset_id, to)))?;
+
+    if let Some(holding) = ctx.account_mut(from).assets.get_mut(&asset_id) {
+        holding.amount -= amount;
+    }
+    if let Some(holding) = ctx.account_mut(to).assets.get_mut(&asset_id) {
+        holding.amount = to_amount;
+    }
+    Ok(())
+}
+
+fn apply_asset_config(
+    ctx: &mut Context,
+    fields: &AssetConfigTransactionFields,
+) -> Result<Option<u64>, Fault> {
+    let sender = &fields.header.sender;
+    if fields.asset_id == 0 {
+        let asset_id = ctx.allocate_id();
+        let total = fields.total.unwrap_or(0);
+        let params = AssetParams {
+            total,
+            decimals: fields.decimals.unwrap_or(0),
+            default_frozen: fields.default_frozen.unwrap_or(false),
+            unit_name: fields.unit_name.clone().unwrap_or_default(),
+            asset_name: fields.asset_name.clone().unwrap_or_default(),
+            url: fields.url.clone().unwrap_or_default(),
+            metadata_hash: fields.metadata_hash.unwrap_or_default(),
+            manager: fields.manager.clone().unwrap_or_default(),
+            reserve: fields.reserve.clone().unwrap_or_default(),
+            freeze: fields.freeze.clone().unwrap_or_default(),
+            clawback: fields.clawback.clone().unwrap_or_default(),
+        };
+        let account = ctx.account_mut(sender);
+        account.created_assets.insert(asset_id, params);
+        account.assets.insert(
+            asset_id,
+            AssetHolding {
+                amount: total,
+                frozen: false,
+            },
+        );
+        account.data.total_created_assets += 1;
+        account.data.total_assets_opted_in += 1;
+        return Ok(Some(asset_id));
+    }
+
+    let asset_id = fields.asset_id;
+    let (creator, params) = ctx.asset(asset_id)?;
+    if params.manager != *sender {
+        return Err(Fault::new(format!(
+            "this transaction should be issued by the manager. It is issued by {}, manager key {}",
+            sender, params.manager
+        )));
+    }
+
+    let is_destroy = fields.manager.is_none()
+        && fields.reserve.is_none()
+        && fields.freeze.is_none()
+        && fields.clawback.is_none();
+    let account = ctx.account_mut(&creator);
+    if is_destroy {
+        let held = account
+            .assets
+            .get(&asset_id)
+            .map_or(0, |holding| holding.amount);
+        if held != params.total {
+            return Err(Fault::new(format!(
+                "cannot destroy asset {}: creator is holding only {}/{}",
+                asset_id, held, params.total
+            )));
+        }
+        account.created_assets.remove(&asset_id);
+        account.data.total_created_assets = account.data.total_created_assets.saturating_sub(1);
+        if account.assets.remove(&asset_id).is_some() {
+            account.data.total_assets_opted_in =
+                account.data.total_assets_opted_in.saturating_sub(1);
+        }
+    } else if let Some(params) = account.created_assets.get_mut(&asset_id) {
+        params.manager = fields.manager.clone().unwrap_or_default();
+        params.reserve = fields.reserve.clone().unwrap_or_default();
+        params.freeze = fields.freeze.clone().unwrap_or_default();
+        params.clawback = fields.clawback.clone().unwrap_or_default();
+    }
+    Ok(None)
+}
+
+fn apply_asset_freeze(
+    ctx: &mut Context,
+    fields: &AssetFreezeTransactionFields,
+) -> Result<(), Fault> {
+    let (_, params) = ctx.asset(fields.asset_id)?;
+    if params.freeze != fields.header.sender {
+        return Err(Fault::new(format!(
+            "freeze not allowed: sender {} is not the freeze address of asset {}",
+            fields.header.sender, fields.asset_id
+        )));
+    }
+    let holding = ctx
+        .account_mut(&fields.freeze_target)
+        .assets
+        .get_mut(&fields.asset_id)
+        .ok_or_else(|| {
+            Fault::new(format!(
+                "account {} has not opted in to asset {}",
+                fields.freeze_target, fields.asset_id
+            ))
+        })?;
+    holding.frozen = fields.frozen;
+    Ok(())
+}
+
+fn state_schema(schema: &Option<algokit_transact::StateSchema>) -> StateSchema {
+    schema
+        .as_ref()
+        .map_or_else(StateSchema::default, |schema| StateSchema {
+            num_uints: schema.num_uints as u64,
+            num_byte_slices: schema.num_byte_slices as u64,
+        })
+}
+
+fn add_schema(total: &mut StateSchema, schema: &StateSchema) {
+    total.num_uints += schema.num_uints;
+    total.num_byte_slices += schema.num_byte_slices;
+}
+
+fn remove_schema(total: &mut StateSchema, schema: &StateSchema) {
+    total.num_uints = total.num_uints.saturating_sub(schema.num_uints);
+    total.num_byte_slices = total.num_byte_slices.saturating_sub(schema.num_byte_slices);
+}
+
+fn opt_in(ctx: &mut Context, address: &Address, app_id: u64, schema: StateSchema) {
+    let account = ctx.account_mut(address);
+    account.apps_local_state.insert(
+        app_id,
+        AppLocalState {
+            schema,
+            key_values: BTreeMap::new(),
+        },
+    );
+    account.data.total_apps_opted_in += 1;
+    add_schema(&mut account.data.total_app_schema, &schema);
+}
+
+fn opt_out(ctx: &mut Context, address: &Address, app_id: u64) {
+    let account = ctx.account_mut(address);
+    if let Some(local_state) = account.apps_local_state.remove(&app_id) {
+        account.data.total_apps_opted_in = account.data.total_apps_opted_in.saturating_sub(1);
+        remove_schema(&mut account.data.total_app_schema, &local_state.schema);
+    }
+}
+
+fn apply_app_call(
+    ctx: &mut Context,
+    group: &GroupView<'_>,
+    index: usize,
+    fields: &AppCallTransactionFields,
+    caller_app_id: Option<u64>,
+    result: &mut EmulatedTransaction,
+) -> Result<Vec<TealValue>, Fault> {
+    let sender = &fields.header.sender;
+    if caller_app_id.is_some() {
+        ctx.budget += APP_CALL_BUDGET;
+        ctx.budget_added += APP_CALL_BUDGET;
+    }
+
+    let app_id = if fields.app_id == 0 {
+        let app_id = ctx.allocate_id();
+        let params = AppParams {
+            approval_program: fields.approval_program.clone().unwrap_or_default(),
+            clear_state_program: fields.clear_state_program.clone().unwrap_or_default(),
+            global_state: BTreeMap::new(),
+            local_state_schema: state_schema(&fields.local_state_schema),
+            global_state_schema: state_schema(&fields.global_state_schema),
+            extra_program_pages: fields.extra_program_pages.unwrap_or(0),
+            version: 0,
+        };
+        let account = ctx.account_mut(sender);
+        account.data.total_created_apps += 1;
+        account.data.total_extra_app_pages += params.extra_program_pages;
+        add_schema(
+            &mut account.data.total_app_schema,
+            &params.global_state_schema,
+        );
+        account.created_apps.insert(app_id, params);
+        result.created_app_id = Some(app_id);
+        app_id
+    } else {
+        fields.app_id
+    };
+    let params = ctx
+        .app_params(app_id)
+        .cloned()
+        .ok_or_else(|| Fault::new(format!("application {} does not exist", app_id)))?;
+
+    if ctx.app_stack.contains(&app_id) {
+        return Err(Fault::new(format!("attempt to re-enter {}", app_id)));
+    }
+    if ctx.app_stack.len() >= MAX_APP_CALL_DEPTH {
+        return Err(Fault::new(format!(
+            "appl depth ({}) exceeded",
+            ctx.app_stack.len()
+        )));
+    }
+    let is_opted_in = ctx.local_state(sender, app_id).is_some();
+    let not_opted_in = || {
+        Fault::new(format!(
+            "account {} is not opted in to application {}",
+            sender, app_id
+        ))
+    };
+
+    if fields.on_complete == OnApplicationComplete::ClearState {
+        if !is_opted_in {
+            return Err(not_opted_in());
+        }
+        let checkpoint = (ctx.ledger.clone(), ctx.next_id);
+        let program = &params.clear_state_program;
+        let evaluation = evaluate(ctx, group, index, fields, app_id, program, caller_app_id);
+        let rollback_error = match (&evaluation.fault, evaluation.approved) {
+            (Some(fault), _) => Some(fault.message.clone()),
+            (None, false) => Some("clear state program rejected".to_string()),
+            (None, true) => None,
+        };
+
+        let mut trace = SimulationTransactionExecTrace {
+            clear_state_program_trace: Some(evaluation.trace),
+            clear_state_program_hash: Some(program_hash(program).to_vec()),
+            inner_trace: non_empty(evaluation.inner_traces),
+            ..Default::default()
+        };
+        if rollback_error.is_some() {
+            (ctx.ledger, ctx.next_id) = checkpoint;
+            trace.clear_state_rollback = Some(true);
+            trace.clear_state_rollback_error = rollback_error;
+        } else {
+            result.logs = evaluation.logs;
+            result.inner_transactions = evaluation.inner_transactions;
+        }
+        result.exec_trace = trace;
+        opt_out(ctx, sender, app_id);
+        return Ok(evaluation.scratch);
+    }
+
+    match fields.on_complete {
+        OnApplicationComplete::OptIn if is_opted_in => {
+            return Err(Fault::new(format!(
+                "account {} has already opted in to application {}",
+                sender, app_id
+            )));
+        }
+        OnApplicationComplete::OptIn => opt_in(ctx, sender, app_id, params.local_state_schema),
+        OnApplicationComplete::CloseOut if !is_opted_in => return Err(not_opted_in()),
+        _ => {}
+    }
+
+    let program = &params.approval_program;
+    let evaluation = evaluate(ctx, group, index, fields, app_id, program, caller_app_id);
+    let trace = SimulationTransactionExecTrace {
+        approval_program_trace: Some(evaluation.trace),
+        approval_program_hash: Some(program_hash(program).to_vec()),
+        inner_trace: non_empty(evaluation.inner_traces),
+        ..Default::default()
+    };
+    if let Some(mut fault) = evaluation.fault {
+        fault.exec_trace = Some(Box::new(trace));
+        return Err(fault);
+    }
+    if !evaluation.approved {
+        return Err(Fault {
+            path: Vec::new(),
+            pc: None,
+            message: "transaction rejected by ApprovalProgram".to_string(),
+            exec_trace: Some(Box::new(trace)),
+        });
+    }
+
+    match fields.on_complete {
+        OnApplicationComplete::CloseOut => opt_out(ctx, sender, app_id),
+        OnApplicationComplete::UpdateApplication => {
+            if let Some(params) = ctx.app_params_mut(app_id) {
+                params.approval_program = fields.approval_program.clone().unwrap_or_default();
+                params.clear_state_program = fields.clear_state_program.clone().unwrap_or_default();
+                params.version += 1;
+            }
+        }
+        OnApplicationComplete::DeleteApplication => {
+            if let Some((creator, _)) = find_app(&ctx.ledger, app_id) {
+                let creator = creator.clone();
+                let account = ctx.account_mut(&creator);
+                if let Some(params) = account.created_apps.remove(&app_id) {
+                    account.data.total_created_apps =
+                        account.data.total_created_apps.saturating_sub(1);
+                    account.data.total_extra_app_pages = account
+                        .data
+                        .total_extra_app_pages
+                        .saturating_sub(params.extra_program_pages);
+                    remove_schema(
+                        &mut account.data.total_app_schema,
+                        &params.global_state_schema,
+                    );
+                }
+            }
+        }
+        _ => {}
+    }
+    result.logs = evaluation.logs;
+    result.inner_transactions = evaluation.inner_transactions;
+    result.exec_trace = trace;
+    Ok(evaluation.scratch)
+}
+
+fn evaluate(
+    ctx: &mut Context,
+    group: &GroupView<'_>,
+    index: usize,
+    fields: &AppCallTransactionFields,
+    app_id: u64,
+    program: &[u8],
+    caller_app_id: Option<u64>,
+) -> Evaluation {
+    ctx.app_stack.push(app_id);
+    let evaluation = run(
+        ctx,
+        Invocation {
+            program,
+            fields,
+            app_id,
+            caller_app_id,
+            group,
+            index,
+        },
+    );
+    ctx.app_stack.pop();
+    evaluation
+}
+
+/// Returns the creator and parameters of an app.
+pub(super) fn find_app(ledger: &LedgerSnapshot, app_id: u64) -> Option<(&Address, &AppParams)> {
+    ledger.accounts.iter().find_map(|(address, account)| {
+        account
+            .created_apps
+            .get(&app_id)
+            .map(|params| (address, params))
+    })
+}
+
+/// Returns the creator and parameters of an asset.
+pub(super) fn find_asset(
+    ledger: &LedgerSnapshot,
+    asset_id: u64,
+) -> Option<(&Address, &AssetParams)> {
+    ledger.accounts.iter().find_map(|(address, account)| {
+        account
+            .created_assets
+            .get(&asset_id)
+            .map(|params| (address, params))
+    })
+}
+
+/// Whether an account holds nothing, so that the ledger no longer needs to keep it.
+fn is_empty_account(account: &AccountSnapshot) -> bool {
+    account.data == AccountData::default()
+        && account.assets.is_empty()
+        && account.created_assets.is_empty()
+        && account.apps_local_state.is_empty()
+        && account.created_apps.is_empty()
+}
+
+pub(super) fn non_empty<T>(values: Vec<T>) -> Option<Vec<T>> {
+    (!values.is_empty()).then_some(values)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::teal::{assemble, disassemble};
+    use algokit_transact::test_utils::{AccountMother, TransactionHeaderMother, TransactionMother};
+    use algokit_transact::{AppCallTransactionBuilder, BoxReference};
+
+    const CLEAR_STATE_PROGRAM: &str = "#pragma version 10\nint 1";
+
+    fn program(source: &str) -> Vec<u8> {
+        assemble(source).unwrap().program
+    }
+
+    fn funded_emulator() -> Emulator {
+        let mut emulator = Emulator::new(LedgerSnapshot::default());
+        emulator.fund(&AccountMother::account().address(), 10_000_000);
+        emulator
+    }
+
+    fn create_app(emulator: &mut Emulator, approval: &str, num_uints: u32) -> u64 {
+        let create = AppCallTransactionBuilder::default()
+            .header(TransactionHeaderMother::simple_testnet().build().unwrap())
+            .app_id(0)
+            .on_complete(OnApplicationComplete::NoOp)
+            .approval_program(program(approval))
+            .clear_state_program(program(CLEAR_STATE_PROGRAM))
+            .global_state_schema(algokit_transact::StateSchema {
+                num_uints,
+                num_byte_slices: 0,
+            })
+            .build()
+            .unwrap();
+        let group = emulator.execute(&[create]).unwrap();
+        assert_eq!(group.failure, None);
+        group.transactions[0].created_app_id.unwrap()
+    }
+
+    fn app_call(app_id: u64) -> Transaction {
+        AppCallTransactionBuilder::default()
+            .header(TransactionHeaderMother::simple_testnet().build().unwrap())
+            .app_id(app_id)
+            .on_complete(OnApplicationComplete::NoOp)
+            .build()
+            .unwrap()
+    }
+
+    fn payment(receiver: Address, amount: u64) -> Transaction {
+        TransactionMother::simple_payment()
+            .receiver(receiver)
+            .amount(amount)
+            .build()
+            .unwrap()
+    }
+
+    #[test]
+    fn test_creates_and_calls_app_with_global_state() {
+        let approval = r#"#pragma version 10
+byte "count"
+byte "count"
+app_global_get
+int 1
++
+app_global_put
+byte "hello"
+log
+int 1
+"#;
+        let mut emulator = funded_emulator();
+        let app_id = create_app(&mut emulator, approval, 1);
+        assert_eq!(app_id, FIRST_CREATABLE_ID);
+
+        let group = emulator.execute(&[app_call(app_id)]).unwrap();
+        assert!(group.is_success());
+        assert_eq!(group.transactions[0].logs, vec![b"hello".to_vec()]);
+        assert_eq!(group.app_budget_added, APP_CALL_BUDGET);
+        assert_eq!(group.app_budget_consumed, 11);
+
+        let (_, params) = find_app(emulator.ledger(), app_id).unwrap();
+        assert_eq!(
+            params.global_state.get(b"count".as_slice()),
+            Some(&TealValue::Uint(2))
+        );
+        let trace = &group.transactions[0].exec_trace;
+        let units = trace.approval_program_trace.as_ref().unwrap();
+        assert_eq!(units.len(), 11);
+        let put = units
+            .iter()
+            .find(|unit| unit.state_changes.is_some())
+            .unwrap();
+        assert_eq!(put.stack_pop_count, Some(2));
+        assert_eq!(put.state_changes.as_ref().unwrap()[0].app_state_type, "g");
+
+        let sender = AccountMother::account().address();
+        assert_eq!(emulator.ledger().balance(&sender), 10_000_000 - 2_000);
+    }
+
+    #[test]
+    fn test_pays_from_app_account_with_inner_transaction() {
+        let approval = r#"#pragma version 10
+txn ApplicationID
+bz done
+gtxn 0 Receiver
+global CurrentApplicationAddress
+==
+assert
+itxn_begin
+int pay
+itxn_field TypeEnum
+txn Sender
+itxn_field Receiver
+gtxn 0 Amount
+int 2
+/
+itxn_field Amount
+int 0
+itxn_field Fee
+itxn_submit
+done:
+int 1
+"#;
+        let mut emulator = funded_emulator();
+        let app_id = create_app(&mut emulator, approval, 0);
+        let app_address = Address::from_app_id(&app_id);
+
+        let mut pay = payment(app_address.clone(), 1_000_000);
+        pay.header_mut().fee = Some(2_000);
+        let group = emulator.execute(&[pay, app_call(app_id)]).unwrap();
+        assert_eq!(group.failure, None);
+
+        let inner = &group.transactions[1].inner_transactions;
+        assert_eq!(inner.len(), 1);
+        assert_eq!(inner[0].transaction.sender(), &app_address);
+        assert_eq!(emulator.ledger().balance(&app_address), 500_000);
+        let trace = &group.transactions[1].exec_trace;
+        assert_eq!(trace.inner_trace.as_ref().map(Vec::len), Some(1));
+        let submit = trace
+            .approval_program_trace
+            .as_ref()
+            .unwrap()
+            .iter()
+            .find(|unit| unit.spawned_inners.is_some())
+            .unwrap();
+        assert_eq!(submit.spawned_inners, Some(vec![0]));
+    }
+
+    #[test]
+    fn test_failed_group_leaves_ledger_unchanged() {
+        let approval = "#pragma version 10\ntxn ApplicationID\nbz done\nint 1\nint 2\n==\nassert\ndone:\nint 1";
+        let mut emulator = funded_emulator();
+        let app_id = create_app(&mut emulator, approval, 0);
+        let receiver = AccountMother::neil().address();
+        let ledger = emulator.ledger().clone();
+
+        let group = emulator
+            .execute(&[payment(receiver.clone(), 200_000), app_call(app_id)])
+            .unwrap();
+        assert!(!group.is_success());
+        let failure = group.failure.unwrap();
+        assert_eq!(failure.path, vec![1]);
+        assert_eq!(failure.message, "assert failed");
+        let failed_op = disassemble(&program(approval))
+            .unwrap()
+            .instructions
+            .into_iter()
+            .find(|instruction| Some(instruction.pc) == failure.pc)
+            .unwrap();
+        assert_eq!(failed_op.op.name, "assert");
+        assert!(failure.exec_trace.is_some());
+        assert_eq!(group.transactions.len(), 1);
+        assert_eq!(emulator.ledger(), &ledger);
+        assert_eq!(emulator.ledger().balance(&receiver), 0);
+
+        let group = emulator.execute(&[]).unwrap_err();
+        assert!(matches!(group, AlgoKitUtilsError::InputError { .. }));
+    }
+
+    #[test]
+    fn test_box_storage_requires_app_account_min_balance() {
+        let approval = r#"#pragma version 10
+txn ApplicationID
+bz done
+byte "box"
+byte 0x0102
+box_put
+done:
+int 1
+"#;
+        let mut emulator = funded_emulator();
+        let app_id = create_app(&mut emulator, approval, 0);
+        let app_address = Address::from_app_id(&app_id);
+
+        let group = emulator.execute(&[app_call(app_id)]).unwrap();
+        let failure = group.failure.unwrap();
+        assert!(
+            failure.message.contains("invalid Box reference"),
+            "{}",
+            failure.message
+        );
+
+        let mut call = app_call(app_id);
+        if let Transaction::AppCall(fields) = &mut call {
+            fields.box_references = Some(vec![BoxReference {
+                app_id: 0,
+                name: b"box".to_vec(),
+            }]);
+        }
+        let group = emulator.execute(&[call.clone()]).unwrap();
+        let failure = group.failure.unwrap();
+        assert!(failure.message.contains("below min"), "{}", failure.message);
+
+        let group = emulator
+            .execute(&[payment(app_address.clone(), 200_000), call])
+            .unwrap();
+        assert_eq!(group.failure, None);
+        assert_eq!(
+            emulator.ledger().boxes.get(&(app_id, b"box".to_vec())),
+            Some(&vec![1, 2])
+        );
+        let data = &emulator.ledger().accounts[&app_address].data;
+        assert_eq!((data.total_boxes, data.total_box_bytes), (1, 5));
+    }
+
+    #[test]
+    fn test_deletes_app_from_ledger_with_inconsistent_totals() {
+        let mut emulator = funded_emulator();
+        let app_id = create_app(&mut emulator, "#pragma version 10\nint 1", 1);
+        let creator = AccountMother::account().address();
+        emulator
+            .ledger_mut()
+            .accounts
+            .get_mut(&creator)
+            .unwrap()
+            .data = AccountData {
+            balance: 10_000_000,
+            ..Default::default()
+        };
+
+        let mut delete = app_call(app_id);
+        if let Transaction::AppCall(fields) = &mut delete {
+            fields.on_complete = OnApplicationComplete::DeleteApplication;
+        }
+        let group = emulator.execute(&[delete]).unwrap();
+
+        assert_eq!(group.failure, None);
+        let account = &emulator.ledger().accounts[&creator];
+        assert!(account.created_apps.is_empty());
+        assert_eq!(account.data.total_created_apps, 0);
+    }
+
+    fn balance_program(version: u64, address: &Address) -> String {
+        format!(
+            "#pragma version {}\ntxn ApplicationID\nbz done\naddr {}\nbalance\npop\ndone:\nint 1",
+            version, address
+        )
+    }
+
+    #[test]
+    fn test_rejects_unnamed_account_unless_allowed() {
+        let neil = AccountMother::neil().address();
+        let approval = balance_program(10, &neil);
+
+        let mut emulator = funded_emulator();
+        let app_id = create_app(&mut emulator, &approval, 0);
+        let group = emulator.execute(&[app_call(app_id)]).unwrap();
+        assert_eq!(
+            group.failure.unwrap().message,
+            format!("unavailable Account {}", neil)
+        );
+
+        let mut emulator = funded_emulator().with_allow_unnamed_resources(true);
+        let app_id = create_app(&mut emulator, &approval, 0);
+        let group = emulator.execute(&[app_call(app_id)]).unwrap();
+        assert_eq!(group.failure, None);
+    }
+
+    #[test]
+    fn test_shares_group_accounts_from_version_9() {
+        let neil = AccountMother::neil().address();
+        for (version, shared) in [(8, false), (9, true)] {
+            let mut emulator = funded_emulator();
+            let app_id = create_app(&mut emulator, &balance_program(version, &neil), 0);
+            let group = emulator
+                .execute(&[payment(neil.clone(), 100_000), app_call(app_id)])
+                .unwrap();
+            assert_eq!(group.failure.is_none(), shared, "version {}", version);
+        }
+    }
+
+    #[test]
+    fn test_requires_holding_to_be_named_by_one_transaction() {
+        let neil = AccountMother::neil().address();
+        let approval = format!(
+            "#pragma version 10\ntxn ApplicationID\nbz done\naddr {}\nint 5\nasset_holding_get AssetBalance\npop\npop\ndone:\nint 1",
+            neil
+        );
+        let mut emulator = funded_emulator();
+        let app_id = create_app(&mut emulator, &approval, 0);
+
+        let mut call = app_call(app_id);
+        if let Transaction::AppCall(fields) = &mut call {
+            fields.asset_references = Some(vec![5]);
+        }
+        let group = emulator
+            .execute(&[payment(neil.clone(), 100_000), call.clone()])
+            .unwrap();
+        assert_eq!(
+            group.failure.unwrap().message,
+            format!("unavailable Holding 5+{}", neil)
+        );
+
+        if let Transaction::AppCall(fields) = &mut call {
+            fields.account_references = Some(vec![neil]);
+        }
+        let group = emulator.execute(&[call]).unwrap();
+        assert_eq!(group.failure, None);
+    }
+}
@@ -0,0 +1,2895 @@
+//! The opcode interpreter that runs app programs for the [`Emulator`](super::Emulator).
+
+use super::analysis::opcode_cost;
+use super::disassembler::{Immediate, Instruction, disassemble};
+use super::emulator::{
+    Context, EmulatedTransaction, Fault, GroupView, apply_group, find_app, find_asset, non_empty,
+};
+use super::opcodes::MAX_PROGRAM_VERSION;
+use super::resources::Availability;
+use crate::ledger_delta::{AppParams, AssetParams, StateSchema, TealValue};
+use algod_client::models::{
+    ApplicationStateOperation, AvmValue, ScratchChange, SimulationOpcodeTraceUnit,
+    SimulationTransactionExecTrace,
+};
+use algokit_transact::{
+    Address, AppCallTransactionFields, AssetConfigTransactionFields, AssetFreezeTransactionFields,
+    AssetTransferTransactionFields, OnApplicationComplete, PaymentTransactionFields, Transaction,
+    TransactionHeader, TransactionId,
+};
+use base64::engine::{DecodePaddingMode, GeneralPurpose, GeneralPurposeConfig};
+use base64::{Engine, alphabet, engine::general_purpose::STANDARD as BASE64};
+use num_bigint::BigUint;
+use sha2::{Digest, Sha256, Sha512_256};
+use sha3::{Keccak256, Sha3_256};
+use std::collections::{BTreeMap, HashMap};
+use std::ops::Range;
+
+const AVM_BYTES_TYPE: u64 = 1;
+const AVM_UINT_TYPE: u64 = 2;
+
+const SCRATCH_SLOTS: usize = 256;
+const MAX_STACK_DEPTH: usize = 1000;
+const MAX_BYTES_LENGTH: usize = 4096;
+const MAX_BYTE_MATH_LENGTH: usize = 64;
+const MAX_LOG_CALLS: usize = 32;
+const MAX_LOG_SIZE: usize = 1024;
+const MAX_KEY_LENGTH: usize = 64;
+const MAX_KEY_VALUE_LENGTH: usize = 128;
+const MAX_BOX_NAME_LENGTH: usize = 64;
+const MAX_INNER_TRANSACTIONS: usize = 256;
+const PROGRAM_PAGE_SIZE: usize = 4096;
+
+const DECODE_CONFIG: GeneralPurposeConfig =
+    GeneralPurposeConfig::new().with_decode_padding_mode(DecodePaddingMode::Indifferent);
+const BASE64_STD_DECODER: GeneralPurpose = GeneralPurpose::new(&alphabet::STANDARD, DECODE_CONFIG);
+const BASE64_URL_DECODER: GeneralPurpose = GeneralPurpose::new(&alphabet::URL_SAFE, DECODE_CONFIG);
+
+/// The transaction types in the order of their `TypeEnum` values, starting from 1.
+const TRANSACTION_TYPES: &[&str] = &[
+    "pay", "keyreg", "acfg", "axfer", "afrz", "appl", "stpf", "hb",
+];
+
+/// The transaction fields an inner transaction may set more than once to build an array.
+const INNER_ARRAY_FIELDS: &[&str] = &[
+    "ApplicationArgs",
+    "Accounts",
+    "Assets",
+    "Applications",
+    "ApprovalProgramPages",
+    "ClearStateProgramPages",
+];
+
+/// The transaction fields an inner transaction may set once.
+const INNER_FIELDS: &[&str] = &[
+    "Type",
+    "TypeEnum",
+    "Sender",
+    "Fee",
+    "Note",
+    "RekeyTo",
+    "Receiver",
+    "Amount",
+    "CloseRemainderTo",
+    "XferAsset",
+    "AssetAmount",
+    "AssetSender",
+    "AssetReceiver",
+    "AssetCloseTo",
+    "ConfigAsset",
+    "ConfigAssetTotal",
+    "ConfigAssetDecimals",
+    "ConfigAssetDefaultFrozen",
+    "ConfigAssetUnitName",
+    "ConfigAssetName",
+    "ConfigAssetURL",
+    "ConfigAssetMetadataHash",
+    "ConfigAssetManager",
+    "ConfigAssetReserve",
+    "ConfigAssetFreeze",
+    "ConfigAssetClawback",
+    "FreezeAsset",
+    "FreezeAssetAccount",
+    "FreezeAssetFrozen",
+    "ApplicationID",
+    "OnCompletion",
+    "ApprovalProgram",
+    "ClearStateProgram",
+    "GlobalNumUint",
+    "GlobalNumByteSlice",
+    "LocalNumUint",
+    "LocalNumByteSlice",
+    "ExtraProgramPages",
+];
+
+/// A program to run for an app call.
+pub(super) struct Invocation<'a> {
+    pub(super) program: &'a [u8],
+    pub(super) fields: &'a AppCallTransactionFields,
+    /// The ID of the app, which has already been allocated when the call creates it.
+    pub(super) app_id: u64,
+    pub(super) caller_app_id: Option<u64>,
+    pub(super) group: &'a GroupView<'a>,
+    pub(super) index: usize,
+}
+
+/// The outcome of running a program.
+#[derive(Default)]
+pub(super) struct Evaluation {
+    pub(super) approved: bool,
+    pub(super) fault: Option<Fault>,
+    pub(super) logs: Vec<Vec<u8>>,
+    pub(super) inner_transactions: Vec<EmulatedTransaction>,
+    pub(super) scratch: Vec<TealValue>,
+    pub(super) trace: Vec<SimulationOpcodeTraceUnit>,
+    pub(super) inner_traces: Vec<SimulationTransactionExecTrace>,
+}
+
+/// Runs a program to completion, recording a trace unit for every opcode executed.
+pub(super) fn run(ctx: &mut Context, invocation: Invocation<'_>) -> Evaluation {
+    let disassembly = match disassemble(invocation.program) {
+        Ok(disassembly) => disassembly,
+        Err(error) => {
+            return Evaluation {
+                fault: Some(Fault::new(error.to_string())),
+                ..Default::default()
+            };
+        }
+    };
+    let positions: HashMap<u64, usize> = disassembly
+        .instructions
+        .iter()
+        .enumerate()
+        .map(|(index, instruction)| (instruction.pc, index))
+        .collect();
+
+    let mut interpreter = Interpreter::new(ctx, invocation, disassembly.version);
+    let outcome = interpreter.execute(&disassembly.instructions, &positions);
+    interpreter.finish(outcome)
+}
+
+enum Flow {
+    Next,
+    Jump(u64),
+    Halt(bool),
+}
+
+/// A subroutine call made with `callsub`.
+struct Frame {
+    return_pc: u64,
+    /// The stack height when the subroutine was called.
+    height: usize,
+    /// The arguments and return values declared by `proto`, if it was executed.
+    proto: Option<(usize, usize)>,
+}
+
+/// The fields set on an inner transaction before it is submitted.
+#[derive(Default)]
+struct InnerFields {
+    values: HashMap<&'static str, TealValue>,
+    arrays: HashMap<&'static str, Vec<TealValue>>,
+}
+
+struct Interpreter<'a> {
+    ctx: &'a mut Context,
+    invocation: Invocation<'a>,
+    version: u64,
+    availability: Availability,
+    op: &'static str,
+    stack: Vec<TealValue>,
+    /// The lowest stack height reached by the current opcode.
+    low_water: usize,
+    scratch: Vec<TealValue>,
+    int_constants: Vec<u64>,
+    byte_constants: Vec<Vec<u8>>,
+    frames: Vec<Frame>,
+    logs: Vec<Vec<u8>>,
+    pending: Vec<InnerFields>,
+    inner_transactions: Vec<EmulatedTransaction>,
+    /// The positions in `inner_transactions` of the last group submitted.
+    last_inner_group: Range<usize>,
+    trace: Vec<SimulationOpcodeTraceUnit>,
+    inner_traces: Vec<SimulationTransactionExecTrace>,
+    scratch_changes: Vec<ScratchChange>,
+    state_changes: Vec<ApplicationStateOperation>,
+    spawned_inners: Vec<u64>,
+}
+
+fn avm_value(value: &TealValue) -> AvmValue {
+    match value {
+        TealValue::Bytes(bytes) => AvmValue {
+            r#type: AVM_BYTES_TYPE,
+            bytes: (!bytes.is_empty()).then(|| BASE64.encode(bytes)),
+            uint: None,
+        },
+        TealValue::Uint(uint) => AvmValue {
+            r#type: AVM_UINT_TYPE,
+            bytes: None,
+            uint: (*uint != 0).then_some(*uint),
+        },
+    }
+}
+
+fn take_non_empty<T>(values: &mut Vec<T>) -> Option<Vec<T>> {
+    (!values.is_empty()).then(|| std::mem::take(values))
+}
+
+fn immediate_uint(instruction: &Instruction, position: usize) -> u64 {
+    match instruction.immediates.get(position) {
+        Some(Immediate::Uint(value)) => *value,
+        _ => 0,
+    }
+}
+
+fn immediate_field(instruction: &Instruction) -> &'static str {
+    instruction
+        .immediates
+        .iter()
+        .find_map(|immediate| match immediate {
+            Immediate::Field(name) => Some(*name),
+            _ => None,
+        })
+        .unwrap_or_default()
+}
+
+fn immediate_label(instruction: &Instruction) -> u64 {
+    match instruction.immediates.first() {
+        Some(Immediate::Label(target)) => *target,
+        _ => 0,
+    }
+}
+
+fn immediate_labels(instruction: &Instruction) -> &[u64] {
+    match instruction.immediates.first() {
+        Some(Immediate::Labels(targets)) => targets,
+        _ => &[],
+    }
+}
+
+fn address_value(address: Option<&Address>) -> TealValue {
+    TealValue::Bytes(address.map_or_else(|| vec![0; 32], |address| address.as_bytes().to_vec()))
+}
+
+fn bool_value(value: bool) -> TealValue {
+    TealValue::Uint(value as u64)
+}
+
+fn bytes_to_uint(bytes: &[u8]) -> u64 {
+    bytes
+        .iter()
+        .fold(0, |value, byte| (value << 8) | u64::from(*byte))
+}
+
+fn big_uint_bytes(value: BigUint) -> Vec<u8> {
+    if value == BigUint::ZERO {
+        Vec::new()
+    } else {
+        value.to_bytes_be()
+    }
+}
+
+fn substring(bytes: &[u8], start: u64, end: u64) -> Result<Vec<u8>, Fault> {
+    if end < start {
+        return Err(Fault::new("substring end before start"));
+    }
+    if end > bytes.len() as u64 {
+        return Err(Fault::new("substring range beyond length of string"));
+    }
+    Ok(bytes[start as usize..end as usize].to_vec())
+}
+
+fn extract(bytes: &[u8], start: u64, length: u64) -> Result<Vec<u8>, Fault> {
+    let end = start.saturating_add(length);
+    if end > bytes.len() as u64 {
+        return Err(Fault::new(format!(
+            "extraction end {} is beyond length {}",
+            end,
+            bytes.len()
+        )));
+    }
+    Ok(bytes[start as usize..end as usize].to_vec())
+}
+
+fn replace(bytes: &mut [u8], start: u64, replacement: &[u8]) -> Result<(), Fault> {
+    let end = start.saturating_add(replacement.len() as u64);
+    if end > bytes.len() as u64 {
+        return Err(Fault::new(format!(
+            "replacement end {} beyond original length {}",
+            end,
+            bytes.len()
+        )));
+    }
+    bytes[start as usize..end as usize].copy_from_slice(replacement);
+    Ok(())
+}
+
+fn check_key_value(key: &[u8], value: &TealValue) -> Result<(), Fault> {
+    if key.len() > MAX_KEY_LENGTH {
+        return Err(Fault::new(format!(
+            "key too long: length was {}, maximum is {}",
+            key.len(),
+            MAX_KEY_LENGTH
+        )));
+    }
+    if let TealValue::Bytes(bytes) = value {
+        if key.len() + bytes.len() > MAX_KEY_VALUE_LENGTH {
+            return Err(Fault::new(format!(
+                "key/value total too long: length was {}, maximum is {}",
+                key.len() + bytes.len(),
+                MAX_KEY_VALUE_LENGTH
+            )));
+        }
+    }
+    Ok(())
+}
+
+fn check_schema(values: &BTreeMap<Vec<u8>, TealValue>, schema: &StateSchema) -> Result<(), Fault> {
+    let uints = values
+        .values()
+        .filter(|value| matches!(value, TealValue::Uint(_)))
+        .count() as u64;
+    let byte_slices = values.len() as u64 - uints;
+    if uints > schema.num_uints {
+        return Err(Fault::new(format!(
+            "store integer count {} exceeds schema integer count {}",
+            uints, schema.num_uints
+        )));
+    }
+    if byte_slices > schema.num_byte_slices {
+        return Err(Fault::new(format!(
+            "store bytes count {} exceeds schema bytes count {}",
+            byte_slices, schema.num_byte_slices
+        )));
+    }
+    Ok(())
+}
+
+fn state_operation(
+    operation: &str,
+    app_state_type: &str,
+    key: &[u8],
+    new_value: Option<&TealValue>,
+    account: Option<&Address>,
+) -> ApplicationStateOperation {
+    ApplicationStateOperation {
+        operation: operation.to_string(),
+        app_state_type: app_state_type.to_string(),
+        key: key.to_vec(),
+        new_value: new_value.map(avm_value),
+        account: account.map(Address::to_string),
+    }
+}
+
+fn transaction_type(tx: &Transaction) -> usize {
+    match tx {
+        Transaction::Payment(_) => 1,
+        Transaction::KeyRegistration(_) => 2,
+        Transaction::AssetConfig(_) => 3,
+        Transaction::AssetTransfer(_) => 4,
+        Transaction::AssetFreeze(_) => 5,
+        Transaction::AppCall(_) => 6,
+        Transaction::StateProof(_) => 7,
+        Transaction::Heartbeat(_) => 8,
+    }
+}
+
+fn program_pages(program: &[u8]) -> Vec<TealValue> {
+    program
+        .chunks(PROGRAM_PAGE_SIZE)
+        .map(|page| TealValue::Bytes(page.to_vec()))
+        .collect()
+}
+
+/// Reads a field of a transaction, which is the zero value of the field's type when the
+/// transaction is not of the type the field belongs to.
+fn transaction_field(
+    tx: &Transaction,
+    applied: Option<&EmulatedTransaction>,
+    group_index: u64,
+    field: &str,
+    array_index: Option<u64>,
+) -> Result<TealValue, Fault> {
+    let header = tx.header();
+    let pay = match tx {
+        Transaction::Payment(fields) => Some(fields),
+        _ => None,
+    };
+    let axfer = match tx {
+        Transaction::AssetTransfer(fields) => Some(fields),
+        _ => None,
+    };
+    let acfg = match tx {
+        Transaction::AssetConfig(fields) => Some(fields),
+        _ => None,
+    };
+    let afrz = match tx {
+        Transaction::AssetFreeze(fields) => Some(fields),
+        _ => None,
+    };
+    let appl = match tx {
+        Transaction::AppCall(fields) => Some(fields),
+        _ => None,
+    };
+    let keyreg = match tx {
+        Transaction::KeyRegistration(fields) => Some(fields),
+        _ => None,
+    };
+
+    let uint = TealValue::Uint;
+    let bytes = |value: &[u8]| TealValue::Bytes(value.to_vec());
+    let string = |value: Option<&String>| bytes(value.map_or(&[][..], |value| value.as_bytes()));
+    let element = |values: Vec<TealValue>| {
+        let index = array_index.unwrap_or(0);
+        values
+            .into_iter()
+            .nth(index as usize)
+            .ok_or_else(|| Fault::new(format!("invalid {} index {}", field, index)))
+    };
+    let args = appl
+        .and_then(|fields| fields.args.as_deref())
+        .unwrap_or_default();
+    let accounts = appl
+        .and_then(|fields| fields.account_references.as_deref())
+        .unwrap_or_default();
+    let assets = appl
+        .and_then(|fields| fields.asset_references.as_deref())
+        .unwrap_or_default();
+    let apps = appl
+        .and_then(|fields| fields.app_references.as_deref())
+        .unwrap_or_default();
+    let approval = appl
+        .and_then(|fields| fields.approval_program.as_deref())
+        .unwrap_or_default();
+    let clear = appl
+        .and_then(|fields| fields.clear_state_program.as_deref())
+        .unwrap_or_default();
+    let global_schema = appl.and_then(|fields| fields.global_state_schema.as_ref());
+    let local_schema = appl.and_then(|fields| fields.local_state_schema.as_ref());
+    let applied = || {
+        applied.ok_or_else(|| {
+            Fault::new(format!(
+                "{} is only available for transactions that have been applied",
+                field
+            ))
+        })
+    };
+
+    Ok(match field {
+        "Sender" => address_value(Some(&header.sender)),
+        "Fee" => uint(header.fee.unwrap_or(0)),
+        "FirstValid" => uint(header.first_valid),
+        "LastValid" => uint(header.last_valid),
+        "Note" => bytes(header.note.as_deref().unwrap_or_default()),
+        "Lease" => bytes(&header.lease.unwrap_or_default()),
+        "RekeyTo" => address_value(header.rekey_to.as_ref()),
+        "Type" => bytes(TRANSACTION_TYPES[transaction_type(tx) - 1].as_bytes()),
+        "TypeEnum" => uint(transaction_type(tx) as u64),
+        "GroupIndex" => uint(group_index),
+        "TxID" => bytes(&tx.id_raw().map_err(|error| Fault::new(error.to_string()))?),
+        "Receiver" => address_value(pay.map(|fields| &fields.receiver)),
+        "Amount" => uint(pay.map_or(0, |fields| fields.amount)),
+        "CloseRemainderTo" => {
+            address_value(pay.and_then(|fields| fields.close_remainder_to.as_ref()))
+        }
+        "VotePK" => bytes(
+            &keyreg
+                .and_then(|fields| fields.vote_key)
+                .unwrap_or_default(),
+        ),
+        "SelectionPK" => bytes(
+            &keyreg
+                .and_then(|fields| fields.selection_key)
+                .unwrap_or_default(),
+        ),
+        "StateProofPK" => bytes(
+            keyreg
+                .and_then(|fields| fields.state_proof_key.as_ref())
+                .map_or(&[][..], |key| key.as_slice()),
+        ),
+        "VoteFirst" => uint(keyreg.and_then(|fields| fields.vote_first).unwrap_or(0)),
+        "VoteLast" => uint(keyreg.and_then(|fields| fields.vote_last).unwrap_or(0)),
+        "VoteKeyDilution" => uint(
+            keyreg
+                .and_then(|fields| fields.vote_key_dilution)
+                .unwrap_or(0),
+        ),
+        "Nonparticipation" => bool_value(
+            keyreg
+                .and_then(|fields| fields.non_participation)
+                .unwrap_or(false),
+        ),
+        "XferAsset" => uint(axfer.map_or(0, |fields| fields.asset_id)),
+        "AssetAmount" => uint(axfer.map_or(0, |fields| fields.amount)),
+        "AssetSender" => address_value(axfer.and_then(|fields| fields.asset_sender.as_ref())),
+        "AssetReceiver" => address_value(axfer.map(|fields| &fields.receiver)),
+        "AssetCloseTo" => {
+            address_value(axfer.and_then(|fields| fields.close_remainder_to.as_ref()))
+        }
+        "ConfigAsset" => uint(acfg.map_or(0, |fields| fields.asset_id)),
+        "ConfigAssetTotal" => uint(acfg.and_then(|fields| fields.total).unwrap_or(0)),
+        "ConfigAssetDecimals" => uint(acfg.and_then(|fields| fields.decimals).unwrap_or(0).into()),
+        "ConfigAssetDefaultFrozen" => bool_value(
+            acfg.and_then(|fields| fields.default_frozen)
+                .unwrap_or(false),
+        ),
+        "ConfigAssetUnitName" => string(acfg.and_then(|fields| fields.unit_name.as_ref())),
+        "ConfigAssetName" => string(acfg.and_then(|fields| fields.asset_name.as_ref())),
+        "ConfigAssetURL" => string(acfg.and_then(|fields| fields.url.as_ref())),
+        "ConfigAssetMetadataHash" => bytes(
+            acfg.and_then(|fields| fields.metadata_hash.as_ref())
+                .map_or(&[][..], |hash| hash.as_slice()),
+        ),
+        "ConfigAssetManager" => address_value(acfg.and_then(|fields| fields.manager.as_ref())),
+        "ConfigAssetReserve" => address_value(acfg.and_then(|fields| fields.reserve.as_ref())),
+        "ConfigAssetFreeze" => address_value(acfg.and_then(|fields| fields.freeze.as_ref())),
+        "ConfigAssetClawback" => address_value(acfg.and_then(|fields| fields.clawback.as_ref())),
+        "FreezeAsset" => uint(afrz.map_or(0, |fields| fields.asset_id)),
+        "FreezeAssetAccount" => address_value(afrz.map(|fields| &fields.freeze_target)),
+        "FreezeAssetFrozen" => bool_value(afrz.is_some_and(|fields| fields.frozen)),
+        "ApplicationID" => uint(appl.map_or(0, |fields| fields.app_id)),
+        "OnCompletion" => uint(appl.map_or(0, |fields| fields.on_complete as u64)),
+        "ApplicationArgs" => element(args.iter().map(|arg| bytes(arg)).collect())?,
+        "NumAppArgs" => uint(args.len() as u64),
+        "Accounts" => element(
+            std::iter::once(&header.sender)
+                .chain(accounts)
+                .map(|address| address_value(Some(address)))
+                .collect(),
+        )?,
+        "NumAccounts" => uint(accounts.len() as u64),
+        "Assets" => element(assets.iter().copied().map(uint).collect())?,
+        "NumAssets" => uint(assets.len() as u64),
+        "Applications" => element(
+            std::iter::once(appl.map_or(0, |fields| fields.app_id))
+                .chain(apps.iter().copied())
+                .map(uint)
+                .collect(),
+        )?,
+        "NumApplications" => uint(apps.len() as u64),
+        "ApprovalProgram" => bytes(approval),
+        "ClearStateProgram" => bytes(clear),
+        "ApprovalProgramPages" => element(program_pages(approval))?,
+        "NumApprovalProgramPages" => uint(approval.len().div_ceil(PROGRAM_PAGE_SIZE) as u64),
+        "ClearStateProgramPages" => element(program_pages(clear))?,
+        "NumClearStateProgramPages" => uint(clear.len().div_ceil(PROGRAM_PAGE_SIZE) as u64),
+        "GlobalNumUint" => uint(global_schema.map_or(0, |schema| schema.num_uints.into())),
+        "GlobalNumByteSlice" => {
+            uint(global_schema.map_or(0, |schema| schema.num_byte_slices.into()))
+        }
+        "LocalNumUint" => uint(local_schema.map_or(0, |schema| schema.num_uints.into())),
+        "LocalNumByteSlice" => uint(local_schema.map_or(0, |schema| schema.num_byte_slices.into())),
+        "ExtraProgramPages" => uint(
+            appl.and_then(|fields| fields.extra_program_pages)
+                .unwrap_or(0)
+                .into(),
+        ),
+        "RejectVersion" => uint(0),
+        "Logs" => element(applied()?.logs.iter().map(|log| bytes(log)).collect())?,
+        "NumLogs" => uint(applied()?.logs.len() as u64),
+        "LastLog" => bytes(applied()?.logs.last().map_or(&[][..], Vec::as_slice)),
+        "CreatedAssetID" => uint(applied()?.created_asset_id.unwrap_or(0)),
+        "CreatedApplicationID" => uint(applied()?.created_app_id.unwrap_or(0)),
+        _ => {
+            return Err(Fault::new(format!(
+                "txn field {} is not supported by the emulator",
+                field
+            )));
+        }
+    })
+}
+
+impl<'a> Interpreter<'a> {
+    fn new(ctx: &'a mut Context, invocation: Invocation<'a>, version: u64) -> Self {
+        let availability = if ctx.allow_unnamed_resources {
+            Availability::unrestricted()
+        } else {
+            Availability::new(
+                invocation.group,
+                invocation.index,
+                invocation.app_id,
+                version,
+            )
+        };
+        Self {
+            ctx,
+            invocation,
+            version,
+            availability,
+            op: "",
+            stack: Vec::new(),
+            low_water: 0,
+            scratch: vec![TealValue::Uint(0); SCRATCH_SLOTS],
+            int_constants: Vec::new(),
+            byte_constants: Vec::new(),
+            frames: Vec::new(),
+            logs: Vec::new(),
+            pending: Vec::new(),
+            inner_transactions: Vec::new(),
+            last_inner_group: 0..0,
+            trace: Vec::new(),
+            inner_traces: Vec::new(),
+            scratch_changes: Vec::new(),
+            state_changes: Vec::new(),
+            spawned_inners: Vec::new(),
+        }
+    }
+
+    fn execute(
+        &mut self,
+        instructions: &[Instruction],
+        positions: &HashMap<u64, usize>,
+    ) -> Result<bool, Fault> {
+        let mut index = 0;
+        while let Some(instruction) = instructions.get(index) {
+            let height = self.stack.len();
+            self.low_water = height;
+            let cost = opcode_cost(instruction, self.version).base;
+            let flow = self
+                .charge(instruction.op.name, cost)
+                .and_then(|()| self.step(instruction))
+                .and_then(|flow| {
+                    if self.stack.len() > MAX_STACK_DEPTH {
+                        Err(Fault::new("stack overflow"))
+                    } else {
+                        Ok(flow)
+                    }
+                });
+            self.record(instruction.pc, height);
+
+            let flow = flow.map_err(|mut fault| {
+                fault.pc.get_or_insert(instruction.pc);
+                fault
+            })?;
+            match flow {
+                Flow::Next => index += 1,
+                Flow::Jump(pc) => match positions.get(&pc) {
+                    Some(target) => index = *target,
+                    None => break,
+                },
+                Flow::Halt(approved) => return Ok(approved),
+            }
+        }
+
+        match self.stack.as_slice() {
+            [TealValue::Uint(value)] => Ok(*value != 0),
+            [TealValue::Bytes(_)] => Err(Fault::new("stack finished with bytes not int")),
+            stack => Err(Fault::new(format!(
+                "stack len is {} instead of 1",
+                stack.len()
+            ))),
+        }
+    }
+
+    fn finish(self, outcome: Result<bool, Fault>) -> Evaluation {
+        let (approved, fault) = match outcome {
+            Ok(approved) => (approved, None),
+            Err(fault) => (false, Some(fault)),
+        };
+        Evaluation {
+            approved,
+            fault,
+            logs: self.logs,
+            inner_transactions: self.inner_transactions,
+            scratch: self.scratch,
+            trace: self.trace,
+            inner_traces: self.inner_traces,
+        }
+    }
+
+    fn charge(&mut self, name: &str, cost: u64) -> Result<(), Fault> {
+        if self.ctx.budget < cost {
+            return Err(Fault::new(format!(
+                "dynamic cost budget exceeded, executing {}: {} left of the pooled budget",
+                name, self.ctx.budget
+            )));
+        }
+        self.ctx.budget -= cost;
+        self.ctx.budget_consumed += cost;
+        Ok(())
+    }
+
+    fn record(&mut self, pc: u64, height: usize) {
+        let additions: Vec<AvmValue> = self.stack[self.low_water..].iter().map(avm_value).collect();
+        let pop_count = (height - self.low_water) as u64;
+        self.trace.push(SimulationOpcodeTraceUnit {
+            pc,
+            scratch_changes: take_non_empty(&mut self.scratch_changes),
+            state_changes: take_non_empty(&mut self.state_changes),
+            spawned_inners: take_non_empty(&mut self.spawned_inners),
+            stack_pop_count: (pop_count > 0).then_some(pop_count),
+            stack_additions: (!additions.is_empty()).then_some(additions),
+        });
+    }
+
+    fn pop(&mut self) -> Result<TealValue, Fault> {
+        let value = self
+            .stack
+            .pop()
+            .ok_or_else(|| Fault::new(format!("{} stack underflow", self.op)))?;
+        self.low_water = self.low_water.min(self.stack.len());
+        Ok(value)
+    }
+
+    fn pop_uint(&mut self) -> Result<u64, Fault> {
+        match self.pop()? {
+            TealValue::Uint(value) => Ok(value),
+            TealValue::Bytes(_) => Err(Fault::new(format!(
+                "{} wanted type uint64 got []byte",
+                self.op
+            ))),
+        }
+    }
+
+    fn pop_uints(&mut self) -> Result<(u64, u64), Fault> {
+        let b = self.pop_uint()?;
+        let a = self.pop_uint()?;
+        Ok((a, b))
+    }
+
+    fn pop_bytes(&mut self) -> Result<Vec<u8>, Fault> {
+        match self.pop()? {
+            TealValue::Bytes(bytes) => Ok(bytes),
+            TealValue::Uint(_) => Err(Fault::new(format!(
+                "{} wanted type []byte got uint64",
+                self.op
+            ))),
+        }
+    }
+
+    fn push(&mut self, value: TealValue) {
+        self.stack.push(value);
+    }
+
+    fn push_uint(&mut self, value: u64) {
+        self.push(TealValue::Uint(value));
+    }
+
+    fn push_bytes(&mut self, bytes: Vec<u8>) -> Result<(), Fault> {
+        if bytes.len() > MAX_BYTES_LENGTH {
+            return Err(Fault::new(format!(
+                "{} produced a too big ({}) byte-array",
+                self.op,
+                bytes.len()
+            )));
+        }
+        self.push(TealValue::Bytes(bytes));
+        Ok(())
+    }
+
+    fn push_bool(&mut self, value: bool) {
+        self.push(bool_value(value));
+    }
+
+    /// Returns the position of the value `depth` places below the top of the stack, marking
+    /// every value from there up as changed by the current opcode.
+    fn touch(&mut self, depth: u64) -> Result<usize, Fault> {
+        let position = (self.stack.len() as u64)
+            .checked_sub(depth + 1)
+            .ok_or_else(|| Fault::new(format!("{} stack underflow", self.op)))?
+            as usize;
+        self.low_water = self.low_water.min(position);
+        Ok(position)
+    }
+
+    fn store(&mut self, slot: u64, value: TealValue) -> Result<(), Fault> {
+        let entry = self
+            .scratch
+            .get_mut(slot as usize)
+            .ok_or_else(|| Fault::new(format!("invalid Scratch index {}", slot)))?;
+        *entry = value.clone();
+        self.scratch_changes.push(ScratchChange {
+            slot,
+            new_value: avm_value(&value),
+        });
+        Ok(())
+    }
+
+    fn load(&self, slot: u64) -> Result<TealValue, Fault> {
+        self.scratch
+            .get(slot as usize)
+            .cloned()
+            .ok_or_else(|| Fault::new(format!("invalid Scratch index {}", slot)))
+    }
+
+    fn sender(&self) -> &'a Address {
+        &self.invocation.fields.header.sender
+    }
+
+    fn app_address(&self) -> Address {
+        Address::from_app_id(&self.invocation.app_id)
+    }
+
+    /// Resolves an account given by address or by its position in the transaction's accounts.
+    fn account_reference(&self, value: TealValue) -> Result<Address, Fault> {
+        match value {
+            TealValue::Bytes(bytes) => {
+                <[u8; 32]>::try_from(bytes.as_slice())
+                    .map(Address)
+                    .map_err(|_| {
+                        Fault::new(format!(
+                            "invalid account reference of {} bytes",
+                            bytes.len()
+                        ))
+                    })
+            }
+            TealValue::Uint(0) => Ok(self.sender().clone()),
+            TealValue::Uint(index) => self
+                .invocation
+                .fields
+                .account_references
+                .as_deref()
+                .and_then(|accounts| accounts.get(index as usize - 1))
+                .cloned()
+                .ok_or_else(|| Fault::new(format!("invalid Account reference {}", index))),
+        }
+    }
+
+    /// Pops an account whose holdings or local state are read, which the caller checks are
+    /// available.
+    fn pop_address(&mut self) -> Result<Address, Fault> {
+        let value = self.pop()?;
+        self.account_reference(value)
+    }
+
+    fn pop_account(&mut self) -> Result<Address, Fault> {
+        let address = self.pop_address()?;
+        self.availability.account(&address)?;
+        Ok(address)
+    }
+
+    /// Resolves an app given by ID or by its position in the transaction's foreign apps.
+    fn app_reference(&self, value: u64) -> u64 {
+        let apps = self
+            .invocation
+            .fields
+            .app_references
+            .as_deref()
+            .unwrap_or_default();
+        if value == 0 {
+            self.invocation.app_id
+        } else if value == self.invocation.app_id || apps.contains(&value) {
+            value
+        } else {
+            apps.get(value as usize - 1).copied().unwrap_or(value)
+        }
+    }
+
+    /// Resolves an asset given by ID or by its position in the transaction's foreign assets.
+    fn asset_reference(&self, value: u64) -> u64 {
+        let assets = self
+            .invocation
+            .fields
+            .asset_references
+            .as_deref()
+            .unwrap_or_default();
+        if assets.contains(&value) {
+            value
+        } else {
+            assets.get(value as usize).copied().unwrap_or(value)
+        }
+    }
+
+    fn group_transaction_field(
+        &self,
+        group_index: u64,
+        field: &str,
+        array_index: Option<u64>,
+    ) -> Result<TealValue, Fault> {
+        let group = self.invocation.group;
+        let tx = group
+            .transactions
+            .get(group_index as usize)
+            .ok_or_else(|| {
+                Fault::new(format!(
+                    "gtxn lookup TxnGroup[{}] but it only has {}",
+                    group_index,
+                    group.transactions.len()
+                ))
+            })?;
+        transaction_field(
+            tx,
+            group.applied.get(group_index as usize),
+            group_index,
+            field,
+            array_index,
+        )
+    }
+
+    fn inner_transaction_field(
+        &self,
+        group_index: Option<u64>,
+        field: &str,
+        array_index: Option<u64>,
+    ) -> Result<TealValue, Fault> {
+        let group = &self.inner_transactions[self.last_inner_group.clone()];
+        let group_index = group_index.unwrap_or((group.len() as u64).saturating_sub(1));
+        let applied = group.get(group_index as usize).ok_or_else(|| {
+            Fault::new(format!(
+                "no inner transaction {} in the last group submitted",
+                group_index
+            ))
+        })?;
+        transaction_field(
+            &applied.transaction,
+            Some(applied),
+            group_index,
+            field,
+            array_index,
+        )
+    }
+
+    fn earlier_transaction(&self, group_index: u64) -> Result<usize, Fault> {
+        if group_index as usize >= self.invocation.index {
+            return Err(Fault::new(format!(
+                "{} can't get a value from transaction {} at or after the current one",
+                self.op, group_index
+            )));
+        }
+        Ok(group_index as usize)
+    }
+
+    fn group_scratch(&self, group_index: u64, slot: u64) -> Result<TealValue, Fault> {
+        let index = self.earlier_transaction(group_index)?;
+        let scratch = self.invocation.group.scratch[index]
+            .as_ref()
+            .ok_or_else(|| {
+                Fault::new(format!(
+                    "can't use {} on non-app call transaction {}",
+                    self.op, group_index
+                ))
+            })?;
+        scratch
+            .get(slot as usize)
+            .cloned()
+            .ok_or_else(|| Fault::new(format!("invalid Scratch index {}", slot)))
+    }
+
+    fn group_created_id(&self, group_index: u64) -> Result<u64, Fault> {
+        let applied = &self.invocation.group.applied[self.earlier_transaction(group_index)?];
+        applied
+            .created_app_id
+            .or(applied.created_asset_id)
+            .ok_or_else(|| {
+                Fault::new(format!(
+                    "transaction {} did not create an app or asset",
+                    group_index
+                ))
+            })
+    }
+
+    fn global_field(&self, field: &str) -> Result<TealValue, Fault> {
+        let ctx = &self.ctx;
+        let app_id = self.invocation.app_id;
+        let caller = self.invocation.caller_app_id;
+        Ok(match field {
+            "MinTxnFee" => TealValue::Uint(ctx.params.min_txn_fee),
+            "MinBalance" | "AssetCreateMinBalance" | "AssetOptInMinBalance" => {
+                TealValue::Uint(ctx.params.min_balance)
+            }
+            "MaxTxnLife" => TealValue::Uint(ctx.params.max_txn_life),
+            "ZeroAddress" => address_value(None),
+            "GroupSize" => TealValue::Uint(self.invocation.group.transactions.len() as u64),
+            "LogicSigVersion" => TealValue::Uint(MAX_PROGRAM_VERSION),
+            "Round" => TealValue::Uint(ctx.round),
+            "LatestTimestamp" => TealValue::Uint(ctx.timestamp),
+            "CurrentApplicationID" => TealValue::Uint(app_id),
+            "CreatorAddress" => {
+                address_value(find_app(&ctx.ledger, app_id).map(|(creator, _)| creator))
+            }
+            "CurrentApplicationAddress" => address_value(Some(&self.app_address())),
+            "GroupID" => TealValue::Bytes(
+                self.invocation
+                    .fields
+                    .header
+                    .group
+                    .unwrap_or_default()
+                    .to_vec(),
+            ),
+            "OpcodeBudget" => TealValue::Uint(ctx.budget),
+            "CallerApplicationID" => TealValue::Uint(caller.unwrap_or(0)),
+            "CallerApplicationAddress" => {
+                address_value(caller.map(|id| Address::from_app_id(&id)).as_ref())
+            }
+            "GenesisHash" => TealValue::Bytes(ctx.genesis_hash.to_vec()),
+            _ => {
+                return Err(Fault::new(format!(
+                    "global field {} is not supported by the emulator",
+                    field
+                )));
+            }
+        })
+    }
+
+    fn asset_holding_field(
+        &self,
+        address: &Address,
+        asset_id: u64,
+        field: &str,
+    ) -> (TealValue, bool) {
+        match self.ctx.asset_holding(address, asset_id) {
+            Some(holding) => {
+                let value = match field {
+                    "AssetBalance" => TealValue::Uint(holding.amount),
+                    _ => bool_value(holding.frozen),
+                };
+                (value, true)
+            }
+            None => (TealValue::Uint(0), false),
+        }
+    }
+
+    fn asset_params_field(&self, asset_id: u64, field: &str) -> (TealValue, bool) {
+        let Some((creator, params)) = find_asset(&self.ctx.ledger, asset_id) else {
+            return (TealValue::Uint(0), false);
+        };
+        let AssetParams {
+            total,
+            decimals,
+            default_frozen,
+            unit_name,
+            asset_name,
+            url,
+            metadata_hash,
+            manager,
+            reserve,
+            freeze,
+            clawback,
+        } = params;
+        let value = match field {
+            "AssetTotal" => TealValue::Uint(*total),
+            "AssetDecimals" => TealValue::Uint((*decimals).into()),
+            "AssetDefaultFrozen" => bool_value(*default_frozen),
+            "AssetUnitName" => TealValue::Bytes(unit_name.as_bytes().to_vec()),
+            "AssetName" => TealValue::Bytes(asset_name.as_bytes().to_vec()),
+            "AssetURL" => TealValue::Bytes(url.as_bytes().to_vec()),
+            "AssetMetadataHash" => TealValue::Bytes(metadata_hash.to_vec()),
+            "AssetManager" => address_value(Some(manager)),
+            "AssetReserve" => address_value(Some(reserve)),
+            "AssetFreeze" => address_value(Some(freeze)),
+            "AssetClawback" => address_value(Some(clawback)),
+            _ => address_value(Some(creator)),
+        };
+        (value, true)
+    }
+
+    fn app_params_field(&self, app_id: u64, field: &str) -> (TealValue, bool) {
+        let Some((creator, params)) = find_app(&self.ctx.ledger, app_id) else {
+            return (TealValue::Uint(0), false);
+        };
+        let AppParams {
+            approval_program,
+            clear_state_program,
+            local_state_schema,
+            global_state_schema,
+            extra_program_pages,
+            version,
+            ..
+        } = params;
+        let value = match field {
+            "AppApprovalProgram" => TealValue::Bytes(approval_program.clone()),
+            "AppClearStateProgram" => TealValue::Bytes(clear_state_program.clone()),
+            "AppGlobalNumUint" => TealValue::Uint(global_state_schema.num_uints),
+            "AppGlobalNumByteSlice" => TealValue::Uint(global_state_schema.num_byte_slices),
+            "AppLocalNumUint" => TealValue::Uint(local_state_schema.num_uints),
+            "AppLocalNumByteSlice" => TealValue::Uint(local_state_schema.num_byte_slices),
+            "AppExtraProgramPages" => TealValue::Uint((*extra_program_pages).into()),
+            "AppCreator" => address_value(Some(creator)),
+            "AppAddress" => address_value(Some(&Address::from_app_id(&app_id))),
+            _ => TealValue::Uint(*version),
+        };
+        (value, true)
+    }
+
+    fn account_params_field(
+        &self,
+        address: &Address,
+        field: &str,
+    ) -> Result<(TealValue, bool), Fault> {
+        let data = self.ctx.account_data(address);
+        let value = match field {
+            "AcctBalance" => TealValue::Uint(data.balance),
+            "AcctMinBalance" => TealValue::Uint(self.ctx.min_balance(&data)),
+            "AcctAuthAddr" => address_value(Some(&data.auth_address)),
+            "AcctTotalNumUint" => TealValue::Uint(data.total_app_schema.num_uints),
+            "AcctTotalNumByteSlice" => TealValue::Uint(data.total_app_schema.num_byte_slices),
+            "AcctTotalExtraAppPages" => TealValue::Uint(data.total_extra_app_pages.into()),
+            "AcctTotalAppsCreated" => TealValue::Uint(data.total_created_apps),
+            "AcctTotalAppsOptedIn" => TealValue::Uint(data.total_apps_opted_in),
+            "AcctTotalAssetsCreated" => TealValue::Uint(data.total_created_assets),
+            "AcctTotalAssets" => TealValue::Uint(data.total_assets_opted_in),
+            "AcctTotalBoxes" => TealValue::Uint(data.total_boxes),
+            "AcctTotalBoxBytes" => TealValue::Uint(data.total_box_bytes),
+            "AcctIncentiveEligible" => bool_value(data.incentive_eligible),
+            "AcctLastProposed" => TealValue::Uint(data.last_proposed),
+            "AcctLastHeartbeat" => TealValue::Uint(data.last_heartbeat),
+            _ => {
+                return Err(Fault::new(format!(
+                    "account field {} is not supported by the emulator",
+                    field
+                )));
+            }
+        };
+        Ok((value, data.balance > 0))
+    }
+
+    fn global_value(&self, app_id: u64, key: &[u8]) -> Option<TealValue> {
+        self.ctx
+            .app_params(app_id)
+            .and_then(|params| params.global_state.get(key))
+            .cloned()
+    }
+
+    fn local_value(&self, address: &Address, app_id: u64, key: &[u8]) -> Option<TealValue> {
+        self.ctx
+            .local_state(address, app_id)
+            .and_then(|local_state| local_state.key_values.get(key))
+            .cloned()
+    }
+
+    fn global_put(&mut self, key: Vec<u8>, value: TealValue) -> Result<(), Fault> {
+        check_key_value(&key, &value)?;
+        let app_id = self.invocation.app_id;
+        let params = self
+            .ctx
+            .app_params_mut(app_id)
+            .ok_or_else(|| Fault::new(format!("application {} does not exist", app_id)))?;
+        params.global_state.insert(key.clone(), value.clone());
+        check_schema(&params.global_state, &params.global_state_schema)?;
+        self.state_changes
+            .push(state_operation("w", "g", &key, Some(&value), None));
+        Ok(())
+    }
+
+    fn global_del(&mut self, key: Vec<u8>) -> Result<(), Fault> {
+        let app_id = self.invocation.app_id;
+        if let Some(params) = self.ctx.app_params_mut(app_id) {
+            params.global_state.remove(&key);
+        }
+        self.state_changes
+            .push(state_operation("d", "g", &key, None, None));
+        Ok(())
+    }
+
+    fn local_put(&mut self, address: Address, key: Vec<u8>, value: TealValue) -> Result<(), Fault> {
+        check_key_value(&key, &value)?;
+        let app_id = self.invocation.app_id;
+        let local_state = self.ctx.local_state_mut(&address, app_id).ok_or_else(|| {
+            Fault::new(format!(
+                "account {} is not opted in to application {}",
+                address, app_id
+            ))
+        })?;
+        local_state.key_values.insert(key.clone(), value.clone());
+        check_schema(&local_state.key_values, &local_state.schema)?;
+        self.state_changes.push(state_operation(
+            "w",
+            "l",
+            &key,
+            Some(&value),
+            Some(&address),
+        ));
+        Ok(())
+    }
+
+    fn local_del(&mut self, address: Address, key: Vec<u8>) -> Result<(), Fault> {
+        let app_id = self.invocation.app_id;
+        let local_state = self.ctx.local_state_mut(&address, app_id).ok_or_else(|| {
+            Fault::new(format!(
+                "account {} is not opted in to application {}",
+                address, app_id
+            ))
+        })?;
+        local_state.key_values.remove(&key);
+        self.state_changes
+            .push(state_operation("d", "l", &key, None, Some(&address)));
+        Ok(())
+    }
+
+    fn box_key(&self, name: Vec<u8>) -> Result<(u64, Vec<u8>), Fault> {
+        if name.is_empty() {
+            return Err(Fault::new("box names may not be zero length"));
+        }
+        if name.len() > MAX_BOX_NAME_LENGTH {
+            return Err(Fault::new(format!(
+                "name too long: length was {}, maximum is {}",
+                name.len(),
+                MAX_BOX_NAME_LENGTH
+            )));
+        }
+        self.availability
+            .box_reference(self.invocation.app_id, &name)?;
+        Ok((self.invocation.app_id, name))
+    }
+
+    fn box_contents(&self, name: Vec<u8>) -> Result<Option<&Vec<u8>>, Fault> {
+        let key = self.box_key(name)?;
+        Ok(self.ctx.ledger.boxes.get(&key))
+    }
+
+    fn existing_box(&self, name: &[u8]) -> Result<Vec<u8>, Fault> {
+        self.box_contents(name.to_vec())?
+            .cloned()
+            .ok_or_else(|| Fault::new(format!("no such box {:?}", String::from_utf8_lossy(name))))
+    }
+
+    fn check_box_size(&self, size: u64) -> Result<(), Fault> {
+        if size > self.ctx.params.max_box_size {
+            return Err(Fault::new(format!(
+                "box size too large: {}, maximum is {}",
+                size, self.ctx.params.max_box_size
+            )));
+        }
+        Ok(())
+    }
+
+    /// Stores the contents of a box, charging a new box to the app account's minimum balance.
+    fn write_box(&mut self, name: Vec<u8>, contents: Vec<u8>) -> Result<(), Fault> {
+        let key = self.box_key(name)?;
+        let previous = self.ctx.ledger.boxes.get(&key).map(Vec::len);
+        let app_address = self.app_address();
+        let data = &mut self.ctx.account_mut(&app_address).data;
+        match previous {
+            Some(previous) => {
+                data.total_box_bytes =
+                    data.total_box_bytes + contents.len() as u64 - previous as u64;
+            }
+            None => {
+                data.total_boxes += 1;
+                data.total_box_bytes += (key.1.len() + contents.len()) as u64;
+            }
+        }
+        self.state_changes.push(state_operation(
+            "w",
+            "b",
+            &key.1,
+            Some(&TealValue::Bytes(contents.clone())),
+            None,
+        ));
+        self.ctx.ledger.boxes.insert(key, contents);
+        Ok(())
+    }
+
+    fn delete_box(&mut self, name: Vec<u8>) -> Result<bool, Fault> {
+        let key = self.box_key(name)?;
+        let Some(contents) = self.ctx.ledger.boxes.remove(&key) else {
+            return Ok(false);
+        };
+        let app_address = self.app_address();
+        let data = &mut self.ctx.account_mut(&app_address).data;
+        data.total_boxes -= 1;
+        data.total_box_bytes -= (key.1.len() + contents.len()) as u64;
+        self.state_changes
+            .push(state_operation("d", "b", &key.1, None, None));
+        Ok(true)
+    }
+
+    fn begin_inner(&mut self) {
+        let mut fields = InnerFields::default();
+        fields
+            .values
+            .insert("Sender", address_value(Some(&self.app_address())));
+        let fee = self
+            .ctx
+            .params
+            .min_txn_fee
+            .saturating_sub(self.ctx.fee_credit);
+        fields.values.insert("Fee", TealValue::Uint(fee));
+        self.pending.push(fields);
+    }
+
+    /// Checks that an account, asset or app assigned to an inner transaction field is available.
+    fn check_inner_field(&self, field: &str, value: &TealValue) -> Result<(), Fault> {
+        match (field, value) {
+            (
+                "Sender" | "Receiver" | "CloseRemainderTo" | "AssetSender" | "AssetReceiver"
+                | "AssetCloseTo" | "FreezeAssetAccount" | "Accounts",
+                _,
+            ) => self
+                .availability
+                .account(&self.account_reference(value.clone())?),
+            ("XferAsset" | "FreezeAsset" | "Assets", TealValue::Uint(asset_id))
+            | ("ConfigAsset", TealValue::Uint(asset_id @ 1..)) => {
+                self.availability.asset(self.asset_reference(*asset_id))
+            }
+            ("Applications", TealValue::Uint(app_id))
+            | ("ApplicationID", TealValue::Uint(app_id @ 1..)) => self.availability.app(*app_id),
+            _ => Ok(()),
+        }
+    }
+
+    fn set_inner_field(&mut self, field: &'static str, value: TealValue) -> Result<(), Fault> {
+        self.check_inner_field(field, &value)?;
+        let fields = self
+            .pending
+            .last_mut()
+            .ok_or_else(|| Fault::new("itxn_field without itxn_begin"))?;
+        if INNER_ARRAY_FIELDS.contains(&field) {
+            fields.arrays.entry(field).or_default().push(value);
+        } else if field == "TypeEnum" {
+            fields.values.insert("Type", value);
+        } else if INNER_FIELDS.contains(&field) {
+            fields.values.insert(field, value);
+        } else {
+            return Err(Fault::new(format!(
+                "{} is not allowed in itxn_field",
+                field
+            )));
+        }
+        Ok(())
+    }
+
+    fn build_inner(&self, fields: &InnerFields) -> Result<Transaction, Fault> {
+        let optional_uint = |name: &str| match fields.values.get(name) {
+            None => Ok(None),
+            Some(TealValue::Uint(value)) => Ok(Some(*value)),
+            Some(TealValue::Bytes(_)) => {
+                Err(Fault::new(format!("{} must be set to a uint64", name)))
+            }
+        };
+        let uint = |name: &str| optional_uint(name).map(Option::unwrap_or_default);
+        let optional_bytes = |name: &str| match fields.values.get(name) {
+            None => Ok(None),
+            Some(TealValue::Bytes(bytes)) => Ok(Some(bytes.clone())),
+            Some(TealValue::Uint(_)) => {
+                Err(Fault::new(format!("{} must be set to a []byte", name)))
+            }
+        };
+        let optional_string = |name: &str| {
+            optional_bytes(name)
+                .map(|bytes| bytes.map(|bytes| String::from_utf8_lossy(&bytes).into_owned()))
+        };
+        let optional_address = |name: &str| {
+            fields
+                .values
+                .get(name)
+                .map(|value| self.account_reference(value.clone()))
+                .transpose()
+        };
+        let address = |name: &str| optional_address(name).map(Option::unwrap_or_default);
+        let array = |name: &str| fields.arrays.get(name).cloned().unwrap_or_default();
+        let program = |name: &str, pages: &str| -> Result<Option<Vec<u8>>, Fault> {
+            match fields.arrays.get(pages) {
+                Some(pages) => Ok(Some(
+                    pages
+                        .iter()
+                        .flat_map(|page| match page {
+                            TealValue::Bytes(bytes) => bytes.clone(),
+                            TealValue::Uint(_) => Vec::new(),
+                        })
+                        .collect(),
+                )),
+                None => optional_bytes(name),
+            }
+        };
+        let schema = |uints: &str, byte_slices: &str| -> Result<_, Fault> {
+            let (uints, byte_slices) = (optional_uint(uints)?, optional_uint(byte_slices)?);
+            Ok(
+                (uints.is_some() || byte_slices.is_some()).then(|| algokit_transact::StateSchema {
+                    num_uints: uints.unwrap_or(0) as u32,
+                    num_byte_slices: byte_slices.unwrap_or(0) as u32,
+                }),
+            )
+        };
+
+        let parent = &self.invocation.fields.header;
+        let header = TransactionHeader {
+            sender: address("Sender")?,
+            fee: Some(uint("Fee")?),
+            first_valid: parent.first_valid,
+            last_valid: parent.last_valid,
+            genesis_hash: parent.genesis_hash,
+            genesis_id: parent.genesis_id.clone(),
+            note: optional_bytes("Note")?,
+            rekey_to: optional_address("RekeyTo")?,
+            lease: None,
+            group: None,
+        };
+        let tx_type = match fields.values.get("Type") {
+            Some(TealValue::Bytes(name)) => String::from_utf8_lossy(name).into_owned(),
+            Some(TealValue::Uint(value)) => TRANSACTION_TYPES
+                .get((*value as usize).wrapping_sub(1))
+                .map_or_else(String::new, |name| name.to_string()),
+            None => String::new(),
+        };
+
+        Ok(match tx_type.as_str() {
+            "pay" => Transaction::Payment(PaymentTransactionFields {
+                header,
+                receiver: address("Receiver")?,
+                amount: uint("Amount")?,
+                close_remainder_to: optional_address("CloseRemainderTo")?,
+            }),
+            "axfer" => Transaction::AssetTransfer(AssetTransferTransactionFields {
+                header,
+                asset_id: self.asset_reference(uint("XferAsset")?),
+                amount: uint("AssetAmount")?,
+                receiver: address("AssetReceiver")?,
+                asset_sender: optional_address("AssetSender")?,
+                close_remainder_to: optional_address("AssetCloseTo")?,
+            }),
+            "acfg" => {
+                let metadata_hash = optional_bytes("ConfigAssetMetadataHash")?
+                    .map(|hash| {
+                        <[u8; 32]>::try_from(hash.as_slice())
+                            .map_err(|_| Fault::new("ConfigAssetMetadataHash must be 32 bytes"))
+                    })
+                    .transpose()?;
+                Transaction::AssetConfig(AssetConfigTransactionFields {
+                    header,
+                    asset_id: optional_uint("ConfigAsset")?
+                        .map_or(0, |asset| self.asset_reference(asset)),
+                    total: optional_uint("ConfigAssetTotal")?,
+                    decimals: optional_uint("ConfigAssetDecimals")?.map(|value| value as u32),
+                    default_frozen: optional_uint("ConfigAssetDefaultFrozen")?
+                        .map(|value| value != 0),
+                    asset_name: optional_string("ConfigAssetName")?,
+                    unit_name: optional_string("ConfigAssetUnitName")?,
+                    url: optional_string("ConfigAssetURL")?,
+                    metadata_hash,
+                    manager: optional_address("ConfigAssetManager")?,
+                    reserve: optional_address("ConfigAssetReserve")?,
+                    freeze: optional_address("ConfigAssetFreeze")?,
+                    clawback: optional_address("ConfigAssetClawback")?,
+                })
+            }
+            "afrz" => Transaction::AssetFreeze(AssetFreezeTransactionFields {
+                header,
+                asset_id: self.asset_reference(uint("FreezeAsset")?),
+                freeze_target: address("FreezeAssetAccount")?,
+                frozen: uint("FreezeAssetFrozen")? != 0,
+            }),
+            "appl" => {
+                let on_complete = match uint("OnCompletion")? {
+                    0 => OnApplicationComplete::NoOp,
+                    1 => OnApplicationComplete::OptIn,
+                    2 => OnApplicationComplete::CloseOut,
+                    3 => OnApplicationComplete::ClearState,
+                    4 => OnApplicationComplete::UpdateApplication,
+                    5 => OnApplicationComplete::DeleteApplication,
+                    value => {
+                        return Err(Fault::new(format!("{} is not a valid OnCompletion", value)));
+                    }
+                };
+                let args = array("ApplicationArgs")
+                    .into_iter()
+                    .map(|arg| match arg {
+                        TealValue::Bytes(bytes) => Ok(bytes),
+                        TealValue::Uint(_) => {
+                            Err(Fault::new("ApplicationArgs must be set to a []byte"))
+                        }
+                    })
+                    .collect::<Result<Vec<_>, _>>()?;
+                let accounts = array("Accounts")
+                    .into_iter()
+                    .map(|account| self.account_reference(account))
+                    .collect::<Result<Vec<_>, _>>()?;
+                let ids = |name: &str| {
+                    array(name)
+                        .into_iter()
+                        .map(|id| match id {
+                            TealValue::Uint(id) => Ok(id),
+                            TealValue::Bytes(_) => {
+                                Err(Fault::new(format!("{} must be set to a uint64", name)))
+                            }
+                        })
+                        .collect::<Result<Vec<_>, _>>()
+                };
+                Transaction::AppCall(AppCallTransactionFields {
+                    header,
+                    app_id: uint("ApplicationID")?,
+                    on_complete,
+                    approval_program: program("ApprovalProgram", "ApprovalProgramPages")?,
+                    clear_state_program: program("ClearStateProgram", "ClearStateProgramPages")?,
+                    global_state_schema: schema("GlobalNumUint", "GlobalNumByteSlice")?,
+                    local_state_schema: schema("LocalNumUint", "LocalNumByteSlice")?,
+                    extra_program_pages: optional_uint("ExtraProgramPages")?
+                        .map(|value| value as u32),
+                    args: non_empty(args),
+                    account_references: non_empty(accounts),
+                    app_references: non_empty(ids("Applications")?),
+                    asset_references: non_empty(ids("Assets")?),
+                    box_references: None,
+                })
+            }
+            "" => return Err(Fault::new("itxn_submit without a transaction type")),
+            other => {
+                return Err(Fault::new(format!(
+                    "{} inner transactions are not supported by the emulator",
+                    other
+                )));
+            }
+        })
+    }
+
+    fn submit_inner(&mut self) -> Result<(), Fault> {
+        if self.pending.is_empty() {
+            return Err(Fault::new("itxn_submit without itxn_begin"));
+        }
+        let pending = std::mem::take(&mut self.pending);
+        if self.ctx.inner_count + pending.len() > MAX_INNER_TRANSACTIONS {
+            return Err(Fault::new(format!(
+                "too many inner transactions {} with {} left",
+                pending.len(),
+                MAX_INNER_TRANSACTIONS - self.ctx.inner_count
+            )));
+        }
+        self.ctx.inner_count += pending.len();
+        let transactions = pending
+            .iter()
+            .map(|fields| self.build_inner(fields))
+            .collect::<Result<Vec<_>, _>>()?;
+
+        let app_address = self.app_address();
+        let min_fee = self.ctx.params.min_txn_fee;
+        for tx in &transactions {
+            let sender = tx.sender();
+            if *sender != app_address && self.ctx.account_data(sender).auth_address != app_address {
+                return Err(Fault::new(format!(
+                    "unauthorized: {} is not rekeyed to application {}",
+                    sender, self.invocation.app_id
+                )));
+            }
+            let fee = tx.header().fee.unwrap_or(0);
+            if fee < min_fee {
+                let deficit = min_fee - fee;
+                if self.ctx.fee_credit < deficit {
+                    return Err(Fault::new(format!(
+                        "fee too small {} with a credit of {}",
+                        fee, self.ctx.fee_credit
+                    )));
+                }
+                self.ctx.fee_credit -= deficit;
+            } else {
+                self.ctx.fee_credit += fee - min_fee;
+            }
+        }
+
+        let (applied, fault) = apply_group(self.ctx, &transactions, Some(self.invocation.app_id));
+        for result in &applied {
+            self.spawned_inners.push(self.inner_traces.len() as u64);
+            self.inner_traces.push(result.exec_trace.clone());
+        }
+        if let Some(mut fault) = fault {
+            self.spawned_inners.push(self.inner_traces.len() as u64);
+            self.inner_traces.push(
+                fault
+                    .exec_trace
+                    .take()
+                    .map(|trace| *trace)
+                    .unwrap_or_default(),
+            );
+            return Err(fault);
+        }
+        for result in &applied {
+            self.availability.add_created(result);
+        }
+        let start = self.inner_transactions.len();
+        self.inner_transactions.extend(applied);
+        self.last_inner_group = start..self.inner_transactions.len();
+        Ok(())
+    }
+
+    fn byte_math(&mut self, name: &str) -> Result<(), Fault> {
+        if name == "b~" {
+            let a = self.pop_bytes()?;
+            return self.push_bytes(a.iter().map(|byte| !byte).collect());
+        }
+        let b = self.pop_bytes()?;
+        let a = self.pop_bytes()?;
+        if matches!(name, "b|" | "b&" | "b^") {
+            let length = a.len().max(b.len());
+            let pad = |bytes: &[u8]| [vec![0; length - bytes.len()], bytes.to_vec()].concat();
+            let (a, b) = (pad(&a), pad(&b));
+            let combined = a
+                .iter()
+                .zip(&b)
+                .map(|(a, b)| match name {
+                    "b|" => a | b,
+                    "b&" => a & b,
+                    _ => a ^ b,
+                })
+                .collect();
+            return self.push_bytes(combined);
+        }
+
+        if a.len() > MAX_BYTE_MATH_LENGTH || b.len() > MAX_BYTE_MATH_LENGTH {
+            return Err(Fault::new("math attempted on large byte-array"));
+        }
+        let (a, b) = (BigUint::from_bytes_be(&a), BigUint::from_bytes_be(&b));
+        match name {
+            "b+" => self.push_bytes(big_uint_bytes(a + b))?,
+            "b-" if a < b => return Err(Fault::new("byte math would have negative result")),
+            "b-" => self.push_bytes(big_uint_bytes(a - b))?,
+            "b*" => self.push_bytes(big_uint_bytes(a * b))?,
+            "b/" | "b%" if b == BigUint::ZERO => return Err(Fault::new("division by zero")),
+            "b/" => self.push_bytes(big_uint_bytes(a / b))?,
+            "b%" => self.push_bytes(big_uint_bytes(a % b))?,
+            "b<" => self.push_bool(a < b),
+            "b>" => self.push_bool(a > b),
+            "b<=" => self.push_bool(a <= b),
+            "b>=" => self.push_bool(a >= b),
+            "b==" => self.push_bool(a == b),
+            _ => self.push_bool(a != b),
+        }
+        Ok(())
+    }
+
+    fn step(&mut self, instruction: &Instruction) -> Result<Flow, Fault> {
+        let name = instruction.op.name;
+        self.op = name;
+        let field = immediate_field(instruction);
+        match name {
+            "err" => return Err(Fault::new("err opcode executed")),
+            "sha256" => {
+                let a = self.pop_bytes()?;
+                self.push_bytes(Sha256::digest(a).to_vec())?;
+            }
+            "keccak256" => {
+                let a = self.pop_bytes()?;
+                self.push_bytes(Keccak256::digest(a).to_vec())?;
+            }
+            "sha512_256" => {
+                let a = self.pop_bytes()?;
+                self.push_bytes(Sha512_256::digest(a).to_vec())?;
+            }
+            "sha3_256" => {
+                let a = self.pop_bytes()?;
+                self.push_bytes(Sha3_256::digest(a).to_vec())?;
+            }
+            "+" => {
+                let (a, b) = self.pop_uints()?;
+                let sum = a.checked_add(b).ok_or_else(|| Fault::new("+ overflowed"))?;
+                self.push_uint(sum);
+            }
+            "-" => {
+                let (a, b) = self.pop_uints()?;
+                let difference = a
+                    .checked_sub(b)
+                    .ok_or_else(|| Fault::new("- would result negative"))?;
+                self.push_uint(difference);
+            }
+            "/" => {
+                let (a, b) = self.pop_uints()?;
+                self.push_uint(a.checked_div(b).ok_or_else(|| Fault::new("/ 0"))?);
+            }
+            "*" => {
+                let (a, b) = self.pop_uints()?;
+                self.push_uint(a.checked_mul(b).ok_or_else(|| Fault::new("* overflowed"))?);
+            }
+            "%" => {
+                let (a, b) = self.pop_uints()?;
+                self.push_uint(a.checked_rem(b).ok_or_else(|| Fault::new("% 0"))?);
+            }
+            "<" | ">" | "<=" | ">=" | "&&" | "||" => {
+                let (a, b) = self.pop_uints()?;
+                self.push_bool(match name {
+                    "<" => a < b,
+                    ">" => a > b,
+                    "<=" => a <= b,
+                    ">=" => a >= b,
+                    "&&" => a != 0 && b != 0,
+                    _ => a != 0 || b != 0,
+                });
+            }
+            "==" | "!=" => {
+                let b = self.pop()?;
+                let a = self.pop()?;
+                if std::mem::discriminant(&a) != std::mem::discriminant(&b) {
+                    return Err(Fault::new(format!(
+                        "cannot compare uint64 to []byte with {}",
+                        name
+                    )));
+                }
+                self.push_bool((a == b) == (name == "=="));
+            }
+            "!" => {
+                let a = self.pop_uint()?;
+                self.push_bool(a == 0);
+            }
+            "len" => {
+                let a = self.pop_bytes()?;
+                self.push_uint(a.len() as u64);
+            }
+            "itob" => {
+                let a = self.pop_uint()?;
+                self.push_bytes(a.to_be_bytes().to_vec())?;
+            }
+            "btoi" => {
+                let a = self.pop_bytes()?;
+                if a.len() > 8 {
+                    return Err(Fault::new(format!(
+                        "btoi arg too long, got [{}]bytes",
+                        a.len()
+                    )));
+                }
+                self.push_uint(bytes_to_uint(&a));
+            }
+            "|" | "&" | "^" => {
+                let (a, b) = self.pop_uints()?;
+                self.push_uint(match name {
+                    "|" => a | b,
+                    "&" => a & b,
+                    _ => a ^ b,
+                });
+            }
+            "~" => {
+                let a = self.pop_uint()?;
+                self.push_uint(!a);
+            }
+            "mulw" => {
+                let (a, b) = self.pop_uints()?;
+                let product = u128::from(a) * u128::from(b);
+                self.push_uint((product >> 64) as u64);
+                self.push_uint(product as u64);
+            }
+            "addw" => {
+                let (a, b) = self.pop_uints()?;
+                let (sum, carry) = a.overflowing_add(b);
+                self.push_uint(carry as u64);
+                self.push_uint(sum);
+            }
+            "divmodw" => {
+                let (c, d) = self.pop_uints()?;
+                let (a, b) = self.pop_uints()?;
+                let dividend = (u128::from(a) << 64) | u128::from(b);
+                let divisor = (u128::from(c) << 64) | u128::from(d);
+                if divisor == 0 {
+                    return Err(Fault::new("/ 0"));
+                }
+                let (quotient, remainder) = (dividend / divisor, dividend % divisor);
+                self.push_uint((quotient >> 64) as u64);
+                self.push_uint(quotient as u64);
+                self.push_uint((remainder >> 64) as u64);
+                self.push_uint(remainder as u64);
+            }
+            "divw" => {
+                let c = self.pop_uint()?;
+                let (a, b) = self.pop_uints()?;
+                if c == 0 {
+                    return Err(Fault::new("/ 0"));
+                }
+                let quotient = ((u128::from(a) << 64) | u128::from(b)) / u128::from(c);
+                let quotient =
+                    u64::try_from(quotient).map_err(|_| Fault::new("divw overflowed"))?;
+                self.push_uint(quotient);
+            }
+            "intcblock" => {
+                if let Some(Immediate::Uints(values)) = instruction.immediates.first() {
+                    self.int_constants = values.clone();
+                }
+            }
+            "bytecblock" => {
+                if let Some(Immediate::ByteList(values)) = instruction.immediates.first() {
+                    self.byte_constants = values.clone();
+                }
+            }
+            "intc" | "intc_0" | "intc_1" | "intc_2" | "intc_3" => {
+                let index = match name {
+                    "intc" => immediate_uint(instruction, 0),
+                    _ => u64::from(instruction.op.opcode - 0x22),
+                };
+                let value = *self.int_constants.get(index as usize).ok_or_else(|| {
+                    Fault::new(format!(
+                        "intc {} beyond {} constants",
+                        index,
+                        self.int_constants.len()
+                    ))
+                })?;
+                self.push_uint(value);
+            }
+            "bytec" | "bytec_0" | "bytec_1" | "bytec_2" | "bytec_3" => {
+                let index = match name {
+                    "bytec" => immediate_uint(instruction, 0),
+                    _ => u64::from(instruction.op.opcode - 0x28),
+                };
+                let value = self
+                    .byte_constants
+                    .get(index as usize)
+                    .cloned()
+                    .ok_or_else(|| {
+                        Fault::new(format!(
+                            "bytec {} beyond {} constants",
+                            index,
+                            self.byte_constants.len()
+                        ))
+                    })?;
+                self.push_bytes(value)?;
+            }
+            "pushint" => self.push_uint(immediate_uint(instruction, 0)),
+            "pushbytes" => {
+                if let Some(Immediate::Bytes(bytes)) = instruction.immediates.first() {
+                    self.push_bytes(bytes.clone())?;
+                }
+            }
+            "pushints" => {
+                if let Some(Immediate::Uints(values)) = instruction.immediates.first() {
+                    for value in values {
+                        self.push_uint(*value);
+                    }
+                }
+            }
+            "pushbytess" => {
+                if let Some(Immediate::ByteList(values)) = instruction.immediates.first() {
+                    for value in values {
+                        self.push_bytes(value.clone())?;
+                    }
+                }
+            }
+            "arg" | "arg_0" | "arg_1" | "arg_2" | "arg_3" | "args" => {
+                return Err(Fault::new(format!(
+                    "{} is only available to logic signatures",
+                    name
+                )));
+            }
+            "txn" => {
+                let index = self.invocation.index as u64;
+                let value = self.group_transaction_field(index, field, None)?;
+                self.push(value);
+            }
+            "txna" => {
+                let index = self.invocation.index as u64;
+                let array_index = immediate_uint(instruction, 1);
+                let value = self.group_transaction_field(index, field, Some(array_index))?;
+                self.push(value);
+            }
+            "txnas" => {
+                let array_index = self.pop_uint()?;
+                let index = self.invocation.index as u64;
+                let value = self.group_transaction_field(index, field, Some(array_index))?;
+                self.push(value);
+            }
+            "gtxn" => {
+                let value =
+                    self.group_transaction_field(immediate_uint(instruction, 0), field, None)?;
+                self.push(value);
+            }
+            "gtxna" => {
+                let array_index = immediate_uint(instruction, 2);
+                let value = self.group_transaction_field(
+                    immediate_uint(instruction, 0),
+                    field,
+                    Some(array_index),
+                )?;
+                self.push(value);
+            }
+            "gtxnas" => {
+                let array_index = self.pop_uint()?;
+                let value = self.group_transaction_field(
+                    immediate_uint(instruction, 0),
+                    field,
+                    Some(array_index),
+                )?;
+                self.push(value);
+            }
+            "gtxns" => {
+                let index = self.pop_uint()?;
+                let value = self.group_transaction_field(index, field, None)?;
+                self.push(value);
+            }
+            "gtxnsa" => {
+                let index = self.pop_uint()?;
+                let array_index = immediate_uint(instruction, 1);
+                let value = self.group_transaction_field(index, field, Some(array_index))?;
+                self.push(value);
+            }
+            "gtxnsas" => {
+                let array_index = self.pop_uint()?;
+                let index = self.pop_uint()?;
+                let value = self.group_transaction_field(index, field, Some(array_index))?;
+                self.push(value);
+            }
+            "global" => {
+                let value = self.global_field(field)?;
+                self.push(value);
+            }
+            "load" => {
+                let value = self.load(immediate_uint(instruction, 0))?;
+                self.push(value);
+            }
+            "store" => {
+                let value = self.pop()?;
+                self.store(immediate_uint(instruction, 0), value)?;
+            }
+            "loads" => {
+                let slot = self.pop_uint()?;
+                let value = self.load(slot)?;
+                self.push(value);
+            }
+            "stores" => {
+                let value = self.pop()?;
+                let slot = self.pop_uint()?;
+                self.store(slot, value)?;
+            }
+            "gload" => {
+                let value = self.group_scratch(
+                    immediate_uint(instruction, 0),
+                    immediate_uint(instruction, 1),
+                )?;
+                self.push(value);
+            }
+            "gloads" => {
+                let index = self.pop_uint()?;
+                let value = self.group_scratch(index, immediate_uint(instruction, 0))?;
+                self.push(value);
+            }
+            "gloadss" => {
+                let slot = self.pop_uint()?;
+                let index = self.pop_uint()?;
+                let value = self.group_scratch(index, slot)?;
+                self.push(value);
+            }
+            "gaid" => {
+                let id = self.group_created_id(immediate_uint(instruction, 0))?;
+                self.push_uint(id);
+            }
+            "gaids" => {
+                let index = self.pop_uint()?;
+                let id = self.group_created_id(index)?;
+                self.push_uint(id);
+            }
+            "bnz" => {
+                if self.pop_uint()? != 0 {
+                    return Ok(Flow::Jump(immediate_label(instruction)));
+                }
+            }
+            "bz" => {
+                if self.pop_uint()? == 0 {
+                    return Ok(Flow::Jump(immediate_label(instruction)));
+                }
+            }
+            "b" => return Ok(Flow::Jump(immediate_label(instruction))),
+            "return" => {
+                let value = self.pop_uint()?;
+                return Ok(Flow::Halt(value != 0));
+            }
+            "assert" => {
+                if self.pop_uint()? == 0 {
+                    return Err(Fault::new("assert failed"));
+                }
+            }
+            "bury" => {
+                let depth = immediate_uint(instruction, 0);
+                if depth == 0 {
+                    return Err(Fault::new("bury 0 always fails"));
+                }
+                let value = self.pop()?;
+                let position = self.touch(depth - 1)?;
+                self.stack[position] = value;
+            }
+            "popn" => {
+                for _ in 0..immediate_uint(instruction, 0) {
+                    self.pop()?;
+                }
+            }
+            "dupn" => {
+                let value = self.pop()?;
+                for _ in 0..=immediate_uint(instruction, 0) {
+                    self.push(value.clone());
+                }
+            }
+            "pop" => {
+                self.pop()?;
+            }
+            "dup" => {
+                let a = self.pop()?;
+                self.push(a.clone());
+                self.push(a);
+            }
+            "dup2" => {
+                let b = self.pop()?;
+                let a = self.pop()?;
+                self.stack.extend([a.clone(), b.clone(), a, b]);
+            }
+            "dig" => {
+                let position = (self.stack.len() as u64)
+                    .checked_sub(immediate_uint(instruction, 0) + 1)
+                    .ok_or_else(|| Fault::new("dig stack underflow"))?;
+                let value = self.stack[position as usize].clone();
+                self.push(value);
+            }
+            "swap" => {
+                let b = self.pop()?;
+                let a = self.pop()?;
+                self.push(b);
+                self.push(a);
+            }
+            "select" => {
+                let c = self.pop_uint()?;
+                let b = self.pop()?;
+                let a = self.pop()?;
+                self.push(if c != 0 { b } else { a });
+            }
+            "cover" => {
+                let depth = immediate_uint(instruction, 0);
+                let position = self.touch(depth)?;
+                let value = self.pop()?;
+                self.stack.insert(position, value);
+            }
+            "uncover" => {
+                let position = self.touch(immediate_uint(instruction, 0))?;
+                let value = self.stack.remove(position);
+                self.push(value);
+            }
+            "concat" => {
+                let b = self.pop_bytes()?;
+                let a = self.pop_bytes()?;
+                self.push_bytes([a, b].concat())?;
+            }
+            "substring" => {
+                let a = self.pop_bytes()?;
+                let bytes = substring(
+                    &a,
+                    immediate_uint(instruction, 0),
+                    immediate_uint(instruction, 1),
+                )?;
+                self.push_bytes(bytes)?;
+            }
+            "substring3" => {
+                let (start, end) = self.pop_uints()?;
+                let a = self.pop_bytes()?;
+                self.push_bytes(substring(&a, start, end)?)?;
+            }
+            "extract" => {
+                let a = self.pop_bytes()?;
+                let start = immediate_uint(instruction, 0);
+                let length = match immediate_uint(instruction, 1) {
+                    0 => (a.len() as u64).checked_sub(start).ok_or_else(|| {
+                        Fault::new(format!(
+                            "extraction start {} is beyond length {}",
+                            start,
+                            a.len()
+                        ))
+                    })?,
+                    length => length,
+                };
+                self.push_bytes(extract(&a, start, length)?)?;
+            }
+            "extract3" => {
+                let (start, length) = self.pop_uints()?;
+                let a = self.pop_bytes()?;
+                self.push_bytes(extract(&a, start, length)?)?;
+            }
+            "extract_uint16" | "extract_uint32" | "extract_uint64" => {
+                let width = match name {
+                    "extract_uint16" => 2,
+                    "extract_uint32" => 4,
+                    _ => 8,
+                };
+                let start = self.pop_uint()?;
+                let a = self.pop_bytes()?;
+                self.push_uint(bytes_to_uint(&extract(&a, start, width)?));
+            }
+            "getbit" => {
+                let index = self.pop_uint()?;
+                let bit = match self.pop()? {
+                    TealValue::Uint(value) if index < 64 => (value >> index) & 1,
+                    TealValue::Bytes(bytes) if index / 8 < bytes.len() as u64 => {
+                        u64::from(bytes[(index / 8) as usize] >> (7 - index % 8)) & 1
+                    }
+                    _ => return Err(Fault::new(format!("getbit index {} beyond value", index))),
+                };
+                self.push_uint(bit);
+            }
+            "setbit" => {
+                let bit = self.pop_uint()?;
+                if bit > 1 {
+                    return Err(Fault::new("setbit value > 1"));
+                }
+                let index = self.pop_uint()?;
+                match self.pop()? {
+                    TealValue::Uint(value) if index < 64 => {
+                        let mask = 1u64 << index;
+                        self.push_uint(if bit == 1 {
+                            value | mask
+                        } else {
+                            value & !mask
+                        });
+                    }
+                    TealValue::Bytes(mut bytes) if index / 8 < bytes.len() as u64 => {
+                        let mask = 0x80u8 >> (index % 8);
+                        let byte = &mut bytes[(index / 8) as usize];
+                        *byte = if bit == 1 {
+                            *byte | mask
+                        } else {
+                            *byte & !mask
+                        };
+                        self.push_bytes(bytes)?;
+                    }
+                    _ => return Err(Fault::new(format!("setbit index {} beyond value", index))),
+                }
+            }
+            "getbyte" => {
+                let index = self.pop_uint()?;
+                let a = self.pop_bytes()?;
+                let byte = a.get(index as usize).ok_or_else(|| {
+                    Fault::new(format!("getbyte index {} beyond length {}", index, a.len()))
+                })?;
+                self.push_uint((*byte).into());
+            }
+            "setbyte" => {
+                let value = self.pop_uint()?;
+                if value > 255 {
+                    return Err(Fault::new("setbyte value > 255"));
+                }
+                let index = self.pop_uint()?;
+                let mut a = self.pop_bytes()?;
+                let length = a.len();
+                let byte = a.get_mut(index as usize).ok_or_else(|| {
+                    Fault::new(format!("setbyte index {} beyond length {}", index, length))
+                })?;
+                *byte = value as u8;
+                self.push_bytes(a)?;
+            }
+            "replace2" => {
+                let b = self.pop_bytes()?;
+                let mut a = self.pop_bytes()?;
+                replace(&mut a, immediate_uint(instruction, 0), &b)?;
+                self.push_bytes(a)?;
+            }
+            "replace3" => {
+                let b = self.pop_bytes()?;
+                let start = self.pop_uint()?;
+                let mut a = self.pop_bytes()?;
+                replace(&mut a, start, &b)?;
+                self.push_bytes(a)?;
+            }
+            "base64_decode" => {
+                let a = self.pop_bytes()?;
+                let decoder = match field {
+                    "URLEncoding" => &BASE64_URL_DECODER,
+                    _ => &BASE64_STD_DECODER,
+                };
+                let decoded = decoder
+                    .decode(a)
+                    .map_err(|error| Fault::new(format!("base64_decode failed: {}", error)))?;
+                self.push_bytes(decoded)?;
+            }
+            "balance" => {
+                let address = self.pop_account()?;
+                self.push_uint(self.ctx.ledger.balance(&address));
+            }
+            "min_balance" => {
+                let address = self.pop_account()?;
+                let data = self.ctx.account_data(&address);
+                self.push_uint(self.ctx.min_balance(&data));
+            }
+            "app_opted_in" => {
+                let app_id = self.pop_uint()?;
+                let app_id = self.app_reference(app_id);
+                let address = self.pop_address()?;
+                self.availability.local(&address, app_id)?;
+                self.push_bool(self.ctx.local_state(&address, app_id).is_some());
+            }
+            "app_local_get" => {
+                let key = self.pop_bytes()?;
+                let address = self.pop_address()?;
+                self.availability.local(&address, self.invocation.app_id)?;
+                let value = self.local_value(&address, self.invocation.app_id, &key);
+                self.push(value.unwrap_or(TealValue::Uint(0)));
+            }
+            "app_local_get_ex" => {
+                let key = self.pop_bytes()?;
+                let app_id = self.pop_uint()?;
+                let app_id = self.app_reference(app_id);
+                let address = self.pop_address()?;
+                self.availability.local(&address, app_id)?;
+                let value = self.local_value(&address, app_id, &key);
+                self.push_bool(value.is_some());
+                self.stack
+                    .insert(self.stack.len() - 1, value.unwrap_or(TealValue::Uint(0)));
+            }
+            "app_global_get" => {
+                let key = self.pop_bytes()?;
+                let value = self.global_value(self.invocation.app_id, &key);
+                self.push(value.unwrap_or(TealValue::Uint(0)));
+            }
+            "app_global_get_ex" => {
+                let key = self.pop_bytes()?;
+                let app_id = self.pop_uint()?;
+                let app_id = self.app_reference(app_id);
+                self.availability.app(app_id)?;
+                let value = self.global_value(app_id, &key);
+                self.push_bool(value.is_some());
+                self.stack
+                    .insert(self.stack.len() - 1, value.unwrap_or(TealValue::Uint(0)));
+            }
+            "app_local_put" => {
+                let value = self.pop()?;
+                let key = self.pop_bytes()?;
+                let address = self.pop_address()?;
+                self.availability.local(&address, self.invocation.app_id)?;
+                self.local_put(address, key, value)?;
+            }
+            "app_global_put" => {
+                let value = self.pop()?;
+                let key = self.pop_bytes()?;
+                self.global_put(key, value)?;
+            }
+            "app_local_del" => {
+                let key = self.pop_bytes()?;
+                let address = self.pop_address()?;
+                self.availability.local(&address, self.invocation.app_id)?;
+                self.local_del(address, key)?;
+            }
+            "app_global_del" => {
+                let key = self.pop_bytes()?;
+                self.global_del(key)?;
+            }
+            "asset_holding_get" => {
+                let asset_id = self.pop_uint()?;
+                let asset_id = self.asset_reference(asset_id);
+                let address = self.pop_address()?;
+                self.availability.holding(&address, asset_id)?;
+                let (value, exists) = self.asset_holding_field(&address, asset_id, field);
+                self.push(value);
+                self.push_bool(exists);
+            }
+            "asset_params_get" => {
+                let asset_id = self.pop_uint()?;
+                let asset_id = self.asset_reference(asset_id);
+                self.availability.asset(asset_id)?;
+                let (value, exists) = self.asset_params_field(asset_id, field);
+                self.push(value);
+                self.push_bool(exists);
+            }
+            "app_params_get" => {
+                let app_id = self.pop_uint()?;
+                let app_id = self.app_reference(app_id);
+                self.availability.app(app_id)?;
+                let (value, exists) = self.app_params_field(app_id, field);
+                self.push(value);
+                self.push_bool(exists);
+            }
+            "acct_params_get" => {
+                let address = self.pop_account()?;
+                let (value, exists) = self.account_params_field(&address, field)?;
+                self.push(value);
+                self.push_bool(exists);
+            }
+            "callsub" => {
+                self.frames.push(Frame {
+                    return_pc: instruction.pc + instruction.size,
+                    height: self.stack.len(),
+                    proto: None,
+                });
+                return Ok(Flow::Jump(immediate_label(instruction)));
+            }
+            "retsub" => {
+                let frame = self
+                    .frames
+                    .pop()
+                    .ok_or_else(|| Fault::new("retsub with empty callstack"))?;
+                if let Some((args, returns)) = frame.proto {
+                    if self.stack.len() < frame.height + returns {
+                        return Err(Fault::new(format!(
+                            "retsub executed with stack below frame. Did you pop args? ({} < {})",
+                            self.stack.len(),
+                            frame.height + returns
+                        )));
+                    }
+                    let base = frame.height - args;
+                    self.low_water = self.low_water.min(base);
+                    let results = self.stack.split_off(self.stack.len() - returns);
+                    self.stack.truncate(base);
+                    self.stack.extend(results);
+                }
+                return Ok(Flow::Jump(frame.return_pc));
+            }
+            "proto" => {
+                let args = immediate_uint(instruction, 0) as usize;
+                let returns = immediate_uint(instruction, 1) as usize;
+                let frame = self
+                    .frames
+                    .last_mut()
+                    .ok_or_else(|| Fault::new("proto with empty callstack"))?;
+                if frame.height < args {
+                    return Err(Fault::new(format!(
+                        "callsub to proto that requires {} args with stack height {}",
+                        args, frame.height
+                    )));
+                }
+                frame.proto = Some((args, returns));
+            }
+            "frame_dig" | "frame_bury" => {
+                let offset = match instruction.immediates.first() {
+                    Some(Immediate::Int(offset)) => *offset,
+                    _ => 0,
+                };
+                let (height, args) = match self.frames.last() {
+                    Some(Frame {
+                        height,
+                        proto: Some((args, _)),
+                        ..
+                    }) => (*height as i64, *args as i64),
+                    _ => return Err(Fault::new(format!("{} with empty callstack", name))),
+                };
+                if offset < -args {
+                    return Err(Fault::new(format!(
+                        "{} {} in sub with {} args",
+                        name, offset, args
+                    )));
+                }
+                let position = height + offset;
+                let value = if name == "frame_bury" {
+                    Some(self.pop()?)
+                } else {
+                    None
+                };
+                if position >= self.stack.len() as i64 {
+                    return Err(Fault::new(format!("{} above stack", name)));
+                }
+                let position = position as usize;
+                match value {
+                    Some(value) => {
+                        self.low_water = self.low_water.min(position);
+                        self.stack[position] = value;
+                    }
+                    None => self.push(self.stack[position].clone()),
+                }
+            }
+            "switch" => {
+                let index = self.pop_uint()?;
+                if let Some(target) = immediate_labels(instruction).get(index as usize) {
+                    return Ok(Flow::Jump(*target));
+                }
+            }
+            "match" => {
+                let targets = immediate_labels(instruction);
+                let value = self.pop()?;
+                let mut candidates = Vec::with_capacity(targets.len());
+                for _ in targets {
+                    candidates.push(self.pop()?);
+                }
+                candidates.reverse();
+                if let Some(position) = candidates.iter().position(|candidate| *candidate == value)
+                {
+                    return Ok(Flow::Jump(targets[position]));
+                }
+            }
+            "shl" | "shr" => {
+                let (a, b) = self.pop_uints()?;
+                if b >= 64 {
+                    return Err(Fault::new(format!("{} arg too big, ({})", name, b)));
+                }
+                self.push_uint(if name == "shl" { a << b } else { a >> b });
+            }
+            "sqrt" => {
+                let a = self.pop_uint()?;
+                self.push_uint(a.isqrt());
+            }
+            "bitlen" => {
+                let bits = match self.pop()? {
+                    TealValue::Uint(value) => u64::from(64 - value.leading_zeros()),
+                    TealValue::Bytes(bytes) => BigUint::from_bytes_be(&bytes).bits(),
+                };
+                self.push_uint(bits);
+            }
+            "exp" => {
+                let (a, b) = self.pop_uints()?;
+                if a == 0 && b == 0 {
+                    return Err(Fault::new("0^0 is undefined"));
+                }
+                let power = u32::try_from(b)
+                    .ok()
+                    .and_then(|b| a.checked_pow(b))
+                    .or(if a <= 1 { Some(a) } else { None })
+                    .ok_or_else(|| Fault::new(format!("{}^{} overflow", a, b)))?;
+                self.push_uint(power);
+            }
+            "expw" => {
+                let (a, b) = self.pop_uints()?;
+                if a == 0 && b == 0 {
+                    return Err(Fault::new("0^0 is undefined"));
+                }
+                let power = u32::try_from(b)
+                    .ok()
+                    .and_then(|b| u128::from(a).checked_pow(b))
+                    .or(if a <= 1 { Some(a.into()) } else { None })
+                    .ok_or_else(|| Fault::new(format!("{}^{} overflow", a, b)))?;
+                self.push_uint((power >> 64) as u64);
+                self.push_uint(power as u64);
+            }
+            "bsqrt" => {
+                let a = self.pop_bytes()?;
+                if a.len() > MAX_BYTE_MATH_LENGTH {
+                    return Err(Fault::new("math attempted on large byte-array"));
+                }
+                self.push_bytes(big_uint_bytes(BigUint::from_bytes_be(&a).sqrt()))?;
+            }
+            "b+" | "b-" | "b/" | "b*" | "b<" | "b>" | "b<=" | "b>=" | "b==" | "b!=" | "b%"
+            | "b|" | "b&" | "b^" | "b~" => self.byte_math(name)?,
+            "bzero" => {
+                let length = self.pop_uint()?;
+                if length > MAX_BYTES_LENGTH as u64 {
+                    return Err(Fault::new("bzero attempted to create a too large string"));
+                }
+                self.push_bytes(vec![0; length as usize])?;
+            }
+            "log" => {
+                let message = self.pop_bytes()?;
+                if self.logs.len() == MAX_LOG_CALLS {
+                    return Err(Fault::new(format!(
+                        "too many log calls in program. up to {} is allowed",
+                        MAX_LOG_CALLS
+                    )));
+                }
+                let size = self.logs.iter().map(Vec::len).sum::<usize>() + message.len();
+                if size > MAX_LOG_SIZE {
+                    return Err(Fault::new(format!(
+                        "program logs too large. {} bytes >  {} bytes limit",
+                        size, MAX_LOG_SIZE
+                    )));
+                }
+                self.logs.push(message);
+            }
+            "itxn_begin" => {
+                if !self.pending.is_empty() {
+                    return Err(Fault::new("itxn_begin without itxn_submit"));
+                }
+                self.begin_inner();
+            }
+            "itxn_next" => {
+                if self.pending.is_empty() {
+                    return Err(Fault::new("itxn_next without itxn_begin"));
+                }
+                self.begin_inner();
+            }
+            "itxn_field" => {
+                let value = self.pop()?;
+                self.set_inner_field(field, value)?;
+            }
+            "itxn_submit" => self.submit_inner()?,
+            "itxn" => {
+                let value = self.inner_transaction_field(None, field, None)?;
+                self.push(value);
+            }
+            "itxna" => {
+                let array_index = immediate_uint(instruction, 1);
+                let value = self.inner_transaction_field(None, field, Some(array_index))?;
+                self.push(value);
+            }
+            "itxnas" => {
+                let array_index = self.pop_uint()?;
+                let value = self.inner_transaction_field(None, field, Some(array_index))?;
+                self.push(value);
+            }
+            "gitxn" => {
+                let index = immediate_uint(instruction, 0);
+                let value = self.inner_transaction_field(Some(index), field, None)?;
+                self.push(value);
+            }
+            "gitxna" => {
+                let index = immediate_uint(instruction, 0);
+                let array_index = immediate_uint(instruction, 2);
+                let value = self.inner_transaction_field(Some(index), field, Some(array_index))?;
+                self.push(value);
+            }
+            "gitxnas" => {
+                let array_index = self.pop_uint()?;
+                let index = immediate_uint(instruction, 0);
+                let value = self.inner_transaction_field(Some(index), field, Some(array_index))?;
+                self.push(value);
+            }
+            "box_create" => {
+                let size = self.pop_uint()?;
+                let name = self.pop_bytes()?;
+                self.check_box_size(size)?;
+                match self.box_contents(name.clone())? {
+                    Some(contents) if contents.len() as u64 != size => {
+                        return Err(Fault::new(format!(
+                            "box size mismatch {} {}",
+                            contents.len(),
+                            size
+                        )));
+                    }
+                    Some(_) => self.push_bool(false),
+                    None => {
+                        self.write_box(name, vec![0; size as usize])?;
+                        self.push_bool(true);
+                    }
+                }
+            }
+            "box_extract" => {
+                let (start, length) = self.pop_uints()?;
+                let name = self.pop_bytes()?;
+                let contents = self.existing_box(&name)?;
+                self.push_bytes(extract(&contents, start, length)?)?;
+            }
+            "box_replace" => {
+                let replacement = self.pop_bytes()?;
+                let start = self.pop_uint()?;
+                let name = self.pop_bytes()?;
+                let mut contents = self.existing_box(&name)?;
+                replace(&mut contents, start, &replacement)?;
+                self.write_box(name, contents)?;
+            }
+            "box_splice" => {
+                let replacement = self.pop_bytes()?;
+                let (start, length) = self.pop_uints()?;
+                let name = self.pop_bytes()?;
+                let contents = self.existing_box(&name)?;
+                let size = contents.len();
+                let end = start.saturating_add(length);
+                if start > size as u64 || end > size as u64 {
+                    return Err(Fault::new(format!(
+                        "splice range {}..{} beyond box length {}",
+                        start, end, size
+                    )));
+                }
+                let mut spliced = [
+                    &contents[..start as usize],
+                    &replacement,
+                    &contents[end as usize..],
+                ]
+                .concat();
+                spliced.resize(size, 0);
+                self.write_box(name, spliced)?;
+            }
+            "box_del" => {
+                let name = self.pop_bytes()?;
+                let deleted = self.delete_box(name)?;
+                self.push_bool(deleted);
+            }
+            "box_len" => {
+                let name = self.pop_bytes()?;
+                let length = self.box_contents(name)?.map(Vec::len);
+                self.push_uint(length.unwrap_or(0) as u64);
+                self.push_bool(length.is_some());
+            }
+            "box_get" => {
+                let name = self.pop_bytes()?;
+                let contents = self.box_contents(name)?.cloned();
+                let exists = contents.is_some();
+                self.push_bytes(contents.unwrap_or_default())?;
+                self.push_bool(exists);
+            }
+            "box_put" => {
+                let value = self.pop_bytes()?;
+                let name = self.pop_bytes()?;
+                if let Some(contents) = self.box_contents(name.clone())? {
+                    if contents.len() != value.len() {
+                        return Err(Fault::new(format!(
+                            "attempt to box_put wrong size {} != {}",
+                            contents.len(),
+                            value.len()
+                        )));
+                    }
+                }
+                self.check_box_size(value.len() as u64)?;
+                self.write_box(name, value)?;
+            }
+            "box_resize" => {
+                let size = self.pop_uint()?;
+                let name = self.pop_bytes()?;
+                self.check_box_size(size)?;
+                let mut contents = self.existing_box(&name)?;
+                contents.resize(size as usize, 0);
+                self.write_box(name, contents)?;
+            }
+            _ => {
+                return Err(Fault::new(format!(
+                    "{} is not supported by the emulator",
+                    name
+                )));
+            }
+        }
+        Ok(Flow::Next)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::ledger_delta::LedgerSnapshot;
+    use crate::teal::{EmulatedGroup, Emulator, assemble, disassemble};
+    use algokit_abi::Arc56Contract;
+    use algokit_test_artifacts::hello_world;
+    use algokit_transact::test_utils::{AccountMother, TransactionHeaderMother, TransactionMother};
+    use algokit_transact::{
+        Address, AppCallTransactionBuilder, BoxReference, OnApplicationComplete, Transaction,
+    };
+
+    const CLEAR_STATE_PROGRAM: &str = "#pragma version 10\nint 1";
+
+    fn app_call(app_id: u64, approval: Option<&str>) -> Transaction {
+        let mut builder = AppCallTransactionBuilder::default();
+        builder
+            .header(TransactionHeaderMother::simple_testnet().build().unwrap())
+            .app_id(app_id)
+            .on_complete(OnApplicationComplete::NoOp);
+        if let Some(approval) = approval {
+            builder
+                .approval_program(assemble(approval).unwrap().program)
+                .clear_state_program(assemble(CLEAR_STATE_PROGRAM).unwrap().program);
+        }
+        builder.build().unwrap()
+    }
+
+    /// Creates an app from an approval program and returns the emulator and the app's ID.
+    fn create_app(approval: &str) -> (Emulator, u64) {
+        let mut emulator = Emulator::new(LedgerSnapshot::default());
+        emulator.fund(&AccountMother::account().address(), 10_000_000);
+        let group = emulator.execute(&[app_call(0, Some(approval))]).unwrap();
+        assert_eq!(group.failure, None);
+        let app_id = group.transactions[0].created_app_id.unwrap();
+        (emulator, app_id)
+    }
+
+    /// Wraps `body` in an approval program that approves its own creation without running it.
+    fn approval_running(body: &str) -> String {
+        format!(
+            "#pragma version 10\ntxn ApplicationID\nbnz main\nint 1\nreturn\nmain:\n{}",
+            body
+        )
+    }
+
+    fn run_body(body: &str) -> EmulatedGroup {
+        let (mut emulator, app_id) = create_app(&approval_running(body));
+        emulator.execute(&[app_call(app_id, None)]).unwrap()
+    }
+
+    fn logs(group: &EmulatedGroup) -> &[Vec<u8>] {
+        assert_eq!(group.failure, None);
+        &group.transactions[0].logs
+    }
+
+    #[test]
+    fn test_faults_at_failing_opcode() {
+        for (body, message) in [
+            ("int 18446744073709551615\nint 1\n+", "+ overflowed"),
+            ("int 1\nint 2\n-", "- would result negative"),
+            ("int 1\nint 0\n/", "/ 0"),
+            ("int 4294967296\nint 4294967296\n*", "* overflowed"),
+            ("int 0\nassert", "assert failed"),
+            (
+                "byte 0x01\nint 2\nint 1\nsubstring3",
+                "substring end before start",
+            ),
+            ("err", "err opcode executed"),
+        ] {
+            let group = run_body(body);
+            let failure = group.failure.unwrap();
+            assert_eq!(failure.message, message, "{}", body);
+            assert_eq!(failure.path, vec![0]);
+            let instructions = disassemble(&assemble(&approval_running(body)).unwrap().program)
+                .unwrap()
+                .instructions;
+            assert_eq!(
+                failure.pc,
+                instructions.last().map(|last| last.pc),
+                "{}",
+                body
+            );
+        }
+    }
+
+    #[test]
+    fn test_evaluates_arithmetic_and_byte_opcodes() {
+        let group = run_body(
+            r#"int 7
+int 3
+-
+int 6
+*
+int 5
+/
+itob
+log
+int 10
+int 3
+%
+itob
+log
+byte "hello"
+byte " world"
+concat
+log
+byte 0x0102030405
+extract 1 3
+log
+byte "abc"
+sha256
+log
+byte 0xff
+byte 0x01
+b+
+log
+int 1
+int 2
+int 3
+uncover 2
+-
++
+itob
+log
+int 1"#,
+        );
+        let expected: Vec<Vec<u8>> = vec![
+            4u64.to_be_bytes().to_vec(),
+            1u64.to_be_bytes().to_vec(),
+            b"hello world".to_vec(),
+            vec![2, 3, 4],
+            vec![
+                0xba, 0x78, 0x16, 0xbf, 0x8f, 0x01, 0xcf, 0xea, 0x41, 0x41, 0x40, 0xde, 0x5d, 0xae,
+                0x22, 0x23, 0xb0, 0x03, 0x61, 0xa3, 0x96, 0x17, 0x7a, 0x9c, 0xb4, 0x10, 0xff, 0x61,
+                0xf2, 0x00, 0x15, 0xad,
+            ],
+            vec![1, 0],
+            4u64.to_be_bytes().to_vec(),
+        ];
+        assert_eq!(logs(&group), expected.as_slice());
+    }
+
+    #[test]
+    fn test_branches_and_calls_subroutines() {
+        let group = run_body(
+            r#"int 3
+callsub double
+store 1
+load 1
+int 2
+switch zero one two
+err
+zero:
+err
+one:
+err
+two:
+itob
+log
+int 1
+return
+double:
+proto 1 1
+frame_dig -1
+int 2
+*
+retsub"#,
+        );
+        assert_eq!(logs(&group), [6u64.to_be_bytes().to_vec()].as_slice());
+
+        let trace = group.transactions[0]
+            .exec_trace
+            .approval_program_trace
+            .as_ref()
+            .unwrap();
+        let stored = trace
+            .iter()
+            .find_map(|unit| unit.scratch_changes.as_ref())
+            .unwrap();
+        assert_eq!(stored[0].slot, 1);
+        assert_eq!(stored[0].new_value.uint, Some(6));
+    }
+
+    #[test]
+    fn test_reads_and_writes_referenced_boxes() {
+        let approval = r#"#pragma version 10
+txn ApplicationID
+bz done
+byte "box"
+int 4
+box_create
+assert
+byte "box"
+int 1
+byte 0xabcd
+box_replace
+byte "box"
+int 0
+int 3
+box_extract
+log
+byte "box"
+box_len
+assert
+itob
+log
+byte "box"
+box_del
+assert
+done:
+int 1"#;
+        let (mut emulator, app_id) = create_app(approval);
+        let mut call = app_call(app_id, None);
+        if let Transaction::AppCall(fields) = &mut call {
+            fields.box_references = Some(vec![BoxReference {
+                app_id: 0,
+                name: b"box".to_vec(),
+            }]);
+        }
+        let fund = TransactionMother::simple_payment()
+            .receiver(Address::from_app_id(&app_id))
+            .amount(200_000)
+            .build()
+            .unwrap();
+
+        let group = emulator.execute(&[fund, call]).unwrap();
+
+        assert_eq!(group.failure, None);
+        assert_eq!(
+            group.transactions[1].logs,
+            vec![vec![0, 0xab, 0xcd], 4u64.to_be_bytes().to_vec()]
+        );
+        assert!(emulator.ledger().boxes.is_empty());
+    }
+
+    /// Calls `hello("World")` on the HelloWorld contract and checks the ABI return value and the
+    /// opcodes traced against the path through the contract's TEAL.
+    #[test]
+    fn test_runs_hello_world_contract() {
+        let contract = Arc56Contract::from_json(hello_world::APPLICATION_ARC56).unwrap();
+        let (approval, _) = contract.decoded_teal().unwrap();
+        let (mut emulator, app_id) = create_app(&approval);
+
+        let mut call = app_call(app_id, None);
+        if let Transaction::AppCall(fields) = &mut call {
+            fields.args = Some(vec![
+                vec![0x02, 0xbe, 0xce, 0x11],
+                [&[0, 5][..], b"World"].concat(),
+            ]);
+        }
+        let group = emulator.execute(&[call]).unwrap();
+
+        let expected_log = [&[0x15, 0x1f, 0x7c, 0x75, 0x00, 0x0d][..], b"Hello2, World"].concat();
+        assert_eq!(logs(&group), [expected_log].as_slice());
+
+        let instructions = disassemble(&assemble(&approval).unwrap().program)
+            .unwrap()
+            .instructions;
+        let trace = group.transactions[0]
+            .exec_trace
+            .approval_program_trace
+            .as_ref()
+            .unwrap();
+        let executed: Vec<&str> = trace
+            .iter()
+            .map(|unit| {
+                instructions
+                    .iter()
+                    .find(|instruction| instruction.pc == unit.pc)
+                    .unwrap()
+                    .op
+                    .name
+            })
+            .collect();
+        assert_eq!(
+            executed,
+            [
+                "intcblock",
+                "callsub",
+                "proto",
+                "txn",
+                "bz",
+                "pushbytes",
+                "txna",
+                "match",
+                "txn",
+                "!",
+                "assert",
+                "txn",
+                "assert",
+                "txna",
+                "extract",
+                "callsub",
+                "proto",
+                "pushbytes",
+                "frame_dig",
+                "concat",
+                "retsub",
+                "dup",
+                "len",
+                "itob",
+                "extract",
+                "swap",
+                "concat",
+                "pushbytes",
+                "swap",
+                "concat",
+                "log",
+                "intc_1",
+                "retsub",
+                "return",
+            ]
+        );
+        assert_eq!(group.app_budget_consumed, executed.len() as u64);
+    }
+}
@@ -0,0 +1,26 @@
+//! Offline tooling for TEAL programs.
+//!
+//! The opcode table in [`opcodes`] describes every opcode and field up to
+//! [`MAX_PROGRAM_VERSION`], which [`assemble`] and [`disassemble`] use to compile TEAL and decode
+//! compiled programs without a round trip to algod, and [`analyze`] uses to price and inspect
+//! them. The [`Emulator`] runs transaction groups against an in-memory ledger, evaluating app
+//! programs locally.
+
+pub mod analysis;
+pub mod assembler;
+pub mod disassembler;
+pub mod emulator;
+mod interpreter;
+pub mod opcodes;
+mod resources;
+
+pub use analysis::{
+    BasicBlock, LogicSigChecks, OpCost, ProgramAnalysis, StateKeyReference, StateKind, analyze,
+    opcode_cost,
+};
+pub use assembler::{Assembler, Assembly, TemplateValue, assemble};
+pub use disassembler::{Disassembly, Immediate, Instruction, Instructions, disassemble};
+pub use emulator::{EmulatedGroup, EmulatedTransaction, EmulationFailure, Emulator};
+pub use opcodes::{
+    FieldGroup, FieldSpec, ImmediateKind, MAX_PROGRAM_VERSION, OpSpec, op_spec, op_spec_by_name,
+};
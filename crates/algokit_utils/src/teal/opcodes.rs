@@ -0,0 +1,521 @@
+//! The AVM opcode table and the named fields that opcodes take as immediates.
+
+use FieldGroup as F;
+use ImmediateKind::*;
+use std::collections::HashMap;
+use std::sync::OnceLock;
+
+/// The latest program version described by the opcode table.
+pub const MAX_PROGRAM_VERSION: u64 = 12;
+
+/// The kinds of immediate arguments encoded in a program after an opcode.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ImmediateKind {
+    /// A single byte.
+    Uint8,
+    /// A single signed byte.
+    Int8,
+    /// A varuint.
+    Varuint,
+    /// A varuint length followed by that many bytes.
+    Bytes,
+    /// A 2-byte branch offset from the end of the instruction.
+    Label,
+    /// A count byte followed by that many 2-byte branch offsets.
+    Labels,
+    /// A varuint count followed by that many varuints.
+    Varuints,
+    /// A varuint count followed by that many length-prefixed byte strings.
+    ByteList,
+    /// A byte indexing a named field.
+    Field(FieldGroup),
+}
+
+/// An AVM opcode.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct OpSpec {
+    pub opcode: u8,
+    pub name: &'static str,
+    /// The first program version the opcode is available in.
+    pub version: u64,
+    pub immediates: &'static [ImmediateKind],
+}
+
+/// A named field of a [`FieldGroup`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct FieldSpec {
+    pub name: &'static str,
+    /// The first program version the field is available in.
+    pub version: u64,
+}
+
+/// The sets of named fields that opcodes select from with an immediate.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum FieldGroup {
+    Txn,
+    Global,
+    AssetHolding,
+    AssetParams,
+    AppParams,
+    AcctParams,
+    VoterParams,
+    EcdsaCurve,
+    Base64Encoding,
+    JsonRefType,
+    VrfStandard,
+    Block,
+    EcGroup,
+    MimcConfig,
+}
+
+const fn field(name: &'static str, version: u64) -> FieldSpec {
+    FieldSpec { name, version }
+}
+
+const TXN_FIELDS: &[FieldSpec] = &[
+    field("Sender", 1),
+    field("Fee", 1),
+    field("FirstValid", 1),
+    field("FirstValidTime", 7),
+    field("LastValid", 1),
+    field("Note", 1),
+    field("Lease", 1),
+    field("Receiver", 1),
+    field("Amount", 1),
+    field("CloseRemainderTo", 1),
+    field("VotePK", 1),
+    field("SelectionPK", 1),
+    field("VoteFirst", 1),
+    field("VoteLast", 1),
+    field("VoteKeyDilution", 1),
+    field("Type", 1),
+    field("TypeEnum", 1),
+    field("XferAsset", 1),
+    field("AssetAmount", 1),
+    field("AssetSender", 1),
+    field("AssetReceiver", 1),
+    field("AssetCloseTo", 1),
+    field("GroupIndex", 1),
+    field("TxID", 1),
+    field("ApplicationID", 2),
+    field("OnCompletion", 2),
+    field("ApplicationArgs", 2),
+    field("NumAppArgs", 2),
+    field("Accounts", 2),
+    field("NumAccounts", 2),
+    field("ApprovalProgram", 2),
+    field("ClearStateProgram", 2),
+    field("RekeyTo", 2),
+    field("ConfigAsset", 2),
+    field("ConfigAssetTotal", 2),
+    field("ConfigAssetDecimals", 2),
+    field("ConfigAssetDefaultFrozen", 2),
+    field("ConfigAssetUnitName", 2),
+    field("ConfigAssetName", 2),
+    field("ConfigAssetURL", 2),
+    field("ConfigAssetMetadataHash", 2),
+    field("ConfigAssetManager", 2),
+    field("ConfigAssetReserve", 2),
+    field("ConfigAssetFreeze", 2),
+    field("ConfigAssetClawback", 2),
+    field("FreezeAsset", 2),
+    field("FreezeAssetAccount", 2),
+    field("FreezeAssetFrozen", 2),
+    field("Assets", 3),
+    field("NumAssets", 3),
+    field("Applications", 3),
+    field("NumApplications", 3),
+    field("GlobalNumUint", 3),
+    field("GlobalNumByteSlice", 3),
+    field("LocalNumUint", 3),
+    field("LocalNumByteSlice", 3),
+    field("ExtraProgramPages", 4),
+    field("Nonparticipation", 5),
+    field("Logs", 5),
+    field("NumLogs", 5),
+    field("CreatedAssetID", 5),
+    field("CreatedApplicationID", 5),
+    field("LastLog", 6),
+    field("StateProofPK", 6),
+    field("ApprovalProgramPages", 7),
+    field("NumApprovalProgramPages", 7),
+    field("ClearStateProgramPages", 7),
+    field("NumClearStateProgramPages", 7),
+    field("RejectVersion", 12),
+];
+
+const GLOBAL_FIELDS: &[FieldSpec] = &[
+    field("MinTxnFee", 1),
+    field("MinBalance", 1),
+    field("MaxTxnLife", 1),
+    field("ZeroAddress", 1),
+    field("GroupSize", 1),
+    field("LogicSigVersion", 2),
+    field("Round", 2),
+    field("LatestTimestamp", 2),
+    field("CurrentApplicationID", 2),
+    field("CreatorAddress", 3),
+    field("CurrentApplicationAddress", 5),
+    field("GroupID", 5),
+    field("OpcodeBudget", 6),
+    field("CallerApplicationID", 6),
+    field("CallerApplicationAddress", 6),
+    field("AssetCreateMinBalance", 10),
+    field("AssetOptInMinBalance", 10),
+    field("GenesisHash", 10),
+    field("PayoutsEnabled", 11),
+    field("PayoutsGoOnlineFee", 11),
+    field("PayoutsPercent", 11),
+    field("PayoutsMinBalance", 11),
+    field("PayoutsMaxBalance", 11),
+];
+
+const ASSET_HOLDING_FIELDS: &[FieldSpec] = &[field("AssetBalance", 2), field("AssetFrozen", 2)];
+
+const ASSET_PARAMS_FIELDS: &[FieldSpec] = &[
+    field("AssetTotal", 2),
+    field("AssetDecimals", 2),
+    field("AssetDefaultFrozen", 2),
+    field("AssetUnitName", 2),
+    field("AssetName", 2),
+    field("AssetURL", 2),
+    field("AssetMetadataHash", 2),
+    field("AssetManager", 2),
+    field("AssetReserve", 2),
+    field("AssetFreeze", 2),
+    field("AssetClawback", 2),
+    field("AssetCreator", 5),
+];
+
+const APP_PARAMS_FIELDS: &[FieldSpec] = &[
+    field("AppApprovalProgram", 5),
+    field("AppClearStateProgram", 5),
+    field("AppGlobalNumUint", 5),
+    field("AppGlobalNumByteSlice", 5),
+    field("AppLocalNumUint", 5),
+    field("AppLocalNumByteSlice", 5),
+    field("AppExtraProgramPages", 5),
+    field("AppCreator", 5),
+    field("AppAddress", 5),
+    field("AppVersion", 12),
+];
+
+const ACCT_PARAMS_FIELDS: &[FieldSpec] = &[
+    field("AcctBalance", 6),
+    field("AcctMinBalance", 6),
+    field("AcctAuthAddr", 6),
+    field("AcctTotalNumUint", 8),
+    field("AcctTotalNumByteSlice", 8),
+    field("AcctTotalExtraAppPages", 8),
+    field("AcctTotalAppsCreated", 8),
+    field("AcctTotalAppsOptedIn", 8),
+    field("AcctTotalAssetsCreated", 8),
+    field("AcctTotalAssets", 8),
+    field("AcctTotalBoxes", 8),
+    field("AcctTotalBoxBytes", 8),
+    field("AcctIncentiveEligible", 11),
+    field("AcctLastProposed", 11),
+    field("AcctLastHeartbeat", 11),
+];
+
+const VOTER_PARAMS_FIELDS: &[FieldSpec] = &[
+    field("VoterBalance", 11),
+    field("VoterIncentiveEligible", 11),
+];
+
+const ECDSA_CURVES: &[FieldSpec] = &[field("Secp256k1", 5), field("Secp256r1", 7)];
+
+const BASE64_ENCODINGS: &[FieldSpec] = &[field("URLEncoding", 7), field("StdEncoding", 7)];
+
+const JSON_REF_TYPES: &[FieldSpec] = &[
+    field("JSONString", 7),
+    field("JSONUint64", 7),
+    field("JSONObject", 7),
+];
+
+const VRF_STANDARDS: &[FieldSpec] = &[field("VrfAlgorand", 7)];
+
+const BLOCK_FIELDS: &[FieldSpec] = &[
+    field("BlkSeed", 7),
+    field("BlkTimestamp", 7),
+    field("BlkProposer", 11),
+    field("BlkFeesCollected", 11),
+    field("BlkBonus", 11),
+    field("BlkBranch", 11),
+    field("BlkFeeSink", 11),
+    field("BlkProtocol", 11),
+    field("BlkTxnCounter", 11),
+    field("BlkProposerPayout", 11),
+];
+
+const EC_GROUPS: &[FieldSpec] = &[
+    field("BN254g1", 10),
+    field("BN254g2", 10),
+    field("BLS12_381g1", 10),
+    field("BLS12_381g2", 10),
+];
+
+const MIMC_CONFIGS: &[FieldSpec] = &[field("BN254Mp110", 11), field("BLS12_381Mp111", 11)];
+
+impl FieldGroup {
+    /// The fields of the group, indexed by the immediate that selects them.
+    pub fn fields(self) -> &'static [FieldSpec] {
+        match self {
+            FieldGroup::Txn => TXN_FIELDS,
+            FieldGroup::Global => GLOBAL_FIELDS,
+            FieldGroup::AssetHolding => ASSET_HOLDING_FIELDS,
+            FieldGroup::AssetParams => ASSET_PARAMS_FIELDS,
+            FieldGroup::AppParams => APP_PARAMS_FIELDS,
+            FieldGroup::AcctParams => ACCT_PARAMS_FIELDS,
+            FieldGroup::VoterParams => VOTER_PARAMS_FIELDS,
+            FieldGroup::EcdsaCurve => ECDSA_CURVES,
+            FieldGroup::Base64Encoding => BASE64_ENCODINGS,
+            FieldGroup::JsonRefType => JSON_REF_TYPES,
+            FieldGroup::VrfStandard => VRF_STANDARDS,
+            FieldGroup::Block => BLOCK_FIELDS,
+            FieldGroup::EcGroup => EC_GROUPS,
+            FieldGroup::MimcConfig => MIMC_CONFIGS,
+        }
+    }
+
+    /// The index and spec of the field with the given name.
+    pub fn field_by_name(self, name: &str) -> Option<(u8, &'static FieldSpec)> {
+        self.fields()
+            .iter()
+            .enumerate()
+            .find(|(_, field)| field.name == name)
+            .map(|(index, field)| (index as u8, field))
+    }
+}
+
+const fn op(
+    opcode: u8,
+    name: &'static str,
+    version: u64,
+    immediates: &'static [ImmediateKind],
+) -> OpSpec {
+    OpSpec {
+        opcode,
+        name,
+        version,
+        immediates,
+    }
+}
+
+const OPS: &[OpSpec] = &[
+    op(0x00, "err", 1, &[]),
+    op(0x01, "sha256", 1, &[]),
+    op(0x02, "keccak256", 1, &[]),
+    op(0x03, "sha512_256", 1, &[]),
+    op(0x04, "ed25519verify", 1, &[]),
+    op(0x05, "ecdsa_verify", 5, &[Field(F::EcdsaCurve)]),
+    op(0x06, "ecdsa_pk_decompress", 5, &[Field(F::EcdsaCurve)]),
+    op(0x07, "ecdsa_pk_recover", 5, &[Field(F::EcdsaCurve)]),
+    op(0x08, "+", 1, &[]),
+    op(0x09, "-", 1, &[]),
+    op(0x0a, "/", 1, &[]),
+    op(0x0b, "*", 1, &[]),
+    op(0x0c, "<", 1, &[]),
+    op(0x0d, ">", 1, &[]),
+    op(0x0e, "<=", 1, &[]),
+    op(0x0f, ">=", 1, &[]),
+    op(0x10, "&&", 1, &[]),
+    op(0x11, "||", 1, &[]),
+    op(0x12, "==", 1, &[]),
+    op(0x13, "!=", 1, &[]),
+    op(0x14, "!", 1, &[]),
+    op(0x15, "len", 1, &[]),
+    op(0x16, "itob", 1, &[]),
+    op(0x17, "btoi", 1, &[]),
+    op(0x18, "%", 1, &[]),
+    op(0x19, "|", 1, &[]),
+    op(0x1a, "&", 1, &[]),
+    op(0x1b, "^", 1, &[]),
+    op(0x1c, "~", 1, &[]),
+    op(0x1d, "mulw", 1, &[]),
+    op(0x1e, "addw", 2, &[]),
+    op(0x1f, "divmodw", 4, &[]),
+    op(0x20, "intcblock", 1, &[Varuints]),
+    op(0x21, "intc", 1, &[Uint8]),
+    op(0x22, "intc_0", 1, &[]),
+    op(0x23, "intc_1", 1, &[]),
+    op(0x24, "intc_2", 1, &[]),
+    op(0x25, "intc_3", 1, &[]),
+    op(0x26, "bytecblock", 1, &[ByteList]),
+    op(0x27, "bytec", 1, &[Uint8]),
+    op(0x28, "bytec_0", 1, &[]),
+    op(0x29, "bytec_1", 1, &[]),
+    op(0x2a, "bytec_2", 1, &[]),
+    op(0x2b, "bytec_3", 1, &[]),
+    op(0x2c, "arg", 1, &[Uint8]),
+    op(0x2d, "arg_0", 1, &[]),
+    op(0x2e, "arg_1", 1, &[]),
+    op(0x2f, "arg_2", 1, &[]),
+    op(0x30, "arg_3", 1, &[]),
+    op(0x31, "txn", 1, &[Field(F::Txn)]),
+    op(0x32, "global", 1, &[Field(F::Global)]),
+    op(0x33, "gtxn", 1, &[Uint8, Field(F::Txn)]),
+    op(0x34, "load", 1, &[Uint8]),
+    op(0x35, "store", 1, &[Uint8]),
+    op(0x36, "txna", 2, &[Field(F::Txn), Uint8]),
+    op(0x37, "gtxna", 2, &[Uint8, Field(F::Txn), Uint8]),
+    op(0x38, "gtxns", 3, &[Field(F::Txn)]),
+    op(0x39, "gtxnsa", 3, &[Field(F::Txn), Uint8]),
+    op(0x3a, "gload", 4, &[Uint8, Uint8]),
+    op(0x3b, "gloads", 4, &[Uint8]),
+    op(0x3c, "gaid", 4, &[Uint8]),
+    op(0x3d, "gaids", 4, &[]),
+    op(0x3e, "loads", 5, &[]),
+    op(0x3f, "stores", 5, &[]),
+    op(0x40, "bnz", 1, &[Label]),
+    op(0x41, "bz", 2, &[Label]),
+    op(0x42, "b", 2, &[Label]),
+    op(0x43, "return", 2, &[]),
+    op(0x44, "assert", 3, &[]),
+    op(0x45, "bury", 8, &[Uint8]),
+    op(0x46, "popn", 8, &[Uint8]),
+    op(0x47, "dupn", 8, &[Uint8]),
+    op(0x48, "pop", 1, &[]),
+    op(0x49, "dup", 1, &[]),
+    op(0x4a, "dup2", 2, &[]),
+    op(0x4b, "dig", 3, &[Uint8]),
+    op(0x4c, "swap", 3, &[]),
+    op(0x4d, "select", 3, &[]),
+    op(0x4e, "cover", 5, &[Uint8]),
+    op(0x4f, "uncover", 5, &[Uint8]),
+    op(0x50, "concat", 2, &[]),
+    op(0x51, "substring", 2, &[Uint8, Uint8]),
+    op(0x52, "substring3", 2, &[]),
+    op(0x53, "getbit", 3, &[]),
+    op(0x54, "setbit", 3, &[]),
+    op(0x55, "getbyte", 3, &[]),
+    op(0x56, "setbyte", 3, &[]),
+    op(0x57, "extract", 5, &[Uint8, Uint8]),
+    op(0x58, "extract3", 5, &[]),
+    op(0x59, "extract_uint16", 5, &[]),
+    op(0x5a, "extract_uint32", 5, &[]),
+    op(0x5b, "extract_uint64", 5, &[]),
+    op(0x5c, "replace2", 7, &[Uint8]),
+    op(0x5d, "replace3", 7, &[]),
+    op(0x5e, "base64_decode", 7, &[Field(F::Base64Encoding)]),
+    op(0x5f, "json_ref", 7, &[Field(F::JsonRefType)]),
+    op(0x60, "balance", 2, &[]),
+    op(0x61, "app_opted_in", 2, &[]),
+    op(0x62, "app_local_get", 2, &[]),
+    op(0x63, "app_local_get_ex", 2, &[]),
+    op(0x64, "app_global_get", 2, &[]),
+    op(0x65, "app_global_get_ex", 2, &[]),
+    op(0x66, "app_local_put", 2, &[]),
+    op(0x67, "app_global_put", 2, &[]),
+    op(0x68, "app_local_del", 2, &[]),
+    op(0x69, "app_global_del", 2, &[]),
+    op(0x70, "asset_holding_get", 2, &[Field(F::AssetHolding)]),
+    op(0x71, "asset_params_get", 2, &[Field(F::AssetParams)]),
+    op(0x72, "app_params_get", 5, &[Field(F::AppParams)]),
+    op(0x73, "acct_params_get", 6, &[Field(F::AcctParams)]),
+    op(0x74, "voter_params_get", 11, &[Field(F::VoterParams)]),
+    op(0x75, "online_stake", 11, &[]),
+    op(0x78, "min_balance", 3, &[]),
+    op(0x80, "pushbytes", 3, &[Bytes]),
+    op(0x81, "pushint", 3, &[Varuint]),
+    op(0x82, "pushbytess", 8, &[ByteList]),
+    op(0x83, "pushints", 8, &[Varuints]),
+    op(0x84, "ed25519verify_bare", 7, &[]),
+    op(0x85, "falcon_verify", 12, &[]),
+    op(0x86, "sumhash512", 12, &[]),
+    op(0x88, "callsub", 4, &[Label]),
+    op(0x89, "retsub", 4, &[]),
+    op(0x8a, "proto", 8, &[Uint8, Uint8]),
+    op(0x8b, "frame_dig", 8, &[Int8]),
+    op(0x8c, "frame_bury", 8, &[Int8]),
+    op(0x8d, "switch", 8, &[Labels]),
+    op(0x8e, "match", 8, &[Labels]),
+    op(0x90, "shl", 4, &[]),
+    op(0x91, "shr", 4, &[]),
+    op(0x92, "sqrt", 4, &[]),
+    op(0x93, "bitlen", 4, &[]),
+    op(0x94, "exp", 4, &[]),
+    op(0x95, "expw", 4, &[]),
+    op(0x96, "bsqrt", 6, &[]),
+    op(0x97, "divw", 6, &[]),
+    op(0x98, "sha3_256", 7, &[]),
+    op(0xa0, "b+", 4, &[]),
+    op(0xa1, "b-", 4, &[]),
+    op(0xa2, "b/", 4, &[]),
+    op(0xa3, "b*", 4, &[]),
+    op(0xa4, "b<", 4, &[]),
+    op(0xa5, "b>", 4, &[]),
+    op(0xa6, "b<=", 4, &[]),
+    op(0xa7, "b>=", 4, &[]),
+    op(0xa8, "b==", 4, &[]),
+    op(0xa9, "b!=", 4, &[]),
+    op(0xaa, "b%", 4, &[]),
+    op(0xab, "b|", 4, &[]),
+    op(0xac, "b&", 4, &[]),
+    op(0xad, "b^", 4, &[]),
+    op(0xae, "b~", 4, &[]),
+    op(0xaf, "bzero", 4, &[]),
+    op(0xb0, "log", 5, &[]),
+    op(0xb1, "itxn_begin", 5, &[]),
+    op(0xb2, "itxn_field", 5, &[Field(F::Txn)]),
+    op(0xb3, "itxn_submit", 5, &[]),
+    op(0xb4, "itxn", 5, &[Field(F::Txn)]),
+    op(0xb5, "itxna", 5, &[Field(F::Txn), Uint8]),
+    op(0xb6, "itxn_next", 6, &[]),
+    op(0xb7, "gitxn", 6, &[Uint8, Field(F::Txn)]),
+    op(0xb8, "gitxna", 6, &[Uint8, Field(F::Txn), Uint8]),
+    op(0xb9, "box_create", 8, &[]),
+    op(0xba, "box_extract", 8, &[]),
+    op(0xbb, "box_replace", 8, &[]),
+    op(0xbc, "box_del", 8, &[]),
+    op(0xbd, "box_len", 8, &[]),
+    op(0xbe, "box_get", 8, &[]),
+    op(0xbf, "box_put", 8, &[]),
+    op(0xc0, "txnas", 5, &[Field(F::Txn)]),
+    op(0xc1, "gtxnas", 5, &[Uint8, Field(F::Txn)]),
+    op(0xc2, "gtxnsas", 5, &[Field(F::Txn)]),
+    op(0xc3, "args", 5, &[]),
+    op(0xc4, "gloadss", 6, &[]),
+    op(0xc5, "itxnas", 6, &[Field(F::Txn)]),
+    op(0xc6, "gitxnas", 6, &[Uint8, Field(F::Txn)]),
+    op(0xd0, "vrf_verify", 7, &[Field(F::VrfStandard)]),
+    op(0xd1, "block", 7, &[Field(F::Block)]),
+    op(0xd2, "box_splice", 10, &[]),
+    op(0xd3, "box_resize", 10, &[]),
+    op(0xe0, "ec_add", 10, &[Field(F::EcGroup)]),
+    op(0xe1, "ec_scalar_mul", 10, &[Field(F::EcGroup)]),
+    op(0xe2, "ec_pairing_check", 10, &[Field(F::EcGroup)]),
+    op(0xe3, "ec_multi_scalar_mul", 10, &[Field(F::EcGroup)]),
+    op(0xe4, "ec_subgroup_check", 10, &[Field(F::EcGroup)]),
+    op(0xe5, "ec_map_to", 10, &[Field(F::EcGroup)]),
+    op(0xe6, "mimc", 11, &[Field(F::MimcConfig)]),
+];
+
+fn ops_by_opcode() -> &'static [Option<&'static OpSpec>; 256] {
+    static TABLE: OnceLock<[Option<&'static OpSpec>; 256]> = OnceLock::new();
+    TABLE.get_or_init(|| {
+        let mut table = [None; 256];
+        for spec in OPS {
+            table[spec.opcode as usize] = Some(spec);
+        }
+        table
+    })
+}
+
+fn ops_by_name() -> &'static HashMap<&'static str, &'static OpSpec> {
+    static TABLE: OnceLock<HashMap<&'static str, &'static OpSpec>> = OnceLock::new();
+    TABLE.get_or_init(|| OPS.iter().map(|spec| (spec.name, spec)).collect())
+}
+
+/// The opcode with the given byte, if it is available in the given program version.
+pub fn op_spec(opcode: u8, version: u64) -> Option<&'static OpSpec> {
+    ops_by_opcode()[opcode as usize].filter(|spec| spec.version <= version)
+}
+
+/// The opcode with the given name, if it is available in the given program version.
+pub fn op_spec_by_name(name: &str, version: u64) -> Option<&'static OpSpec> {
+    ops_by_name()
+        .get(name)
+        .copied()
+        .filter(|spec| spec.version <= version)
+}
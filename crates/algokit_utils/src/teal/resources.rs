@@ -0,0 +1,301 @@
+//! The resources an app call may access under the AVM's availability rules.
+//!
+//! Before program version 9 a program may only access the accounts, apps and assets its own
+//! transaction references, along with the current app and anything created earlier in the
+//! group. From version 9 the references of every transaction in the group are pooled, and a
+//! holding or local state is available when its account and asset or app were referenced by the
+//! same transaction. Boxes are pooled across the group at every version.
+
+use super::emulator::{EmulatedTransaction, Fault, GroupView};
+use algokit_transact::{Address, AppCallTransactionFields, Transaction};
+use std::collections::HashSet;
+
+/// The program version from which resources are shared across the group.
+const SHARED_RESOURCES_VERSION: u64 = 9;
+
+/// The accounts, apps, assets, holdings and local states named by some transactions.
+#[derive(Default)]
+struct References {
+    accounts: HashSet<Address>,
+    apps: HashSet<u64>,
+    assets: HashSet<u64>,
+    holdings: HashSet<(Address, u64)>,
+    locals: HashSet<(Address, u64)>,
+}
+
+impl References {
+    /// Adds the resources an app call names, with `app_id` as the ID of the app it calls.
+    fn add_app_call(&mut self, fields: &AppCallTransactionFields, app_id: u64) {
+        let foreign_apps = fields.app_references.as_deref().unwrap_or_default();
+        let foreign_assets = fields.asset_references.as_deref().unwrap_or_default();
+        let accounts: Vec<Address> = std::iter::once(fields.header.sender.clone())
+            .chain(fields.account_references.iter().flatten().cloned())
+            .chain(
+                std::iter::once(&app_id)
+                    .chain(foreign_apps)
+                    .map(Address::from_app_id),
+            )
+            .collect();
+        let apps: Vec<u64> = std::iter::once(app_id)
+            .chain(foreign_apps.iter().copied())
+            .collect();
+
+        for account in &accounts {
+            self.holdings.extend(
+                foreign_assets
+                    .iter()
+                    .map(|asset_id| (account.clone(), *asset_id)),
+            );
+            self.locals
+                .extend(apps.iter().map(|app_id| (account.clone(), *app_id)));
+        }
+        self.accounts.extend(accounts);
+        self.apps.extend(apps);
+        self.assets.extend(foreign_assets);
+    }
+
+    /// Adds the resources any transaction names, which other transactions may use from version 9.
+    fn add_transaction(&mut self, tx: &Transaction, app_id: u64) {
+        self.accounts.insert(tx.sender().clone());
+        match tx {
+            Transaction::Payment(fields) => {
+                self.accounts.insert(fields.receiver.clone());
+                self.accounts.extend(fields.close_remainder_to.clone());
+            }
+            Transaction::AssetTransfer(fields) => {
+                self.assets.insert(fields.asset_id);
+                let accounts = [&fields.header.sender, &fields.receiver]
+                    .into_iter()
+                    .chain(&fields.asset_sender)
+                    .chain(&fields.close_remainder_to);
+                for account in accounts {
+                    self.accounts.insert(account.clone());
+                    self.holdings.insert((account.clone(), fields.asset_id));
+                }
+            }
+            Transaction::AssetConfig(fields) => {
+                self.assets.insert(fields.asset_id);
+            }
+            Transaction::AssetFreeze(fields) => {
+                self.assets.insert(fields.asset_id);
+                self.accounts.insert(fields.freeze_target.clone());
+                self.holdings
+                    .insert((fields.freeze_target.clone(), fields.asset_id));
+            }
+            Transaction::AppCall(fields) => self.add_app_call(fields, app_id),
+            _ => {}
+        }
+    }
+}
+
+/// Checks the resources a program accesses against those available to its app call.
+pub(super) struct Availability {
+    /// Set when unnamed resources are allowed, so every resource is available.
+    unrestricted: bool,
+    version: u64,
+    /// The resources available to the program, which are those of the whole group from
+    /// version 9.
+    references: References,
+    created_apps: HashSet<u64>,
+    created_assets: HashSet<u64>,
+    boxes: HashSet<(u64, Vec<u8>)>,
+}
+
+impl Availability {
+    /// Makes every resource available, as simulate does with `allow_unnamed_resources`.
+    pub(super) fn unrestricted() -> Self {
+        Self {
+            unrestricted: true,
+            version: 0,
+            references: References::default(),
+            created_apps: HashSet::new(),
+            created_assets: HashSet::new(),
+            boxes: HashSet::new(),
+        }
+    }
+
+    /// Collects the resources available to the app call at `index` in a group, where `app_id`
+    /// is the app it calls and `version` the version of the program it runs.
+    pub(super) fn new(group: &GroupView<'_>, index: usize, app_id: u64, version: u64) -> Self {
+        let app_id_of = |position: usize, fields: &AppCallTransactionFields| {
+            if position == index {
+                app_id
+            } else if fields.app_id == 0 {
+                group
+                    .applied
+                    .get(position)
+                    .and_then(|result| result.created_app_id)
+                    .unwrap_or(0)
+            } else {
+                fields.app_id
+            }
+        };
+
+        let mut references = References::default();
+        let mut boxes = HashSet::new();
+        for (position, tx) in group.transactions.iter().enumerate() {
+            let Transaction::AppCall(fields) = tx else {
+                if version >= SHARED_RESOURCES_VERSION {
+                    references.add_transaction(tx, 0);
+                }
+                continue;
+            };
+            let called = app_id_of(position, fields);
+            if position == index {
+                references.add_app_call(fields, called);
+            } else if version >= SHARED_RESOURCES_VERSION {
+                references.add_transaction(tx, called);
+            }
+            let foreign_apps = fields.app_references.as_deref().unwrap_or_default();
+            for reference in fields.box_references.iter().flatten() {
+                let owner = match reference.app_id {
+                    0 => Some(called),
+                    position => foreign_apps.get(position as usize - 1).copied(),
+                };
+                if let Some(owner) = owner {
+                    boxes.insert((owner, reference.name.clone()));
+                }
+            }
+        }
+
+        let mut availability = Self {
+            unrestricted: false,
+            version,
+            references,
+            created_apps: HashSet::new(),
+            created_assets: HashSet::new(),
+            boxes,
+        };
+        for result in group.applied {
+            availability.add_created(result);
+        }
+        if let Some(Transaction::AppCall(fields)) = group.transactions.get(index) {
+            if fields.app_id == 0 {
+                availability.created_apps.insert(app_id);
+            }
+        }
+        availability
+    }
+
+    /// Makes the apps and assets a transaction and its inner transactions created available.
+    pub(super) fn add_created(&mut self, result: &EmulatedTransaction) {
+        self.created_apps.extend(result.created_app_id);
+        self.created_assets.extend(result.created_asset_id);
+        for inner in &result.inner_transactions {
+            self.add_created(inner);
+        }
+    }
+
+    fn is_created_app_address(&self, address: &Address) -> bool {
+        self.created_apps
+            .iter()
+            .any(|app_id| Address::from_app_id(app_id) == *address)
+    }
+
+    fn has_account(&self, address: &Address) -> bool {
+        self.unrestricted
+            || self.references.accounts.contains(address)
+            || self.is_created_app_address(address)
+    }
+
+    fn has_app(&self, app_id: u64) -> bool {
+        self.unrestricted
+            || self.references.apps.contains(&app_id)
+            || self.created_apps.contains(&app_id)
+    }
+
+    fn has_asset(&self, asset_id: u64) -> bool {
+        self.unrestricted
+            || self.references.assets.contains(&asset_id)
+            || self.created_assets.contains(&asset_id)
+    }
+
+    /// # Errors
+    /// Fails with `unavailable Account` if the account is not available.
+    pub(super) fn account(&self, address: &Address) -> Result<(), Fault> {
+        if self.has_account(address) {
+            Ok(())
+        } else {
+            Err(Fault::new(format!("unavailable Account {}", address)))
+        }
+    }
+
+    /// # Errors
+    /// Fails with `unavailable App` if the app is not available.
+    pub(super) fn app(&self, app_id: u64) -> Result<(), Fault> {
+        if self.has_app(app_id) {
+            Ok(())
+        } else {
+            Err(Fault::new(format!("unavailable App {}", app_id)))
+        }
+    }
+
+    /// # Errors
+    /// Fails with `unavailable Asset` if the asset is not available.
+    pub(super) fn asset(&self, asset_id: u64) -> Result<(), Fault> {
+        if self.has_asset(asset_id) {
+            Ok(())
+        } else {
+            Err(Fault::new(format!("unavailable Asset {}", asset_id)))
+        }
+    }
+
+    /// # Errors
+    /// Fails with `unavailable Holding` if the account's holding of the asset is not available.
+    pub(super) fn holding(&self, address: &Address, asset_id: u64) -> Result<(), Fault> {
+        let available = if self.version >= SHARED_RESOURCES_VERSION {
+            self.unrestricted
+                || self.created_assets.contains(&asset_id)
+                || self.is_created_app_address(address)
+                || self
+                    .references
+                    .holdings
+                    .contains(&(address.clone(), asset_id))
+        } else {
+            self.has_account(address) && self.has_asset(asset_id)
+        };
+        if available {
+            Ok(())
+        } else {
+            Err(Fault::new(format!(
+                "unavailable Holding {}+{}",
+                asset_id, address
+            )))
+        }
+    }
+
+    /// # Errors
+    /// Fails with `unavailable Local State` if the account's local state in the app is not
+    /// available.
+    pub(super) fn local(&self, address: &Address, app_id: u64) -> Result<(), Fault> {
+        let available = if self.version >= SHARED_RESOURCES_VERSION {
+            self.unrestricted
+                || self.created_apps.contains(&app_id)
+                || self.is_created_app_address(address)
+                || self.references.locals.contains(&(address.clone(), app_id))
+        } else {
+            self.has_account(address) && self.has_app(app_id)
+        };
+        if available {
+            Ok(())
+        } else {
+            Err(Fault::new(format!(
+                "unavailable Local State {}+{}",
+                app_id, address
+            )))
+        }
+    }
+
+    /// # Errors
+    /// Fails with `invalid Box reference` if no transaction in the group references the box.
+    pub(super) fn box_reference(&self, app_id: u64, name: &[u8]) -> Result<(), Fault> {
+        if self.unrestricted || self.boxes.contains(&(app_id, name.to_vec())) {
+            Ok(())
+        } else {
+            Err(Fault::new(format!(
+                "invalid Box reference {:?}",
+                String::from_utf8_lossy(name)
+            )))
+        }
+    }
+}
@@ -0,0 +1,259 @@
+//! Verification of transaction Merkle proofs.
+//!
+//! The transactions of a block are committed to by a vector commitment: a Merkle tree whose
+//! leaves are `"TL" || txid || stibhash`, whose inner nodes are `"MA" || left || right`, and whose
+//! leaf positions are the bit-reversed transaction indices. The root of the SHA-512/256 tree is
+//! the block's `transactions_root` and the root of the SHA-256 tree is its
+//! `transactions_root_sha256`. A [`TransactionProof`] returned by algod contains the sibling
+//! hashes from the leaf up to the root, which is enough to recompute the root locally.
+
+use crate::error::AlgoKitUtilsError;
+use crate::merkle::{hash_parts, invalid_proof, vector_commitment_root};
+use algod_client::apis::parameter_enums::Hashtype;
+use algod_client::models::{Block, TransactionProof};
+use std::str::FromStr;
+
+const TRANSACTION_LEAF_PREFIX: &[u8] = b"TL";
+const DIGEST_SIZE: usize = 32;
+
+fn decode_transaction_id(txid: &str) -> Result<Vec<u8>, AlgoKitUtilsError> {
+    base32::decode(base32::Alphabet::Rfc4648 { padding: false }, txid)
+        .filter(|bytes| bytes.len() == DIGEST_SIZE)
+        .ok_or_else(|| AlgoKitUtilsError::InputError {
+            err_msg: format!("Invalid transaction ID {}", txid),
+        })
+}
+
+/// Returns the hash type used by a proof.
+pub fn proof_hashtype(proof: &TransactionProof) -> Result<Hashtype, AlgoKitUtilsError> {
+    Hashtype::from_str(&proof.hashtype).map_err(invalid_proof)
+}
+
+/// Computes the transactions root implied by a proof for the transaction with ID `txid`.
+pub fn transaction_proof_root(
+    proof: &TransactionProof,
+    txid: &str,
+) -> Result<Vec<u8>, AlgoKitUtilsError> {
+    let hashtype = proof_hashtype(proof)?;
+    let txid = decode_transaction_id(txid)?;
+
+    if proof.stibhash.len() != DIGEST_SIZE {
+        return Err(invalid_proof(format!(
+            "stibhash must be {} bytes, got {}",
+            DIGEST_SIZE,
+            proof.stibhash.len()
+        )));
+    }
+    let proof_length = proof
+        .treedepth
+        .checked_mul(DIGEST_SIZE as u64)
+        .ok_or_else(|| invalid_proof(format!("tree depth {} is too large", proof.treedepth)))?;
+    if proof.proof.len() as u64 != proof_length {
+        return Err(invalid_proof(format!(
+            "expected {} bytes of proof for a tree of depth {}, got {}",
+            proof_length,
+            proof.treedepth,
+            proof.proof.len()
+        )));
+    }
+
+    let leaf = hash_parts(hashtype, &[TRANSACTION_LEAF_PREFIX, &txid, &proof.stibhash]);
+    let path: Vec<Vec<u8>> = proof
+        .proof
+        .chunks(DIGEST_SIZE)
+        .map(<[u8]>::to_vec)
+        .collect();
+
+    vector_commitment_root(
+        [(proof.idx, leaf)],
+        &path,
+        proof.treedepth,
+        DIGEST_SIZE,
+        |parts| Ok(hash_parts(hashtype, parts)),
+    )
+}
+
+/// Verifies that a proof shows the transaction with ID `txid` is committed to by `root`.
+pub fn verify_transaction_proof(
+    proof: &TransactionProof,
+    txid: &str,
+    root: &[u8],
+) -> Result<(), AlgoKitUtilsError> {
+    if transaction_proof_root(proof, txid)? != root {
+        return Err(invalid_proof(format!(
+            "transaction {} is not committed to by the transactions root",
+            txid
+        )));
+    }
+    Ok(())
+}
+
+/// Verifies that a proof shows the transaction with ID `txid` is included in `block`, using the
+/// transactions root that matches the proof's hash type.
+pub fn verify_transaction_in_block(
+    proof: &TransactionProof,
+    txid: &str,
+    block: &Block,
+) -> Result<(), AlgoKitUtilsError> {
+    let hashtype = proof_hashtype(proof)?;
+    let root = match hashtype {
+        Hashtype::Sha512256 => block.transactions_root.as_ref(),
+        Hashtype::Sha256 => block.transactions_root_sha256.as_ref(),
+    }
+    .ok_or_else(|| invalid_proof(format!("block has no {} transactions root", hashtype)))?;
+
+    verify_transaction_proof(proof, txid, root)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::merkle::vector_commitment_index;
+
+    const TXIDS: [&str; 3] = [
+        "VAHP4FRJH4GRV6ID2BZRK5VYID376EV3VE6T2TKKDFJBBDOXWCCA",
+        "JIDBHDPLBASULQZFI4EY5FJWR6VQRMPPFSGYBKE2XKW65N3UQJXA",
+        "TZM3P4ZL4DLIEZ3WOEP67MQ6JITTO4D3NJN3RCA5YDBC3V4LA5LA",
+    ];
+
+    fn stibhash(index: usize, hashtype: Hashtype) -> Vec<u8> {
+        hash_parts(hashtype, &[b"STIB", &[index as u8]])
+    }
+
+    /// Builds the full vector commitment tree for TXIDS, padded with zero hashes, and returns
+    /// the root along with a proof for each transaction.
+    fn tree(hashtype: Hashtype) -> (Vec<u8>, Vec<TransactionProof>) {
+        let depth = 2u64;
+        let mut level = vec![vec![0u8; DIGEST_SIZE]; 1 << depth];
+        for (index, txid) in TXIDS.iter().enumerate() {
+            let position = vector_commitment_index(index as u64, depth).unwrap() as usize;
+            level[position] = hash_parts(
+                hashtype,
+                &[
+                    TRANSACTION_LEAF_PREFIX,
+                    &decode_transaction_id(txid).unwrap(),
+                    &stibhash(index, hashtype),
+                ],
+            );
+        }
+
+        let mut levels = vec![level];
+        while levels.last().unwrap().len() > 1 {
+            let next = levels
+                .last()
+                .unwrap()
+                .chunks(2)
+                .map(|pair| hash_parts(hashtype, &[b"MA", &pair[0], &pair[1]]))
+                .collect();
+            levels.push(next);
+        }
+
+        let proofs = (0..TXIDS.len())
+            .map(|index| {
+                let mut position = vector_commitment_index(index as u64, depth).unwrap() as usize;
+                let mut path = Vec::new();
+                for level in &levels[..levels.len() - 1] {
+                    path.extend(&level[position ^ 1]);
+                    position >>= 1;
+                }
+                TransactionProof::new(
+                    path,
+                    stibhash(index, hashtype),
+                    depth,
+                    index as u64,
+                    hashtype.to_string(),
+                )
+            })
+            .collect();
+
+        (levels.last().unwrap()[0].clone(), proofs)
+    }
+
+    #[test]
+    fn test_verifies_both_hash_types() {
+        for hashtype in [Hashtype::Sha512256, Hashtype::Sha256] {
+            let (root, proofs) = tree(hashtype);
+            for (proof, txid) in proofs.iter().zip(TXIDS) {
+                verify_transaction_proof(proof, txid, &root).unwrap();
+            }
+        }
+    }
+
+    #[test]
+    fn test_verifies_against_block_root() {
+        let (root, proofs) = tree(Hashtype::Sha512256);
+        let (root_sha256, proofs_sha256) = tree(Hashtype::Sha256);
+        let block = Block {
+            transactions_root: Some(root),
+            transactions_root_sha256: Some(root_sha256),
+            ..Default::default()
+        };
+
+        verify_transaction_in_block(&proofs[1], TXIDS[1], &block).unwrap();
+        verify_transaction_in_block(&proofs_sha256[2], TXIDS[2], &block).unwrap();
+    }
+
+    #[test]
+    fn test_single_transaction_tree() {
+        let leaf = hash_parts(
+            Hashtype::Sha256,
+            &[
+                TRANSACTION_LEAF_PREFIX,
+                &decode_transaction_id(TXIDS[0]).unwrap(),
+                &stibhash(0, Hashtype::Sha256),
+            ],
+        );
+        let proof = TransactionProof::new(
+            vec![],
+            stibhash(0, Hashtype::Sha256),
+            0,
+            0,
+            "sha256".to_string(),
+        );
+
+        verify_transaction_proof(&proof, TXIDS[0], &leaf).unwrap();
+    }
+
+    #[test]
+    fn test_rejects_invalid_proofs() {
+        let (root, proofs) = tree(Hashtype::Sha512256);
+
+        let error = verify_transaction_proof(&proofs[0], TXIDS[1], &root).unwrap_err();
+        assert_eq!(
+            error.to_string(),
+            format!(
+                "Invalid proof: transaction {} is not committed to by the transactions root",
+                TXIDS[1]
+            )
+        );
+
+        let mut tampered = proofs[0].clone();
+        tampered.stibhash[0] ^= 1;
+        assert!(verify_transaction_proof(&tampered, TXIDS[0], &root).is_err());
+
+        let mut wrong_type = proofs[0].clone();
+        wrong_type.hashtype = "sha256".to_string();
+        assert!(verify_transaction_proof(&wrong_type, TXIDS[0], &root).is_err());
+
+        let mut truncated = proofs[0].clone();
+        truncated.proof.pop();
+        assert!(verify_transaction_proof(&truncated, TXIDS[0], &root).is_err());
+
+        let mut too_deep = proofs[0].clone();
+        too_deep.treedepth = u64::MAX;
+        assert_eq!(
+            verify_transaction_proof(&too_deep, TXIDS[0], &root)
+                .unwrap_err()
+                .to_string(),
+            format!("Invalid proof: tree depth {} is too large", u64::MAX)
+        );
+
+        let block = Block::default();
+        assert_eq!(
+            verify_transaction_in_block(&proofs[0], TXIDS[0], &block)
+                .unwrap_err()
+                .to_string(),
+            "Invalid proof: block has no sha512_256 transactions root"
+        );
+    }
+}
@@ -0,0 +1,523 @@
+//! A typed representation of algod blocks.
+//!
+//! The generated [`Block`] model exposes addresses and hashes as raw bytes and drops any header
+//! field it does not know about. [`TypedBlock`] uses [`Address`] and [`Byte32`] instead, groups
+//! the protocol upgrade, rewards and participation state into their own types and keeps unknown
+//! header fields, so that decoding and re-encoding a canonical `GetBlock` payload with
+//! [`TypedBlockResponse`] reproduces the original bytes, certificate included. The transactions
+//! of the block are restored to [`BlockTransaction`]s when it is decoded.
+
+use crate::block::BlockTransaction;
+use crate::block_header::BlockHeader;
+use crate::error::AlgoKitUtilsError;
+use algod_client::models::{Block, SignedTxnInBlock};
+use algokit_transact::{Address, AlgorandMsgpack, Byte32};
+use serde::{Deserialize, Deserializer, Serialize, Serializer};
+use serde_with::{Bytes, serde_as};
+use std::collections::BTreeMap;
+
+pub(crate) fn is_zero(value: &u64) -> bool {
+    *value == 0
+}
+
+pub(crate) fn is_false(value: &bool) -> bool {
+    !*value
+}
+
+pub(crate) fn is_zero_bytes(bytes: &[u8]) -> bool {
+    bytes.iter().all(|b| *b == 0)
+}
+
+pub(crate) fn is_zero_address(address: &Address) -> bool {
+    is_zero_bytes(&address.0)
+}
+
+/// The state of protocol upgrades as of a block.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct UpgradeState {
+    /// The protocol version the block was produced under.
+    #[serde(rename = "proto", default, skip_serializing_if = "String::is_empty")]
+    pub current_protocol: String,
+
+    /// The protocol version being voted on, if any.
+    #[serde(
+        rename = "nextproto",
+        default,
+        skip_serializing_if = "String::is_empty"
+    )]
+    pub next_protocol: String,
+
+    /// The number of blocks that approved the next protocol so far.
+    #[serde(rename = "nextyes", default, skip_serializing_if = "is_zero")]
+    pub next_protocol_approvals: u64,
+
+    /// The round by which the next protocol must be approved.
+    #[serde(rename = "nextbefore", default, skip_serializing_if = "is_zero")]
+    pub next_protocol_vote_before: u64,
+
+    /// The round at which the next protocol is switched on, once approved.
+    #[serde(rename = "nextswitch", default, skip_serializing_if = "is_zero")]
+    pub next_protocol_switch_on: u64,
+}
+
+/// The upgrade vote cast by the proposer of a block.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct UpgradeVote {
+    /// The protocol version proposed by the block.
+    #[serde(
+        rename = "upgradeprop",
+        default,
+        skip_serializing_if = "String::is_empty"
+    )]
+    pub upgrade_propose: String,
+
+    /// The number of rounds to wait after approval before switching to the proposed protocol.
+    #[serde(rename = "upgradedelay", default, skip_serializing_if = "is_zero")]
+    pub upgrade_delay: u64,
+
+    /// Whether the block approves the protocol being voted on.
+    #[serde(rename = "upgradeyes", default, skip_serializing_if = "is_false")]
+    pub upgrade_approve: bool,
+}
+
+/// The state of the rewards pool as of a block.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct RewardsState {
+    /// The account that collects transaction fees.
+    #[serde(rename = "fees", default, skip_serializing_if = "is_zero_address")]
+    pub fee_sink: Address,
+
+    /// The account that rewards are paid from.
+    #[serde(rename = "rwd", default, skip_serializing_if = "is_zero_address")]
+    pub rewards_pool: Address,
+
+    /// The number of rewards units earned per reward unit of stake since genesis.
+    #[serde(rename = "earn", default, skip_serializing_if = "is_zero")]
+    pub rewards_level: u64,
+
+    /// The number of microalgos added to the rewards level per round.
+    #[serde(rename = "rate", default, skip_serializing_if = "is_zero")]
+    pub rewards_rate: u64,
+
+    /// The microalgos left over after distributing the rewards rate.
+    #[serde(rename = "frac", default, skip_serializing_if = "is_zero")]
+    pub rewards_residue: u64,
+
+    /// The round at which the rewards rate is next recalculated.
+    #[serde(rename = "rwcalr", default, skip_serializing_if = "is_zero")]
+    pub rewards_recalculation_round: u64,
+}
+
+/// Accounts whose participation status was changed by a block.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct ParticipationUpdates {
+    /// Accounts whose participation keys expired and were taken offline.
+    #[serde(rename = "partupdrmv", default, skip_serializing_if = "Vec::is_empty")]
+    pub expired_participation_accounts: Vec<Address>,
+
+    /// Accounts that were taken offline for failing to propose.
+    #[serde(rename = "partupdabs", default, skip_serializing_if = "Vec::is_empty")]
+    pub absent_participation_accounts: Vec<Address>,
+}
+
+/// State proof tracking data for a single state proof type.
+#[serde_as]
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct StateProofTracking {
+    /// The commitment to the voters of the next state proof.
+    #[serde(rename = "v", default, skip_serializing_if = "Vec::is_empty")]
+    #[serde_as(as = "Bytes")]
+    pub voters_commitment: Vec<u8>,
+
+    /// The total online stake of the voters of the next state proof.
+    #[serde(rename = "t", default, skip_serializing_if = "is_zero")]
+    pub online_total_weight: u64,
+
+    /// The next round for which a state proof will be accepted.
+    #[serde(rename = "n", default, skip_serializing_if = "is_zero")]
+    pub next_round: u64,
+}
+
+/// A block with typed header fields and transactions.
+#[serde_as]
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(remote = "Self")]
+pub struct TypedBlock {
+    #[serde(rename = "rnd", default, skip_serializing_if = "is_zero")]
+    pub round: u64,
+
+    #[serde(rename = "prev", default, skip_serializing_if = "is_zero_bytes")]
+    #[serde_as(as = "Bytes")]
+    pub previous_block_hash: Byte32,
+
+    /// The SHA-512 hash of the previous block.
+    #[serde(rename = "prev512", default, skip_serializing_if = "Vec::is_empty")]
+    #[serde_as(as = "Bytes")]
+    pub previous_block_hash_512: Vec<u8>,
+
+    #[serde(rename = "seed", default, skip_serializing_if = "is_zero_bytes")]
+    #[serde_as(as = "Bytes")]
+    pub seed: Byte32,
+
+    /// The root of the SHA-512/256 transactions Merkle tree.
+    #[serde(rename = "txn", default, skip_serializing_if = "is_zero_bytes")]
+    #[serde_as(as = "Bytes")]
+    pub transactions_root: Byte32,
+
+    /// The root of the SHA-256 transactions vector commitment.
+    #[serde(rename = "txn256", default, skip_serializing_if = "is_zero_bytes")]
+    #[serde_as(as = "Bytes")]
+    pub transactions_root_sha256: Byte32,
+
+    /// The root of the SHA-512 transactions vector commitment.
+    #[serde(rename = "txn512", default, skip_serializing_if = "Vec::is_empty")]
+    #[serde_as(as = "Bytes")]
+    pub transactions_root_sha512: Vec<u8>,
+
+    #[serde(rename = "ts", default, skip_serializing_if = "is_zero")]
+    pub timestamp: u64,
+
+    #[serde(rename = "gen", default, skip_serializing_if = "String::is_empty")]
+    pub genesis_id: String,
+
+    #[serde(rename = "gh", default, skip_serializing_if = "is_zero_bytes")]
+    #[serde_as(as = "Bytes")]
+    pub genesis_hash: Byte32,
+
+    /// The account that proposed the block.
+    #[serde(rename = "prp", default, skip_serializing_if = "is_zero_address")]
+    pub proposer: Address,
+
+    /// The fees collected from the transactions of the block.
+    #[serde(rename = "fc", default, skip_serializing_if = "is_zero")]
+    pub fees_collected: u64,
+
+    /// The bonus added to the proposer payout.
+    #[serde(rename = "bi", default, skip_serializing_if = "is_zero")]
+    pub bonus: u64,
+
+    /// The amount paid to the proposer.
+    #[serde(rename = "pp", default, skip_serializing_if = "is_zero")]
+    pub proposer_payout: u64,
+
+    #[serde(flatten)]
+    pub rewards: RewardsState,
+
+    #[serde(flatten)]
+    pub upgrade_state: UpgradeState,
+
+    #[serde(flatten)]
+    pub upgrade_vote: UpgradeVote,
+
+    /// The number of transactions committed to the ledger after this block.
+    #[serde(rename = "tc", default, skip_serializing_if = "is_zero")]
+    pub transaction_counter: u64,
+
+    /// State proof tracking data, by state proof type.
+    #[serde(rename = "spt", default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub state_proof_tracking: BTreeMap<u64, StateProofTracking>,
+
+    #[serde(flatten)]
+    pub participation_updates: ParticipationUpdates,
+
+    /// The transactions of the block as they are stored in it, with their genesis ID and hash
+    /// removed. They are kept so that re-encoding is lossless.
+    #[serde(rename = "txns", default, skip_serializing_if = "Vec::is_empty")]
+    payset: Vec<SignedTxnInBlock>,
+
+    /// The transactions of the block restored to the form they were signed in.
+    #[serde(skip)]
+    transactions: Vec<BlockTransaction>,
+
+    /// Header fields that are not modelled by this type, kept so they are re-encoded as is.
+    #[serde(flatten)]
+    pub unknown_fields: BTreeMap<String, rmpv::Value>,
+}
+
+impl Serialize for TypedBlock {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        Self::serialize(self, serializer)
+    }
+}
+
+impl<'de> Deserialize<'de> for TypedBlock {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let mut block = Self::deserialize(deserializer)?;
+        block.transactions = block
+            .restore_transactions()
+            .map_err(serde::de::Error::custom)?;
+        Ok(block)
+    }
+}
+
+impl AlgorandMsgpack for TypedBlock {}
+
+impl TypedBlock {
+    /// Converts a decoded block.
+    ///
+    /// Header fields that are not part of the [`Block`] model are lost when a block is decoded,
+    /// so prefer [`TypedBlockResponse::from_msgpack`] when the raw block bytes are available.
+    /// Fails if a transaction of the block cannot be restored.
+    pub fn from_block(block: &Block) -> Result<Self, AlgoKitUtilsError> {
+        Ok(Self::decode(&block.encode()?)?)
+    }
+
+    /// Converts the block into the generated [`Block`] model.
+    pub fn to_block(&self) -> Result<Block, AlgoKitUtilsError> {
+        Ok(Block::decode(&self.encode()?)?)
+    }
+
+    /// Returns the header of the block, which can be hashed.
+    pub fn header(&self) -> Result<BlockHeader, AlgoKitUtilsError> {
+        BlockHeader::from_msgpack(&self.encode()?)
+    }
+
+    /// The transactions of the block, restored to the form they were signed in.
+    pub fn transactions(&self) -> &[BlockTransaction] {
+        &self.transactions
+    }
+
+    fn restore_transactions(&self) -> Result<Vec<BlockTransaction>, AlgoKitUtilsError> {
+        let genesis_id = (!self.genesis_id.is_empty()).then_some(self.genesis_id.as_str());
+        let genesis_hash = (!is_zero_bytes(&self.genesis_hash)).then_some(&self.genesis_hash[..]);
+
+        self.payset
+            .iter()
+            .map(|stib| BlockTransaction::from_signed_txn_in_block(stib, genesis_id, genesis_hash))
+            .collect()
+    }
+}
+
+/// The msgpack payload returned by algod's `GetBlock` endpoint.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct TypedBlockResponse {
+    #[serde(rename = "block")]
+    pub block: TypedBlock,
+
+    /// The agreement certificate of the block, present when requested from algod.
+    #[serde(rename = "cert", default, skip_serializing_if = "Option::is_none")]
+    pub certificate: Option<rmpv::Value>,
+}
+
+impl AlgorandMsgpack for TypedBlockResponse {}
+
+impl TypedBlockResponse {
+    /// Decodes a msgpack `GetBlock` payload, failing if a transaction of the block cannot be
+    /// restored.
+    pub fn from_msgpack(bytes: &[u8]) -> Result<Self, AlgoKitUtilsError> {
+        Ok(Self::decode(bytes)?)
+    }
+
+    /// Encodes the payload canonically, as algod does.
+    pub fn to_msgpack(&self) -> Result<Vec<u8>, AlgoKitUtilsError> {
+        Ok(self.encode()?)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use algokit_transact::SignedTransaction;
+    use algokit_transact::test_utils::{AccountMother, TransactionMother};
+    use rmpv::Value;
+
+    fn map(entries: Vec<(&str, Value)>) -> Value {
+        Value::Map(
+            entries
+                .into_iter()
+                .map(|(k, v)| (Value::from(k), v))
+                .collect(),
+        )
+    }
+
+    fn bytes(byte: u8, len: usize) -> Value {
+        Value::Binary(vec![byte; len])
+    }
+
+    fn signed_payment() -> SignedTransaction {
+        SignedTransaction {
+            transaction: TransactionMother::simple_payment().build().unwrap(),
+            signature: Some([1; 64]),
+            auth_address: None,
+            multisignature: None,
+        }
+    }
+
+    fn payset_entry() -> Value {
+        let mut stripped = signed_payment();
+        let header = stripped.transaction.header_mut();
+        header.genesis_id = None;
+        header.genesis_hash = None;
+        let entry = SignedTxnInBlock {
+            signed_transaction: stripped,
+            has_genesis_id: Some(true),
+            sender_rewards: Some(12),
+            ..Default::default()
+        };
+        rmpv::decode::read_value(&mut entry.encode().unwrap().as_slice()).unwrap()
+    }
+
+    /// A canonically encoded `GetBlock` payload with every header field set, a field that is not
+    /// modelled and a certificate.
+    fn get_block_payload() -> Vec<u8> {
+        let header = signed_payment().transaction.header().clone();
+        let account = |byte: u8| bytes(byte, 32);
+        let mut entries = vec![
+            ("bi", Value::from(5)),
+            ("earn", Value::from(27521)),
+            ("fc", Value::from(2000)),
+            ("fees", account(1)),
+            ("frac", Value::from(2_000_000)),
+            ("gen", Value::from(header.genesis_id.clone().unwrap())),
+            ("gh", Value::Binary(header.genesis_hash.unwrap().to_vec())),
+            ("nextbefore", Value::from(1_000_200)),
+            ("nextproto", Value::from("future")),
+            ("nextswitch", Value::from(1_000_300)),
+            ("nextyes", Value::from(42)),
+            (
+                "partupdabs",
+                Value::Array(vec![AccountMother::account().address().0.to_vec().into()]),
+            ),
+            ("partupdrmv", Value::Array(vec![account(4)])),
+            ("pp", Value::from(1_500_000)),
+            ("prev", bytes(6, 32)),
+            ("prev512", bytes(7, 64)),
+            (
+                "proto",
+                Value::from("https://github.com/algorandfoundation/specs/tree/abc"),
+            ),
+            ("prp", account(8)),
+            ("rate", Value::from(0)),
+            ("rnd", Value::from(1_000_000)),
+            ("rwcalr", Value::from(1_500_000)),
+            ("rwd", account(9)),
+            ("seed", bytes(10, 32)),
+            (
+                "spt",
+                Value::Map(vec![(
+                    Value::from(0),
+                    map(vec![
+                        ("n", Value::from(1_000_192)),
+                        ("t", Value::from(5_000_000_000_000u64)),
+                        ("v", bytes(11, 64)),
+                    ]),
+                )]),
+            ),
+            ("tc", Value::from(123_456_789)),
+            ("ts", Value::from(1_700_000_000)),
+            ("txn", bytes(12, 32)),
+            ("txn256", bytes(13, 32)),
+            ("txn512", bytes(14, 64)),
+            ("txns", Value::Array(vec![payset_entry()])),
+            ("upgradedelay", Value::from(140_000)),
+            ("upgradeprop", Value::from("future")),
+            ("upgradeyes", Value::from(true)),
+            ("zzfuture", map(vec![("a", Value::from(1))])),
+        ];
+        entries.retain(|(_, v)| v.as_u64() != Some(0));
+        let cert = map(vec![
+            (
+                "prop",
+                map(vec![("dig", bytes(15, 32)), ("oprop", account(8))]),
+            ),
+            ("rnd", Value::from(1_000_000)),
+            ("step", Value::from(2)),
+            ("vote", Value::Array(vec![map(vec![("snd", account(16))])])),
+        ]);
+        let payload = map(vec![("block", map(entries)), ("cert", cert)]);
+
+        let mut encoded = Vec::new();
+        rmpv::encode::write_value(&mut encoded, &payload).unwrap();
+        encoded
+    }
+
+    #[test]
+    fn test_get_block_round_trip_is_lossless() {
+        let payload = get_block_payload();
+
+        let response = TypedBlockResponse::from_msgpack(&payload).unwrap();
+
+        assert_eq!(response.to_msgpack().unwrap(), payload);
+        assert!(response.certificate.is_some());
+        assert_eq!(
+            response.block.unknown_fields.keys().collect::<Vec<_>>(),
+            vec!["zzfuture"]
+        );
+    }
+
+    #[test]
+    fn test_fields_are_typed() {
+        let block = TypedBlockResponse::from_msgpack(&get_block_payload())
+            .unwrap()
+            .block;
+
+        assert_eq!(block.round, 1_000_000);
+        assert_eq!(block.proposer, Address([8; 32]));
+        assert_eq!(block.rewards.fee_sink, Address([1; 32]));
+        assert_eq!(block.rewards.rewards_rate, 0);
+        assert_eq!(block.upgrade_state.next_protocol, "future");
+        assert_eq!(block.upgrade_state.next_protocol_approvals, 42);
+        assert!(block.upgrade_vote.upgrade_approve);
+        assert_eq!(
+            block.participation_updates.absent_participation_accounts,
+            vec![AccountMother::account().address()]
+        );
+        assert_eq!(block.state_proof_tracking[&0].next_round, 1_000_192);
+        assert_eq!(block.transactions_root_sha512, vec![14; 64]);
+    }
+
+    #[test]
+    fn test_signed_transactions_are_restored() {
+        let block = TypedBlockResponse::from_msgpack(&get_block_payload())
+            .unwrap()
+            .block;
+
+        let transactions = block.transactions();
+
+        assert_eq!(transactions.len(), 1);
+        assert_eq!(transactions[0].signed_transaction, signed_payment());
+        assert_eq!(transactions[0].apply_data.sender_rewards, Some(12));
+    }
+
+    #[test]
+    fn test_rejects_transactions_that_cannot_be_restored() {
+        let mut block = TypedBlockResponse::from_msgpack(&get_block_payload())
+            .unwrap()
+            .block;
+        block.genesis_hash = [0; 32];
+
+        let error = TypedBlock::decode(&block.encode().unwrap()).unwrap_err();
+
+        assert!(
+            error
+                .to_string()
+                .contains("Block transaction has no genesis hash and neither does the block")
+        );
+    }
+
+    #[test]
+    fn test_converts_to_and_from_generated_block() {
+        let response = TypedBlockResponse::from_msgpack(&get_block_payload()).unwrap();
+
+        let block = response.block.to_block().unwrap();
+        assert_eq!(block.proposer, Some(vec![8; 32]));
+        assert_eq!(block.next_protocol_approvals, Some(42));
+
+        let converted = TypedBlock::from_block(&block).unwrap();
+        assert!(converted.unknown_fields.is_empty());
+        assert_eq!(
+            TypedBlock {
+                unknown_fields: BTreeMap::new(),
+                ..response.block.clone()
+            },
+            converted
+        );
+        assert_eq!(
+            converted.header().unwrap().hash().unwrap(),
+            BlockHeader::from_msgpack(&block.encode_raw().unwrap())
+                .unwrap()
+                .hash()
+                .unwrap()
+        );
+    }
+}